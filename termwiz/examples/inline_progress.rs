@@ -0,0 +1,52 @@
+//! This example shows how to use an inline `BufferedTerminal` to
+//! show a live updating progress display below the cursor, printing
+//! a line above it as each task completes.
+use std::time::Duration;
+use termwiz::caps::Capabilities;
+use termwiz::color::AnsiColor;
+use termwiz::styled::{Span, StyledText};
+use termwiz::surface::{Change, Position};
+use termwiz::terminal::buffered::BufferedTerminal;
+use termwiz::terminal::new_terminal;
+use termwiz::Error;
+
+fn main() -> Result<(), Error> {
+    let caps = Capabilities::new_from_env()?;
+    let terminal = new_terminal(caps)?;
+    let mut buf = BufferedTerminal::new_inline(terminal, 2)?;
+
+    let tasks = ["fetch", "build", "test", "package"];
+    for (idx, task) in tasks.iter().enumerate() {
+        for pct in (0..=100).step_by(5) {
+            let width = buf.dimensions().0.saturating_sub(12).min(40);
+            let done = width * pct / 100;
+            buf.add_change(Change::ClearScreen(Default::default()));
+            buf.add_changes(
+                StyledText::new()
+                    .push(Span::new(format!("[{}/{}] ", idx + 1, tasks.len())).bold())
+                    .push(*task)
+                    .to_changes(),
+            );
+            buf.add_change(Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(1),
+            });
+            buf.add_changes(
+                StyledText::new()
+                    .push(Span::new("=".repeat(done)).foreground(AnsiColor::Lime))
+                    .push(format!("{} {:3}%", " ".repeat(width - done), pct))
+                    .to_changes(),
+            );
+            buf.check_for_resize()?;
+            buf.flush()?;
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        buf.print_above(&StyledText::from(Span::new(format!("finished {}", task))).to_changes())?;
+    }
+
+    buf.add_change(Change::ClearScreen(Default::default()));
+    buf.add_change("all done");
+    buf.finish_inline()?;
+
+    Ok(())
+}
@@ -1,22 +1,32 @@
 use termwiz::caps::Capabilities;
+use termwiz::escape::csi::KittyKeyboardFlags;
 use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
 use termwiz::terminal::{new_terminal, Terminal};
 use termwiz::Error;
 
-const CTRL_C: KeyEvent = KeyEvent {
-    key: KeyCode::Char('c'),
-    modifiers: Modifiers::CTRL,
-};
+const CTRL_C: KeyEvent = KeyEvent::new(KeyCode::Char('c'), Modifiers::CTRL);
 
 fn main() -> Result<(), Error> {
     let caps = Capabilities::new_from_env()?;
     let mut terminal = new_terminal(caps)?;
     terminal.set_raw_mode()?;
 
+    // Pass --kitty to see the additional detail reported by terminals
+    // that implement the kitty keyboard protocol
+    if std::env::args().any(|arg| arg == "--kitty") {
+        terminal.push_kitty_keyboard_flags(KittyKeyboardFlags::all())?;
+        terminal.flush()?;
+    }
+
     while let Some(event) = terminal.poll_input(None)? {
         print!("{:?}\r\n", event);
-        if event == InputEvent::Key(CTRL_C) {
-            break;
+        match event {
+            InputEvent::Key(KeyEvent { key, modifiers, .. })
+                if key == CTRL_C.key && modifiers == CTRL_C.modifiers =>
+            {
+                break;
+            }
+            _ => {}
         }
     }
 
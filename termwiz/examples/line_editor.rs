@@ -28,17 +28,28 @@ impl LineEditorHost for Host {
         &mut self.history
     }
 
+    // Suggest the rest of a previously entered line
+    fn suggestion(&mut self, line: &str) -> Option<String> {
+        self.history.suggest(line)
+    }
+
     /// Demo of the completion API for words starting with "h" or "he"
     fn complete(&self, line: &str, cursor_position: usize) -> Vec<CompletionCandidate> {
         let mut candidates = vec![];
         if let Some((range, word)) = word_at_cursor(line, cursor_position) {
-            let words = &["hello", "help", "he-man"];
+            let words = &[
+                ("hello", "a greeting"),
+                ("help", "a request for assistance"),
+                ("he-man", "a cartoon character"),
+            ];
 
-            for w in words {
+            for (w, description) in words {
                 if w.starts_with(word) {
                     candidates.push(CompletionCandidate {
                         range: range.clone(),
                         text: w.to_string(),
+                        description: Some(description.to_string()),
+                        ..Default::default()
                     });
                 }
             }
@@ -95,6 +106,7 @@ fn main() -> termwiz::Result<()> {
     println!("Type `exit` to quit this example, or start a word with `h` and press Tab.");
     let mut terminal = line_editor_terminal()?;
     let mut editor = LineEditor::new(&mut terminal);
+    editor.set_completion_menu(CompletionMenu::List);
 
     let mut host = Host::default();
     loop {
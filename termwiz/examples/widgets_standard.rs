@@ -0,0 +1,153 @@
+//! This example shows how to compose an app from the standard widgets
+#[cfg(feature = "widgets")]
+mod inner {
+    use termwiz::caps::Capabilities;
+    use termwiz::input::*;
+    use termwiz::surface::Change;
+    use termwiz::terminal::buffered::BufferedTerminal;
+    use termwiz::terminal::{new_terminal, Terminal};
+    use termwiz::widgets::layout::{ChildOrientation, Constraints, VerticalAlignment};
+    use termwiz::widgets::*;
+    use termwiz::Error;
+
+    type Editor = Split<Frame<TextInput>, Frame<Label>>;
+
+    /// Arranges the tabs above the status bar
+    struct MainScreen {}
+
+    impl Widget for MainScreen {
+        fn render(&mut self, _args: &mut RenderArgs) {}
+
+        fn get_size_constraints(&self) -> Constraints {
+            let mut c = Constraints::default();
+            c.child_orientation = ChildOrientation::Vertical;
+            c
+        }
+    }
+
+    fn build_tabs() -> Tabs {
+        let mut tabs = Tabs::new();
+
+        let fruit = ["apple", "banana", "cherry", "damson", "elderberry", "fig"];
+        tabs.add_tab(
+            "List",
+            Frame::new(
+                "Fruit",
+                List::new(fruit.iter().map(|s| s.to_string()).collect()),
+            ),
+        );
+
+        let mut table = Table::new(vec![
+            Column::new("Name", ColumnWidth::Auto),
+            Column::new("Kind", ColumnWidth::Fixed(10)),
+            Column::new("Description", ColumnWidth::Auto),
+        ]);
+        table.set_rows(vec![
+            vec!["Label".into(), "display".into(), "Wrapped text".into()],
+            vec![
+                "TextInput".into(),
+                "input".into(),
+                "A single line of text".into(),
+            ],
+            vec![
+                "List".into(),
+                "input".into(),
+                "Selects one of a list of items".into(),
+            ],
+            vec![
+                "Table".into(),
+                "input".into(),
+                "Selects a row of a table".into(),
+            ],
+        ]);
+        tabs.add_tab("Table", Frame::new("Widgets", table));
+
+        let mut input = TextInput::new();
+        input.set_placeholder("Type here; press Enter to copy the text below");
+        let mut split: Editor = Split::new(
+            ChildOrientation::Vertical,
+            Frame::new("Input", input),
+            Frame::new("Output", Label::new("")),
+        );
+        split.set_size(SplitSize::Fixed(3));
+        tabs.add_tab("Input", split);
+
+        tabs
+    }
+
+    pub fn run() -> Result<(), Error> {
+        let caps = Capabilities::new_from_env()?;
+        let mut buf = BufferedTerminal::new(new_terminal(caps)?)?;
+        buf.terminal().set_raw_mode()?;
+        buf.terminal().enter_alternate_screen()?;
+
+        let mut ui = Ui::new();
+        let root_id = ui.set_root(MainScreen {});
+        let tabs_id = ui.add_child(root_id, build_tabs());
+        let mut progress = ProgressBar::new();
+        progress.set_label(Some("CTRL-PageUp/PageDown to change tabs, Escape to quit"));
+        progress.set_constraints(
+            *Constraints::default()
+                .set_fixed_height(1)
+                .set_valign(VerticalAlignment::Bottom),
+        );
+        let progress_id = ui.add_child(root_id, progress);
+        ui.set_focus(tabs_id);
+
+        loop {
+            ui.process_event_queue()?;
+
+            // Show how far through the tabs we are
+            let tabs = ui.widget::<Tabs>(tabs_id).unwrap();
+            let fraction = (tabs.selected() + 1) as f64 / tabs.len() as f64;
+            ui.widget_mut::<ProgressBar>(progress_id)
+                .unwrap()
+                .set_progress(fraction);
+
+            if ui.render_to_screen(&mut buf)? {
+                continue;
+            }
+            buf.flush()?;
+
+            match ui.poll_input(buf.terminal(), None) {
+                Ok(Some(InputEvent::Resized { rows, cols })) => {
+                    buf.add_change(Change::ClearScreen(Default::default()));
+                    buf.resize(cols, rows);
+                }
+                Ok(Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    ..
+                }))) => break,
+                Ok(Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    ..
+                }))) => {
+                    // The text input leaves Enter for the application
+                    let tabs = ui.widget_mut::<Tabs>(tabs_id).unwrap();
+                    if let Some(split) = tabs.widget_mut::<Editor>(2) {
+                        let text = split.first().inner().text().to_string();
+                        split.second_mut().inner_mut().set_text(&text);
+                    }
+                }
+                Ok(Some(input)) => ui.queue_event(WidgetEvent::Input(input)),
+                Ok(None) => {}
+                Err(e) => {
+                    print!("{:?}\r\n", e);
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "widgets")]
+fn main() -> Result<(), termwiz::Error> {
+    inner::run()
+}
+
+#[cfg(not(feature = "widgets"))]
+fn main() {
+    println!("recompile with --features widgets");
+}
@@ -59,6 +59,8 @@ use semver::Version;
 use std::env::var;
 use terminfo::{self, capability as cap};
 
+pub mod probed;
+
 builder! {
     /// Use the `ProbeHints` to configure an instance of
     /// the `ProbeHints` struct.  `ProbeHints` are passed to the `Capabilities`
@@ -92,6 +94,23 @@ builder! {
         /// See <https://www.iterm2.com/documentation-images.html>
         iterm2_image: Option<bool>,
 
+        /// Configure whether the kitty graphics protocol is supported
+        /// See <https://sw.kovidgoyal.net/kitty/graphics-protocol/>
+        kitty_graphics: Option<bool>,
+
+        /// Configure whether the kitty keyboard protocol is supported
+        /// See <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>
+        kitty_keyboard: Option<bool>,
+
+        /// Configure whether synchronized output (DEC private mode 2026)
+        /// is supported
+        synchronized_output: Option<bool>,
+
+        /// When synchronized output isn't supported, hide the cursor
+        /// while rendering changes that move it, so that it isn't seen
+        /// jumping around the screen.  This is off by default.
+        hide_cursor_while_rendering: Option<bool>,
+
         /// Specify whether `bce`, background color erase, is supported.
         bce: Option<bool>,
 
@@ -157,6 +176,10 @@ pub struct Capabilities {
     hyperlinks: bool,
     sixel: bool,
     iterm2_image: bool,
+    kitty_graphics: bool,
+    kitty_keyboard: bool,
+    synchronized_output: bool,
+    hide_cursor_while_rendering: bool,
     bce: bool,
     terminfo_db: Option<terminfo::Database>,
     bracketed_paste: bool,
@@ -287,6 +310,18 @@ impl Capabilities {
             }
         });
 
+        let kitty_graphics = hints.kitty_graphics.unwrap_or_else(|| {
+            // There's no environment variable that advertises this,
+            // so we only assume support for kitty itself
+            matches!(hints.term.as_ref().map(String::as_ref), Some("xterm-kitty"))
+        });
+
+        // These can only be reliably discovered by probing the terminal;
+        // see `probed::ProbeResults::apply_to_hints`.
+        let kitty_keyboard = hints.kitty_keyboard.unwrap_or(false);
+        let synchronized_output = hints.synchronized_output.unwrap_or(false);
+        let hide_cursor_while_rendering = hints.hide_cursor_while_rendering.unwrap_or(false);
+
         let bracketed_paste = hints.bracketed_paste.unwrap_or(true);
         let mouse_reporting = hints.mouse_reporting.unwrap_or(true);
 
@@ -298,6 +333,10 @@ impl Capabilities {
             sixel,
             hyperlinks,
             iterm2_image,
+            kitty_graphics,
+            kitty_keyboard,
+            synchronized_output,
+            hide_cursor_while_rendering,
             bce,
             terminfo_db,
             bracketed_paste,
@@ -328,6 +367,30 @@ impl Capabilities {
         self.iterm2_image
     }
 
+    /// Does the terminal support the kitty graphics protocol?
+    /// See <https://sw.kovidgoyal.net/kitty/graphics-protocol/>
+    pub fn kitty_graphics(&self) -> bool {
+        self.kitty_graphics
+    }
+
+    /// Does the terminal support the kitty keyboard protocol?
+    /// See <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>
+    pub fn kitty_keyboard(&self) -> bool {
+        self.kitty_keyboard
+    }
+
+    /// Does the terminal support synchronized output (DEC private
+    /// mode 2026)?
+    pub fn synchronized_output(&self) -> bool {
+        self.synchronized_output
+    }
+
+    /// Should the cursor be hidden while rendering changes that move
+    /// it, when synchronized output isn't available?
+    pub fn hide_cursor_while_rendering(&self) -> bool {
+        self.hide_cursor_while_rendering
+    }
+
     /// Is `bce`, background color erase supported?
     /// <http://invisible-island.net/ncurses/ncurses-slang.html#env_COLORTERM_BCE>
     pub fn bce(&self) -> bool {
@@ -375,6 +438,9 @@ mod test {
         assert_eq!(caps.sixel(), false);
         assert_eq!(caps.hyperlinks(), true);
         assert_eq!(caps.iterm2_image(), false);
+        assert_eq!(caps.kitty_graphics(), false);
+        assert!(!caps.kitty_keyboard());
+        assert!(!caps.synchronized_output());
         assert_eq!(caps.bce(), false);
     }
 
@@ -488,4 +554,28 @@ mod test {
         .unwrap();
         assert_eq!(caps.iterm2_image(), true);
     }
+
+    #[test]
+    fn kitty_graphics() {
+        let caps =
+            Capabilities::new_with_hints(ProbeHints::default().term(Some("xterm-kitty".into())))
+                .unwrap();
+        assert_eq!(caps.kitty_graphics(), true);
+
+        let caps = Capabilities::new_with_hints(
+            ProbeHints::default()
+                .term(Some("xterm-kitty".into()))
+                .kitty_graphics(Some(false)),
+        )
+        .unwrap();
+        assert_eq!(caps.kitty_graphics(), false);
+
+        let caps = Capabilities::new_with_hints(
+            ProbeHints::default()
+                .term(Some("xterm-256color".into()))
+                .kitty_graphics(Some(true)),
+        )
+        .unwrap();
+        assert_eq!(caps.kitty_graphics(), true);
+    }
 }
@@ -1,12 +1,97 @@
-use crate::escape::csi::{Device, Window};
+use crate::caps::{ColorLevel, ProbeHints};
+use crate::color::SrgbaTuple;
+use crate::escape::apc::{
+    KittyImageCompression, KittyImageData, KittyImageFormat, KittyImageTransmit,
+};
+use crate::escape::csi::{
+    DecPrivateMode, DecPrivateModeCode, Device, DeviceAttributes, Keyboard, KittyKeyboardFlags,
+    Mode, ModeSetting, Window,
+};
+use crate::escape::osc::{ColorOrQuery, DynamicColorNumber};
 use crate::escape::parser::Parser;
-use crate::escape::{Action, DeviceControlMode, Esc, EscCode, CSI};
+use crate::escape::{
+    Action, DeviceControlMode, Esc, EscCode, KittyImage, OperatingSystemCommand, CSI,
+};
 use crate::terminal::ScreenSize;
 use crate::{bail, Result};
 use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+use thiserror::Error;
 
-const TMUX_BEGIN: &str = "\u{1b}Ptmux;\u{1b}";
-const TMUX_END: &str = "\u{1b}\\";
+/// How long to wait for the terminal to respond to a probe when
+/// `ProbeCapabilities` is able to wait for input with a timeout
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The image id used when probing for kitty graphics support.
+/// The probe uses the query action, so nothing is stored
+/// under this id by the terminal.
+const KITTY_PROBE_IMAGE_ID: u32 = 31;
+
+/// Wrap `seq` so that tmux passes it through to the outer terminal.
+/// Every ESC in the wrapped sequence must be doubled.
+fn tmux_wrap(seq: &str) -> String {
+    format!("\x1bPtmux;{}\x1b\\", seq.replace('\x1b', "\x1b\x1b"))
+}
+
+fn is_device_attributes(action: &Action) -> bool {
+    match action {
+        Action::CSI(CSI::Device(dev)) => matches!(**dev, Device::DeviceAttributes(_)),
+        _ => false,
+    }
+}
+
+/// Returns true if `action` is the kind of thing that a terminal
+/// sends in response to a query, rather than something produced
+/// by the user typing on the keyboard
+fn is_probe_response(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::CSI(CSI::Device(_))
+            | Action::CSI(CSI::Window(_))
+            | Action::CSI(CSI::Keyboard(Keyboard::ReportKittyState(_)))
+            | Action::CSI(CSI::Mode(Mode::ReportDecPrivateMode { .. }))
+            | Action::CSI(CSI::Mode(Mode::ReportMode { .. }))
+            | Action::DeviceControl(_)
+            | Action::OperatingSystemCommand(_)
+            | Action::KittyImage(_)
+            | Action::Esc(Esc::Code(EscCode::StringTerminator))
+    )
+}
+
+/// The error type returned by the probing functions
+#[derive(Error, Debug)]
+pub enum ProbeError {
+    /// The terminal didn't respond before the deadline, or
+    /// input reached EOF.  The terminal is most likely not one
+    /// that understands escape sequence queries, such as a serial
+    /// console or a dumb terminal.
+    #[error("the terminal did not respond to the probe")]
+    NoResponse,
+    #[error(transparent)]
+    Other(crate::Error),
+}
+
+impl<E> From<E> for ProbeError
+where
+    E: Into<crate::Error>,
+{
+    fn from(err: E) -> Self {
+        Self::Other(err.into())
+    }
+}
+
+/// A Result whose error type is a ProbeError
+pub type ProbeResult<T> = std::result::Result<T, ProbeError>;
+
+/// Converts the result of a probe that the terminal didn't answer
+/// into None, passing other errors through
+fn unanswered_as_none<T>(result: ProbeResult<T>) -> ProbeResult<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(ProbeError::NoResponse) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
 
 /// Represents a terminal name and version.
 /// The name XtVersion is because this value is produced
@@ -55,14 +140,316 @@ mod test {
             assert_eq!(version.name_and_version(), result, "{input}");
         }
     }
+
+    /// A reader that returns one response per call to read
+    struct Responses(Vec<Vec<u8>>);
+
+    impl Read for Responses {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let response = self.0.remove(0);
+            buf[..response.len()].copy_from_slice(&response);
+            Ok(response.len())
+        }
+    }
+
+    /// Run `probe` against a terminal that responds with `response`,
+    /// returning the result and whatever was written to the terminal
+    fn probe<T>(
+        response: &str,
+        probe: impl FnOnce(&mut ProbeCapabilities) -> ProbeResult<T>,
+    ) -> (T, String) {
+        let mut read = std::io::Cursor::new(response.as_bytes().to_vec());
+        let mut write = vec![];
+        let result = {
+            let mut caps = ProbeCapabilities::new(&mut read, &mut write);
+            probe(&mut caps).unwrap()
+        };
+        (result, String::from_utf8(write).unwrap())
+    }
+
+    #[test]
+    fn kitty_keyboard() {
+        let (flags, written) = probe("\x1b[?1u\x1b[?62c", |p| p.kitty_keyboard());
+        assert_eq!(flags, Some(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES));
+        assert_eq!(written, "\x1b[?u\x1b[c");
+
+        let (flags, _) = probe("\x1b[?62c", |p| p.kitty_keyboard());
+        assert_eq!(flags, None);
+    }
+
+    #[test]
+    fn kitty_graphics() {
+        let (supported, written) = probe("\x1b_Gi=31;OK\x1b\\\x1b[?62c", |p| p.kitty_graphics());
+        assert!(supported);
+        assert_eq!(written, "\x1b_Ga=q,f=24,i=31,s=1,v=1;AAAA\x1b\\\x1b[c");
+
+        let (supported, _) = probe("\x1b_Gi=31;EINVAL:bad\x1b\\\x1b[?62c", |p| {
+            p.kitty_graphics()
+        });
+        assert!(!supported);
+
+        let (supported, _) = probe("\x1b[?62c", |p| p.kitty_graphics());
+        assert!(!supported);
+    }
+
+    #[test]
+    fn synchronized_output() {
+        let (supported, written) = probe("\x1b[?2026;2$y\x1b[?62c", |p| p.synchronized_output());
+        assert!(supported);
+        assert_eq!(written, "\x1b[?2026$p\x1b[c");
+
+        let (supported, _) = probe("\x1b[?2026;0$y\x1b[?62c", |p| p.synchronized_output());
+        assert!(!supported);
+
+        let (supported, _) = probe("\x1b[?62c", |p| p.synchronized_output());
+        assert!(!supported);
+    }
+
+    #[test]
+    fn xt_get_tcap() {
+        let (value, written) = probe("\x1bP1+r524742=382f382f38\x1b\\\x1b[?62c", |p| {
+            p.xt_get_tcap("RGB")
+        });
+        assert_eq!(value, Some("8/8/8".to_string()));
+        assert_eq!(written, "\x1bP+q524742\x1b\\\x1b[c");
+
+        // The terminal answers each query with a separate write
+        let mut read = Responses(vec![
+            b"\x1bP0+r\x1b\\\x1b[?62c".to_vec(),
+            b"\x1bP1+r5463\x1b\\\x1b[?62c".to_vec(),
+        ]);
+        let mut write = vec![];
+        assert!(ProbeCapabilities::new(&mut read, &mut write)
+            .true_color()
+            .unwrap());
+
+        let mut read = Responses(vec![b"\x1b[?62c".to_vec(), b"\x1b[?62c".to_vec()]);
+        assert!(!ProbeCapabilities::new(&mut read, &mut write)
+            .true_color()
+            .unwrap());
+    }
+
+    #[test]
+    fn colors() {
+        let (color, written) = probe("\x1b]11;rgb:0000/8080/ffff\x1b\\\x1b[?62c", |p| {
+            p.background_color()
+        });
+        assert_eq!(color, Some(SrgbaTuple(0., 128. / 255., 1., 1.)));
+        assert_eq!(written, "\x1b]11;?\x1b\\\x1b[c");
+
+        let (color, _) = probe("\x1b[?62c", |p| p.foreground_color());
+        assert_eq!(color, None);
+    }
+
+    #[test]
+    fn sixel() {
+        let (sixel, _) = probe("\x1b[?65;4;22c", |p| p.sixel());
+        assert!(sixel);
+        let (sixel, _) = probe("\x1b[?62;22c", |p| p.sixel());
+        assert!(!sixel);
+    }
+
+    #[test]
+    fn tmux_passthrough() {
+        let (_, written) = probe("\x1b[?62c", |p| {
+            p.set_tmux_passthrough(true);
+            p.kitty_keyboard()
+        });
+        assert_eq!(written, "\x1bPtmux;\x1b\x1b[?u\x1b\\\x1b[c");
+    }
+
+    #[test]
+    fn no_response() {
+        let mut read = std::io::Cursor::new(vec![]);
+        let mut write = vec![];
+        let mut caps = ProbeCapabilities::new(&mut read, &mut write);
+        assert!(matches!(caps.kitty_keyboard(), Err(ProbeError::NoResponse)));
+    }
+
+    #[test]
+    fn timeout() {
+        let mut read = std::io::Cursor::new(b"q".to_vec());
+        let mut write = vec![];
+        let mut available = 1;
+        let mut caps = ProbeCapabilities::new(&mut read, &mut write);
+        caps.set_timeout(Duration::from_millis(10));
+        caps.set_wait_for_input(|_timeout| {
+            // Only the first byte arrives before the deadline
+            available -= 1;
+            Ok(available >= 0)
+        });
+
+        assert!(matches!(caps.probe_all(), Err(ProbeError::NoResponse)));
+        assert_eq!(caps.take_typeahead(), b"q".to_vec());
+        drop(caps);
+        // We should give up after the first unanswered query
+        assert_eq!(String::from_utf8(write).unwrap(), "\x1b[>q\x1b[c");
+    }
+
+    #[test]
+    fn partial_response() {
+        // The terminal answers XTVERSION and the kitty keyboard query,
+        // but nothing after that
+        let mut read = Responses(vec![
+            b"\x1bP>|WezTerm 1\x1b\\\x1b[?62c".to_vec(),
+            b"\x1b[?1u\x1b[?62c".to_vec(),
+        ]);
+        let mut write = vec![];
+        let results = ProbeCapabilities::new(&mut read, &mut write)
+            .probe_all()
+            .unwrap();
+        let written = String::from_utf8(write).unwrap();
+        assert_eq!(
+            results,
+            ProbeResults {
+                xt_version: Some(XtVersion("WezTerm 1".to_string())),
+                kitty_keyboard: Some(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES),
+                ..Default::default()
+            }
+        );
+        // Each of the remaining probes was still attempted
+        assert!(written.contains("\x1b[?2026$p"));
+        assert!(written.ends_with("\x1b[c\x1b[c"));
+
+        // Unanswered probes leave the hints alone
+        let hints = results.apply_to_hints(ProbeHints::default());
+        assert_eq!(hints.sixel, None);
+        assert_eq!(hints.synchronized_output, None);
+    }
+
+    #[test]
+    fn typeahead() {
+        let mut read = std::io::Cursor::new(b"a\x1b[A\x1b[?1u\xc3\xa9\x1b[?62cxyz".to_vec());
+        let mut write = vec![];
+        let mut sink = vec![];
+        {
+            let mut caps = ProbeCapabilities::new(&mut read, &mut write);
+            caps.set_wait_for_input(|_timeout| Ok(true));
+            caps.set_typeahead_sink(&mut sink);
+            assert_eq!(
+                caps.kitty_keyboard().unwrap(),
+                Some(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES)
+            );
+        }
+        assert_eq!(
+            String::from_utf8(sink).unwrap(),
+            "a\x1b[A\u{e9}xyz".to_string()
+        );
+    }
+
+    #[test]
+    fn apply_to_hints() {
+        let results = ProbeResults {
+            xt_version: Some(XtVersion("WezTerm 20240203".to_string())),
+            kitty_keyboard: Some(KittyKeyboardFlags::NONE),
+            kitty_graphics: Some(true),
+            sixel: Some(false),
+            true_color: Some(true),
+            synchronized_output: Some(true),
+            foreground: None,
+            background: None,
+        };
+        let hints = results.apply_to_hints(ProbeHints::default().sixel(Some(true)));
+        let caps = crate::caps::Capabilities::new_with_hints(hints).unwrap();
+
+        assert!(caps.kitty_keyboard());
+        assert!(caps.kitty_graphics());
+        assert!(caps.synchronized_output());
+        assert_eq!(caps.color_level(), ColorLevel::TrueColor);
+        // explicitly set hints are not overridden by the probe
+        assert!(caps.sixel());
+        // XTVERSION is used in place of TERM_PROGRAM
+        assert!(caps.iterm2_image());
+    }
+}
+
+/// The outcome of `ProbeCapabilities::probe_all`.
+/// Probes that the terminal didn't answer in time are recorded as None.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProbeResults {
+    /// The XTVERSION response, if any
+    pub xt_version: Option<XtVersion>,
+    /// The currently active kitty keyboard protocol flags,
+    /// or None if the protocol is not supported
+    pub kitty_keyboard: Option<KittyKeyboardFlags>,
+    /// Whether the kitty graphics protocol is supported
+    pub kitty_graphics: Option<bool>,
+    /// Whether sixel graphics are supported
+    pub sixel: Option<bool>,
+    /// Whether 24-bit color is supported
+    pub true_color: Option<bool>,
+    /// Whether synchronized output (DEC private mode 2026) is supported
+    pub synchronized_output: Option<bool>,
+    /// The default foreground color
+    pub foreground: Option<SrgbaTuple>,
+    /// The default background color
+    pub background: Option<SrgbaTuple>,
+}
+
+impl ProbeResults {
+    /// Fill in any hints that have not already been set with the
+    /// results of probing.  Hints that were set explicitly are
+    /// left unchanged, so that the application retains the ability
+    /// to override what the terminal claims, as are those whose probe
+    /// wasn't answered.
+    pub fn apply_to_hints(&self, mut hints: ProbeHints) -> ProbeHints {
+        hints
+            .kitty_keyboard
+            .get_or_insert(self.kitty_keyboard.is_some());
+        hints.kitty_graphics = hints.kitty_graphics.or(self.kitty_graphics);
+        hints.sixel = hints.sixel.or(self.sixel);
+        hints.synchronized_output = hints.synchronized_output.or(self.synchronized_output);
+        // The absence of RGB/Tc in the XTGETTCAP response doesn't mean
+        // that the terminal lacks support, so only use it to upgrade
+        if self.true_color == Some(true) && hints.color_level.is_none() {
+            hints.color_level = Some(ColorLevel::TrueColor);
+        }
+        if hints.term_program.is_none() {
+            if let Some((name, version)) = self
+                .xt_version
+                .as_ref()
+                .and_then(XtVersion::name_and_version)
+            {
+                hints.term_program = Some(name.to_string());
+                hints.term_program_version = Some(version.to_string());
+            }
+        }
+        hints
+    }
 }
 
 /// This struct is a helper that uses probing to determine specific capabilities
 /// of the associated Terminal instance.
 /// It will write and read data to and from the associated Terminal.
+///
+/// By default reads block until the terminal responds.  Use
+/// `set_wait_for_input` to allow probing to give up after a timeout;
+/// `Terminal::probe_capabilities` does this for you.
+///
+/// Any input that is not part of a response to a probe, such as keys
+/// typed by the user while probing is in progress, is retained and can
+/// be retrieved via `take_typeahead`.  When obtained via
+/// `Terminal::probe_capabilities`, that input is instead returned from
+/// subsequent calls to `Terminal::poll_input`.
 pub struct ProbeCapabilities<'a> {
     read: Box<&'a mut dyn Read>,
     write: Box<&'a mut dyn Write>,
+    tmux_passthrough: bool,
+    timeout: Duration,
+    wait_for_input: Option<Box<dyn FnMut(Duration) -> Result<bool> + 'a>>,
+    typeahead: Vec<u8>,
+    typeahead_sink: Option<&'a mut Vec<u8>>,
+}
+
+impl<'a> Drop for ProbeCapabilities<'a> {
+    fn drop(&mut self) {
+        if let Some(sink) = self.typeahead_sink.as_mut() {
+            sink.append(&mut self.typeahead);
+        }
+    }
 }
 
 impl<'a> ProbeCapabilities<'a> {
@@ -70,104 +457,412 @@ impl<'a> ProbeCapabilities<'a> {
         Self {
             read: Box::new(read),
             write: Box::new(write),
+            tmux_passthrough: false,
+            timeout: DEFAULT_PROBE_TIMEOUT,
+            wait_for_input: None,
+            typeahead: vec![],
+            typeahead_sink: None,
         }
     }
 
-    /// Probe for the XTVERSION response
-    pub fn xt_version(&mut self) -> Result<XtVersion> {
-        self.xt_version_impl(false)
+    /// Provide a function that waits up to the specified duration
+    /// for input to become available to read, returning true if it
+    /// is available, or false if the duration elapsed.
+    /// This enables probes to give up with `ProbeError::NoResponse`
+    /// when the terminal doesn't respond within the timeout.
+    pub fn set_wait_for_input<F>(&mut self, wait_for_input: F)
+    where
+        F: FnMut(Duration) -> Result<bool> + 'a,
+    {
+        self.wait_for_input = Some(Box::new(wait_for_input));
     }
 
-    /// Assuming that we are talking to tmux, probe for the XTVERSION response
-    /// of its outer terminal.
-    pub fn outer_xt_version(&mut self) -> Result<XtVersion> {
-        self.xt_version_impl(true)
+    /// Set how long to wait for the terminal to respond to each probe.
+    /// This has no effect unless `set_wait_for_input` has been used.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
     }
 
-    fn xt_version_impl(&mut self, tmux_escape: bool) -> Result<XtVersion> {
-        let xt_version = CSI::Device(Box::new(Device::RequestTerminalNameAndVersion));
+    /// Arrange for any typeahead to be appended to `sink` when
+    /// this instance is dropped
+    pub(crate) fn set_typeahead_sink(&mut self, sink: &'a mut Vec<u8>) {
+        self.typeahead_sink = Some(sink);
+    }
+
+    /// Take the input that was read while probing but which was not
+    /// part of a response to a probe.  This is typically keyboard
+    /// input that the user typed ahead, and the caller should feed
+    /// it to its input parser so that it is not lost.
+    pub fn take_typeahead(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.typeahead)
+    }
+
+    /// When enabled, queries are wrapped in tmux's passthrough
+    /// sequence so that they are answered by the terminal outside
+    /// of tmux rather than by tmux itself.
+    /// The outer terminal may be configured to disallow passthrough
+    /// (`allow-passthrough` in tmux 3.3 and later), in which case
+    /// the probes report no support.
+    pub fn set_tmux_passthrough(&mut self, enable: bool) {
+        self.tmux_passthrough = enable;
+    }
+
+    /// Send `query` followed by a request for the primary device
+    /// attributes, and return the parsed responses up to and including
+    /// the device attributes response.
+    /// Practically every terminal responds to DA1, so if the query is not
+    /// understood by the terminal we find out when the DA1 response
+    /// arrives, rather than waiting indefinitely for an answer.
+    fn query(&mut self, query: &str) -> ProbeResult<Vec<Action>> {
+        if self.tmux_passthrough {
+            // tmux answers DA1 itself, and will do so before the outer
+            // terminal has had a chance to respond unless we delay.
+            self.send_query(&tmux_wrap(query), true)
+        } else {
+            self.send_query(query, false)
+        }
+    }
+
+    fn send_query(&mut self, query: &str, delay: bool) -> ProbeResult<Vec<Action>> {
         let dev_attributes = CSI::Device(Box::new(Device::RequestPrimaryDeviceAttributes));
 
-        if tmux_escape {
-            write!(self.write, "{TMUX_BEGIN}{xt_version}{TMUX_END}")?;
+        write!(self.write, "{query}")?;
+        if delay {
             self.write.flush()?;
-            std::thread::sleep(std::time::Duration::from_millis(100));
-            write!(self.write, "{dev_attributes}")?;
-        } else {
-            write!(self.write, "{xt_version}{dev_attributes}")?;
+            std::thread::sleep(Duration::from_millis(100));
         }
+        write!(self.write, "{dev_attributes}")?;
         self.write.flush()?;
-        let mut term = vec![];
+
+        let deadline = Instant::now() + self.timeout;
         let mut parser = Parser::new();
-        let mut done = false;
-
-        while !done {
-            let mut byte = [0u8];
-            self.read.read(&mut byte)?;
-
-            parser.parse(&byte, |action| {
-                // print!("{action:?}\r\n");
-                match action {
-                    Action::Esc(Esc::Code(EscCode::StringTerminator)) => {}
-                    Action::DeviceControl(dev) => {
-                        if let DeviceControlMode::Data(b) = dev {
-                            term.push(b);
-                        }
+        let mut actions = vec![];
+        // The bytes that make up the sequence currently being parsed
+        let mut sequence = vec![];
+
+        loop {
+            if let Some(wait_for_input) = self.wait_for_input.as_mut() {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() || !wait_for_input(remaining)? {
+                    self.typeahead.append(&mut sequence);
+                    return Err(ProbeError::NoResponse);
+                }
+            }
+
+            let mut buf = [0u8; 256];
+            let len = match self.read.read(&mut buf) {
+                Ok(0) => {
+                    self.typeahead.append(&mut sequence);
+                    return Err(ProbeError::NoResponse);
+                }
+                Ok(len) => len,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            for (idx, &byte) in buf[..len].iter().enumerate() {
+                sequence.push(byte);
+
+                let mut is_response = false;
+                let mut done = false;
+                parser.parse(&[byte], |action| {
+                    done |= is_device_attributes(&action);
+                    if is_probe_response(&action) {
+                        is_response = true;
+                        actions.push(action);
+                    } else {
+                        is_response = false;
                     }
-                    _ => {
-                        done = true;
+                });
+
+                if parser.is_ground() {
+                    if !is_response {
+                        // This is something that the user typed while
+                        // we were waiting; keep it for the application
+                        self.typeahead.extend_from_slice(&sequence);
                     }
+                    sequence.clear();
+                }
+
+                if done {
+                    self.typeahead.extend_from_slice(&buf[idx + 1..len]);
+                    return Ok(actions);
                 }
-            });
+            }
+        }
+    }
+
+    /// Probe the primary device attributes (DA1).
+    /// The response indicates, amongst other things, whether sixel
+    /// graphics are supported.
+    pub fn primary_device_attributes(&mut self) -> ProbeResult<DeviceAttributes> {
+        for action in self.query("")? {
+            if let Action::CSI(CSI::Device(dev)) = action {
+                if let Device::DeviceAttributes(attributes) = *dev {
+                    return Ok(attributes);
+                }
+            }
+        }
+        bail!("no device attributes response")
+    }
+
+    /// Probe for sixel graphics support by examining the
+    /// primary device attributes.
+    pub fn sixel(&mut self) -> ProbeResult<bool> {
+        Ok(self.primary_device_attributes()?.has_sixel())
+    }
+
+    /// Probe for the kitty keyboard protocol.
+    /// Returns the currently active flags if the protocol is
+    /// supported, or None if it is not.
+    /// See <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>
+    pub fn kitty_keyboard(&mut self) -> ProbeResult<Option<KittyKeyboardFlags>> {
+        let query = CSI::Keyboard(Keyboard::QueryKittySupport);
+        Ok(self
+            .query(&query.to_string())?
+            .into_iter()
+            .find_map(|action| match action {
+                Action::CSI(CSI::Keyboard(Keyboard::ReportKittyState(flags))) => Some(flags),
+                _ => None,
+            }))
+    }
+
+    /// Probe for the kitty graphics protocol, by asking the terminal
+    /// to validate a 1x1 pixel image without storing it.
+    /// See <https://sw.kovidgoyal.net/kitty/graphics-protocol/#querying-support-and-available-transmission-mediums>
+    pub fn kitty_graphics(&mut self) -> ProbeResult<bool> {
+        let query = KittyImage::Query {
+            transmit: KittyImageTransmit {
+                format: Some(KittyImageFormat::Rgb),
+                data: KittyImageData::DirectBin(vec![0, 0, 0]),
+                width: Some(1),
+                height: Some(1),
+                image_id: Some(KITTY_PROBE_IMAGE_ID),
+                image_number: None,
+                compression: KittyImageCompression::None,
+                more_data_follows: false,
+            },
+        };
+        let query = format!("{}{}", query, Esc::Code(EscCode::StringTerminator));
+
+        Ok(self.query(&query)?.iter().any(|action| match action {
+            Action::KittyImage(image) => match &**image {
+                KittyImage::TransmitData {
+                    transmit:
+                        KittyImageTransmit {
+                            image_id: Some(KITTY_PROBE_IMAGE_ID),
+                            data: KittyImageData::Direct(status),
+                            ..
+                        },
+                    ..
+                } => status == "OK",
+                _ => false,
+            },
+            _ => false,
+        }))
+    }
+
+    /// Use DECRQM to probe the state of a DEC private mode.
+    /// Returns `ModeSetting::NotRecognized` if the terminal
+    /// doesn't respond to the request.
+    pub fn dec_private_mode(&mut self, mode: DecPrivateMode) -> ProbeResult<ModeSetting> {
+        let query = CSI::Mode(Mode::QueryDecPrivateMode(mode.clone()));
+        Ok(self
+            .query(&query.to_string())?
+            .into_iter()
+            .find_map(|action| match action {
+                Action::CSI(CSI::Mode(Mode::ReportDecPrivateMode {
+                    mode: reported,
+                    setting,
+                })) if reported == mode => Some(setting),
+                _ => None,
+            })
+            .unwrap_or(ModeSetting::NotRecognized))
+    }
+
+    /// Probe for synchronized output support (DEC private mode 2026)
+    pub fn synchronized_output(&mut self) -> ProbeResult<bool> {
+        Ok(self
+            .dec_private_mode(DecPrivateMode::Code(DecPrivateModeCode::SynchronizedOutput))?
+            .is_recognized())
+    }
+
+    /// Use XTGETTCAP to look up the named terminfo capability in the
+    /// terminal's own notion of its terminfo.
+    /// Returns None if the capability is unknown to the terminal,
+    /// otherwise returns its value, which is empty for boolean
+    /// capabilities.
+    pub fn xt_get_tcap(&mut self, name: &str) -> ProbeResult<Option<String>> {
+        let query = format!(
+            "{}{}",
+            Action::XtGetTcap(vec![name.to_string()]),
+            Esc::Code(EscCode::StringTerminator)
+        );
+
+        for action in self.query(&query)? {
+            if let Action::DeviceControl(DeviceControlMode::ShortDeviceControl(dcs)) = action {
+                if dcs.intermediates != [b'+'] || dcs.byte != b'r' || dcs.params != [1] {
+                    continue;
+                }
+                let data = String::from_utf8_lossy(&dcs.data);
+                let (key, value) = data.split_once('=').unwrap_or((&data, ""));
+                if hex::decode(key).ok().as_deref() != Some(name.as_bytes()) {
+                    continue;
+                }
+                let value = hex::decode(value).unwrap_or_default();
+                return Ok(Some(String::from_utf8_lossy(&value).into()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Probe for 24-bit color support using XTGETTCAP to look
+    /// for the `RGB` or `Tc` capabilities
+    pub fn true_color(&mut self) -> ProbeResult<bool> {
+        Ok(self.xt_get_tcap("RGB")?.is_some() || self.xt_get_tcap("Tc")?.is_some())
+    }
+
+    fn dynamic_color(&mut self, which: DynamicColorNumber) -> ProbeResult<Option<SrgbaTuple>> {
+        let query = OperatingSystemCommand::ChangeDynamicColors(which, vec![ColorOrQuery::Query]);
+        Ok(self
+            .query(&query.to_string())?
+            .into_iter()
+            .find_map(|action| match action {
+                Action::OperatingSystemCommand(osc) => match *osc {
+                    OperatingSystemCommand::ChangeDynamicColors(number, colors)
+                        if number == which =>
+                    {
+                        match colors.first() {
+                            Some(ColorOrQuery::Color(color)) => Some(*color),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                },
+                _ => None,
+            }))
+    }
+
+    /// Probe the default foreground color using OSC 10
+    pub fn foreground_color(&mut self) -> ProbeResult<Option<SrgbaTuple>> {
+        self.dynamic_color(DynamicColorNumber::TextForegroundColor)
+    }
+
+    /// Probe the default background color using OSC 11
+    pub fn background_color(&mut self) -> ProbeResult<Option<SrgbaTuple>> {
+        self.dynamic_color(DynamicColorNumber::TextBackgroundColor)
+    }
+
+    /// Run all of the probes and collect the results.
+    /// When running inside tmux, the graphics probes are passed
+    /// through to the outer terminal, as that is what will
+    /// ultimately be rendering the images.
+    ///
+    /// Fails with `ProbeError::NoResponse` if the terminal doesn't
+    /// answer XTVERSION.  Once it has, a later probe that isn't answered
+    /// in time, perhaps because of latency over ssh, is recorded as None
+    /// so that the other results are kept.
+    pub fn probe_all(&mut self) -> ProbeResult<ProbeResults> {
+        let xt_version = match self.xt_version() {
+            Ok(version) => Some(version),
+            // If the terminal doesn't respond to this, it isn't going
+            // to respond to the others either, so don't wait for them
+            Err(ProbeError::NoResponse) => return Err(ProbeError::NoResponse),
+            Err(_) => None,
+        };
+        let is_tmux = xt_version.as_ref().map(XtVersion::is_tmux).unwrap_or(false);
+
+        let kitty_keyboard = unanswered_as_none(self.kitty_keyboard())?.flatten();
+        let synchronized_output = unanswered_as_none(self.synchronized_output())?;
+        let true_color = unanswered_as_none(self.true_color())?;
+        let foreground = unanswered_as_none(self.foreground_color())?.flatten();
+        let background = unanswered_as_none(self.background_color())?.flatten();
+
+        let tmux_passthrough = self.tmux_passthrough;
+        self.tmux_passthrough |= is_tmux;
+        let kitty_graphics = self.kitty_graphics();
+        self.tmux_passthrough = tmux_passthrough;
+        let kitty_graphics = unanswered_as_none(kitty_graphics)?;
+        // DA1 is the sentinel for every other probe, so it is never
+        // passed through and reflects tmux's own answer.
+        let sixel = unanswered_as_none(self.sixel())?;
+
+        Ok(ProbeResults {
+            xt_version,
+            kitty_keyboard,
+            kitty_graphics,
+            sixel,
+            true_color,
+            synchronized_output,
+            foreground,
+            background,
+        })
+    }
+
+    /// Probe for the XTVERSION response
+    pub fn xt_version(&mut self) -> ProbeResult<XtVersion> {
+        self.xt_version_impl(false)
+    }
+
+    /// Assuming that we are talking to tmux, probe for the XTVERSION response
+    /// of its outer terminal.
+    pub fn outer_xt_version(&mut self) -> ProbeResult<XtVersion> {
+        self.xt_version_impl(true)
+    }
+
+    fn xt_version_impl(&mut self, tmux_escape: bool) -> ProbeResult<XtVersion> {
+        let xt_version = CSI::Device(Box::new(Device::RequestTerminalNameAndVersion));
+
+        let tmux_passthrough = self.tmux_passthrough;
+        self.tmux_passthrough = tmux_escape;
+        let actions = self.query(&xt_version.to_string());
+        self.tmux_passthrough = tmux_passthrough;
+
+        let mut term = vec![];
+        for action in actions? {
+            if let Action::DeviceControl(DeviceControlMode::Data(b)) = action {
+                term.push(b);
+            }
         }
 
         Ok(XtVersion(String::from_utf8_lossy(&term).into()))
     }
 
     /// Probe the terminal and determine the ScreenSize.
-    pub fn screen_size(&mut self) -> Result<ScreenSize> {
+    pub fn screen_size(&mut self) -> ProbeResult<ScreenSize> {
         let xt_version = self.xt_version()?;
 
         let is_tmux = xt_version.is_tmux();
 
         // some tmux versions have their rows/cols swapped in ReportTextAreaSizeCells
-        let swapped_cols_rows = match xt_version.full_version() {
-            "tmux 3.2" | "tmux 3.2a" | "tmux 3.3" | "tmux 3.3a" => true,
-            _ => false,
-        };
+        let swapped_cols_rows = matches!(
+            xt_version.full_version(),
+            "tmux 3.2" | "tmux 3.2a" | "tmux 3.3" | "tmux 3.3a"
+        );
 
         let query_cells = CSI::Window(Box::new(Window::ReportTextAreaSizeCells));
         let query_pixels = CSI::Window(Box::new(Window::ReportCellSizePixels));
-        let dev_attributes = CSI::Device(Box::new(Device::RequestPrimaryDeviceAttributes));
 
-        write!(self.write, "{query_cells}{query_pixels}")?;
+        let mut query = format!("{query_cells}{query_pixels}");
 
         // tmux refuses to directly support responding to 14t or 16t queries
         // for pixel dimensions, so we need to jump through to the outer
         // terminal and see what it says
         if is_tmux {
-            write!(self.write, "{TMUX_BEGIN}{query_pixels}{TMUX_END}")?;
+            query.push_str(&tmux_wrap(&query_pixels.to_string()));
         }
 
-        if is_tmux || cfg!(windows) {
-            self.write.flush()?;
-            // I really wanted to avoid a delay here, but tmux and conpty will
-            // both re-order the response to dev_attributes before sending the
-            // response for the passthru of query_pixels if we don't delay.
-            // The delay is potentially imperfect for things like a laggy ssh
-            // connection. The consequence of the timing being wrong is that
-            // we won't be able to reason about the pixel dimensions, which is
-            // "OK", but that was kinda the whole point of probing this way
-            // vs. termios.
+        // I really wanted to avoid a delay here, but tmux and conpty will
+        // both re-order the response to dev_attributes before sending the
+        // response for the passthru of query_pixels if we don't delay.
+        // The delay is potentially imperfect for things like a laggy ssh
+        // connection. The consequence of the timing being wrong is that
+        // we won't be able to reason about the pixel dimensions, which is
+        // "OK", but that was kinda the whole point of probing this way
+        // vs. termios.
+        let delay = is_tmux || cfg!(windows);
 
-            std::thread::sleep(std::time::Duration::from_millis(100));
-        }
-
-        write!(self.write, "{dev_attributes}")?;
-        self.write.flush()?;
-
-        let mut parser = Parser::new();
-        let mut done = false;
         let mut size = ScreenSize {
             rows: 0,
             cols: 0,
@@ -175,61 +870,41 @@ impl<'a> ProbeCapabilities<'a> {
             ypixel: 0,
         };
 
-        while !done {
-            let mut byte = [0u8];
-            self.read.read(&mut byte)?;
-
-            parser.parse(&byte, |action| {
-                // print!("{action:?}\r\n");
-                match action {
-                    // ConPTY appears to trigger 1 or more xtversion queries
-                    // to wezterm in response to this probe, so we need to
-                    // prepared to accept and discard data of that shape
-                    // here, so that we keep going until we get our reports
-                    Action::DeviceControl(_) => {}
-                    Action::Esc(Esc::Code(EscCode::StringTerminator)) => {}
-
-                    // and now look for the actual responses we're expecting
-                    Action::CSI(csi) => match csi {
-                        CSI::Window(win) => match *win {
-                            Window::ResizeWindowCells { width, height } => {
-                                let width = width.unwrap_or(1);
-                                let height = height.unwrap_or(1);
-                                if width > 0 && height > 0 {
-                                    let width = width as usize;
-                                    let height = height as usize;
-                                    if swapped_cols_rows {
-                                        size.rows = width;
-                                        size.cols = height;
-                                    } else {
-                                        size.rows = height;
-                                        size.cols = width;
-                                    }
-                                }
+        for action in self.send_query(&query, delay)? {
+            // ConPTY appears to trigger 1 or more xtversion queries
+            // to wezterm in response to this probe; those arrive as
+            // DeviceControl actions and are skipped here along with
+            // anything else that isn't one of our reports
+            if let Action::CSI(CSI::Window(win)) = action {
+                match *win {
+                    Window::ResizeWindowCells { width, height } => {
+                        let width = width.unwrap_or(1);
+                        let height = height.unwrap_or(1);
+                        if width > 0 && height > 0 {
+                            let width = width as usize;
+                            let height = height as usize;
+                            if swapped_cols_rows {
+                                size.rows = width;
+                                size.cols = height;
+                            } else {
+                                size.rows = height;
+                                size.cols = width;
                             }
-                            Window::ReportCellSizePixelsResponse { width, height } => {
-                                let width = width.unwrap_or(1);
-                                let height = height.unwrap_or(1);
-                                if width > 0 && height > 0 {
-                                    let width = width as usize;
-                                    let height = height as usize;
-                                    size.xpixel = width;
-                                    size.ypixel = height;
-                                }
-                            }
-                            _ => {
-                                done = true;
-                            }
-                        },
-                        _ => {
-                            done = true;
                         }
-                    },
-                    _ => {
-                        done = true;
                     }
+                    Window::ReportCellSizePixelsResponse { width, height } => {
+                        let width = width.unwrap_or(1);
+                        let height = height.unwrap_or(1);
+                        if width > 0 && height > 0 {
+                            let width = width as usize;
+                            let height = height as usize;
+                            size.xpixel = width;
+                            size.ypixel = height;
+                        }
+                    }
+                    _ => {}
                 }
-            });
+            }
         }
 
         if size.rows == 0 && size.cols == 0 {
@@ -286,6 +286,12 @@ impl From<AnsiColor> for ColorAttribute {
     }
 }
 
+impl From<SrgbaTuple> for ColorAttribute {
+    fn from(color: SrgbaTuple) -> Self {
+        ColorAttribute::TrueColorWithDefaultFallback(color)
+    }
+}
+
 impl From<ColorSpec> for ColorAttribute {
     fn from(spec: ColorSpec) -> Self {
         match spec {
@@ -234,6 +234,31 @@ pub enum DeviceAttributes {
     Vt220(DeviceAttributeFlags),
     Vt320(DeviceAttributeFlags),
     Vt420(DeviceAttributeFlags),
+    Vt500(DeviceAttributeFlags),
+}
+
+impl DeviceAttributes {
+    /// Returns the feature flags reported alongside the device class,
+    /// or None for the older device classes that report no flags.
+    pub fn flags(&self) -> Option<&DeviceAttributeFlags> {
+        match self {
+            Self::Vt220(flags) | Self::Vt320(flags) | Self::Vt420(flags) | Self::Vt500(flags) => {
+                Some(flags)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns true if the terminal reported support for sixel graphics
+    pub fn has_sixel(&self) -> bool {
+        self.flags()
+            .map(|flags| {
+                flags
+                    .attributes
+                    .contains(&DeviceAttribute::Code(DeviceAttributeCodes::SixelGraphics))
+            })
+            .unwrap_or(false)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -373,6 +398,7 @@ impl Display for Device {
             Device::DeviceAttributes(DeviceAttributes::Vt220(attr)) => attr.emit(f, "?62")?,
             Device::DeviceAttributes(DeviceAttributes::Vt320(attr)) => attr.emit(f, "?63")?,
             Device::DeviceAttributes(DeviceAttributes::Vt420(attr)) => attr.emit(f, "?64")?,
+            Device::DeviceAttributes(DeviceAttributes::Vt500(attr)) => attr.emit(f, "?65")?,
             Device::SoftReset => write!(f, "!p")?,
             Device::RequestPrimaryDeviceAttributes => write!(f, "c")?,
             Device::RequestSecondaryDeviceAttributes => write!(f, ">c")?,
@@ -700,6 +726,16 @@ pub enum Mode {
     SetMode(TerminalMode),
     ResetMode(TerminalMode),
     QueryMode(TerminalMode),
+    /// DECRPM: the terminal's response to QueryDecPrivateMode
+    ReportDecPrivateMode {
+        mode: DecPrivateMode,
+        setting: ModeSetting,
+    },
+    /// DECRPM: the terminal's response to QueryMode
+    ReportMode {
+        mode: TerminalMode,
+        setting: ModeSetting,
+    },
     XtermKeyMode {
         resource: XtermKeyModifierResource,
         value: Option<i64>,
@@ -743,6 +779,12 @@ impl Display for Mode {
                 write!(f, "?{}$p", mode.to_u16().ok_or_else(|| FmtError)?)
             }
             Mode::QueryMode(TerminalMode::Unspecified(mode)) => write!(f, "?{}$p", mode),
+            Mode::ReportDecPrivateMode { mode, setting } => {
+                emit!(format_args!(";{}$y", *setting as u8), mode)
+            }
+            Mode::ReportMode { mode, setting } => {
+                emit_mode!(format_args!(";{}$y", *setting as u8), mode)
+            }
             Mode::XtermKeyMode { resource, value } => {
                 write!(
                     f,
@@ -765,6 +807,29 @@ impl Display for Mode {
     }
 }
 
+/// The state of a mode, as reported by DECRPM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum ModeSetting {
+    NotRecognized = 0,
+    Set = 1,
+    Reset = 2,
+    PermanentlySet = 3,
+    PermanentlyReset = 4,
+}
+
+impl ModeSetting {
+    /// Returns true if the mode is recognized by the terminal,
+    /// regardless of whether it is currently set.
+    pub fn is_recognized(self) -> bool {
+        self != ModeSetting::NotRecognized
+    }
+
+    /// Returns true if the mode is either set or permanently set
+    pub fn is_set(self) -> bool {
+        matches!(self, ModeSetting::Set | ModeSetting::PermanentlySet)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DecPrivateMode {
     Code(DecPrivateModeCode),
@@ -1746,6 +1811,14 @@ impl<'a> CSIParser<'a> {
             | ('p', [CsiParam::P(b'?'), CsiParam::Integer(_), CsiParam::P(b'$')]) => {
                 self.decrqm(params)
             }
+            (
+                'y',
+                [CsiParam::Integer(_), CsiParam::P(b';'), CsiParam::Integer(_), CsiParam::P(b'$')],
+            )
+            | (
+                'y',
+                [CsiParam::P(b'?'), CsiParam::Integer(_), CsiParam::P(b';'), CsiParam::Integer(_), CsiParam::P(b'$')],
+            ) => self.decrpm(params),
             ('h', [CsiParam::P(b'?'), ..]) => self
                 .dec(self.focus(params, 1, 0))
                 .map(|mode| CSI::Mode(Mode::SetDecPrivateMode(mode))),
@@ -2168,6 +2241,13 @@ impl<'a> CSIParser<'a> {
                     DeviceAttributeFlags::from_params(&params[2..]),
                 )),
             )),
+            [_, CsiParam::Integer(65), ..] => Ok(self.advance_by(
+                params.len(),
+                params,
+                Device::DeviceAttributes(DeviceAttributes::Vt500(
+                    DeviceAttributeFlags::from_params(&params[2..]),
+                )),
+            )),
             _ => Err(()),
         }
     }
@@ -2263,6 +2343,30 @@ impl<'a> CSIParser<'a> {
         }))
     }
 
+    fn decrpm(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        Ok(CSI::Mode(match params {
+            [CsiParam::Integer(p), CsiParam::P(b';'), CsiParam::Integer(s), CsiParam::P(b'$')] => {
+                Mode::ReportMode {
+                    mode: match FromPrimitive::from_i64(*p) {
+                        None => TerminalMode::Unspecified(p.to_u16().ok_or(())?),
+                        Some(mode) => TerminalMode::Code(mode),
+                    },
+                    setting: FromPrimitive::from_i64(*s).ok_or(())?,
+                }
+            }
+            [CsiParam::P(b'?'), CsiParam::Integer(p), CsiParam::P(b';'), CsiParam::Integer(s), CsiParam::P(b'$')] => {
+                Mode::ReportDecPrivateMode {
+                    mode: match FromPrimitive::from_i64(*p) {
+                        None => DecPrivateMode::Unspecified(p.to_u16().ok_or(())?),
+                        Some(mode) => DecPrivateMode::Code(mode),
+                    },
+                    setting: FromPrimitive::from_i64(*s).ok_or(())?,
+                }
+            }
+            _ => return Err(()),
+        }))
+    }
+
     fn dec(&mut self, params: &'a [CsiParam]) -> Result<DecPrivateMode, ()> {
         match params {
             [CsiParam::Integer(p0), ..] => match FromPrimitive::from_i64(*p0) {
@@ -9,7 +9,6 @@
 use crate::tmux_cc::Event;
 use num_derive::*;
 use std::fmt::{Display, Error as FmtError, Formatter, Write as FmtWrite};
-use wezterm_color_types::LinearRgba;
 
 pub mod apc;
 pub mod csi;
@@ -336,7 +335,7 @@ impl Display for Sixel {
         if self.pixel_width.is_some() {
             write!(
                 f,
-                "\x1bP;{}{}q\"{};{};{};{}",
+                "\x1bP0;{}{}q\"{};{};{};{}",
                 if self.background_is_transparent { 1 } else { 0 },
                 match self.horizontal_grid_size {
                     Some(h) => format!(";{}", h),
@@ -392,7 +391,7 @@ pub enum SixelData {
     Repeat { repeat_count: u32, data: SixelValue },
 
     /// Set the specified color map entry to the specified
-    /// linear RGB color value
+    /// sRGB color value
     DefineColorMapRGB {
         color_number: u16,
         rgb: crate::color::RgbColor,
@@ -428,14 +427,18 @@ impl Display for SixelData {
                 write!(f, "!{}{}", repeat_count, (data + 0x3f) as char)
             }
             Self::DefineColorMapRGB { color_number, rgb } => {
-                let LinearRgba(r, g, b, _) = rgb.to_linear_tuple_rgba();
+                // The percentages are interpreted as sRGB by the parser
+                // and by terminals, so round rather than truncate to
+                // allow the value to round trip.
+                let (r, g, b) = rgb.to_tuple_rgb8();
+                let percent = |v: u8| (v as f32 * 100. / 255.).round() as u8;
                 write!(
                     f,
                     "#{};2;{};{};{}",
                     color_number,
-                    (r * 100.) as u8,
-                    (g * 100.) as u8,
-                    (b * 100.0) as u8
+                    percent(r),
+                    percent(g),
+                    percent(b)
                 )
             }
             Self::DefineColorMapHSL {
@@ -154,6 +154,13 @@ impl Parser {
         }
     }
 
+    /// Returns true if the parser is not part way through a sequence,
+    /// which means that the bytes parsed so far have been fully
+    /// translated into actions.
+    pub fn is_ground(&self) -> bool {
+        self.state_machine.is_ground()
+    }
+
     pub fn parse_as_vec(&mut self, bytes: &[u8]) -> Vec<Action> {
         let mut result = Vec::new();
         self.parse(bytes, |action| result.push(action));
@@ -190,9 +197,12 @@ struct Performer<'a, F: FnMut(Action) + 'a> {
 }
 
 fn is_short_dcs(intermediates: &[u8], byte: u8) -> bool {
-    if intermediates == &[b'$'] && byte == b'q' {
+    if intermediates == b"$" && byte == b'q' {
         // DECRQSS
         true
+    } else if intermediates == b"+" && byte == b'r' {
+        // XTGETTCAP response
+        true
     } else {
         false
     }
@@ -341,8 +351,9 @@ mod test {
     use crate::cell::{Intensity, Underline};
     use crate::color::ColorSpec;
     use crate::escape::csi::{
-        CharacterPath, DecPrivateMode, DecPrivateModeCode, Device, Mode, Sgr, Window, XtSmGraphics,
-        XtSmGraphicsItem, XtermKeyModifierResource,
+        CharacterPath, DecPrivateMode, DecPrivateModeCode, Device, Mode, ModeSetting, Sgr,
+        TerminalMode, TerminalModeCode, Window, XtSmGraphics, XtSmGraphicsItem,
+        XtermKeyModifierResource,
     };
     use crate::escape::{EscCode, OneBased};
     use k9::assert_equal as assert_eq;
@@ -754,6 +765,50 @@ mod test {
         );
     }
 
+    #[test]
+    fn xtgettcap_response() {
+        let mut p = Parser::new();
+        assert_eq!(
+            p.parse_as_vec(b"\x1bP1+r5463=\x1b\\"),
+            vec![
+                Action::DeviceControl(DeviceControlMode::ShortDeviceControl(Box::new(
+                    ShortDeviceControl {
+                        params: vec![1],
+                        intermediates: vec![b'+'],
+                        byte: b'r',
+                        data: b"5463=".to_vec(),
+                    }
+                ))),
+                Action::Esc(Esc::Code(EscCode::StringTerminator)),
+            ]
+        );
+    }
+
+    #[test]
+    fn decrpm() {
+        assert_eq!(
+            round_trip_parse("\x1b[?2026;2$y"),
+            vec![Action::CSI(CSI::Mode(Mode::ReportDecPrivateMode {
+                mode: DecPrivateMode::Code(DecPrivateModeCode::SynchronizedOutput),
+                setting: ModeSetting::Reset,
+            }))]
+        );
+        assert_eq!(
+            round_trip_parse("\x1b[?9999;0$y"),
+            vec![Action::CSI(CSI::Mode(Mode::ReportDecPrivateMode {
+                mode: DecPrivateMode::Unspecified(9999),
+                setting: ModeSetting::NotRecognized,
+            }))]
+        );
+        assert_eq!(
+            round_trip_parse("\x1b[4;1$y"),
+            vec![Action::CSI(CSI::Mode(Mode::ReportMode {
+                mode: TerminalMode::Code(TerminalModeCode::Insert),
+                setting: ModeSetting::Set,
+            }))]
+        );
+    }
+
     #[test]
     fn bidi_modes() {
         assert_eq!(
@@ -41,7 +41,7 @@ pub struct KeyCodeEncodeModes {
 
 #[cfg(windows)]
 use winapi::um::wincon::{
-    INPUT_RECORD, KEY_EVENT, KEY_EVENT_RECORD, MOUSE_EVENT, MOUSE_EVENT_RECORD,
+    FOCUS_EVENT, INPUT_RECORD, KEY_EVENT, KEY_EVENT_RECORD, MOUSE_EVENT, MOUSE_EVENT_RECORD,
     WINDOW_BUFFER_SIZE_EVENT, WINDOW_BUFFER_SIZE_RECORD,
 };
 
@@ -61,7 +61,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum InputEvent {
     Key(KeyEvent),
     Mouse(MouseEvent),
@@ -76,6 +76,17 @@ pub enum InputEvent {
     Paste(String),
     /// The program has woken the input thread.
     Wake,
+    /// The terminal gained focus.  Only reported once focus
+    /// reporting has been enabled with `Terminal::enable_focus_reporting`.
+    FocusGained,
+    /// The terminal lost focus
+    FocusLost,
+    /// A reply sent by the terminal in response to a query made by
+    /// the application, such as device attributes, a mode report
+    /// or an OSC color report.
+    /// `Action` isn't `Eq`, because color reports hold floating point
+    /// values, so `InputEvent` only implements `PartialEq`.
+    TerminalResponse(Action),
 }
 
 #[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
@@ -104,6 +115,59 @@ pub struct KeyEvent {
 
     /// Which modifiers are down
     pub modifiers: Modifiers,
+
+    /// Whether the key was pressed, auto-repeated or released.
+    /// Terminals only report repeats and releases when the kitty
+    /// keyboard protocol is enabled with `REPORT_EVENT_TYPES`.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub kind: KeyEventKind,
+
+    /// The shifted version of `key`, if reported by the terminal
+    /// via the kitty `REPORT_ALTERNATE_KEYS` flag
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub shifted_key: Option<KeyCode>,
+
+    /// The key in the same position on a standard PC-101 layout,
+    /// if reported by the terminal via the kitty `REPORT_ALTERNATE_KEYS`
+    /// flag.  Useful for matching shortcuts independently of the
+    /// active keyboard layout.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub base_layout_key: Option<KeyCode>,
+
+    /// The text generated by the key, if reported by the terminal
+    /// via the kitty `REPORT_ASSOCIATED_TEXT` flag
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub text: Option<String>,
+}
+
+impl KeyEvent {
+    /// Construct a key press event without any of the additional
+    /// information that the kitty keyboard protocol can report
+    pub const fn new(key: KeyCode, modifiers: Modifiers) -> Self {
+        Self {
+            key,
+            modifiers,
+            kind: KeyEventKind::Press,
+            shifted_key: None,
+            base_layout_key: None,
+            text: None,
+        }
+    }
+
+    /// Returns true if this event represents the key being released
+    pub fn is_release(&self) -> bool {
+        self.kind == KeyEventKind::Release
+    }
+}
+
+/// Distinguishes the different kinds of key event
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyEventKind {
+    #[default]
+    Press,
+    Repeat,
+    Release,
 }
 
 /// Which key is pressed.  Not all of these are probable to appear
@@ -619,6 +683,327 @@ fn csi_u_encode(
     Ok(())
 }
 
+/// The outcome of trying to decode a kitty keyboard protocol report
+/// from the start of the input buffer
+enum KittyKeyReport {
+    /// The buffer doesn't start with a kitty key report
+    NotKitty,
+    /// The buffer holds a prefix of what may be a kitty key report
+    NeedData,
+    /// A complete report of the given length.  The event is `None`
+    /// for keys that have no `KeyCode` equivalent.
+    Key(usize, Option<KeyEvent>),
+}
+
+/// Decodes the kitty keyboard protocol form
+/// `CSI key[:shifted[:base]] ; mods[:event] ; text u`, along with the
+/// legacy `~` and letter terminated forms when they carry fields that
+/// only the kitty protocol produces, such as an event type.
+/// Legacy sequences without those fields are left for the key map.
+/// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>
+fn decode_kitty_key(buf: &[u8]) -> KittyKeyReport {
+    if buf.len() < 2 || buf[0] != 0x1b || buf[1] != b'[' {
+        return KittyKeyReport::NotKitty;
+    }
+
+    // Semicolon separated fields of colon separated sub-parameters
+    let mut fields: Vec<Vec<Option<u32>>> = vec![vec![None]];
+    for (idx, &b) in buf.iter().enumerate().skip(2) {
+        match b {
+            b'0'..=b'9' => {
+                let part = fields.last_mut().unwrap().last_mut().unwrap();
+                match part
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|v| v.checked_add((b - b'0') as u32))
+                {
+                    Some(v) => *part = Some(v),
+                    None => return KittyKeyReport::NotKitty,
+                }
+            }
+            b':' => fields.last_mut().unwrap().push(None),
+            b';' => fields.push(vec![None]),
+            b'u' | b'~' | b'A' | b'B' | b'C' | b'D' | b'E' | b'F' | b'H' | b'P' | b'Q' | b'S' => {
+                return match kitty_key_event(&fields, b) {
+                    Some(event) => KittyKeyReport::Key(idx + 1, event),
+                    None => KittyKeyReport::NotKitty,
+                };
+            }
+            _ => return KittyKeyReport::NotKitty,
+        }
+    }
+
+    KittyKeyReport::NeedData
+}
+
+/// Interprets the parameters of a kitty key report.
+/// Returns None if the parameters aren't in the kitty form.
+fn kitty_key_event(fields: &[Vec<Option<u32>>], final_byte: u8) -> Option<Option<KeyEvent>> {
+    let param = |field: usize, sub: usize| fields.get(field)?.get(sub).copied().flatten();
+
+    // Alternate keys and associated text are only reported in the
+    // `CSI u` form; the other forms are kitty specific only when they
+    // carry an event type, otherwise they are plain xterm sequences.
+    let has_event_type = fields.get(1).is_some_and(|f| f.len() > 1);
+    if fields.len() > 3
+        || fields[0].len() > 3
+        || fields.get(1).is_some_and(|f| f.len() > 2)
+        || (final_byte != b'u' && (!has_event_type || fields.len() > 2 || fields[0].len() > 1))
+    {
+        return None;
+    }
+
+    let code = param(0, 0);
+    let key = match final_byte {
+        b'u' => kitty_functional_key(code?),
+        b'~' => legacy_tilde_key(code?),
+        letter => {
+            if code.unwrap_or(1) != 1 {
+                return None;
+            }
+            Some(match letter {
+                b'A' => KeyCode::UpArrow,
+                b'B' => KeyCode::DownArrow,
+                b'C' => KeyCode::RightArrow,
+                b'D' => KeyCode::LeftArrow,
+                b'E' => KeyCode::KeyPadBegin,
+                b'F' => KeyCode::End,
+                b'H' => KeyCode::Home,
+                b'P' => KeyCode::Function(1),
+                b'Q' => KeyCode::Function(2),
+                b'S' => KeyCode::Function(4),
+                _ => unreachable!(),
+            })
+        }
+    };
+    let key = match key {
+        Some(key) => key,
+        None => return Some(None),
+    };
+
+    let kind = match param(1, 1) {
+        Some(2) => KeyEventKind::Repeat,
+        Some(3) => KeyEventKind::Release,
+        _ => KeyEventKind::Press,
+    };
+
+    let text = fields.get(2).and_then(|f| {
+        let text: String = f
+            .iter()
+            .filter_map(|c| std::char::from_u32((*c)?))
+            .collect();
+        if text.is_empty() {
+            None
+        } else {
+            Some(text)
+        }
+    });
+
+    Some(Some(KeyEvent {
+        key,
+        modifiers: kitty_modifiers(param(1, 0).unwrap_or(1)),
+        kind,
+        shifted_key: param(0, 1).and_then(kitty_functional_key),
+        base_layout_key: param(0, 2).and_then(kitty_functional_key),
+        text,
+    }))
+}
+
+/// The outcome of trying to decode a reply to a query from the start
+/// of the input buffer
+enum TerminalResponseReport {
+    /// The buffer doesn't start with a reply
+    NotResponse,
+    /// The buffer holds a prefix of what may be a reply
+    NeedData,
+    /// A complete reply of the given length
+    Response(usize),
+}
+
+/// Recognizes the replies that terminals send to the queries made
+/// by `ProbeCapabilities`: `CSI ? ... c` (DA1), `CSI ? ... $y` (DECRQM),
+/// `CSI ? ... u` (kitty keyboard flags), DCS `>|` (XTVERSION), `+r`
+/// (XTGETTCAP) and `$r` (DECRQSS), OSC 10/11 color reports and kitty
+/// graphics APC replies.  Only these complete shapes are matched, so
+/// that Alt modified keys such as Alt+P or `Alt+\` are left for the
+/// key map.
+fn decode_terminal_response(buf: &[u8]) -> TerminalResponseReport {
+    // A lone ESC followed by a single byte is an Alt modified key
+    // until proven otherwise
+    if buf.len() < 3 || buf[0] != 0x1b {
+        return TerminalResponseReport::NotResponse;
+    }
+    match buf[1] {
+        b'[' => decode_csi_response(buf),
+        b'P' => decode_string_response(buf, &[b">|", b"0+r", b"1+r", b"0$r", b"1$r"], false),
+        b']' => decode_string_response(buf, &[b"10;", b"11;"], true),
+        b'_' => decode_string_response(buf, &[b"G"], true),
+        _ => TerminalResponseReport::NotResponse,
+    }
+}
+
+/// Decodes `CSI ? params c`, `CSI ? params $y` and `CSI ? params u`
+fn decode_csi_response(buf: &[u8]) -> TerminalResponseReport {
+    if buf[2] != b'?' {
+        return TerminalResponseReport::NotResponse;
+    }
+    for (idx, &b) in buf.iter().enumerate().skip(3) {
+        match b {
+            b'0'..=b'9' | b';' | b':' => {}
+            b'c' | b'u' => return TerminalResponseReport::Response(idx + 1),
+            b'$' => {
+                return match buf.get(idx + 1) {
+                    Some(b'y') => TerminalResponseReport::Response(idx + 2),
+                    Some(_) => TerminalResponseReport::NotResponse,
+                    None => TerminalResponseReport::NeedData,
+                }
+            }
+            _ => return TerminalResponseReport::NotResponse,
+        }
+    }
+    TerminalResponseReport::NeedData
+}
+
+/// Decodes a DCS, OSC or APC reply: the two byte introducer, one of
+/// `prefixes` and a payload without control characters, terminated
+/// by ST or BEL.
+fn decode_string_response(
+    buf: &[u8],
+    prefixes: &[&[u8]],
+    need_payload: bool,
+) -> TerminalResponseReport {
+    let body = &buf[2..];
+    let start = match prefixes.iter().find(|prefix| body.starts_with(prefix)) {
+        Some(prefix) => 2 + prefix.len(),
+        None if prefixes.iter().any(|prefix| prefix.starts_with(body)) => {
+            return TerminalResponseReport::NeedData
+        }
+        None => return TerminalResponseReport::NotResponse,
+    };
+    for (idx, &b) in buf.iter().enumerate().skip(start) {
+        let len = match (b, buf.get(idx + 1)) {
+            (0x07, _) => idx + 1,
+            (0x1b, Some(b'\\')) => idx + 2,
+            (0x1b, None) => return TerminalResponseReport::NeedData,
+            (0x00..=0x1f, _) | (0x7f, _) => return TerminalResponseReport::NotResponse,
+            _ => continue,
+        };
+        if need_payload && idx == start {
+            return TerminalResponseReport::NotResponse;
+        }
+        return TerminalResponseReport::Response(len);
+    }
+    TerminalResponseReport::NeedData
+}
+
+/// Decodes the kitty modifier parameter, which is one plus a bitmask.
+/// Hyper, meta, caps lock and num lock have no `Modifiers` equivalent
+/// and are ignored.
+fn kitty_modifiers(encoded: u32) -> Modifiers {
+    let bits = encoded.saturating_sub(1);
+    let mut mods = Modifiers::NONE;
+    if bits & 1 != 0 {
+        mods |= Modifiers::SHIFT;
+    }
+    if bits & 2 != 0 {
+        mods |= Modifiers::ALT;
+    }
+    if bits & 4 != 0 {
+        mods |= Modifiers::CTRL;
+    }
+    if bits & 8 != 0 {
+        mods |= Modifiers::SUPER;
+    }
+    mods
+}
+
+/// Maps the key number from a kitty `CSI ... u` report to a KeyCode.
+/// Keys without an equivalent KeyCode yield None.
+fn kitty_functional_key(code: u32) -> Option<KeyCode> {
+    use KeyCode::*;
+    Some(match code {
+        9 => Tab,
+        13 => Enter,
+        27 => Escape,
+        127 => Backspace,
+        57358 => CapsLock,
+        57359 => ScrollLock,
+        57360 => NumLock,
+        57361 => PrintScreen,
+        57362 => Pause,
+        57363 => Menu,
+        57376..=57398 => Function((code - 57376 + 13) as u8),
+        57399 => Numpad0,
+        57400 => Numpad1,
+        57401 => Numpad2,
+        57402 => Numpad3,
+        57403 => Numpad4,
+        57404 => Numpad5,
+        57405 => Numpad6,
+        57406 => Numpad7,
+        57407 => Numpad8,
+        57408 => Numpad9,
+        57409 => Decimal,
+        57410 => Divide,
+        57411 => Multiply,
+        57412 => Subtract,
+        57413 => Add,
+        57414 => Enter,
+        57415 => Char('='),
+        57416 => Separator,
+        57417 => LeftArrow,
+        57418 => RightArrow,
+        57419 => UpArrow,
+        57420 => DownArrow,
+        57421 => KeyPadPageUp,
+        57422 => KeyPadPageDown,
+        57423 => KeyPadHome,
+        57424 => KeyPadEnd,
+        57425 => Insert,
+        57426 => Delete,
+        57427 => KeyPadBegin,
+        57428..=57430 => MediaPlayPause,
+        57432 => MediaStop,
+        57435 => MediaNextTrack,
+        57436 => MediaPrevTrack,
+        57438 => VolumeDown,
+        57439 => VolumeUp,
+        57440 => VolumeMute,
+        57441 => LeftShift,
+        57442 => LeftControl,
+        57443 => LeftAlt,
+        57444 => LeftWindows,
+        57445 | 57451 => Hyper,
+        57446 | 57452 => Meta,
+        57447 => RightShift,
+        57448 => RightControl,
+        57449 => RightAlt,
+        57450 => RightWindows,
+        // The remainder of the private use area that kitty allocates from
+        57344..=63743 => return None,
+        _ => Char(std::char::from_u32(code)?),
+    })
+}
+
+/// Maps the number from a `CSI number ~` key report to a KeyCode
+fn legacy_tilde_key(code: u32) -> Option<KeyCode> {
+    use KeyCode::*;
+    Some(match code {
+        2 => Insert,
+        3 => Delete,
+        5 => PageUp,
+        6 => PageDown,
+        7 => Home,
+        8 => End,
+        11..=15 => Function((code - 10) as u8),
+        17..=21 => Function((code - 11) as u8),
+        23 | 24 => Function((code - 12) as u8),
+        29 => Menu,
+        57427 => KeyPadBegin,
+        _ => return None,
+    })
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum InputState {
     Normal,
@@ -784,10 +1169,7 @@ mod windows {
                 }
             }
 
-            let input_event = InputEvent::Key(KeyEvent {
-                key: key_code,
-                modifiers,
-            });
+            let input_event = InputEvent::Key(KeyEvent::new(key_code, modifiers));
             for _ in 0..event.wRepeatCount {
                 callback(input_event.clone());
             }
@@ -866,6 +1248,13 @@ mod windows {
                         unsafe { record.Event.WindowBufferSizeEvent() },
                         callback,
                     ),
+                    FOCUS_EVENT => {
+                        if unsafe { record.Event.FocusEvent() }.bSetFocus != 0 {
+                            callback(InputEvent::FocusGained);
+                        } else {
+                            callback(InputEvent::FocusLost);
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -933,20 +1322,17 @@ impl InputParser {
             let ctrl = [alpha & 0x1f];
             map.insert(
                 &ctrl,
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Char((alpha as char).to_ascii_lowercase()),
-                    modifiers: Modifiers::CTRL,
-                }),
+                InputEvent::Key(KeyEvent::new(
+                    KeyCode::Char((alpha as char).to_ascii_lowercase()),
+                    Modifiers::CTRL,
+                )),
             );
 
             // ALT A-Z is often sent with a leading ESC
             let alt = [0x1b, alpha];
             map.insert(
                 &alt,
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Char(alpha as char),
-                    modifiers: Modifiers::ALT,
-                }),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char(alpha as char), Modifiers::ALT)),
             );
         }
 
@@ -957,10 +1343,7 @@ impl InputParser {
                 let key = format!("\x1b[{}{}u", c, suffix);
                 map.insert(
                     key,
-                    InputEvent::Key(KeyEvent {
-                        key: KeyCode::Char(c as char),
-                        modifiers: *modifiers,
-                    }),
+                    InputEvent::Key(KeyEvent::new(KeyCode::Char(c as char), *modifiers)),
                 );
 
                 if !suffix.is_empty() {
@@ -968,16 +1351,16 @@ impl InputParser {
                     let key = format!("\x1b[27{};{}~", suffix, c);
                     map.insert(
                         key,
-                        InputEvent::Key(KeyEvent {
-                            key: match c {
+                        InputEvent::Key(KeyEvent::new(
+                            match c {
                                 8 | 0x7f => KeyCode::Backspace,
                                 0x1b => KeyCode::Escape,
                                 9 => KeyCode::Tab,
                                 10 | 13 => KeyCode::Enter,
                                 _ => KeyCode::Char(c as char),
                             },
-                            modifiers: *modifiers,
-                        }),
+                            *modifiers,
+                        )),
                     );
                 }
             }
@@ -996,20 +1379,11 @@ impl InputParser {
             let arrow = [0x1b, b'[', *dir];
             map.insert(
                 &arrow,
-                InputEvent::Key(KeyEvent {
-                    key: *keycode,
-                    modifiers: Modifiers::NONE,
-                }),
+                InputEvent::Key(KeyEvent::new(*keycode, Modifiers::NONE)),
             );
             for (suffix, modifiers) in modifier_combos_including_meta() {
                 let key = format!("\x1b[1{}{}", suffix, *dir as char);
-                map.insert(
-                    key,
-                    InputEvent::Key(KeyEvent {
-                        key: *keycode,
-                        modifiers: *modifiers,
-                    }),
-                );
+                map.insert(key, InputEvent::Key(KeyEvent::new(*keycode, *modifiers)));
             }
         }
         for &(keycode, dir) in &[
@@ -1023,13 +1397,7 @@ impl InputParser {
                 ([0x1b, b'[', dir], Modifiers::SHIFT),
                 ([0x1b, b'O', dir], Modifiers::CTRL),
             ] {
-                map.insert(
-                    &seq,
-                    InputEvent::Key(KeyEvent {
-                        key: keycode,
-                        modifiers: mods,
-                    }),
-                );
+                map.insert(&seq, InputEvent::Key(KeyEvent::new(keycode, mods)));
             }
         }
 
@@ -1043,20 +1411,11 @@ impl InputParser {
             let app = [0x1b, b'O', *dir];
             map.insert(
                 &app,
-                InputEvent::Key(KeyEvent {
-                    key: *keycode,
-                    modifiers: Modifiers::NONE,
-                }),
+                InputEvent::Key(KeyEvent::new(*keycode, Modifiers::NONE)),
             );
             for (suffix, modifiers) in modifier_combos {
                 let key = format!("\x1bO1{}{}", suffix, *dir as char);
-                map.insert(
-                    key,
-                    InputEvent::Key(KeyEvent {
-                        key: *keycode,
-                        modifiers: *modifiers,
-                    }),
-                );
+                map.insert(key, InputEvent::Key(KeyEvent::new(*keycode, *modifiers)));
             }
         }
 
@@ -1070,10 +1429,7 @@ impl InputParser {
             let key = [0x1b, b'O', *c];
             map.insert(
                 &key,
-                InputEvent::Key(KeyEvent {
-                    key: *keycode,
-                    modifiers: Modifiers::NONE,
-                }),
+                InputEvent::Key(KeyEvent::new(*keycode, Modifiers::NONE)),
             );
         }
 
@@ -1086,13 +1442,7 @@ impl InputParser {
         ] {
             for (suffix, modifiers) in modifier_combos_including_meta() {
                 let key = format!("\x1b[1{suffix}{code}", code = *c as char, suffix = suffix);
-                map.insert(
-                    key,
-                    InputEvent::Key(KeyEvent {
-                        key: *keycode,
-                        modifiers: *modifiers,
-                    }),
-                );
+                map.insert(key, InputEvent::Key(KeyEvent::new(*keycode, *modifiers)));
             }
         }
 
@@ -1115,10 +1465,7 @@ impl InputParser {
                     let key = format!("\x1b[{code}{suffix}~", code = n + offset, suffix = suffix);
                     map.insert(
                         key,
-                        InputEvent::Key(KeyEvent {
-                            key: KeyCode::Function(n),
-                            modifiers: *modifiers,
-                        }),
+                        InputEvent::Key(KeyEvent::new(KeyCode::Function(n), *modifiers)),
                     );
                 }
             }
@@ -1142,92 +1489,60 @@ impl InputParser {
                 (b'@', Modifiers::SHIFT | Modifiers::CTRL),
             ] {
                 let key = [0x1b, b'[', *c, *suffix];
-                map.insert(
-                    key,
-                    InputEvent::Key(KeyEvent {
-                        key: *keycode,
-                        modifiers: *modifiers,
-                    }),
-                );
+                map.insert(key, InputEvent::Key(KeyEvent::new(*keycode, *modifiers)));
             }
         }
 
         map.insert(
             &[0x7f],
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Backspace,
-                modifiers: Modifiers::NONE,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::Backspace, Modifiers::NONE)),
         );
 
         map.insert(
             &[0x8],
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Backspace,
-                modifiers: Modifiers::NONE,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::Backspace, Modifiers::NONE)),
         );
 
         map.insert(
             &[0x1b],
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Escape,
-                modifiers: Modifiers::NONE,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::Escape, Modifiers::NONE)),
         );
 
         map.insert(
             &[b'\t'],
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Tab,
-                modifiers: Modifiers::NONE,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::Tab, Modifiers::NONE)),
         );
         map.insert(
             b"\x1b[Z",
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Tab,
-                modifiers: Modifiers::SHIFT,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::Tab, Modifiers::SHIFT)),
         );
 
         map.insert(
             &[b'\r'],
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Enter,
-                modifiers: Modifiers::NONE,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::Enter, Modifiers::NONE)),
         );
         map.insert(
             &[b'\n'],
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Enter,
-                modifiers: Modifiers::NONE,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::Enter, Modifiers::NONE)),
         );
 
         map.insert(
             b"\x1b[200~",
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::InternalPasteStart,
-                modifiers: Modifiers::NONE,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::InternalPasteStart, Modifiers::NONE)),
         );
         map.insert(
             b"\x1b[201~",
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::InternalPasteEnd,
-                modifiers: Modifiers::NONE,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::InternalPasteEnd, Modifiers::NONE)),
         );
         map.insert(
             b"\x1b[",
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('['),
-                modifiers: Modifiers::ALT,
-            }),
+            InputEvent::Key(KeyEvent::new(KeyCode::Char('['), Modifiers::ALT)),
         );
 
+        // Focus reporting (DECSET 1004)
+        map.insert(b"\x1b[I", InputEvent::FocusGained);
+        map.insert(b"\x1b[O", InputEvent::FocusLost);
+
         map
     }
 
@@ -1286,27 +1601,27 @@ impl InputParser {
             ) => {
                 // The prior ESC was not part of an ALT sequence, so emit
                 // it before we start collecting for paste.
-                callback(InputEvent::Key(KeyEvent {
-                    key: KeyCode::Escape,
-                    modifiers: Modifiers::NONE,
-                }));
+                callback(InputEvent::Key(KeyEvent::new(
+                    KeyCode::Escape,
+                    Modifiers::NONE,
+                )));
                 self.state = InputState::Pasting(0);
             }
-            (InputState::EscapeMaybeAlt, InputEvent::Key(KeyEvent { key, modifiers })) => {
+            (InputState::EscapeMaybeAlt, InputEvent::Key(KeyEvent { key, modifiers, .. })) => {
                 // Treat this as ALT-key
                 self.state = InputState::Normal;
-                callback(InputEvent::Key(KeyEvent {
+                callback(InputEvent::Key(KeyEvent::new(
                     key,
-                    modifiers: modifiers | Modifiers::ALT,
-                }));
+                    modifiers | Modifiers::ALT,
+                )));
             }
             (InputState::EscapeMaybeAlt, event) => {
                 // The prior ESC was not part of an ALT sequence, so emit
                 // both it and the current event
-                callback(InputEvent::Key(KeyEvent {
-                    key: KeyCode::Escape,
-                    modifiers: Modifiers::NONE,
-                }));
+                callback(InputEvent::Key(KeyEvent::new(
+                    KeyCode::Escape,
+                    Modifiers::NONE,
+                )));
                 callback(event);
             }
             (_, event) => callback(event),
@@ -1378,6 +1693,35 @@ impl InputParser {
                             }
                             continue;
                         }
+
+                        match decode_kitty_key(self.buf.as_slice()) {
+                            KittyKeyReport::Key(len, event) => {
+                                self.buf.advance(len);
+                                if let Some(event) = event {
+                                    callback(InputEvent::Key(event));
+                                }
+                                continue;
+                            }
+                            KittyKeyReport::NeedData if maybe_more => return,
+                            KittyKeyReport::NeedData | KittyKeyReport::NotKitty => {}
+                        }
+
+                        // Replies to queries; these may span several actions,
+                        // eg: the hook, data and unhook of a DCS response.
+                        match decode_terminal_response(self.buf.as_slice()) {
+                            TerminalResponseReport::Response(len) => {
+                                let actions =
+                                    Parser::new().parse_as_vec(&self.buf.as_slice()[..len]);
+                                self.buf.advance(len);
+                                for action in actions {
+                                    callback(InputEvent::TerminalResponse(action));
+                                }
+                                continue;
+                            }
+                            TerminalResponseReport::NeedData if maybe_more => return,
+                            TerminalResponseReport::NeedData
+                            | TerminalResponseReport::NotResponse => {}
+                        }
                     }
 
                     match (
@@ -1395,6 +1739,7 @@ impl InputParser {
                                 InputEvent::Key(KeyEvent {
                                     key: KeyCode::Escape,
                                     modifiers: Modifiers::NONE,
+                                    ..
                                 }),
                             ),
                             _,
@@ -1415,10 +1760,10 @@ impl InputParser {
                                 self.buf.advance(len);
                                 self.dispatch_callback(
                                     &mut callback,
-                                    InputEvent::Key(KeyEvent {
-                                        key: KeyCode::Char(c),
-                                        modifiers: Modifiers::NONE,
-                                    }),
+                                    InputEvent::Key(KeyEvent::new(
+                                        KeyCode::Char(c),
+                                        Modifiers::NONE,
+                                    )),
                                 );
                             } else {
                                 // We need more data to recognize the input, so
@@ -1478,26 +1823,11 @@ mod test {
         let inputs = p.parse_as_vec(b"hello", NO_MORE);
         assert_eq!(
             vec![
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::Char('h'),
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::Char('e'),
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::Char('l'),
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::Char('l'),
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::Char('o'),
-                }),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('h'), Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('e'), Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('l'), Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('l'), Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('o'), Modifiers::NONE)),
             ],
             inputs
         );
@@ -1509,18 +1839,9 @@ mod test {
         let inputs = p.parse_as_vec(b"\x03\x1bJ\x7f", NO_MORE);
         assert_eq!(
             vec![
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::CTRL,
-                    key: KeyCode::Char('c'),
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::ALT,
-                    key: KeyCode::Char('J'),
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::Backspace,
-                }),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('c'), Modifiers::CTRL)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('J'), Modifiers::ALT)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Backspace, Modifiers::NONE)),
             ],
             inputs
         );
@@ -1532,22 +1853,19 @@ mod test {
         let inputs = p.parse_as_vec(b"\x1bOA\x1bOB\x1bOC\x1bOD", NO_MORE);
         assert_eq!(
             vec![
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::ApplicationUpArrow,
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::ApplicationDownArrow,
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::ApplicationRightArrow,
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::ApplicationLeftArrow,
-                }),
+                InputEvent::Key(KeyEvent::new(KeyCode::ApplicationUpArrow, Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(
+                    KeyCode::ApplicationDownArrow,
+                    Modifiers::NONE
+                )),
+                InputEvent::Key(KeyEvent::new(
+                    KeyCode::ApplicationRightArrow,
+                    Modifiers::NONE
+                )),
+                InputEvent::Key(KeyEvent::new(
+                    KeyCode::ApplicationLeftArrow,
+                    Modifiers::NONE
+                )),
             ],
             inputs
         );
@@ -1562,10 +1880,10 @@ mod test {
         p.parse(b"~", |evt| inputs.push(evt), true);
         // make sure we recognize it as just the F-key
         assert_eq!(
-            vec![InputEvent::Key(KeyEvent {
-                modifiers: Modifiers::NONE,
-                key: KeyCode::Function(1),
-            })],
+            vec![InputEvent::Key(KeyEvent::new(
+                KeyCode::Function(1),
+                Modifiers::NONE
+            ))],
             inputs
         );
     }
@@ -1575,10 +1893,10 @@ mod test {
         let mut p = InputParser::new();
 
         assert_eq!(
-            vec![InputEvent::Key(KeyEvent {
-                key: KeyCode::Escape,
-                modifiers: Modifiers::NONE,
-            })],
+            vec![InputEvent::Key(KeyEvent::new(
+                KeyCode::Escape,
+                Modifiers::NONE
+            ))],
             p.parse_as_vec(b"\x1b", false)
         );
 
@@ -1590,18 +1908,9 @@ mod test {
         // parts of said f-key sequence
         assert_eq!(
             vec![
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::ALT,
-                    key: KeyCode::Char('['),
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::Char('1'),
-                }),
-                InputEvent::Key(KeyEvent {
-                    modifiers: Modifiers::NONE,
-                    key: KeyCode::Char('1'),
-                }),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('['), Modifiers::ALT)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('1'), Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('1'), Modifiers::NONE)),
             ],
             inputs
         );
@@ -1617,10 +1926,10 @@ mod test {
         p.parse(b"\x1b[", |evt| inputs.push(evt), false);
 
         assert_eq!(
-            vec![InputEvent::Key(KeyEvent {
-                modifiers: Modifiers::ALT,
-                key: KeyCode::Char('['),
-            }),],
+            vec![InputEvent::Key(KeyEvent::new(
+                KeyCode::Char('['),
+                Modifiers::ALT
+            )),],
             inputs
         );
     }
@@ -1634,26 +1943,17 @@ mod test {
         );
         assert_eq!(
             vec![
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Enter,
-                    modifiers: Modifiers::CTRL,
-                }),
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Tab,
-                    modifiers: Modifiers::CTRL,
-                }),
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Backspace,
-                    modifiers: Modifiers::CTRL | Modifiers::SHIFT,
-                }),
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Backspace,
-                    modifiers: Modifiers::SHIFT,
-                }),
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Escape,
-                    modifiers: Modifiers::CTRL | Modifiers::SHIFT,
-                }),
+                InputEvent::Key(KeyEvent::new(KeyCode::Enter, Modifiers::CTRL)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Tab, Modifiers::CTRL)),
+                InputEvent::Key(KeyEvent::new(
+                    KeyCode::Backspace,
+                    Modifiers::CTRL | Modifiers::SHIFT
+                )),
+                InputEvent::Key(KeyEvent::new(KeyCode::Backspace, Modifiers::SHIFT)),
+                InputEvent::Key(KeyEvent::new(
+                    KeyCode::Escape,
+                    Modifiers::CTRL | Modifiers::SHIFT
+                )),
             ],
             inputs
         );
@@ -1921,4 +2221,212 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn kitty_disambiguate() {
+        let mut p = InputParser::new();
+        let inputs = p.parse_as_vec(b"\x1b[105;5u\t\x1b[27u\x1b[13;2u", NO_MORE);
+        assert_eq!(
+            vec![
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('i'), Modifiers::CTRL)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Tab, Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Escape, Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Enter, Modifiers::SHIFT)),
+            ],
+            inputs
+        );
+    }
+
+    #[test]
+    fn kitty_event_types() {
+        let mut p = InputParser::new();
+        let inputs = p.parse_as_vec(
+            b"\x1b[97;1:1u\x1b[97;1:2u\x1b[97;1:3u\x1b[1;5:3A\x1b[5;1:2~",
+            NO_MORE,
+        );
+        let key = |key, modifiers, kind| {
+            InputEvent::Key(KeyEvent {
+                kind,
+                ..KeyEvent::new(key, modifiers)
+            })
+        };
+        assert_eq!(
+            vec![
+                key(KeyCode::Char('a'), Modifiers::NONE, KeyEventKind::Press),
+                key(KeyCode::Char('a'), Modifiers::NONE, KeyEventKind::Repeat),
+                key(KeyCode::Char('a'), Modifiers::NONE, KeyEventKind::Release),
+                key(KeyCode::UpArrow, Modifiers::CTRL, KeyEventKind::Release),
+                key(KeyCode::PageUp, Modifiers::NONE, KeyEventKind::Repeat),
+            ],
+            inputs
+        );
+    }
+
+    #[test]
+    fn kitty_alternate_keys_and_text() {
+        let mut p = InputParser::new();
+        let inputs = p.parse_as_vec(b"\x1b[97:65:113;2;65u\x1b[1089::99;5u", NO_MORE);
+        assert_eq!(
+            vec![
+                InputEvent::Key(KeyEvent {
+                    shifted_key: Some(KeyCode::Char('A')),
+                    base_layout_key: Some(KeyCode::Char('q')),
+                    text: Some("A".to_string()),
+                    ..KeyEvent::new(KeyCode::Char('a'), Modifiers::SHIFT)
+                }),
+                InputEvent::Key(KeyEvent {
+                    base_layout_key: Some(KeyCode::Char('c')),
+                    ..KeyEvent::new(KeyCode::Char('\u{441}'), Modifiers::CTRL)
+                }),
+            ],
+            inputs
+        );
+    }
+
+    #[test]
+    fn kitty_functional_keys() {
+        let mut p = InputParser::new();
+        let inputs = p.parse_as_vec(
+            b"\x1b[57376u\x1b[57441;2u\x1b[57399u\x1b[57364u\x1b[57444;9:3u",
+            NO_MORE,
+        );
+        assert_eq!(
+            vec![
+                InputEvent::Key(KeyEvent::new(KeyCode::Function(13), Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(KeyCode::LeftShift, Modifiers::SHIFT)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Numpad0, Modifiers::NONE)),
+                // 57364 (Fn) has no KeyCode and is dropped
+                InputEvent::Key(KeyEvent {
+                    kind: KeyEventKind::Release,
+                    ..KeyEvent::new(KeyCode::LeftWindows, Modifiers::SUPER)
+                }),
+            ],
+            inputs
+        );
+    }
+
+    #[test]
+    fn kitty_partial_report() {
+        let mut p = InputParser::new();
+        let inputs = p.parse_as_vec(b"\x1b[97;5", MAYBE_MORE);
+        assert_eq!(inputs, vec![]);
+        let inputs = p.parse_as_vec(b":3u", NO_MORE);
+        assert_eq!(
+            vec![InputEvent::Key(KeyEvent {
+                kind: KeyEventKind::Release,
+                ..KeyEvent::new(KeyCode::Char('a'), Modifiers::CTRL)
+            })],
+            inputs
+        );
+    }
+
+    #[test]
+    fn legacy_sequences_are_not_kitty() {
+        let mut p = InputParser::new();
+        let inputs = p.parse_as_vec(b"\x1b[1;5A\x1b[5~\x1b[1;2P", NO_MORE);
+        assert_eq!(
+            vec![
+                InputEvent::Key(KeyEvent::new(KeyCode::UpArrow, Modifiers::CTRL)),
+                InputEvent::Key(KeyEvent::new(KeyCode::PageUp, Modifiers::NONE)),
+                InputEvent::Key(KeyEvent::new(KeyCode::Function(1), Modifiers::SHIFT)),
+            ],
+            inputs
+        );
+    }
+
+    #[test]
+    fn focus_events() {
+        let mut p = InputParser::new();
+        let inputs = p.parse_as_vec(b"\x1b[Ia\x1b[O", NO_MORE);
+        assert_eq!(
+            vec![
+                InputEvent::FocusGained,
+                InputEvent::Key(KeyEvent::new(KeyCode::Char('a'), Modifiers::NONE)),
+                InputEvent::FocusLost,
+            ],
+            inputs
+        );
+    }
+
+    #[test]
+    fn terminal_responses() {
+        let responses: &[&[u8]] = &[
+            b"\x1b[?65;4;6c",
+            b"\x1b[?2026;2$y",
+            b"\x1b]11;rgb:0000/0000/0000\x1b\\",
+            b"\x1b[?15u",
+            b"\x1bP>|WezTerm 20230712\x1b\\",
+            b"\x1bP1+r524742=382f382f38\x1b\\",
+            b"\x1bP0+r\x1b\\",
+            b"\x1b_Gi=31;OK\x1b\\",
+        ];
+        for response in responses {
+            let mut p = InputParser::new();
+            let mut input = response.to_vec();
+            input.push(b'x');
+            let inputs = p.parse_as_vec(&input, NO_MORE);
+
+            let mut expected: Vec<InputEvent> = Parser::new()
+                .parse_as_vec(response)
+                .into_iter()
+                .map(InputEvent::TerminalResponse)
+                .collect();
+            expected.push(InputEvent::Key(KeyEvent::new(
+                KeyCode::Char('x'),
+                Modifiers::NONE,
+            )));
+            assert_eq!(expected, inputs, "{:?}", String::from_utf8_lossy(response));
+        }
+    }
+
+    #[test]
+    fn partial_terminal_response() {
+        let mut p = InputParser::new();
+        let inputs = p.parse_as_vec(b"\x1b]11;rgb:ffff/", MAYBE_MORE);
+        assert_eq!(inputs, vec![]);
+        let inputs = p.parse_as_vec(b"ffff/ffff\x07", NO_MORE);
+        assert_eq!(
+            Parser::new()
+                .parse_as_vec(b"\x1b]11;rgb:ffff/ffff/ffff\x07")
+                .into_iter()
+                .map(InputEvent::TerminalResponse)
+                .collect::<Vec<_>>(),
+            inputs
+        );
+    }
+
+    #[test]
+    fn alt_keys_are_not_responses() {
+        let mut p = InputParser::new();
+        let inputs = p.parse_as_vec(b"\x1b\\", NO_MORE);
+        assert_eq!(
+            vec![InputEvent::Key(KeyEvent::new(
+                KeyCode::Char('\\'),
+                Modifiers::ALT
+            ))],
+            inputs
+        );
+
+        // A string introducer doesn't hold back the keys that follow it
+        for introducer in [b'P', b']', b'_'] {
+            let mut p = InputParser::new();
+            let mut inputs = p.parse_as_vec(&[0x1b, introducer], MAYBE_MORE);
+            inputs.extend(p.parse_as_vec(b"ab", MAYBE_MORE));
+            inputs.extend(p.parse_as_vec(b"c", NO_MORE));
+            assert_eq!(
+                vec![
+                    InputEvent::Key(KeyEvent::new(
+                        KeyCode::Char(introducer as char),
+                        Modifiers::ALT
+                    )),
+                    InputEvent::Key(KeyEvent::new(KeyCode::Char('a'), Modifiers::NONE)),
+                    InputEvent::Key(KeyEvent::new(KeyCode::Char('b'), Modifiers::NONE)),
+                    InputEvent::Key(KeyEvent::new(KeyCode::Char('c'), Modifiers::NONE)),
+                ],
+                inputs,
+                "{:?}",
+                introducer as char
+            );
+        }
+    }
 }
@@ -34,6 +34,8 @@
 //!   level.
 //! * `LineEditor` provides line editing facilities similar to those
 //!   in the unix shell.
+//! * `StyledText` builds runs of text with attributes, either span by
+//!   span or from a simple markup language.
 //!
 //! ## Features
 //!
@@ -62,6 +64,7 @@ pub mod nerdfonts;
 mod nerdfonts_data;
 mod readbuf;
 pub mod render;
+pub mod styled;
 pub mod surface;
 pub mod terminal;
 pub mod tmux_cc;
@@ -6,11 +6,71 @@ pub enum Movement {
     BackwardWord(RepeatCount),
     ForwardChar(RepeatCount),
     ForwardWord(RepeatCount),
+    /// Move to the same column of the previous line of input
+    /// that spans multiple lines
+    BackwardLine(RepeatCount),
+    /// Move to the same column of the next line of input
+    /// that spans multiple lines
+    ForwardLine(RepeatCount),
+    /// Move to the last character of the current or next word
+    ForwardWordEnd(RepeatCount),
+    /// Move onto the next occurrence of the char
+    ForwardFindChar(RepeatCount, char),
+    /// Move to just before the next occurrence of the char
+    ForwardTillChar(RepeatCount, char),
+    /// Move onto the previous occurrence of the char
+    BackwardFindChar(RepeatCount, char),
+    /// Move to just after the previous occurrence of the char
+    BackwardTillChar(RepeatCount, char),
     StartOfLine,
+    /// Move to the first non-whitespace character of the line
+    FirstNonBlank,
     EndOfLine,
+    /// Moves to the start of the line; when used with an `Operator`
+    /// the operator applies to the entire line
+    WholeLine,
+    /// Moves to the start of the word containing the cursor; when used
+    /// with an `Operator` the operator applies to the word, as with
+    /// vi's `iw`
+    InnerWord,
+    /// Moves to the start of the word containing the cursor; when used
+    /// with an `Operator` the operator applies to the word and the
+    /// whitespace around it, as with vi's `aw`
+    AroundWord,
     None,
 }
 
+/// The vi editing modes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ViMode {
+    #[default]
+    Insert,
+    Normal,
+    Visual,
+}
+
+/// The vi operators that act on a range of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// Delete the text, saving it in the register
+    Delete,
+    /// Delete the text, saving it in the register, and enter insert mode
+    Change,
+    /// Copy the text into the register
+    Yank,
+}
+
+/// Moves the selection within the completion menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuMovement {
+    Next,
+    Previous,
+    /// Move to the candidate in the row above
+    Up,
+    /// Move to the candidate in the row below
+    Down,
+}
+
 #[derive(Debug, Clone)]
 pub enum Action {
     AcceptLine,
@@ -25,7 +85,39 @@ pub enum Action {
     HistoryPrevious,
     HistoryNext,
     Complete,
+    /// Append the suggestion offered by `LineEditorHost::suggestion`
+    AcceptSuggestion,
+    /// Append the next word of the suggestion
+    AcceptSuggestionWord,
+    /// Select another candidate from the completion menu,
+    /// starting completion if it is not already in progress
+    SelectCompletion(MenuMovement),
     NoAction,
     HistoryIncSearchBackwards,
     HistoryIncSearchForwards,
+    /// Apply the operator to the text between the cursor and
+    /// the position after the movement
+    Operate(Operator, Movement),
+    /// Apply the operator to the vi visual mode selection
+    OperateOnSelection(Operator),
+    /// Insert the register contents after the cursor
+    PasteAfter(RepeatCount),
+    /// Insert the register contents before the cursor
+    PasteBefore(RepeatCount),
+    /// Apply the movement and then enter vi insert mode
+    ViInsert(Movement),
+    /// Enter vi normal mode
+    ViNormal,
+    /// Enter vi visual mode, anchoring the selection at the cursor
+    ViVisual,
+    /// Revert the most recent edit.  A run of inserted characters
+    /// is reverted as a single edit.
+    Undo,
+    /// Reapply the most recently undone edit
+    Redo,
+    /// Insert the most recently killed text
+    Yank,
+    /// Immediately after a `Yank` or `YankPop`, replace the inserted
+    /// text with the kill that preceded it in the kill ring
+    YankPop,
 }
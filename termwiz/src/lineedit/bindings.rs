@@ -0,0 +1,253 @@
+//! The default key bindings, which follow those of emacs and readline
+use super::actions::{Action, MenuMovement, Movement};
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
+
+/// Returns the action bound to `event` in the default key bindings
+pub(crate) fn emacs_action(event: &InputEvent) -> Option<Action> {
+    match event {
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('C'),
+            modifiers: Modifiers::CTRL,
+            ..
+        }) => Some(Action::Cancel),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Tab,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::Complete),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Tab,
+            modifiers: Modifiers::SHIFT,
+            ..
+        }) => Some(Action::SelectCompletion(MenuMovement::Previous)),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('D'),
+            modifiers: Modifiers::CTRL,
+            ..
+        }) => Some(Action::EndOfFile),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('J'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('M'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::Enter,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::AcceptLine),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Enter,
+            modifiers: Modifiers::ALT,
+            ..
+        }) => Some(Action::InsertChar(1, '\n')),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('H'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::Backspace,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::Kill(Movement::BackwardChar(1))),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Delete,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::KillAndMove(
+            Movement::ForwardChar(1),
+            Movement::None,
+        )),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('P'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::UpArrow,
+            modifiers: Modifiers::NONE,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::ApplicationUpArrow,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::HistoryPrevious),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('N'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::DownArrow,
+            modifiers: Modifiers::NONE,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::ApplicationDownArrow,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::HistoryNext),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('B'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::ApplicationLeftArrow,
+            modifiers: Modifiers::NONE,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::LeftArrow,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::Move(Movement::BackwardChar(1))),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('W'),
+            modifiers: Modifiers::CTRL,
+            ..
+        }) => Some(Action::Kill(Movement::BackwardWord(1))),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('b'),
+            modifiers: Modifiers::ALT,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::LeftArrow,
+            modifiers: Modifiers::ALT,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::ApplicationLeftArrow,
+            modifiers: Modifiers::ALT,
+            ..
+        }) => Some(Action::Move(Movement::BackwardWord(1))),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('f'),
+            modifiers: Modifiers::ALT,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::RightArrow,
+            modifiers: Modifiers::ALT,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::ApplicationRightArrow,
+            modifiers: Modifiers::ALT,
+            ..
+        }) => Some(Action::Move(Movement::ForwardWord(1))),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('A'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::Home,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::Move(Movement::StartOfLine)),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('E'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::End,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::Move(Movement::EndOfLine)),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('F'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::RightArrow,
+            modifiers: Modifiers::NONE,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::ApplicationRightArrow,
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::Move(Movement::ForwardChar(1))),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('Y'),
+            modifiers: Modifiers::CTRL,
+            ..
+        }) => Some(Action::Yank),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('y'),
+            modifiers: Modifiers::ALT,
+            ..
+        }) => Some(Action::YankPop),
+
+        // Without a protocol that reports modifiers, Ctrl-_ is sent
+        // as the unit separator control character
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('_'),
+            modifiers: Modifiers::CTRL,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('\x1f'),
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::Undo),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char(c),
+            modifiers: Modifiers::SHIFT,
+            ..
+        })
+        | InputEvent::Key(KeyEvent {
+            key: KeyCode::Char(c),
+            modifiers: Modifiers::NONE,
+            ..
+        }) => Some(Action::InsertChar(1, *c)),
+        InputEvent::Paste(text) => Some(Action::InsertText(1, text.clone())),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('L'),
+            modifiers: Modifiers::CTRL,
+            ..
+        }) => Some(Action::Repaint),
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('K'),
+            modifiers: Modifiers::CTRL,
+            ..
+        }) => Some(Action::Kill(Movement::EndOfLine)),
+
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('R'),
+            modifiers: Modifiers::CTRL,
+            ..
+        }) => Some(Action::HistoryIncSearchBackwards),
+
+        // This is the common binding for forwards, but it is usually
+        // masked by the stty stop setting
+        InputEvent::Key(KeyEvent {
+            key: KeyCode::Char('S'),
+            modifiers: Modifiers::CTRL,
+            ..
+        }) => Some(Action::HistoryIncSearchForwards),
+
+        _ => None,
+    }
+}
@@ -1,5 +1,12 @@
+use crate::Result;
 use std::borrow::Cow;
-use std::collections::VecDeque;
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Represents a position within the history.
 /// Smaller numbers are assumed to be before larger numbers,
@@ -25,6 +32,69 @@ pub trait History {
         direction: SearchDirection,
         pattern: &str,
     ) -> Option<SearchResult>;
+
+    /// Returns the remainder of the most recent entry that begins with
+    /// `prefix` and is longer than it, for use as an inline suggestion.
+    fn suggest(&self, prefix: &str) -> Option<String> {
+        if prefix.is_empty() {
+            return None;
+        }
+        let mut idx = self.last()?;
+        loop {
+            if let Some(line) = self.get(idx) {
+                if line.len() > prefix.len() && line.starts_with(prefix) {
+                    return Some(line[prefix.len()..].to_string());
+                }
+            }
+            idx = SearchDirection::Backwards.next(idx)?;
+        }
+    }
+
+    /// Collect up to `limit` distinct entries that match `pattern`,
+    /// best match first.  `SearchStyle::Fuzzy` matches are ranked by
+    /// their score; otherwise, and between equal scores, more recent
+    /// entries rank higher.
+    fn search_candidates(
+        &self,
+        style: SearchStyle,
+        pattern: &str,
+        limit: usize,
+    ) -> Vec<SearchCandidate> {
+        let mut candidates = vec![];
+        let mut seen = HashSet::new();
+        let mut idx = match self.last() {
+            Some(idx) => idx,
+            None => return candidates,
+        };
+
+        loop {
+            if let Some(line) = self.get(idx) {
+                if let Some(matched) = style.find(pattern, &line) {
+                    if seen.insert(line.to_string()) {
+                        candidates.push(SearchCandidate {
+                            idx,
+                            line: line.into_owned(),
+                            matched,
+                        });
+                        if style != SearchStyle::Fuzzy && candidates.len() >= limit {
+                            break;
+                        }
+                    }
+                }
+            }
+            idx = match SearchDirection::Backwards.next(idx) {
+                Some(idx) => idx,
+                None => break,
+            };
+        }
+
+        if style == SearchStyle::Fuzzy {
+            // The sort is stable, so equal scores remain in recency order
+            candidates.sort_by_key(|c| std::cmp::Reverse(c.matched.score));
+        }
+        candidates.truncate(limit);
+        candidates
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -34,21 +104,186 @@ pub struct SearchResult<'a> {
     pub cursor: usize,
 }
 
+/// An entry returned by `History::search_candidates`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SearchCandidate {
+    pub idx: HistoryIndex,
+    pub line: String,
+    pub matched: SearchMatch,
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum SearchStyle {
+    /// The pattern may appear anywhere in the line
     Substring,
+    /// The line must begin with the pattern
+    Prefix,
+    /// The pattern is a regular expression.  A pattern that
+    /// fails to compile doesn't match anything.
+    Regex,
+    /// The characters of the pattern must appear in the line in the
+    /// same order, but not necessarily next to each other, in the
+    /// style of fzf.  Matches are case insensitive unless the pattern
+    /// contains an uppercase character.
+    Fuzzy,
+}
+
+/// Describes where, and how well, a pattern matched a line
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SearchMatch {
+    /// The relative quality of the match; larger is better.
+    /// Only meaningful when comparing matches for the same pattern.
+    pub score: i64,
+    /// The byte ranges of the line that matched the pattern
+    pub ranges: Vec<Range<usize>>,
 }
 
 impl SearchStyle {
     /// Matches pattern against line, returning the byte index of the
     /// first matching character
     pub fn match_against(&self, pattern: &str, line: &str) -> Option<usize> {
+        let matched = self.find(pattern, line)?;
+        Some(matched.ranges.first().map(|r| r.start).unwrap_or(0))
+    }
+
+    /// Matches pattern against line, returning the matching portions
+    /// of the line along with a score that can be used to rank the
+    /// match against matches in other lines
+    pub fn find(&self, pattern: &str, line: &str) -> Option<SearchMatch> {
+        let single = |range: Range<usize>| {
+            Some(SearchMatch {
+                score: -(range.start as i64),
+                ranges: if range.is_empty() {
+                    vec![]
+                } else {
+                    vec![range]
+                },
+            })
+        };
         match self {
-            Self::Substring => line.find(pattern),
+            Self::Substring => {
+                let start = line.find(pattern)?;
+                single(start..start + pattern.len())
+            }
+            Self::Prefix => {
+                if line.starts_with(pattern) {
+                    single(0..pattern.len())
+                } else {
+                    None
+                }
+            }
+            Self::Regex => regex_find(pattern, line).and_then(single),
+            Self::Fuzzy => fuzzy_find(pattern, line),
         }
     }
 }
 
+thread_local! {
+    /// Incremental search re-uses the same pattern for every history
+    /// entry, so we keep hold of the most recently compiled regex
+    static LAST_REGEX: RefCell<Option<(String, Option<fancy_regex::Regex>)>> = const { RefCell::new(None) };
+}
+
+fn regex_find(pattern: &str, line: &str) -> Option<Range<usize>> {
+    LAST_REGEX.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.as_ref().map(|(p, _)| p.as_str()) != Some(pattern) {
+            *cache = Some((pattern.to_string(), fancy_regex::Regex::new(pattern).ok()));
+        }
+        let regex = cache.as_ref()?.1.as_ref()?;
+        let found = regex.find(line).ok()??;
+        Some(found.start()..found.end())
+    })
+}
+
+const FUZZY_SCORE_MATCH: i64 = 16;
+const FUZZY_BONUS_CONSECUTIVE: i64 = 8;
+const FUZZY_BONUS_BOUNDARY: i64 = 8;
+const FUZZY_BONUS_CAMEL: i64 = 6;
+const FUZZY_PENALTY_GAP_START: i64 = 3;
+const FUZZY_PENALTY_GAP_EXTENSION: i64 = 1;
+
+fn fuzzy_find(pattern: &str, line: &str) -> Option<SearchMatch> {
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let pattern: Vec<char> = pattern.chars().map(fold).collect();
+    if pattern.is_empty() {
+        return Some(SearchMatch::default());
+    }
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+
+    // Try each position where the first character of the pattern
+    // matches, greedily matching the rest, and keep the best scoring
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in 0..chars.len() {
+        if fold(chars[start].1) != pattern[0] {
+            continue;
+        }
+        let mut positions = vec![start];
+        for (idx, &(_, c)) in chars.iter().enumerate().skip(start + 1) {
+            if positions.len() == pattern.len() {
+                break;
+            }
+            if fold(c) == pattern[positions.len()] {
+                positions.push(idx);
+            }
+        }
+        if positions.len() < pattern.len() {
+            // Later starting positions can't match either
+            break;
+        }
+        let score = fuzzy_score(&chars, &positions);
+        if !matches!(&best, Some((best, _)) if *best >= score) {
+            best = Some((score, positions));
+        }
+    }
+
+    let (score, positions) = best?;
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for pos in positions {
+        let (start, c) = chars[pos];
+        let end = start + c.len_utf8();
+        match ranges.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+    Some(SearchMatch { score, ranges })
+}
+
+/// Scores a fuzzy match, favoring runs of consecutive characters and
+/// matches at the start of words, and penalizing gaps between matches
+fn fuzzy_score(chars: &[(usize, char)], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    for (n, &pos) in positions.iter().enumerate() {
+        score += FUZZY_SCORE_MATCH;
+
+        let c = chars[pos].1;
+        match pos.checked_sub(1).map(|prior| chars[prior].1) {
+            None => score += FUZZY_BONUS_BOUNDARY,
+            Some(prior) if !prior.is_alphanumeric() => score += FUZZY_BONUS_BOUNDARY,
+            Some(prior) if prior.is_lowercase() && c.is_uppercase() => score += FUZZY_BONUS_CAMEL,
+            _ => {}
+        }
+
+        if n > 0 {
+            let gap = (pos - positions[n - 1] - 1) as i64;
+            if gap == 0 {
+                score += FUZZY_BONUS_CONSECUTIVE;
+            } else {
+                score -= FUZZY_PENALTY_GAP_START + FUZZY_PENALTY_GAP_EXTENSION * (gap - 1);
+            }
+        }
+    }
+    score
+}
+
 /// Encodes the direction the search should take, relative to the
 /// current HistoryIndex.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -135,3 +370,607 @@ impl History for BasicHistory {
         }
     }
 }
+
+/// Controls how `FileHistory` treats a line that is already present
+/// in the history.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Record every line, even if it repeats an earlier entry
+    Keep,
+    /// Skip a line that is identical to the most recent entry
+    IgnoreConsecutive,
+    /// Record the line and remove any earlier identical entries
+    EraseOlder,
+}
+
+#[derive(Debug, Clone)]
+struct FileHistoryEntry {
+    line: String,
+    /// Seconds since the unix epoch
+    timestamp: Option<u64>,
+}
+
+/// A history implementation that persists entries to a file, so that
+/// they are available to subsequent sessions.
+///
+/// Each entry is appended to the file as a single record of the form
+/// `<unix-timestamp>;<line>`, with backslashes, newlines and carriage
+/// returns in the line escaped so that multi-line entries occupy a
+/// single line of the file.  Lines without a timestamp prefix are
+/// accepted when loading, and taken verbatim, so a plain list of
+/// commands can be imported.
+///
+/// Records are written with a single append, so several processes may
+/// share the same history file without interleaving their entries.
+/// Appending and compacting are serialized by an advisory lock on a
+/// `.lock` file alongside the history file, so that compacting doesn't
+/// discard entries appended by another process in the meantime.
+/// Each process only sees the entries that were present when it opened
+/// the file, along with those it added itself.
+pub struct FileHistory {
+    path: PathBuf,
+    entries: VecDeque<FileHistoryEntry>,
+    /// The number of records in the file, which may be larger than
+    /// the number of entries when the file holds duplicates or more
+    /// than max_entries records
+    records_in_file: usize,
+    max_entries: Option<usize>,
+    duplicates: DuplicatePolicy,
+    ignore_space_prefixed: bool,
+}
+
+impl FileHistory {
+    /// Load the history from `path`.  The file is created when the
+    /// first entry is added if it doesn't already exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut history = Self {
+            path: path.as_ref().to_path_buf(),
+            entries: VecDeque::new(),
+            records_in_file: 0,
+            max_entries: None,
+            duplicates: DuplicatePolicy::IgnoreConsecutive,
+            ignore_space_prefixed: false,
+        };
+        history.reload()?;
+        Ok(history)
+    }
+
+    /// Limit the history to the most recent `max_entries` entries.
+    /// Older entries are discarded, and the file is rewritten the next
+    /// time that its size exceeds twice the limit.
+    pub fn set_max_entries(&mut self, max_entries: Option<usize>) {
+        self.max_entries = max_entries;
+        self.apply_max_entries();
+    }
+
+    /// Set the policy for lines that duplicate existing entries.
+    /// The default is `DuplicatePolicy::IgnoreConsecutive`.
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.duplicates = policy;
+        if policy == DuplicatePolicy::EraseOlder {
+            self.erase_older_duplicates();
+        }
+    }
+
+    /// When enabled, lines that start with a space are not recorded.
+    /// This mirrors the `HISTCONTROL=ignorespace` behavior of bash.
+    pub fn set_ignore_space_prefixed(&mut self, ignore: bool) {
+        self.ignore_space_prefixed = ignore;
+    }
+
+    /// Returns the path to the history file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the time at which the entry was recorded, if known
+    pub fn timestamp(&self, idx: HistoryIndex) -> Option<SystemTime> {
+        let secs = self.entries.get(idx)?.timestamp?;
+        Some(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Discard the in-memory entries and load them again from the
+    /// file, picking up entries added by other processes.
+    pub fn reload(&mut self) -> Result<()> {
+        self.entries.clear();
+        self.records_in_file = 0;
+
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        for record in BufReader::new(file).split(b'\n') {
+            let record = record?;
+            if record.is_empty() {
+                continue;
+            }
+            self.records_in_file += 1;
+            let entry = decode_record(&String::from_utf8_lossy(&record));
+            self.push_entry(entry);
+        }
+        self.apply_max_entries();
+
+        Ok(())
+    }
+
+    /// Rewrite the file so that it holds only the current entries.
+    /// The replacement file is written alongside the original and then
+    /// renamed over it, so that readers never observe a partial file.
+    pub fn compact(&mut self) -> Result<()> {
+        let _lock = HistoryLock::acquire(&self.path)?;
+        self.compact_locked()
+    }
+
+    fn compact_locked(&mut self) -> Result<()> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+        let mut data = String::new();
+        for entry in &self.entries {
+            encode_record(entry, &mut data);
+        }
+        temp.write_all(data.as_bytes())?;
+        temp.as_file().sync_all()?;
+        temp.persist(&self.path).map_err(|err| err.error)?;
+        self.records_in_file = self.entries.len();
+        Ok(())
+    }
+
+    fn push_entry(&mut self, entry: FileHistoryEntry) {
+        match self.duplicates {
+            DuplicatePolicy::Keep => {}
+            DuplicatePolicy::IgnoreConsecutive => {
+                if self.entries.back().map(|e| e.line.as_str()) == Some(entry.line.as_str()) {
+                    return;
+                }
+            }
+            DuplicatePolicy::EraseOlder => {
+                self.entries.retain(|e| e.line != entry.line);
+            }
+        }
+        self.entries.push_back(entry);
+    }
+
+    fn apply_max_entries(&mut self) {
+        if let Some(max) = self.max_entries {
+            while self.entries.len() > max {
+                self.entries.pop_front();
+            }
+        }
+    }
+
+    fn erase_older_duplicates(&mut self) {
+        let mut seen = std::collections::HashSet::new();
+        let mut entries: Vec<FileHistoryEntry> = self.entries.drain(..).rev().collect();
+        entries.retain(|e| seen.insert(e.line.clone()));
+        self.entries = entries.into_iter().rev().collect();
+    }
+
+    fn append_to_file(&mut self, entry: &FileHistoryEntry) -> Result<()> {
+        let mut record = String::new();
+        encode_record(entry, &mut record);
+
+        // Held until we return, so that another process can't replace
+        // the file between our opening it and appending to it, or
+        // append between our reloading it and replacing it
+        let _lock = HistoryLock::acquire(&self.path)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // A single write of the whole record keeps concurrent appenders
+        // from interleaving their records
+        file.write_all(record.as_bytes())?;
+        self.records_in_file += 1;
+
+        let needs_compaction = match self.max_entries {
+            Some(max) => self.records_in_file > max.saturating_mul(2).max(1),
+            None => false,
+        };
+        if needs_compaction {
+            // Pick up entries added by other processes before
+            // replacing the file, so that we don't discard them
+            self.reload()?;
+            self.compact_locked()?;
+        }
+        Ok(())
+    }
+}
+
+/// An exclusive advisory lock on the lock file that accompanies a
+/// history file.  A separate file is locked because compacting replaces
+/// the history file itself.  The lock is released when this is dropped.
+struct HistoryLock {
+    _file: File,
+}
+
+impl HistoryLock {
+    fn acquire(path: &Path) -> Result<Self> {
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(lock_path)?;
+        Self::lock(&file)?;
+        Ok(Self { _file: file })
+    }
+
+    #[cfg(unix)]
+    fn lock(file: &File) -> std::io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        loop {
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+                return Ok(());
+            }
+            let err = std::io::Error::last_os_error();
+            if err.kind() != ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    fn lock(file: &File) -> std::io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use winapi::um::fileapi::LockFileEx;
+        use winapi::um::minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, OVERLAPPED};
+        let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+        let ok = unsafe {
+            LockFileEx(
+                file.as_raw_handle() as _,
+                LOCKFILE_EXCLUSIVE_LOCK,
+                0,
+                !0,
+                !0,
+                &mut overlapped,
+            )
+        };
+        if ok == 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl History for FileHistory {
+    fn get(&self, idx: HistoryIndex) -> Option<Cow<'_, str>> {
+        self.entries
+            .get(idx)
+            .map(|e| Cow::Borrowed(e.line.as_str()))
+    }
+
+    fn last(&self) -> Option<HistoryIndex> {
+        if self.entries.is_empty() {
+            None
+        } else {
+            Some(self.entries.len() - 1)
+        }
+    }
+
+    fn add(&mut self, line: &str) {
+        if line.is_empty() || (self.ignore_space_prefixed && line.starts_with(' ')) {
+            return;
+        }
+        if self.duplicates == DuplicatePolicy::IgnoreConsecutive
+            && self.entries.back().map(|e| e.line.as_str()) == Some(line)
+        {
+            return;
+        }
+
+        let entry = FileHistoryEntry {
+            line: line.to_owned(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs()),
+        };
+        self.push_entry(entry.clone());
+        self.apply_max_entries();
+        if let Err(err) = self.append_to_file(&entry) {
+            log::error!(
+                "failed to append to history file {}: {:#}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+
+    fn search(
+        &self,
+        idx: HistoryIndex,
+        style: SearchStyle,
+        direction: SearchDirection,
+        pattern: &str,
+    ) -> Option<SearchResult<'_>> {
+        let mut idx = idx;
+
+        loop {
+            let line = &self.entries.get(idx)?.line;
+
+            if let Some(cursor) = style.match_against(pattern, line) {
+                return Some(SearchResult {
+                    line: Cow::Borrowed(line.as_str()),
+                    idx,
+                    cursor,
+                });
+            }
+
+            idx = direction.next(idx)?;
+        }
+    }
+}
+
+fn encode_record(entry: &FileHistoryEntry, out: &mut String) {
+    // Records without a timestamp are kept verbatim, as they were
+    // imported; one that can't fit on a line is given a timestamp so
+    // that it is escaped
+    let timestamp = match entry.timestamp {
+        None if !entry.line.contains(&['\n', '\r'][..]) => {
+            out.push_str(&entry.line);
+            out.push('\n');
+            return;
+        }
+        timestamp => timestamp.unwrap_or(0),
+    };
+    out.push_str(&timestamp.to_string());
+    out.push(';');
+    for c in entry.line.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('\n');
+}
+
+/// Decodes a record written by `encode_record`.  Escapes are only
+/// decoded in records with a timestamp prefix; other lines are taken
+/// verbatim, so that a plain list of commands can be imported.
+fn decode_record(record: &str) -> FileHistoryEntry {
+    let (timestamp, escaped) = match record.split_once(';') {
+        Some((prefix, rest))
+            if !prefix.is_empty() && prefix.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            (prefix.parse().ok(), rest)
+        }
+        _ => {
+            return FileHistoryEntry {
+                line: record.to_owned(),
+                timestamp: None,
+            }
+        }
+    };
+
+    let mut line = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            line.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => line.push('\n'),
+            Some('r') => line.push('\r'),
+            Some(c) => line.push(c),
+            None => line.push('\\'),
+        }
+    }
+
+    FileHistoryEntry { line, timestamp }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(history: &FileHistory) -> Vec<String> {
+        (0..)
+            .map_while(|idx| history.get(idx).map(|l| l.into_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn persists_across_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        let mut history = FileHistory::open(&path).unwrap();
+        history.add("ls");
+        history.add("echo 'one\ntwo' \\");
+        history.add("ls");
+        assert!(history.timestamp(0).is_some());
+
+        let history = FileHistory::open(&path).unwrap();
+        assert_eq!(lines(&history), vec!["ls", "echo 'one\ntwo' \\", "ls"]);
+        assert!(history.timestamp(1).is_some());
+    }
+
+    #[test]
+    fn concurrent_writers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        let mut a = FileHistory::open(&path).unwrap();
+        let mut b = FileHistory::open(&path).unwrap();
+        a.add("from a");
+        b.add("from b");
+        a.add("again a");
+        assert_eq!(lines(&a), vec!["from a", "again a"]);
+
+        a.reload().unwrap();
+        assert_eq!(lines(&a), vec!["from a", "from b", "again a"]);
+    }
+
+    #[test]
+    fn concurrent_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        // `a` repeatedly records the same two lines, so its file grows
+        // and is compacted while `b` appends distinct lines
+        let mut a = FileHistory::open(&path).unwrap();
+        a.set_duplicate_policy(DuplicatePolicy::EraseOlder);
+        a.set_max_entries(Some(200));
+        let mut b = FileHistory::open(&path).unwrap();
+
+        let writer = std::thread::spawn(move || {
+            for n in 0..2000 {
+                a.add(if n % 2 == 0 { "a0" } else { "a1" });
+            }
+        });
+        for n in 0..150 {
+            b.add(&format!("b{}", n));
+            std::thread::yield_now();
+        }
+        writer.join().unwrap();
+
+        let records = std::fs::read_to_string(&path).unwrap();
+        let records: HashSet<&str> = records
+            .lines()
+            .map(|line| line.split_once(';').map_or(line, |(_, line)| line))
+            .collect();
+        for n in 0..150 {
+            let line = format!("b{}", n);
+            assert!(records.contains(line.as_str()), "{} was lost", line);
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+
+        let mut history = FileHistory::open(&path).unwrap();
+        history.set_ignore_space_prefixed(true);
+        history.add("a");
+        history.add("a");
+        history.add(" secret");
+        history.add("b");
+        assert_eq!(lines(&history), vec!["a", "b"]);
+
+        history.set_duplicate_policy(DuplicatePolicy::EraseOlder);
+        history.add("a");
+        assert_eq!(lines(&history), vec!["b", "a"]);
+
+        history.set_max_entries(Some(1));
+        assert_eq!(lines(&history), vec!["a"]);
+        history.add("c");
+        assert_eq!(lines(&history), vec!["c"]);
+
+        // The file has grown beyond twice the limit and was compacted
+        let mut reloaded = FileHistory::open(&path).unwrap();
+        reloaded.set_max_entries(Some(1));
+        assert_eq!(lines(&reloaded), vec!["c"]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1,);
+    }
+
+    #[test]
+    fn search_styles() {
+        let ranges = |style: SearchStyle, pattern, line| {
+            style.find(pattern, line).map(|m| {
+                m.ranges
+                    .into_iter()
+                    .map(|r| (r.start, r.end))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            ranges(SearchStyle::Substring, "it", "git commit"),
+            Some(vec![(1, 3)])
+        );
+        assert_eq!(ranges(SearchStyle::Prefix, "it", "git commit"), None);
+        assert_eq!(
+            ranges(SearchStyle::Prefix, "git", "git commit"),
+            Some(vec![(0, 3)])
+        );
+        assert_eq!(
+            ranges(SearchStyle::Regex, "c[o]+m", "git commit"),
+            Some(vec![(4, 7)])
+        );
+        assert_eq!(ranges(SearchStyle::Regex, "(", "git commit"), None);
+        assert_eq!(
+            ranges(SearchStyle::Fuzzy, "gcm", "git commit"),
+            Some(vec![(0, 1), (4, 5), (6, 7)])
+        );
+        assert_eq!(ranges(SearchStyle::Fuzzy, "GC", "git commit"), None);
+        assert_eq!(
+            SearchStyle::Fuzzy.match_against("cmt", "git commit"),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn fuzzy_ranking() {
+        let mut history = BasicHistory::default();
+        for line in [
+            "cargo build --release",
+            "git commit -m 'fix cursor'",
+            "cat README.md",
+            "git checkout main",
+            "cargo test",
+        ] {
+            history.add(line);
+        }
+
+        let lines: Vec<String> = history
+            .search_candidates(SearchStyle::Fuzzy, "gco", 10)
+            .into_iter()
+            .map(|c| c.line)
+            .collect();
+        assert_eq!(
+            lines,
+            vec!["git commit -m 'fix cursor'", "git checkout main"]
+        );
+
+        let lines: Vec<String> = history
+            .search_candidates(SearchStyle::Prefix, "ca", 2)
+            .into_iter()
+            .map(|c| c.line)
+            .collect();
+        assert_eq!(lines, vec!["cargo test", "cat README.md"]);
+
+        assert_eq!(history.suggest("car").as_deref(), Some("go test"));
+        assert_eq!(
+            history.suggest("cargo b").as_deref(),
+            Some("uild --release")
+        );
+        assert_eq!(history.suggest("cargo test"), None);
+        assert_eq!(history.suggest(""), None);
+    }
+
+    #[test]
+    fn plain_text_history() {
+        let record = decode_record("git commit -m 'x;y'");
+        assert_eq!(record.line, "git commit -m 'x;y'");
+        assert_eq!(record.timestamp, None);
+
+        let record = decode_record("C:\\Users\\me && echo a\\nb");
+        assert_eq!(record.line, "C:\\Users\\me && echo a\\nb");
+        assert_eq!(record.timestamp, None);
+        let mut encoded = String::new();
+        encode_record(&record, &mut encoded);
+        assert_eq!(encoded, "C:\\Users\\me && echo a\\nb\n");
+
+        let record = decode_record("1700000000;a\\\\b\\nc");
+        assert_eq!(record.line, "a\\b\nc");
+        assert_eq!(record.timestamp, Some(1700000000));
+
+        // Imported commands keep their backslashes
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history");
+        std::fs::write(&path, "dir C:\\Users\\me\necho a\\nb\n").unwrap();
+        let history = FileHistory::open(&path).unwrap();
+        assert_eq!(lines(&history), vec!["dir C:\\Users\\me", "echo a\\nb"]);
+        assert!(history.timestamp(0).is_none());
+    }
+}
@@ -1,8 +1,9 @@
-use crate::cell::{AttributeChange, CellAttributes};
+use crate::cell::{AttributeChange, CellAttributes, Intensity, Underline};
 use crate::input::InputEvent;
-use crate::lineedit::actions::Action;
+use crate::lineedit::actions::{Action, ViMode};
 use crate::lineedit::{BasicHistory, History, LineEditor};
 use crate::surface::Change;
+use std::ops::Range;
 
 /// The `OutputElement` type allows returning graphic attribute changes
 /// as well as textual output.
@@ -42,7 +43,8 @@ pub trait LineEditorHost {
     /// it chooses; for instance, the application can opt to expand its own
     /// application specific escape sequences as it sees fit.
     /// The `OutputElement` type allows returning graphic attribute changes
-    /// as well as textual output.
+    /// as well as textual output; `crate::styled::StyledText` can be used
+    /// to build them.
     /// The default implementation returns the prompt as-is with no coloring
     /// and no textual transformation.
     fn render_prompt(&self, prompt: &str) -> Vec<OutputElement> {
@@ -52,6 +54,9 @@ pub trait LineEditorHost {
     /// Given a reference to the current line being edited, render a preview
     /// of its outcome. The preview is cleared when the input is accepted,
     /// or canceled.
+    /// During an incremental history search, the line is the matching
+    /// line that Enter would accept, and the preview follows the list
+    /// of search candidates.
     fn render_preview(&self, _line: &str) -> Vec<OutputElement> {
         Vec::new()
     }
@@ -71,6 +76,103 @@ pub trait LineEditorHost {
         (vec![OutputElement::Text(line.to_owned())], cursor_x_pos)
     }
 
+    /// Given a line from the history and the byte ranges within it
+    /// that matched the history search pattern, return the rendered
+    /// form of the line.  This is used for the matching line shown in
+    /// the input area while searching, as well as for the list of
+    /// search candidates.  As with `highlight_line`, the column
+    /// positions of the graphemes should be preserved.
+    /// The default implementation shows the matches in bold and underlined.
+    fn highlight_search_match(&self, line: &str, matches: &[Range<usize>]) -> Vec<OutputElement> {
+        let mut elements = vec![];
+        let mut pos = 0;
+        for range in matches {
+            let start = range.start.max(pos);
+            if start >= range.end {
+                continue;
+            }
+            if start > pos {
+                elements.push(OutputElement::Text(line[pos..start].to_owned()));
+            }
+            elements.push(OutputElement::Attribute(AttributeChange::Intensity(
+                Intensity::Bold,
+            )));
+            elements.push(OutputElement::Attribute(AttributeChange::Underline(
+                Underline::Single,
+            )));
+            elements.push(OutputElement::Text(line[start..range.end].to_owned()));
+            elements.push(OutputElement::Attribute(AttributeChange::Intensity(
+                Intensity::Normal,
+            )));
+            elements.push(OutputElement::Attribute(AttributeChange::Underline(
+                Underline::None,
+            )));
+            pos = range.end;
+        }
+        if pos < line.len() {
+            elements.push(OutputElement::Text(line[pos..].to_owned()));
+        }
+        elements
+    }
+
+    /// Given the line being edited and the byte range of the vi visual
+    /// mode selection within it, return the rendered form of the line.
+    /// As with `highlight_line`, the column positions of the graphemes
+    /// should be preserved.
+    /// The default implementation shows the selection in reverse video.
+    fn highlight_selection(&self, line: &str, selection: Range<usize>) -> Vec<OutputElement> {
+        vec![
+            OutputElement::Text(line[..selection.start].to_owned()),
+            OutputElement::Attribute(AttributeChange::Reverse(true)),
+            OutputElement::Text(line[selection.clone()].to_owned()),
+            OutputElement::Attribute(AttributeChange::Reverse(false)),
+            OutputElement::Text(line[selection.end..].to_owned()),
+        ]
+    }
+
+    /// Called when the vi editing mode changes, including when
+    /// `read_line` starts a new line in insert mode.  This allows the
+    /// application to indicate the mode in its prompt; `render_prompt`
+    /// is called after each change.
+    /// The default implementation does nothing.
+    fn vi_mode_changed(&mut self, _mode: ViMode) {}
+
+    /// Called when the user asks to accept the input.  Return false
+    /// to indicate that the input is incomplete, for example because
+    /// it has unbalanced brackets, in which case a newline is inserted
+    /// and editing continues on the next line after the continuation
+    /// prompt.
+    /// The default implementation considers all input to be complete.
+    fn is_complete(&self, _line: &str) -> bool {
+        true
+    }
+
+    /// Given the line being edited, return text to suggest as its
+    /// continuation, in the style of the fish shell.  The suggestion is
+    /// shown after the cursor while it is at the end of the line, and is
+    /// accepted by moving the cursor right.  A typical implementation
+    /// suggests from the history:
+    ///
+    /// ```ignore
+    /// fn suggestion(&mut self, line: &str) -> Option<String> {
+    ///     self.history().suggest(line)
+    /// }
+    /// ```
+    ///
+    /// The default implementation makes no suggestion.
+    fn suggestion(&mut self, _line: &str) -> Option<String> {
+        None
+    }
+
+    /// Given the text returned by `suggestion`, return its rendered form.
+    /// The default implementation shows it dimmed.
+    fn render_suggestion(&self, suggestion: &str) -> Vec<OutputElement> {
+        vec![
+            OutputElement::Attribute(AttributeChange::Intensity(Intensity::Half)),
+            OutputElement::Text(suggestion.to_owned()),
+        ]
+    }
+
     /// Returns the history implementation
     fn history(&mut self) -> &mut dyn History;
 
@@ -103,11 +205,18 @@ pub trait LineEditorHost {
 /// completion of "he" in that context, then the corresponding CompletionCandidate
 /// would have its range set to [4..6] (the "he" slice range) and its text
 /// set to "hello".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CompletionCandidate {
     /// The section of the input line to be replaced
     pub range: std::ops::Range<usize>,
     /// The replacement text
     pub text: String,
+    /// How the candidate appears in the completion menu,
+    /// if different from `text`
+    pub display: Option<String>,
+    /// An explanation of the candidate, shown alongside it
+    /// when the completion menu is `CompletionMenu::List`
+    pub description: Option<String>,
 }
 
 /// A concrete implementation of `LineEditorHost` that uses the default behaviors.
@@ -0,0 +1,87 @@
+//! The emacs style kill ring, from which killed text can be yanked
+use std::collections::VecDeque;
+
+/// How many kills are remembered, matching the readline default
+const KILL_RING_SIZE: usize = 10;
+
+#[derive(Debug, Default)]
+pub(crate) struct KillRing {
+    /// The most recent kill is at the front
+    entries: VecDeque<String>,
+    /// The entry that was most recently yanked
+    yank_index: usize,
+}
+
+impl KillRing {
+    /// Saves killed text.  When `accumulate` is true the text is
+    /// joined to the most recent kill rather than becoming a new entry;
+    /// `backward` places it before that kill rather than after, so that
+    /// repeatedly killing words backwards yields them in order.
+    pub fn kill(&mut self, text: &str, accumulate: bool, backward: bool) {
+        if text.is_empty() {
+            return;
+        }
+        match self.entries.front_mut() {
+            Some(front) if accumulate => {
+                if backward {
+                    front.insert_str(0, text);
+                } else {
+                    front.push_str(text);
+                }
+            }
+            _ => {
+                if self.entries.len() == KILL_RING_SIZE {
+                    self.entries.pop_back();
+                }
+                self.entries.push_front(text.to_string());
+            }
+        }
+        self.yank_index = 0;
+    }
+
+    /// Returns the most recent kill
+    pub fn yank(&mut self) -> Option<&str> {
+        self.yank_index = 0;
+        self.entries.front().map(String::as_str)
+    }
+
+    /// Rotates to the kill prior to the one most recently yanked,
+    /// wrapping around to the most recent kill after the oldest
+    pub fn yank_pop(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.yank_index = (self.yank_index + 1) % self.entries.len();
+        self.entries.get(self.yank_index).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accumulate_and_rotate() {
+        let mut ring = KillRing::default();
+        assert_eq!(ring.yank(), None);
+
+        ring.kill("one", false, false);
+        ring.kill("three ", false, true);
+        ring.kill("two ", true, true);
+        ring.kill("four", true, false);
+
+        assert_eq!(ring.yank(), Some("two three four"));
+        assert_eq!(ring.yank_pop(), Some("one"));
+        assert_eq!(ring.yank_pop(), Some("two three four"));
+        assert_eq!(ring.yank_pop(), Some("one"));
+
+        for n in 0..20 {
+            ring.kill(&n.to_string(), false, false);
+        }
+        assert_eq!(ring.yank(), Some("19"));
+        for _ in 0..9 {
+            ring.yank_pop();
+        }
+        assert_eq!(ring.yank_pop(), Some("19"));
+    }
+}
@@ -16,6 +16,12 @@
 //! }
 //! ```
 //!
+//! `read_line` waits for input from the terminal until the line is
+//! complete.  Applications with their own event loop can instead
+//! call `LineEditor::begin_line` and then pass each input event to
+//! `LineEditor::feed_input`, using `LineEditor::print_above` to show
+//! output without disturbing the line being edited.
+//!
 //! ## Key Bindings
 //!
 //! The following key bindings are supported:
@@ -23,32 +29,75 @@
 //! Keystroke     | Action
 //! ---------     | ------
 //! Ctrl-A, Home  | Move cursor to the beginning of the line
-//! Ctrl-E, End   | Move cursor to the end of the line
+//! Ctrl-E, End   | Move cursor to the end of the line, or accept a suggestion
 //! Ctrl-B, Left  | Move cursor one grapheme to the left
 //! Ctrl-C        | Cancel the line editor
 //! Ctrl-D        | Cancel the line editor with an End-of-File result
-//! Ctrl-F, Right | Move cursor one grapheme to the right
+//! Ctrl-F, Right | Move cursor one grapheme to the right, or accept a suggestion
 //! Ctrl-H, Backspace | Delete the grapheme to the left of the cursor
 //! Delete        | Delete the grapheme to the right of the cursor
 //! Ctrl-J, Ctrl-M, Enter | Finish line editing and accept the current line
+//! Alt-Enter     | Insert a newline
 //! Ctrl-K        | Delete from cursor to end of line
 //! Ctrl-L        | Move the cursor to the top left, clear screen and repaint
 //! Ctrl-R        | Incremental history search mode
 //! Ctrl-W        | Delete word leading up to cursor
+//! Tab           | Complete the word at the cursor, or select the next completion
+//! Shift-Tab     | Select the previous completion
+//! Ctrl-Y        | Insert the most recently deleted text
+//! Ctrl-_        | Undo the last edit
 //! Alt-b, Alt-Left | Move the cursor backwards one word
-//! Alt-f, Alt-Right | Move the cursor forwards one word
+//! Alt-f, Alt-Right | Move the cursor forwards one word, or accept the next word of a suggestion
+//! Alt-y         | After Ctrl-Y, replace the inserted text with the deleted text before it
+//! Up, Down, Ctrl-P, Ctrl-N | Move between the lines of input that spans multiple lines, then through the history
+//!
+//! Text removed by Ctrl-K and Ctrl-W is saved in a kill ring, from which
+//! Ctrl-Y and Alt-y retrieve it; consecutive deletions are joined together.
+//! Deleting single characters does not affect the kill ring.
+//!
+//! When the cursor is at the end of the input, the text returned by
+//! `LineEditorHost::suggestion` is shown dimmed after it, and the
+//! movement keys above accept it.
+//!
+//! When `LineEditorHost::is_complete` reports that the input is incomplete,
+//! Enter inserts a newline rather than accepting it, and editing continues
+//! on the next line after the continuation prompt.
+//!
+//! ## Vi Mode
+//!
+//! `LineEditor::set_edit_mode(EditMode::Vi)` enables vi style editing.
+//! Each line starts in insert mode, where the keys above apply, and
+//! Escape switches to normal mode.  `LineEditorHost::vi_mode_changed`
+//! is called whenever the mode changes so that the prompt can show it.
+//!
+//! Normal mode supports counts (`3w`), the motions `h l w b e f t F T 0 ^ $`
+//! (words are delimited by whitespace), the operators `d c y` combined
+//! with a motion, the text objects `iw aw`, or doubled to act on the
+//! whole line, `x X s S C D Y p P`,
+//! `i a I A` to return to insert mode, `u` and Ctrl-R to undo and redo,
+//! `k j` to walk the history,
+//! `/` to search it and `v` for visual mode, in which the motions
+//! extend the selection and `d c y x s` act upon it.
 use crate::caps::{Capabilities, ProbeHints};
+use crate::cell::{unicode_column_width, AttributeChange, CellAttributes, Intensity};
 use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
 use crate::surface::change::ChangeSequence;
 use crate::surface::{Change, Position};
 use crate::terminal::{new_terminal, Terminal};
 use crate::{bail, ensure, Result};
-use unicode_segmentation::GraphemeCursor;
+use std::ops::Range;
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
 mod actions;
+pub(crate) mod bindings;
 mod history;
 mod host;
-pub use actions::{Action, Movement, RepeatCount};
+pub(crate) mod killring;
+pub(crate) mod movement;
+mod rows;
+pub(crate) mod undo;
+mod vi;
+pub use actions::{Action, MenuMovement, Movement, Operator, RepeatCount, ViMode};
 pub use history::*;
 pub use host::*;
 
@@ -71,6 +120,7 @@ pub use host::*;
 pub struct LineEditor<'term> {
     terminal: &'term mut dyn Terminal,
     prompt: String,
+    continuation_prompt: String,
     line: String,
     /// byte index into the UTF-8 string data of the insertion
     /// point.  This is NOT the number of graphemes!
@@ -85,6 +135,55 @@ pub struct LineEditor<'term> {
     move_to_editor_end: Option<Change>,
 
     state: EditorState,
+
+    search_style: SearchStyle,
+    /// How many search candidates to list below the prompt while
+    /// searching; zero shows just the current match
+    search_candidate_rows: usize,
+
+    completion_menu: CompletionMenu,
+    /// The most rows of the completion menu to show at once
+    completion_menu_rows: usize,
+
+    edit_mode: EditMode,
+    vi: vi::ViState,
+
+    undo: undo::UndoStack,
+    kill_ring: killring::KillRing,
+    last_action: LastAction,
+}
+
+/// The kind of the previously applied action, for the actions
+/// whose behavior depends on what came before them
+#[derive(Debug, Default)]
+pub(crate) enum LastAction {
+    #[default]
+    Other,
+    /// Text was saved to the kill ring
+    Kill,
+    /// The byte range of the line that was inserted by a yank
+    Yank(Range<usize>),
+}
+
+/// The outcome of passing input to `LineEditor::feed_input`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditStatus {
+    /// The line is still being edited
+    Editing,
+    /// The line was accepted
+    Accepted(String),
+    /// Editing was cancelled, eg: via CTRL-C
+    Cancelled,
+}
+
+/// Selects the key bindings used by the `LineEditor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EditMode {
+    /// Emacs style bindings, as used by default in most shells
+    #[default]
+    Emacs,
+    /// Vi style modal editing, similar to `set -o vi` in bash
+    Vi,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -98,40 +197,114 @@ enum EditorState {
         direction: SearchDirection,
         matching_line: String,
         cursor: usize,
+        /// The byte ranges of matching_line that matched the pattern
+        matches: Vec<Range<usize>>,
+        candidates: Vec<SearchCandidate>,
+        selected: usize,
     },
 }
 
 struct CompletionState {
     candidates: Vec<CompletionCandidate>,
-    index: usize,
+    /// The selected candidate.  This is `None` when the menu is first
+    /// shown, in which case the line holds the common prefix of the
+    /// candidates.
+    index: Option<usize>,
     original_line: String,
     original_cursor: usize,
+    /// The number of columns in the menu grid as most recently
+    /// rendered, so that Up and Down can move by whole rows
+    columns: usize,
 }
 
 impl CompletionState {
-    fn next(&mut self) {
-        self.index += 1;
-        if self.index >= self.candidates.len() {
-            self.index = 0;
+    fn select(&mut self, movement: MenuMovement) {
+        let len = self.candidates.len();
+        let columns = self.columns.max(1);
+        self.index = Some(match (self.index, movement) {
+            (None, MenuMovement::Next) | (None, MenuMovement::Down) => 0,
+            (None, MenuMovement::Previous) | (None, MenuMovement::Up) => len - 1,
+            (Some(idx), MenuMovement::Next) => (idx + 1) % len,
+            (Some(idx), MenuMovement::Previous) => (idx + len - 1) % len,
+            // Moving off the bottom or top of the grid wraps around
+            // to the same column at the other end
+            (Some(idx), MenuMovement::Down) if idx + columns < len => idx + columns,
+            (Some(idx), MenuMovement::Down) => idx % columns,
+            (Some(idx), MenuMovement::Up) if idx >= columns => idx - columns,
+            (Some(idx), MenuMovement::Up) => {
+                let rows = len.div_ceil(columns);
+                let bottom = idx + (rows - 1) * columns;
+                if bottom >= len {
+                    bottom - columns
+                } else {
+                    bottom
+                }
+            }
+        });
+    }
+
+    /// Returns the range of the original line to be replaced, and its
+    /// replacement, for the longest prefix shared by all candidates.
+    /// That is only meaningful if they all replace the same range and
+    /// the prefix is at least as long as the text it replaces.
+    fn common_prefix(&self) -> (Range<usize>, &str) {
+        let first = &self.candidates[0];
+        let mut len = first.text.len();
+        for candidate in &self.candidates[1..] {
+            if candidate.range != first.range {
+                return (self.original_cursor..self.original_cursor, "");
+            }
+            len = first
+                .text
+                .char_indices()
+                .zip(candidate.text.chars())
+                .take_while(|((_, a), b)| a == b)
+                .last()
+                .map_or(0, |((idx, c), _)| idx + c.len_utf8())
+                .min(len);
+        }
+        if len < first.range.end - first.range.start {
+            return (self.original_cursor..self.original_cursor, "");
         }
+        (first.range.clone(), &first.text[..len])
     }
 
     fn current(&self) -> (usize, String) {
         let mut line = self.original_line.clone();
-        let candidate = &self.candidates[self.index];
-        line.replace_range(candidate.range.clone(), &candidate.text);
+        let (range, text) = match self.index {
+            Some(idx) => {
+                let candidate = &self.candidates[idx];
+                (candidate.range.clone(), candidate.text.as_str())
+            }
+            None => self.common_prefix(),
+        };
+        line.replace_range(range.clone(), text);
 
         // To figure the new cursor position do a little math:
         // "he<TAB>" when the completion is "hello" will set the completion
         // candidate to replace "he" with "hello", so the difference in the
         // lengths of these two is how far the cursor needs to move.
-        let range_len = candidate.range.end - candidate.range.start;
-        let new_cursor = self.original_cursor + candidate.text.len() - range_len;
+        let range_len = range.end - range.start;
+        let new_cursor = self.original_cursor + text.len() - range_len;
 
         (new_cursor, line)
     }
 }
 
+/// Selects how the candidates for tab completion are presented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompletionMenu {
+    /// No menu is shown; each Tab replaces the input with the
+    /// next candidate
+    #[default]
+    None,
+    /// The candidates are arranged in columns below the input
+    Grid,
+    /// The candidates are listed one per line below the input,
+    /// alongside their descriptions
+    List,
+}
+
 impl<'term> LineEditor<'term> {
     /// Create a new line editor.
     /// In most cases, you'll want to use the `line_editor` function,
@@ -155,6 +328,7 @@ impl<'term> LineEditor<'term> {
         Self {
             terminal,
             prompt: "> ".to_owned(),
+            continuation_prompt: "... ".to_owned(),
             line: String::new(),
             cursor: 0,
             history_pos: None,
@@ -163,6 +337,15 @@ impl<'term> LineEditor<'term> {
             move_to_editor_start: None,
             move_to_editor_end: None,
             state: EditorState::Inactive,
+            search_style: SearchStyle::Substring,
+            search_candidate_rows: 0,
+            completion_menu: CompletionMenu::None,
+            completion_menu_rows: 10,
+            edit_mode: EditMode::Emacs,
+            vi: vi::ViState::default(),
+            undo: undo::UndoStack::default(),
+            kill_ring: killring::KillRing::default(),
+            last_action: LastAction::Other,
         }
     }
 
@@ -178,21 +361,27 @@ impl<'term> LineEditor<'term> {
         }
         changes.add(Change::AllAttributes(Default::default()));
 
+        let cursor_position_after_printing_prompt = changes.current_cursor_position();
+
         // If we're searching, the input area shows the match rather than the input,
         // and the cursor moves to the first matching character
-        let (line_to_display, cursor) = match &self.state {
+        let (elements, cursor_x_pos) = match &self.state {
             EditorState::Searching {
                 matching_line,
                 cursor,
+                matches,
                 ..
-            } => (matching_line, *cursor),
-            _ => (&self.line, self.cursor),
+            } => (
+                host.highlight_search_match(matching_line, matches),
+                unicode_column_width(&matching_line[0..*cursor], None),
+            ),
+            _ if self.vi_mode() == Some(ViMode::Visual) => (
+                host.highlight_selection(&self.line, self.selection_range()),
+                unicode_column_width(&self.line[0..self.cursor], None),
+            ),
+            _ => host.highlight_line(&self.line, self.cursor),
         };
 
-        let cursor_position_after_printing_prompt = changes.current_cursor_position();
-
-        let (elements, cursor_x_pos) = host.highlight_line(line_to_display, cursor);
-
         // Calculate what the cursor position would be after printing X columns
         // of text from the specified location.
         // Returns (x, y) of the resultant cursor position.
@@ -210,47 +399,124 @@ impl<'term> LineEditor<'term> {
 
             (col, row)
         }
+
+        // If the cursor position remains in the first column
+        // then the renderer may still consider itself to be on
+        // the prior line; force out an additional character to force
+        // it to apply wrapping/flush.
+        fn flush_wrap(changes: &mut ChangeSequence, screen_cols: usize) {
+            if changes.current_cursor_position().0 == screen_cols {
+                changes.add(" ");
+            }
+        }
+
+        // Input spanning multiple lines is rendered a row at a time,
+        // with each subsequent row following the continuation prompt.
+        // The position at which each row begins is recorded so that
+        // the cursor can be placed within its row.
+        let mut row_starts = vec![cursor_position_after_printing_prompt];
+        let mut attributes = CellAttributes::default();
+        for ele in elements {
+            match ele {
+                OutputElement::Text(text) if text.contains('\n') => {
+                    for (idx, part) in text.split('\n').enumerate() {
+                        if idx > 0 {
+                            flush_wrap(&mut changes, screen_size.cols);
+                            changes.add("\r\n");
+                            changes.add(Change::AllAttributes(Default::default()));
+                            for ele in host.render_prompt(&self.continuation_prompt) {
+                                changes.add(ele);
+                            }
+                            // Resume with the attributes that were set by
+                            // the highlighting, so that eg: a multi-line
+                            // string retains its color
+                            changes.add(Change::AllAttributes(attributes.clone()));
+                            row_starts.push(changes.current_cursor_position());
+                        }
+                        if !part.is_empty() {
+                            changes.add(part);
+                        }
+                    }
+                }
+                OutputElement::Attribute(change) => {
+                    attributes.apply_change(&change);
+                    changes.add(Change::Attribute(change));
+                }
+                OutputElement::AllAttributes(all) => {
+                    attributes = all.clone();
+                    changes.add(Change::AllAttributes(all));
+                }
+                ele => changes.add(ele),
+            }
+        }
+
+        // The suggestion follows the input, but the cursor remains
+        // at the end of the input
+        if let Some(suggestion) = self.suggestion(host) {
+            changes.add(Change::AllAttributes(Default::default()));
+            for ele in host.render_suggestion(suggestion.lines().next().unwrap_or("")) {
+                changes.add(ele);
+            }
+        }
+
+        let (cursor_row, cursor_x_pos) = if row_starts.len() > 1 {
+            let (line, cursor) = match &self.state {
+                EditorState::Searching {
+                    matching_line,
+                    cursor,
+                    ..
+                } => (matching_line.as_str(), *cursor),
+                _ => (self.line.as_str(), self.cursor),
+            };
+            let row = rows::row_range(line, cursor);
+            (
+                rows::row_index(line, cursor).min(row_starts.len() - 1),
+                unicode_column_width(&line[row.start..cursor], None),
+            )
+        } else {
+            (0, cursor_x_pos)
+        };
         let cursor_position = compute_cursor_after_printing_x_columns(
-            cursor_position_after_printing_prompt.0,
-            cursor_position_after_printing_prompt.1,
+            row_starts[cursor_row].0,
+            row_starts[cursor_row].1,
             cursor_x_pos,
             screen_size.cols,
         );
 
-        for ele in elements {
-            changes.add(ele);
-        }
+        flush_wrap(&mut changes, screen_size.cols);
 
-        let cursor_after_line_render = changes.current_cursor_position();
-        if cursor_after_line_render.0 == screen_size.cols {
-            // If the cursor position remains in the first column
-            // then the renderer may still consider itself to be on
-            // the prior line; force out an additional character to force
-            // it to apply wrapping/flush.
-            changes.add(" ");
+        if let EditorState::Editing = &self.state {
+            add_preview(&mut changes, host.render_preview(&self.line));
         }
 
-        if let EditorState::Editing = &self.state {
-            let preview_elements = host.render_preview(line_to_display);
-            if !preview_elements.is_empty() {
-                // Preview starts from a new line.
-                changes.add("\r\n");
-                // Do not be affected by attributes set by highlight_line.
-                changes.add(Change::AllAttributes(Default::default()));
-                for ele in preview_elements {
-                    changes.add(ele);
+        if let Some(state) = &self.completion {
+            if self.completion_menu != CompletionMenu::None {
+                let columns = self.render_completion_menu(state, &mut changes, screen_size.cols);
+                if let Some(state) = self.completion.as_mut() {
+                    state.columns = columns;
                 }
             }
         }
 
         if let EditorState::Searching {
-            style, direction, ..
+            style,
+            direction,
+            candidates,
+            selected,
+            matching_line,
+            ..
         } = &self.state
         {
             // We want to draw the search state below the input area
-            let label = match (style, direction) {
-                (SearchStyle::Substring, SearchDirection::Backwards) => "bck-i-search",
-                (SearchStyle::Substring, SearchDirection::Forwards) => "fwd-i-search",
+            let direction = match direction {
+                SearchDirection::Backwards => "bck",
+                SearchDirection::Forwards => "fwd",
+            };
+            let style = match style {
+                SearchStyle::Substring => "i-search",
+                SearchStyle::Prefix => "prefix-search",
+                SearchStyle::Regex => "regex-search",
+                SearchStyle::Fuzzy => "fuzzy-search",
             };
             // Do not be affected by attributes set by previous lines.
             changes.add(Change::AllAttributes(Default::default()));
@@ -258,7 +524,38 @@ impl<'term> LineEditor<'term> {
             // the text in the line editing area, but since the input
             // is drawn here, we render an `_` to indicate where the input
             // position really is.
-            changes.add(format!("\r\n{}: {}_", label, self.line));
+            changes.add(format!("\r\n{}-{}: {}_", direction, style, self.line));
+
+            // The preview area below holds the list of candidates, if
+            // enabled, followed by the host's preview of the line that
+            // Enter would accept.  Each candidate is limited to its
+            // first line and to the width of the screen so that the
+            // list occupies a predictable number of rows.
+            let width = screen_size.cols.saturating_sub(3);
+            let mut preview_elements = vec![];
+            for (idx, candidate) in candidates.iter().enumerate() {
+                if idx > 0 {
+                    preview_elements.push(OutputElement::Text("\r\n".to_string()));
+                    preview_elements.push(OutputElement::AllAttributes(Default::default()));
+                }
+                if idx == *selected {
+                    preview_elements.push(OutputElement::Attribute(AttributeChange::Reverse(true)));
+                    preview_elements.push(OutputElement::Text("> ".to_string()));
+                } else {
+                    preview_elements.push(OutputElement::Text("  ".to_string()));
+                }
+                let line = truncate_to_width(candidate.line.lines().next().unwrap_or(""), width);
+                let matches: Vec<Range<usize>> = candidate
+                    .matched
+                    .ranges
+                    .iter()
+                    .filter(|r| r.start < line.len())
+                    .map(|r| r.start..r.end.min(line.len()))
+                    .collect();
+                preview_elements.extend(host.highlight_search_match(line, &matches));
+            }
+            add_preview(&mut changes, preview_elements);
+            add_preview(&mut changes, host.render_preview(matching_line));
         }
 
         // Add some debugging status at the bottom
@@ -293,15 +590,182 @@ impl<'term> LineEditor<'term> {
         Ok(())
     }
 
+    /// Draws the completion menu below the input and any preview.
+    /// Returns the number of columns in which the candidates were arranged.
+    fn render_completion_menu(
+        &self,
+        state: &CompletionState,
+        changes: &mut ChangeSequence,
+        screen_cols: usize,
+    ) -> usize {
+        // Candidates are shown using the first line of their display text
+        let display = |candidate: &CompletionCandidate| -> String {
+            let text = candidate.display.as_deref().unwrap_or(&candidate.text);
+            text.lines().next().unwrap_or("").to_string()
+        };
+        const GUTTER: usize = 2;
+        let max_width = screen_cols.saturating_sub(1).max(1);
+        let item_width = state
+            .candidates
+            .iter()
+            .map(|c| unicode_column_width(&display(c), None))
+            .max()
+            .unwrap_or(0)
+            .min(max_width);
+
+        let columns = match self.completion_menu {
+            CompletionMenu::Grid => ((max_width + GUTTER) / (item_width + GUTTER)).max(1),
+            _ => 1,
+        };
+        let total_rows = state.candidates.len().div_ceil(columns);
+        let selected_row = state.index.map_or(0, |idx| idx / columns);
+        let first_row = (selected_row + 1).saturating_sub(self.completion_menu_rows);
+        let last_row = (first_row + self.completion_menu_rows).min(total_rows);
+
+        for row in first_row..last_row {
+            changes.add("\r\n");
+            changes.add(Change::AllAttributes(Default::default()));
+            for col in 0..columns {
+                let idx = row * columns + col;
+                let candidate = match state.candidates.get(idx) {
+                    Some(candidate) => candidate,
+                    None => break,
+                };
+                if col > 0 {
+                    changes.add(" ".repeat(GUTTER));
+                }
+                let text = display(candidate);
+                let text = truncate_to_width(&text, item_width);
+                let padding = item_width - unicode_column_width(text, None);
+
+                if state.index == Some(idx) {
+                    changes.add(Change::Attribute(AttributeChange::Reverse(true)));
+                }
+                changes.add(format!("{}{}", text, " ".repeat(padding)));
+                changes.add(Change::Attribute(AttributeChange::Reverse(false)));
+
+                if self.completion_menu == CompletionMenu::List {
+                    if let Some(description) = &candidate.description {
+                        let width = max_width.saturating_sub(item_width + GUTTER);
+                        let description =
+                            truncate_to_width(description.lines().next().unwrap_or(""), width);
+                        if !description.is_empty() {
+                            changes.add(" ".repeat(GUTTER));
+                            changes.add(Change::Attribute(AttributeChange::Intensity(
+                                Intensity::Half,
+                            )));
+                            changes.add(description);
+                            changes.add(Change::Attribute(AttributeChange::Intensity(
+                                Intensity::Normal,
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+
+        if last_row - first_row < total_rows {
+            changes.add("\r\n");
+            changes.add(Change::AllAttributes(Default::default()));
+            changes.add(Change::Attribute(AttributeChange::Intensity(
+                Intensity::Half,
+            )));
+            changes.add(format!(
+                "rows {}-{} of {}",
+                first_row + 1,
+                last_row,
+                total_rows
+            ));
+        }
+
+        columns
+    }
+
     pub fn set_prompt(&mut self, prompt: &str) {
         self.prompt = prompt.to_owned();
     }
 
+    /// Set the prompt that is shown at the start of each subsequent
+    /// line when the input spans multiple lines.  Like the primary
+    /// prompt it is rendered via `LineEditorHost::render_prompt`.
+    /// The default is `"... "`.
+    pub fn set_continuation_prompt(&mut self, prompt: &str) {
+        self.continuation_prompt = prompt.to_owned();
+    }
+
+    /// Set how the incremental history search (Ctrl-R and Ctrl-S)
+    /// matches the pattern against history entries.
+    /// The default is `SearchStyle::Substring`.
+    pub fn set_history_search_style(&mut self, style: SearchStyle) {
+        self.search_style = style;
+    }
+
+    /// When `rows` is non-zero, the incremental history search lists
+    /// up to `rows` of the best matching history entries below the
+    /// prompt, rather than showing only the most recent match.
+    /// Ctrl-R, Ctrl-S and the arrow keys move the selection through
+    /// the list, and Enter accepts the selected entry.
+    pub fn set_history_search_candidates(&mut self, rows: usize) {
+        self.search_candidate_rows = rows;
+    }
+
+    /// Selects how tab completion candidates are presented.
+    /// With a menu, the first Tab inserts the prefix common to all
+    /// of the candidates and shows the menu; further presses of Tab
+    /// and Shift-Tab, or the arrow keys, select from the menu and
+    /// Enter accepts the selection.
+    /// The default is `CompletionMenu::None`.
+    pub fn set_completion_menu(&mut self, menu: CompletionMenu) {
+        self.completion_menu = menu;
+    }
+
+    /// Sets the most rows of the completion menu that are shown at
+    /// once; the menu scrolls to keep the selection visible.
+    /// The default is 10.
+    pub fn set_completion_menu_rows(&mut self, rows: usize) {
+        self.completion_menu_rows = rows.max(1);
+    }
+
+    /// Selects between emacs and vi style key bindings.
+    /// The default is `EditMode::Emacs`.
+    pub fn set_edit_mode(&mut self, mode: EditMode) {
+        self.edit_mode = mode;
+        self.vi = vi::ViState::default();
+    }
+
+    /// Returns the current vi mode, or `None` when using emacs bindings
+    pub fn vi_mode(&self) -> Option<ViMode> {
+        match self.edit_mode {
+            EditMode::Vi => Some(self.vi.mode),
+            EditMode::Emacs => None,
+        }
+    }
+
+    fn set_vi_mode(&mut self, host: &mut dyn LineEditorHost, mode: ViMode) {
+        self.vi.mode = mode;
+        self.vi.reset_pending();
+        host.vi_mode_changed(mode);
+    }
+
     /// Enter line editing mode.
     /// Control is not returned to the caller until a line has been
     /// accepted, or until an error is detected.
     /// Returns Ok(None) if the editor was cancelled eg: via CTRL-C.
     pub fn read_line(&mut self, host: &mut dyn LineEditorHost) -> Result<Option<String>> {
+        self.begin_line(host)?;
+        let res = self.read_line_impl(host);
+        self.end_line()?;
+        res
+    }
+
+    /// Enter line editing mode without waiting for input.
+    /// This is the non-blocking counterpart to `read_line` for use
+    /// with an external event loop: the prompt is displayed and the
+    /// application then passes each `InputEvent` that it receives to
+    /// `feed_input` until the line is accepted or cancelled.
+    /// `LineEditor::terminal` provides access to the terminal, so that
+    /// the event loop can poll it for input.
+    pub fn begin_line(&mut self, host: &mut dyn LineEditorHost) -> Result<()> {
         ensure!(
             self.state == EditorState::Inactive,
             "recursive call to read_line!"
@@ -314,7 +778,49 @@ impl<'term> LineEditor<'term> {
 
         self.terminal.set_raw_mode()?;
         self.state = EditorState::Editing;
-        let res = self.read_line_impl(host);
+
+        self.line.clear();
+        self.cursor = 0;
+        self.history_pos = None;
+        self.bottom_line = None;
+        self.clear_completion();
+        self.undo.clear();
+        self.last_action = LastAction::Other;
+        if self.edit_mode == EditMode::Vi {
+            self.vi.anchor = 0;
+            self.set_vi_mode(host, ViMode::Insert);
+        }
+
+        if let Err(err) = self.render(host) {
+            self.end_line()?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Processes an input event for the line started by `begin_line`.
+    /// Once the line is accepted or cancelled, the editor leaves line
+    /// editing mode and `begin_line` may be called again.  The editor
+    /// also leaves line editing mode if an error is returned.
+    pub fn feed_input(
+        &mut self,
+        host: &mut dyn LineEditorHost,
+        event: &InputEvent,
+    ) -> Result<EditStatus> {
+        ensure!(
+            self.state != EditorState::Inactive,
+            "feed_input called without begin_line!"
+        );
+        let res = self.process_event(host, event);
+        if !matches!(res, Ok(EditStatus::Editing)) {
+            self.end_line()?;
+        }
+        res
+    }
+
+    /// Leave line editing mode, clearing away any state that was
+    /// rendered below the input
+    fn end_line(&mut self) -> Result<()> {
         self.state = EditorState::Inactive;
 
         if let Some(move_end) = self.move_to_editor_end.take() {
@@ -324,7 +830,47 @@ impl<'term> LineEditor<'term> {
 
         self.terminal.flush()?;
         self.terminal.set_cooked_mode()?;
-        res
+        Ok(())
+    }
+
+    /// Writes `text` to the terminal above the prompt, then redraws
+    /// the prompt and the input below it.  This allows output that
+    /// arrives while a line is being edited, such as a log message
+    /// from a background task, to be shown without corrupting the input.
+    /// When no line is being edited the text is written as-is.
+    pub fn print_above(&mut self, host: &mut dyn LineEditorHost, text: &str) -> Result<()> {
+        if self.state == EditorState::Inactive {
+            self.terminal.render(&[Change::Text(text.to_owned())])?;
+            return self.terminal.flush();
+        }
+
+        let mut changes = vec![];
+        if let Some(start) = self.move_to_editor_start.take() {
+            changes.push(start);
+        }
+        changes.push(Change::ClearToEndOfScreen(Default::default()));
+        changes.push(Change::AllAttributes(Default::default()));
+
+        // The terminal is in raw mode, so each line feed needs a
+        // carriage return, and the text must end with one so that
+        // the prompt begins on a line of its own
+        let mut text = text.replace("\r\n", "\n").replace('\n', "\r\n");
+        if !text.ends_with("\r\n") {
+            text.push_str("\r\n");
+        }
+        changes.push(Change::Text(text));
+        self.terminal.render(&changes)?;
+
+        // The cursor is now where the prompt should start, so the
+        // render doesn't need to move back to it
+        self.render(host)?;
+        self.terminal.flush()
+    }
+
+    /// Returns the terminal used by the editor, so that an application
+    /// using `begin_line` and `feed_input` can poll it for input
+    pub fn terminal(&mut self) -> &mut dyn Terminal {
+        &mut *self.terminal
     }
 
     fn resolve_action(
@@ -336,299 +882,183 @@ impl<'term> LineEditor<'term> {
             return Some(action);
         }
 
-        match event {
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('C'),
-                modifiers: Modifiers::CTRL,
-            }) => Some(Action::Cancel),
+        if let InputEvent::Key(key) = event {
+            if key.is_release() {
+                // Key releases are only reported when the kitty keyboard
+                // protocol is enabled; the editor acts on presses alone.
+                return None;
+            }
+        }
 
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Tab,
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::Complete),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('D'),
-                modifiers: Modifiers::CTRL,
-            }) => Some(Action::EndOfFile),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('J'),
-                modifiers: Modifiers::CTRL,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('M'),
-                modifiers: Modifiers::CTRL,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::Enter,
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::AcceptLine),
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('H'),
-                modifiers: Modifiers::CTRL,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::Backspace,
+        // While the completion menu is shown, the arrow keys move
+        // its selection rather than the cursor
+        if self.completion_menu != CompletionMenu::None && self.completion.is_some() {
+            if let InputEvent::Key(KeyEvent {
+                key,
                 modifiers: Modifiers::NONE,
-            }) => Some(Action::Kill(Movement::BackwardChar(1))),
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Delete,
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::KillAndMove(
-                Movement::ForwardChar(1),
-                Movement::None,
-            )),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('P'),
-                modifiers: Modifiers::CTRL,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::UpArrow,
-                modifiers: Modifiers::NONE,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::ApplicationUpArrow,
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::HistoryPrevious),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('N'),
-                modifiers: Modifiers::CTRL,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::DownArrow,
-                modifiers: Modifiers::NONE,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::ApplicationDownArrow,
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::HistoryNext),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('B'),
-                modifiers: Modifiers::CTRL,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::ApplicationLeftArrow,
-                modifiers: Modifiers::NONE,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::LeftArrow,
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::Move(Movement::BackwardChar(1))),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('W'),
-                modifiers: Modifiers::CTRL,
-            }) => Some(Action::Kill(Movement::BackwardWord(1))),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('b'),
-                modifiers: Modifiers::ALT,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::LeftArrow,
-                modifiers: Modifiers::ALT,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::ApplicationLeftArrow,
-                modifiers: Modifiers::ALT,
-            }) => Some(Action::Move(Movement::BackwardWord(1))),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('f'),
-                modifiers: Modifiers::ALT,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::RightArrow,
-                modifiers: Modifiers::ALT,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::ApplicationRightArrow,
-                modifiers: Modifiers::ALT,
-            }) => Some(Action::Move(Movement::ForwardWord(1))),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('A'),
-                modifiers: Modifiers::CTRL,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::Home,
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::Move(Movement::StartOfLine)),
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('E'),
-                modifiers: Modifiers::CTRL,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::End,
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::Move(Movement::EndOfLine)),
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('F'),
-                modifiers: Modifiers::CTRL,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::RightArrow,
-                modifiers: Modifiers::NONE,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::ApplicationRightArrow,
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::Move(Movement::ForwardChar(1))),
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char(c),
-                modifiers: Modifiers::SHIFT,
-            })
-            | InputEvent::Key(KeyEvent {
-                key: KeyCode::Char(c),
-                modifiers: Modifiers::NONE,
-            }) => Some(Action::InsertChar(1, *c)),
-            InputEvent::Paste(text) => Some(Action::InsertText(1, text.clone())),
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('L'),
-                modifiers: Modifiers::CTRL,
-            }) => Some(Action::Repaint),
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('K'),
-                modifiers: Modifiers::CTRL,
-            }) => Some(Action::Kill(Movement::EndOfLine)),
-
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('R'),
-                modifiers: Modifiers::CTRL,
-            }) => Some(Action::HistoryIncSearchBackwards),
-
-            // This is the common binding for forwards, but it is usually
-            // masked by the stty stop setting
-            InputEvent::Key(KeyEvent {
-                key: KeyCode::Char('S'),
-                modifiers: Modifiers::CTRL,
-            }) => Some(Action::HistoryIncSearchForwards),
+                ..
+            }) = event
+            {
+                let movement = match key {
+                    KeyCode::UpArrow | KeyCode::ApplicationUpArrow => Some(MenuMovement::Up),
+                    KeyCode::DownArrow | KeyCode::ApplicationDownArrow => Some(MenuMovement::Down),
+                    KeyCode::LeftArrow | KeyCode::ApplicationLeftArrow => {
+                        Some(MenuMovement::Previous)
+                    }
+                    KeyCode::RightArrow | KeyCode::ApplicationRightArrow => {
+                        Some(MenuMovement::Next)
+                    }
+                    _ => None,
+                };
+                if let Some(movement) = movement {
+                    return Some(Action::SelectCompletion(movement));
+                }
+            }
+        }
 
-            _ => None,
+        let searching = matches!(self.state, EditorState::Searching { .. });
+        match self.vi_mode() {
+            Some(ViMode::Normal) | Some(ViMode::Visual) if !searching => {
+                if let Some(action) = self.vi.resolve(event) {
+                    return Some(action);
+                }
+            }
+            Some(_) => {
+                // Escape leaves insert mode, or abandons a history search
+                if let InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    ..
+                }) = event
+                {
+                    return Some(Action::ViNormal);
+                }
+            }
+            _ => {}
         }
+
+        bindings::emacs_action(event)
     }
 
     /// Compute the cursor position after applying movement
     fn eval_movement(&self, movement: Movement) -> usize {
-        match movement {
-            Movement::BackwardChar(rep) => {
-                let mut position = self.cursor;
-                for _ in 0..rep {
-                    let mut cursor = GraphemeCursor::new(position, self.line.len(), false);
-                    if let Ok(Some(pos)) = cursor.prev_boundary(&self.line, 0) {
-                        position = pos;
-                    } else {
-                        break;
-                    }
-                }
-                position
-            }
-            Movement::BackwardWord(rep) => {
-                let char_indices: Vec<(usize, char)> = self.line.char_indices().collect();
-                if char_indices.is_empty() {
-                    return self.cursor;
-                }
-                let mut char_position = char_indices
-                    .iter()
-                    .position(|(idx, _)| *idx == self.cursor)
-                    .unwrap_or(char_indices.len() - 1);
+        movement::eval_movement(&self.line, self.cursor, movement)
+    }
 
-                for _ in 0..rep {
-                    if char_position == 0 {
-                        break;
-                    }
+    /// Returns the byte index of the grapheme boundary after `pos`
+    fn next_grapheme(&self, pos: usize) -> usize {
+        movement::next_grapheme(&self.line, pos)
+    }
 
-                    let mut found = None;
-                    for prev in (0..char_position - 1).rev() {
-                        if char_indices[prev].1.is_whitespace() {
-                            found = Some(prev + 1);
-                            break;
-                        }
-                    }
+    /// Returns the byte index of the grapheme boundary before `pos`
+    fn prev_grapheme(&self, pos: usize) -> usize {
+        movement::prev_grapheme(&self.line, pos)
+    }
 
-                    char_position = found.unwrap_or(0);
-                }
-                char_indices[char_position].0
+    /// Computes the range of text that an operator applies to
+    /// for the specified movement.  As in vi, the motions that land on
+    /// the last character of their target include that character,
+    /// and a find that fails to match selects nothing.
+    fn operator_range(&self, movement: Movement) -> Range<usize> {
+        let target = self.eval_movement(movement);
+        match movement {
+            Movement::WholeLine => 0..self.line.len(),
+            Movement::InnerWord => vi::word_object(&self.line, self.cursor, false),
+            Movement::AroundWord => vi::word_object(&self.line, self.cursor, true),
+            Movement::ForwardFindChar(..) | Movement::ForwardTillChar(..)
+                if target == self.cursor =>
+            {
+                self.cursor..self.cursor
             }
-            Movement::ForwardWord(rep) => {
-                let char_indices: Vec<(usize, char)> = self.line.char_indices().collect();
-                if char_indices.is_empty() {
-                    return self.cursor;
-                }
-                let mut char_position = char_indices
-                    .iter()
-                    .position(|(idx, _)| *idx == self.cursor)
-                    .unwrap_or_else(|| char_indices.len());
+            Movement::ForwardWordEnd(_)
+            | Movement::ForwardFindChar(..)
+            | Movement::ForwardTillChar(..) => self.cursor..self.next_grapheme(target),
+            _ if target < self.cursor => target..self.cursor,
+            _ => self.cursor..target,
+        }
+    }
 
-                for _ in 0..rep {
-                    // Skip any non-whitespace characters
-                    while char_position < char_indices.len()
-                        && !char_indices[char_position].1.is_whitespace()
-                    {
-                        char_position += 1;
-                    }
+    /// The vi visual mode selection, which includes the characters
+    /// at both the anchor and the cursor
+    fn selection_range(&self) -> Range<usize> {
+        let anchor = self.vi.anchor.min(self.line.len());
+        let start = anchor.min(self.cursor);
+        let end = anchor.max(self.cursor);
+        start..self.next_grapheme(end)
+    }
 
-                    // Skip any whitespace characters
-                    while char_position < char_indices.len()
-                        && char_indices[char_position].1.is_whitespace()
-                    {
-                        char_position += 1;
-                    }
+    fn operate(&mut self, host: &mut dyn LineEditorHost, op: Operator, range: Range<usize>) {
+        self.clear_completion();
+        self.cancel_search_state();
+        if !range.is_empty() {
+            self.vi.register = self.line[range.clone()].to_string();
+        }
+        if op != Operator::Yank {
+            self.line.replace_range(range.clone(), "");
+        }
+        self.cursor = range.start.min(self.line.len());
+        match op {
+            Operator::Change => self.set_vi_mode(host, ViMode::Insert),
+            _ if self.vi.mode == ViMode::Visual => self.set_vi_mode(host, ViMode::Normal),
+            _ => {}
+        }
+    }
 
-                    // We are now on the start of the next word
-                }
-                char_indices
-                    .get(char_position)
-                    .map(|(i, _)| *i)
-                    .unwrap_or_else(|| self.line.len())
-            }
-            Movement::ForwardChar(rep) => {
-                let mut position = self.cursor;
-                for _ in 0..rep {
-                    let mut cursor = GraphemeCursor::new(position, self.line.len(), false);
-                    if let Ok(Some(pos)) = cursor.next_boundary(&self.line, 0) {
-                        position = pos;
-                    } else {
-                        break;
-                    }
-                }
-                position
-            }
-            Movement::StartOfLine => 0,
-            Movement::EndOfLine => {
-                let mut cursor =
-                    GraphemeCursor::new(self.line.len().saturating_sub(1), self.line.len(), false);
-                if let Ok(Some(pos)) = cursor.next_boundary(&self.line, 0) {
-                    pos
-                } else {
-                    self.cursor
-                }
-            }
-            Movement::None => self.cursor,
+    fn paste(&mut self, host: &mut dyn LineEditorHost, rep: RepeatCount, after: bool) {
+        if self.vi.register.is_empty() {
+            return;
         }
+        self.clear_completion();
+        self.cancel_search_state();
+        if after && !self.line.is_empty() {
+            self.cursor = self.next_grapheme(self.cursor);
+        }
+        let text = self.vi.register.repeat(rep.max(1));
+        self.line.insert_str(self.cursor, &text);
+        // Leave the cursor on the last pasted character
+        self.cursor = self.prev_grapheme(self.cursor + text.len());
+        self.reapply_search_pattern(host);
     }
 
-    fn kill_text(&mut self, kill_movement: Movement, move_movement: Movement) {
+    /// Deletes the text between the cursor and the position after
+    /// `kill_movement`, then applies `move_movement`.
+    /// Returns true if the text was saved to the kill ring; `accumulate`
+    /// joins it to the most recent kill.
+    fn kill_text(
+        &mut self,
+        kill_movement: Movement,
+        move_movement: Movement,
+        accumulate: bool,
+    ) -> bool {
         self.clear_completion();
         let kill_pos = self.eval_movement(kill_movement);
         let new_cursor = self.eval_movement(move_movement);
 
-        let (lower, upper) = if kill_pos < self.cursor {
+        let backward = kill_pos < self.cursor;
+        let (lower, upper) = if backward {
             (kill_pos, self.cursor)
         } else {
             (self.cursor, kill_pos)
         };
 
+        // As in readline, deleting single characters doesn't
+        // disturb the kill ring
+        let save = lower < upper
+            && !matches!(
+                kill_movement,
+                Movement::BackwardChar(1) | Movement::ForwardChar(1)
+            );
+        if save {
+            self.kill_ring
+                .kill(&self.line[lower..upper], accumulate, backward);
+        }
+
         self.line.replace_range(lower..upper, "");
 
         // Clamp to the line length, otherwise a kill to end of line
         // command will leave the cursor way off beyond the end of
         // the line.
         self.cursor = new_cursor.min(self.line.len());
+        save
     }
 
     fn clear_completion(&mut self) {
@@ -681,41 +1111,79 @@ impl<'term> LineEditor<'term> {
             direction,
             matching_line,
             cursor,
+            ..
         } = &self.state
         {
+            let (style, direction) = (*style, *direction);
+            let mut last_matching_line = matching_line.clone();
+            let mut last_cursor = *cursor;
+            let mut candidates = vec![];
+
             // We always start again from the bottom
             self.history_pos.take();
 
-            let history_pos = match host.history().last() {
-                Some(p) => p,
-                None => {
-                    // TODO: there's no way we can match anything.
-                    // Generate a failed match result?
-                    return;
+            if self.search_candidate_rows > 0 {
+                candidates =
+                    host.history()
+                        .search_candidates(style, &self.line, self.search_candidate_rows);
+                if let Some(best) = candidates.first() {
+                    self.history_pos.replace(best.idx);
+                    last_matching_line = best.line.clone();
+                    last_cursor = best.matched.ranges.first().map_or(0, |r| r.start);
                 }
-            };
-
-            let last_matching_line;
-            let last_cursor;
-
-            if let Some(result) = host
-                .history()
-                .search(history_pos, *style, *direction, &self.line)
-            {
-                self.history_pos.replace(result.idx);
-                last_matching_line = result.line.to_string();
-                last_cursor = result.cursor;
             } else {
-                last_matching_line = matching_line.clone();
-                last_cursor = *cursor;
+                let history_pos = match host.history().last() {
+                    Some(p) => p,
+                    None => {
+                        // TODO: there's no way we can match anything.
+                        // Generate a failed match result?
+                        return;
+                    }
+                };
+
+                if let Some(result) =
+                    host.history()
+                        .search(history_pos, style, direction, &self.line)
+                {
+                    self.history_pos.replace(result.idx);
+                    last_matching_line = result.line.to_string();
+                    last_cursor = result.cursor;
+                }
             }
 
-            self.state = EditorState::Searching {
-                style: *style,
-                direction: *direction,
-                matching_line: last_matching_line,
-                cursor: last_cursor,
-            };
+            self.state = self.search_state(
+                style,
+                direction,
+                last_matching_line,
+                last_cursor,
+                candidates,
+                0,
+            );
+        }
+    }
+
+    /// Produces the searching state for the current pattern
+    fn search_state(
+        &self,
+        style: SearchStyle,
+        direction: SearchDirection,
+        matching_line: String,
+        cursor: usize,
+        candidates: Vec<SearchCandidate>,
+        selected: usize,
+    ) -> EditorState {
+        let matches = style
+            .find(&self.line, &matching_line)
+            .map(|m| m.ranges)
+            .unwrap_or_default();
+        EditorState::Searching {
+            style,
+            direction,
+            matching_line,
+            cursor,
+            matches,
+            candidates,
+            selected,
         }
     }
 
@@ -737,6 +1205,11 @@ impl<'term> LineEditor<'term> {
             self.history_pos.take();
         }
 
+        if self.search_candidate_rows > 0 {
+            self.select_search_candidate(style, direction, host);
+            return;
+        }
+
         let history_pos = match self.history_pos {
             Some(p) => match direction.next(p) {
                 Some(p) => p,
@@ -776,12 +1249,120 @@ impl<'term> LineEditor<'term> {
             last_cursor = 0;
         }
 
-        self.state = EditorState::Searching {
+        self.state =
+            self.search_state(style, direction, last_matching_line, last_cursor, vec![], 0);
+    }
+
+    /// When listing search candidates, starting a search populates the
+    /// list and repeating the search moves the selection.  The list is
+    /// ordered best match first, so searching backwards moves down
+    /// the list and searching forwards moves up.
+    fn select_search_candidate(
+        &mut self,
+        style: SearchStyle,
+        direction: SearchDirection,
+        host: &mut dyn LineEditorHost,
+    ) {
+        let (candidates, selected) = match &self.state {
+            EditorState::Searching {
+                candidates,
+                selected,
+                ..
+            } if !candidates.is_empty() => {
+                let selected = match direction {
+                    SearchDirection::Backwards => (*selected + 1).min(candidates.len() - 1),
+                    SearchDirection::Forwards => selected.saturating_sub(1),
+                };
+                (candidates.clone(), selected)
+            }
+            _ => (
+                host.history()
+                    .search_candidates(style, &self.line, self.search_candidate_rows),
+                0,
+            ),
+        };
+
+        let (matching_line, cursor) = match candidates.get(selected) {
+            Some(candidate) => {
+                self.history_pos.replace(candidate.idx);
+                (
+                    candidate.line.clone(),
+                    candidate.matched.ranges.first().map_or(0, |r| r.start),
+                )
+            }
+            None => match &self.state {
+                EditorState::Searching {
+                    matching_line,
+                    cursor,
+                    ..
+                } => (matching_line.clone(), *cursor),
+                _ => (String::new(), 0),
+            },
+        };
+
+        self.state = self.search_state(
             style,
             direction,
-            matching_line: last_matching_line,
-            cursor: last_cursor,
+            matching_line,
+            cursor,
+            candidates,
+            selected,
+        );
+    }
+
+    /// Returns the text suggested by the host to follow the input.
+    /// Suggestions are only offered while the cursor is at the end of
+    /// the input, and not while searching or completing.
+    fn suggestion(&self, host: &mut dyn LineEditorHost) -> Option<String> {
+        if self.state != EditorState::Editing
+            || self.cursor != self.line.len()
+            || self.completion.is_some()
+        {
+            return None;
+        }
+        host.suggestion(&self.line).filter(|s| !s.is_empty())
+    }
+
+    /// Starts completion, or when it is already in progress, moves
+    /// the selection to another candidate
+    fn complete(&mut self, host: &mut dyn LineEditorHost, movement: MenuMovement) {
+        self.cancel_search_state();
+
+        if let Some(state) = self.completion.as_mut() {
+            state.select(movement);
+            let (cursor, line) = state.current();
+            self.cursor = cursor;
+            self.line = line;
+            return;
+        }
+
+        let candidates = host.complete(&self.line, self.cursor);
+        if candidates.is_empty() {
+            return;
+        }
+        let mut state = CompletionState {
+            candidates,
+            index: None,
+            original_line: self.line.clone(),
+            original_cursor: self.cursor,
+            columns: 1,
         };
+        // Without a menu there is nothing to choose from, so the
+        // first candidate is applied straight away
+        if state.candidates.len() == 1 || self.completion_menu == CompletionMenu::None {
+            state.select(movement);
+        }
+
+        let (cursor, line) = state.current();
+        self.cursor = cursor;
+        self.line = line;
+
+        // If there is only a single completion then don't
+        // leave us in a state where we just cycle on the
+        // same completion over and over.
+        if state.candidates.len() > 1 {
+            self.completion = Some(state);
+        }
     }
 
     /// Applies the effect of the specified action to the line editor.
@@ -789,28 +1370,73 @@ impl<'term> LineEditor<'term> {
     /// custom key mapping or custom actions in your embedding application.
     pub fn apply_action(&mut self, host: &mut dyn LineEditorHost, action: Action) -> Result<()> {
         // When searching, reinterpret history next/prev as repeated
-        // search actions in the appropriate direction
+        // search actions in the appropriate direction.  The candidate
+        // list shows the best match at the top, so there the arrow keys
+        // move the selection up and down the list instead.
+        let listing = self.search_candidate_rows > 0;
         let action = match (action, &self.state) {
-            (
-                Action::HistoryPrevious,
-                EditorState::Searching {
-                    style: SearchStyle::Substring,
-                    ..
-                },
-            ) => Action::HistoryIncSearchBackwards,
-            (
-                Action::HistoryNext,
-                EditorState::Searching {
-                    style: SearchStyle::Substring,
-                    ..
-                },
-            ) => Action::HistoryIncSearchForwards,
+            (Action::HistoryPrevious, EditorState::Searching { .. }) if listing => {
+                Action::HistoryIncSearchForwards
+            }
+            (Action::HistoryNext, EditorState::Searching { .. }) if listing => {
+                Action::HistoryIncSearchBackwards
+            }
+            (Action::HistoryPrevious, EditorState::Searching { .. }) => {
+                Action::HistoryIncSearchBackwards
+            }
+            (Action::HistoryNext, EditorState::Searching { .. }) => {
+                Action::HistoryIncSearchForwards
+            }
             (action, _) => action,
         };
 
+        // While a candidate is selected in the completion menu,
+        // Enter accepts it rather than the line
+        let selecting = self.completion_menu != CompletionMenu::None
+            && self
+                .completion
+                .as_ref()
+                .is_some_and(|state| state.index.is_some());
+        let action = match action {
+            Action::AcceptLine if selecting => {
+                self.clear_completion();
+                Action::NoAction
+            }
+            action => action,
+        };
+
+        // Moving right from the end of the input accepts the suggestion
+        let action = match action {
+            Action::Move(Movement::ForwardChar(1)) | Action::Move(Movement::EndOfLine)
+                if self.suggestion(host).is_some() =>
+            {
+                Action::AcceptSuggestion
+            }
+            Action::Move(Movement::ForwardWord(1)) if self.suggestion(host).is_some() => {
+                Action::AcceptSuggestionWord
+            }
+            action => action,
+        };
+
+        let last_action = std::mem::take(&mut self.last_action);
+        let was_editing = self.state == EditorState::Editing;
+        let prior_line = self.line.clone();
+        let prior_cursor = self.cursor;
+        let coalesce = matches!(action, Action::InsertChar(..));
+        let record_undo = !matches!(
+            action,
+            Action::Undo | Action::Redo | Action::HistoryPrevious | Action::HistoryNext
+        );
+
         match action {
             Action::Cancel => self.state = EditorState::Cancelled,
             Action::NoAction => {}
+            Action::AcceptLine
+                if self.state == EditorState::Editing && !host.is_complete(&self.line) =>
+            {
+                // The host wants more input, so continue on a new line
+                return self.apply_action(host, Action::InsertChar(1, '\n'));
+            }
             Action::AcceptLine => {
                 // Make sure that hitting Enter for a line that
                 // shows in the incremental search causes that
@@ -825,13 +1451,58 @@ impl<'term> LineEditor<'term> {
                 )
             }
             Action::Kill(movement) => {
-                self.kill_text(movement, movement);
+                let accumulate = matches!(last_action, LastAction::Kill);
+                if self.kill_text(movement, movement, accumulate) {
+                    self.last_action = LastAction::Kill;
+                }
                 self.reapply_search_pattern(host);
             }
             Action::KillAndMove(kill_movement, move_movement) => {
-                self.kill_text(kill_movement, move_movement);
+                let accumulate = matches!(last_action, LastAction::Kill);
+                if self.kill_text(kill_movement, move_movement, accumulate) {
+                    self.last_action = LastAction::Kill;
+                }
                 self.reapply_search_pattern(host);
             }
+            Action::Yank => {
+                self.clear_completion();
+                if let Some(text) = self.kill_ring.yank() {
+                    let start = self.cursor;
+                    self.line.insert_str(start, text);
+                    self.cursor += text.len();
+                    self.last_action = LastAction::Yank(start..self.cursor);
+                }
+                self.reapply_search_pattern(host);
+            }
+            Action::YankPop => {
+                // Only meaningful immediately after a yank, whose text
+                // is replaced by the prior kill
+                if let LastAction::Yank(range) = last_action {
+                    self.clear_completion();
+                    if let Some(text) = self.kill_ring.yank_pop() {
+                        self.line.replace_range(range.clone(), text);
+                        self.cursor = range.start + text.len();
+                        self.last_action = LastAction::Yank(range.start..self.cursor);
+                    }
+                    self.reapply_search_pattern(host);
+                }
+            }
+            Action::Undo => {
+                self.clear_completion();
+                self.cancel_search_state();
+                if let Some(snapshot) = self.undo.undo(&self.line, self.cursor) {
+                    self.line = snapshot.line;
+                    self.cursor = snapshot.cursor;
+                }
+            }
+            Action::Redo => {
+                self.clear_completion();
+                self.cancel_search_state();
+                if let Some(snapshot) = self.undo.redo(&self.line, self.cursor) {
+                    self.line = snapshot.line;
+                    self.cursor = snapshot.cursor;
+                }
+            }
 
             Action::Move(movement) => {
                 self.clear_completion();
@@ -862,9 +1533,19 @@ impl<'term> LineEditor<'term> {
                 self.terminal
                     .render(&[Change::ClearScreen(Default::default())])?;
             }
+            Action::HistoryPrevious if rows::row_index(&self.line, self.cursor) > 0 => {
+                // Move within the input before moving through the history
+                self.clear_completion();
+                self.cursor = self.eval_movement(Movement::BackwardLine(1));
+            }
+            Action::HistoryNext if rows::vertical_move(&self.line, self.cursor, 1).is_some() => {
+                self.clear_completion();
+                self.cursor = self.eval_movement(Movement::ForwardLine(1));
+            }
             Action::HistoryPrevious => {
                 self.clear_completion();
                 self.cancel_search_state();
+                self.undo.clear();
 
                 if let Some(cur_pos) = self.history_pos.as_ref() {
                     let prior_idx = cur_pos.saturating_sub(1);
@@ -887,6 +1568,7 @@ impl<'term> LineEditor<'term> {
             Action::HistoryNext => {
                 self.clear_completion();
                 self.cancel_search_state();
+                self.undo.clear();
 
                 if let Some(cur_pos) = self.history_pos.as_ref() {
                     let next_idx = cur_pos.saturating_add(1);
@@ -905,76 +1587,152 @@ impl<'term> LineEditor<'term> {
             }
 
             Action::HistoryIncSearchBackwards => {
-                self.trigger_search(SearchStyle::Substring, SearchDirection::Backwards, host);
+                self.trigger_search(self.search_style, SearchDirection::Backwards, host);
             }
             Action::HistoryIncSearchForwards => {
-                self.trigger_search(SearchStyle::Substring, SearchDirection::Forwards, host);
+                self.trigger_search(self.search_style, SearchDirection::Forwards, host);
             }
 
-            Action::Complete => {
-                self.cancel_search_state();
+            Action::AcceptSuggestion => {
+                if let Some(suggestion) = self.suggestion(host) {
+                    self.line.push_str(&suggestion);
+                    self.cursor = self.line.len();
+                }
+            }
+            Action::AcceptSuggestionWord => {
+                if let Some(suggestion) = self.suggestion(host) {
+                    // Take any leading whitespace and the word after it
+                    let word_start = suggestion
+                        .find(|c: char| !c.is_whitespace())
+                        .unwrap_or(suggestion.len());
+                    let word_end = suggestion[word_start..]
+                        .find(char::is_whitespace)
+                        .map_or(suggestion.len(), |idx| word_start + idx);
+                    self.line.push_str(&suggestion[..word_end]);
+                    self.cursor = self.line.len();
+                }
+            }
 
-                if self.completion.is_none() {
-                    let candidates = host.complete(&self.line, self.cursor);
-                    if !candidates.is_empty() {
-                        let state = CompletionState {
-                            candidates,
-                            index: 0,
-                            original_line: self.line.clone(),
-                            original_cursor: self.cursor,
-                        };
-
-                        let (cursor, line) = state.current();
-                        self.cursor = cursor;
-                        self.line = line;
-
-                        // If there is only a single completion then don't
-                        // leave us in a state where we just cycle on the
-                        // same completion over and over.
-                        if state.candidates.len() > 1 {
-                            self.completion = Some(state);
-                        }
-                    }
-                } else if let Some(state) = self.completion.as_mut() {
-                    state.next();
-                    let (cursor, line) = state.current();
-                    self.cursor = cursor;
-                    self.line = line;
+            Action::Complete => self.complete(host, MenuMovement::Next),
+            Action::SelectCompletion(movement) => self.complete(host, movement),
+
+            Action::Operate(op, movement) => {
+                let range = self.operator_range(movement);
+                self.operate(host, op, range);
+            }
+            Action::OperateOnSelection(op) => {
+                let range = self.selection_range();
+                self.operate(host, op, range);
+            }
+            Action::PasteAfter(rep) => self.paste(host, rep, true),
+            Action::PasteBefore(rep) => self.paste(host, rep, false),
+            Action::ViInsert(movement) => {
+                self.clear_completion();
+                self.cancel_search_state();
+                self.cursor = self.eval_movement(movement);
+                self.set_vi_mode(host, ViMode::Insert);
+            }
+            Action::ViNormal => {
+                self.clear_completion();
+                self.cancel_search_state();
+                if self.vi.mode == ViMode::Insert {
+                    // As in vi, leaving insert mode steps back onto the
+                    // character that was last inserted
+                    self.cursor = self.prev_grapheme(self.cursor);
                 }
+                self.set_vi_mode(host, ViMode::Normal);
+            }
+            Action::ViVisual => {
+                self.clear_completion();
+                self.cancel_search_state();
+                self.vi.anchor = self.cursor;
+                self.set_vi_mode(host, ViMode::Visual);
+            }
+        }
+
+        // Edits to the search pattern are not recorded; only those
+        // to the line itself
+        if record_undo && was_editing && self.state == EditorState::Editing {
+            if self.line != prior_line {
+                self.undo.record(&prior_line, prior_cursor, coalesce);
+            } else if !coalesce {
+                self.undo.break_coalescing();
             }
         }
 
+        if matches!(self.vi_mode(), Some(ViMode::Normal) | Some(ViMode::Visual))
+            && !matches!(self.state, EditorState::Searching { .. })
+            && self.cursor >= self.line.len()
+        {
+            // Outside of insert mode the cursor rests on a character
+            // rather than after the end of the line
+            self.cursor = self.prev_grapheme(self.line.len());
+        }
+
         Ok(())
     }
 
-    fn read_line_impl(&mut self, host: &mut dyn LineEditorHost) -> Result<Option<String>> {
-        self.line.clear();
-        self.cursor = 0;
-        self.history_pos = None;
-        self.bottom_line = None;
-        self.clear_completion();
+    fn process_event(
+        &mut self,
+        host: &mut dyn LineEditorHost,
+        event: &InputEvent,
+    ) -> Result<EditStatus> {
+        if let Some(action) = self.resolve_action(event, host) {
+            self.apply_action(host, action)?;
+            // Editor state might have changed. Re-render to clear
+            // preview or highlight lines differently.
+            self.render(host)?;
+            match self.state {
+                EditorState::Searching { .. } | EditorState::Editing => Ok(EditStatus::Editing),
+                EditorState::Cancelled => Ok(EditStatus::Cancelled),
+                EditorState::Accepted => Ok(EditStatus::Accepted(self.line.clone())),
+                EditorState::Inactive => bail!("editor is inactive during read line!?"),
+            }
+        } else {
+            self.render(host)?;
+            Ok(EditStatus::Editing)
+        }
+    }
 
-        self.render(host)?;
+    fn read_line_impl(&mut self, host: &mut dyn LineEditorHost) -> Result<Option<String>> {
         while let Some(event) = self.terminal.poll_input(None)? {
-            if let Some(action) = self.resolve_action(&event, host) {
-                self.apply_action(host, action)?;
-                // Editor state might have changed. Re-render to clear
-                // preview or highlight lines differently.
-                self.render(host)?;
-                match self.state {
-                    EditorState::Searching { .. } | EditorState::Editing => {}
-                    EditorState::Cancelled => return Ok(None),
-                    EditorState::Accepted => return Ok(Some(self.line.clone())),
-                    EditorState::Inactive => bail!("editor is inactive during read line!?"),
-                }
-            } else {
-                self.render(host)?;
+            match self.process_event(host, &event)? {
+                EditStatus::Editing => {}
+                EditStatus::Cancelled => return Ok(None),
+                EditStatus::Accepted(line) => return Ok(Some(line)),
             }
         }
         Ok(Some(self.line.clone()))
     }
 }
 
+/// Returns the longest prefix of `s` that fits within `width` columns
+fn truncate_to_width(s: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (idx, grapheme) in s.grapheme_indices(true) {
+        used += unicode_column_width(grapheme, None);
+        if used > width {
+            return &s[..idx];
+        }
+    }
+    s
+}
+
+/// Adds the elements of a preview on the rows below what has been
+/// rendered so far
+fn add_preview(changes: &mut ChangeSequence, elements: Vec<OutputElement>) {
+    if elements.is_empty() {
+        return;
+    }
+    // Preview starts from a new line.
+    changes.add("\r\n");
+    // Do not be affected by attributes set by highlight_line.
+    changes.add(Change::AllAttributes(Default::default()));
+    for ele in elements {
+        changes.add(ele);
+    }
+}
+
 /// Create a `Terminal` with the recommended settings for use with
 /// a `LineEditor`.
 pub fn line_editor_terminal() -> Result<impl Terminal> {
@@ -982,3 +1740,297 @@ pub fn line_editor_terminal() -> Result<impl Terminal> {
     let caps = Capabilities::new_with_hints(hints)?;
     new_terminal(caps)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::InputParser;
+    use crate::surface::Surface;
+    use crate::terminal::surface_terminal::SurfaceTerminal;
+
+    /// Parses the bytes that a terminal would send for some typing
+    fn parse(parser: &mut InputParser, bytes: &str) -> Vec<InputEvent> {
+        let mut events = vec![];
+        parser.parse(bytes.as_bytes(), |event| events.push(event), false);
+        events
+    }
+
+    /// Feeds each of `events` to the editor, returning the statuses
+    fn feed(
+        editor: &mut LineEditor,
+        host: &mut dyn LineEditorHost,
+        events: &[InputEvent],
+    ) -> Vec<EditStatus> {
+        events
+            .iter()
+            .map(|event| editor.feed_input(host, event).unwrap())
+            .collect()
+    }
+
+    /// Returns the rows of the screen without trailing blanks
+    fn screen_rows(term: &SurfaceTerminal) -> Vec<String> {
+        term.screen
+            .screen_chars_to_string()
+            .lines()
+            .map(|row| row.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn feed_input_in_chunks() {
+        let mut term = SurfaceTerminal::new(Surface::new(20, 4));
+        let mut host = NopLineEditorHost::default();
+        let mut parser = InputParser::new();
+        let mut editor = LineEditor::new(&mut term);
+        editor.begin_line(&mut host).unwrap();
+
+        for chunk in ["hel", "lo w", "orld"] {
+            let events = parse(&mut parser, chunk);
+            assert!(!events.is_empty());
+            for status in feed(&mut editor, &mut host, &events) {
+                assert_eq!(status, EditStatus::Editing);
+            }
+        }
+
+        let events = parse(&mut parser, "\r");
+        assert_eq!(
+            feed(&mut editor, &mut host, &events),
+            vec![EditStatus::Accepted("hello world".to_string())]
+        );
+
+        // The editor can then begin another line
+        editor.begin_line(&mut host).unwrap();
+        let events = parse(&mut parser, "again\r");
+        let statuses = feed(&mut editor, &mut host, &events);
+        assert_eq!(
+            statuses.last(),
+            Some(&EditStatus::Accepted("again".to_string()))
+        );
+        drop(editor);
+
+        assert_eq!(screen_rows(&term), vec!["> hello world", "> again", "", ""]);
+    }
+
+    #[test]
+    fn print_above_redraws_input() {
+        let mut term = SurfaceTerminal::new(Surface::new(20, 4));
+        let mut host = NopLineEditorHost::default();
+        let mut parser = InputParser::new();
+        let mut editor = LineEditor::new(&mut term);
+        editor.begin_line(&mut host).unwrap();
+        let events = parse(&mut parser, "partial");
+        feed(&mut editor, &mut host, &events);
+
+        editor.print_above(&mut host, "first\nsecond").unwrap();
+        drop(editor);
+
+        assert_eq!(screen_rows(&term), vec!["first", "second", "> partial", ""]);
+        assert_eq!(term.screen.cursor_position(), (9, 2));
+    }
+
+    #[test]
+    fn completion_selection() {
+        let candidate = |text: &str| CompletionCandidate {
+            range: 4..6,
+            text: text.to_string(),
+            ..Default::default()
+        };
+        let mut state = CompletionState {
+            candidates: ["help", "hello", "helm", "helium", "helix"]
+                .iter()
+                .map(|text| candidate(text))
+                .collect(),
+            index: None,
+            original_line: "why he".to_string(),
+            original_cursor: 6,
+            columns: 2,
+        };
+        assert_eq!(state.current(), (7, "why hel".to_string()));
+
+        state.select(MenuMovement::Up);
+        assert_eq!(state.index, Some(4));
+        state.select(MenuMovement::Up);
+        assert_eq!(state.index, Some(2));
+        state.select(MenuMovement::Next);
+        state.select(MenuMovement::Down);
+        assert_eq!(state.index, Some(1));
+        state.select(MenuMovement::Up);
+        assert_eq!(state.index, Some(3));
+        state.select(MenuMovement::Down);
+        assert_eq!(state.index, Some(1));
+        state.select(MenuMovement::Previous);
+        state.select(MenuMovement::Previous);
+        assert_eq!(state.current(), (9, "why helix".to_string()));
+
+        // Candidates that replace differing ranges have no common prefix
+        state.candidates[0].range = 5..6;
+        state.index = None;
+        assert_eq!(state.current(), (6, "why he".to_string()));
+    }
+
+    /// Completes words from a fixed vocabulary
+    #[derive(Default)]
+    struct WordHost {
+        history: BasicHistory,
+    }
+
+    impl LineEditorHost for WordHost {
+        fn history(&mut self) -> &mut dyn History {
+            &mut self.history
+        }
+
+        fn complete(&self, line: &str, cursor_position: usize) -> Vec<CompletionCandidate> {
+            let start = line[..cursor_position].rfind(' ').map_or(0, |idx| idx + 1);
+            let word = &line[start..cursor_position];
+            ["hello", "help", "helm"]
+                .iter()
+                .filter(|candidate| candidate.starts_with(word))
+                .map(|candidate| CompletionCandidate {
+                    range: start..cursor_position,
+                    text: candidate.to_string(),
+                    ..Default::default()
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn tab_completion() {
+        let mut term = SurfaceTerminal::new(Surface::new(30, 6));
+        let mut host = WordHost::default();
+        let mut parser = InputParser::new();
+        let mut editor = LineEditor::new(&mut term);
+
+        // Without a menu, Tab cycles through the candidates
+        editor.begin_line(&mut host).unwrap();
+        let events = parse(&mut parser, "say he\t\t");
+        feed(&mut editor, &mut host, &events);
+        assert_eq!(editor.get_line_and_cursor(), ("say help", 8));
+        let events = parse(&mut parser, "\r");
+        assert_eq!(
+            feed(&mut editor, &mut host, &events),
+            vec![EditStatus::Accepted("say help".to_string())]
+        );
+
+        // With a menu, the first Tab inserts the common prefix, then
+        // Tab and the arrow keys select and Enter takes the selection
+        editor.set_completion_menu(CompletionMenu::Grid);
+        editor.begin_line(&mut host).unwrap();
+        let events = parse(&mut parser, "say he\t");
+        feed(&mut editor, &mut host, &events);
+        assert_eq!(editor.get_line_and_cursor(), ("say hel", 7));
+        let events = parse(&mut parser, "\t\x1b[C");
+        feed(&mut editor, &mut host, &events);
+        assert_eq!(editor.get_line_and_cursor(), ("say help", 8));
+        let events = parse(&mut parser, "\r");
+        assert_eq!(
+            feed(&mut editor, &mut host, &events),
+            vec![EditStatus::Editing]
+        );
+
+        // Once the menu is closed, the arrow keys move the cursor
+        let events = parse(&mut parser, "\x1b[D\x1b[Dx\r");
+        let statuses = feed(&mut editor, &mut host, &events);
+        assert_eq!(
+            statuses.last(),
+            Some(&EditStatus::Accepted("say hexlp".to_string()))
+        );
+    }
+
+    /// Previews the line that would be accepted
+    #[derive(Default)]
+    struct PreviewHost {
+        history: BasicHistory,
+    }
+
+    impl LineEditorHost for PreviewHost {
+        fn history(&mut self) -> &mut dyn History {
+            &mut self.history
+        }
+
+        fn render_preview(&self, line: &str) -> Vec<OutputElement> {
+            vec![OutputElement::Text(format!("run {}", line))]
+        }
+    }
+
+    #[test]
+    fn search_candidates() {
+        let mut host = PreviewHost::default();
+        for line in ["git status", "ls", "git commit", "grep x"] {
+            host.history.add(line);
+        }
+        let mut parser = InputParser::new();
+        let ctrl_r = InputEvent::Key(KeyEvent::new(KeyCode::Char('R'), Modifiers::CTRL));
+        let mut events = vec![ctrl_r];
+        events.extend(parse(&mut parser, "gi\x1b[B"));
+
+        // The candidates are listed in the preview area, followed by
+        // the host's preview of the selected candidate
+        let mut term = SurfaceTerminal::new(Surface::new(30, 6));
+        let mut editor = LineEditor::new(&mut term);
+        editor.set_history_search_candidates(3);
+        editor.begin_line(&mut host).unwrap();
+        feed(&mut editor, &mut host, &events);
+        drop(editor);
+        assert_eq!(
+            screen_rows(&term),
+            vec![
+                "> git status",
+                "bck-i-search: gi_",
+                "  git commit",
+                "> git status",
+                "run git status",
+                "",
+            ]
+        );
+
+        // Enter accepts the selected candidate
+        let mut term = SurfaceTerminal::new(Surface::new(30, 6));
+        let mut editor = LineEditor::new(&mut term);
+        editor.set_history_search_candidates(3);
+        editor.begin_line(&mut host).unwrap();
+        feed(&mut editor, &mut host, &events);
+        let events = parse(&mut parser, "\r");
+        assert_eq!(
+            feed(&mut editor, &mut host, &events),
+            vec![EditStatus::Accepted("git status".to_string())]
+        );
+        drop(editor);
+        assert_eq!(screen_rows(&term), vec!["> git status", "", "", "", "", ""]);
+    }
+
+    #[test]
+    fn vi_operators() {
+        let mut term = SurfaceTerminal::new(Surface::new(30, 4));
+        let mut host = NopLineEditorHost::default();
+        let mut parser = InputParser::new();
+        let mut editor = LineEditor::new(&mut term);
+        editor.set_edit_mode(EditMode::Vi);
+        editor.begin_line(&mut host).unwrap();
+
+        let mut type_keys = |editor: &mut LineEditor, keys: &str| {
+            let events = parse(&mut parser, keys);
+            feed(editor, &mut host, &events)
+        };
+        type_keys(&mut editor, "one two three four");
+        type_keys(&mut editor, "\x1b");
+        assert_eq!(editor.vi_mode(), Some(ViMode::Normal));
+
+        type_keys(&mut editor, "0dw");
+        assert_eq!(editor.get_line_and_cursor(), ("two three four", 0));
+
+        type_keys(&mut editor, "wlciw");
+        assert_eq!(editor.vi_mode(), Some(ViMode::Insert));
+        assert_eq!(editor.get_line_and_cursor(), ("two  four", 4));
+        type_keys(&mut editor, "3");
+        type_keys(&mut editor, "\x1b");
+
+        type_keys(&mut editor, "bdaw");
+        assert_eq!(editor.get_line_and_cursor(), ("3 four", 0));
+
+        assert_eq!(
+            type_keys(&mut editor, "\r"),
+            vec![EditStatus::Accepted("3 four".to_string())]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Evaluation of the cursor movements used when editing a line
+use super::actions::Movement;
+use super::{rows, vi};
+use unicode_segmentation::GraphemeCursor;
+
+/// Compute the cursor position after applying movement
+pub(crate) fn eval_movement(line: &str, cursor: usize, movement: Movement) -> usize {
+    match movement {
+        Movement::BackwardChar(rep) => {
+            let mut position = cursor;
+            for _ in 0..rep {
+                let mut graphemes = GraphemeCursor::new(position, line.len(), false);
+                if let Ok(Some(pos)) = graphemes.prev_boundary(line, 0) {
+                    position = pos;
+                } else {
+                    break;
+                }
+            }
+            position
+        }
+        Movement::BackwardWord(rep) => {
+            let char_indices: Vec<(usize, char)> = line.char_indices().collect();
+            if char_indices.is_empty() {
+                return cursor;
+            }
+            let mut char_position = char_indices
+                .iter()
+                .position(|(idx, _)| *idx == cursor)
+                .unwrap_or(char_indices.len() - 1);
+
+            for _ in 0..rep {
+                if char_position == 0 {
+                    break;
+                }
+
+                let mut found = None;
+                for prev in (0..char_position - 1).rev() {
+                    if char_indices[prev].1.is_whitespace() {
+                        found = Some(prev + 1);
+                        break;
+                    }
+                }
+
+                char_position = found.unwrap_or(0);
+            }
+            char_indices[char_position].0
+        }
+        Movement::ForwardWord(rep) => {
+            let char_indices: Vec<(usize, char)> = line.char_indices().collect();
+            if char_indices.is_empty() {
+                return cursor;
+            }
+            let mut char_position = char_indices
+                .iter()
+                .position(|(idx, _)| *idx == cursor)
+                .unwrap_or_else(|| char_indices.len());
+
+            for _ in 0..rep {
+                // Skip any non-whitespace characters
+                while char_position < char_indices.len()
+                    && !char_indices[char_position].1.is_whitespace()
+                {
+                    char_position += 1;
+                }
+
+                // Skip any whitespace characters
+                while char_position < char_indices.len()
+                    && char_indices[char_position].1.is_whitespace()
+                {
+                    char_position += 1;
+                }
+
+                // We are now on the start of the next word
+            }
+            char_indices
+                .get(char_position)
+                .map(|(i, _)| *i)
+                .unwrap_or_else(|| line.len())
+        }
+        Movement::ForwardChar(rep) => {
+            let mut position = cursor;
+            for _ in 0..rep {
+                let mut graphemes = GraphemeCursor::new(position, line.len(), false);
+                if let Ok(Some(pos)) = graphemes.next_boundary(line, 0) {
+                    position = pos;
+                } else {
+                    break;
+                }
+            }
+            position
+        }
+        Movement::ForwardWordEnd(rep) => vi::word_end(line, cursor, rep),
+        Movement::ForwardFindChar(rep, c) => {
+            vi::find_char(line, cursor, rep, c, true).unwrap_or(cursor)
+        }
+        Movement::ForwardTillChar(rep, c) => match vi::find_char(line, cursor, rep, c, true) {
+            Some(pos) => prev_grapheme(line, pos).max(cursor),
+            None => cursor,
+        },
+        Movement::BackwardFindChar(rep, c) => {
+            vi::find_char(line, cursor, rep, c, false).unwrap_or(cursor)
+        }
+        Movement::BackwardTillChar(rep, c) => match vi::find_char(line, cursor, rep, c, false) {
+            Some(pos) => next_grapheme(line, pos).min(cursor),
+            None => cursor,
+        },
+        Movement::BackwardLine(rep) => {
+            rows::vertical_move(line, cursor, -(rep as isize)).unwrap_or(cursor)
+        }
+        Movement::ForwardLine(rep) => {
+            rows::vertical_move(line, cursor, rep as isize).unwrap_or(cursor)
+        }
+        // In input that spans multiple lines, these apply to
+        // the line containing the cursor
+        Movement::StartOfLine => rows::row_range(line, cursor).start,
+        Movement::FirstNonBlank => {
+            let row = rows::row_range(line, cursor);
+            row.start + vi::first_non_blank(&line[row])
+        }
+        Movement::EndOfLine => rows::row_range(line, cursor).end,
+        Movement::WholeLine => 0,
+        Movement::InnerWord => vi::word_object(line, cursor, false).start,
+        Movement::AroundWord => vi::word_object(line, cursor, true).start,
+        Movement::None => cursor,
+    }
+}
+
+/// Returns the byte index of the grapheme boundary after `pos`
+pub(crate) fn next_grapheme(line: &str, pos: usize) -> usize {
+    let mut cursor = GraphemeCursor::new(pos, line.len(), false);
+    match cursor.next_boundary(line, 0) {
+        Ok(Some(pos)) => pos,
+        _ => line.len(),
+    }
+}
+
+/// Returns the byte index of the grapheme boundary before `pos`
+pub(crate) fn prev_grapheme(line: &str, pos: usize) -> usize {
+    let mut cursor = GraphemeCursor::new(pos, line.len(), false);
+    match cursor.prev_boundary(line, 0) {
+        Ok(Some(pos)) => pos,
+        _ => 0,
+    }
+}
@@ -0,0 +1,71 @@
+//! Navigation within input that spans multiple lines.
+//! The rows of the input are separated by `\n`.
+use crate::cell::unicode_column_width;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Returns the byte range of the row containing `cursor`,
+/// excluding its trailing newline
+pub(crate) fn row_range(line: &str, cursor: usize) -> Range<usize> {
+    let start = line[..cursor].rfind('\n').map_or(0, |idx| idx + 1);
+    let end = line[cursor..]
+        .find('\n')
+        .map_or(line.len(), |idx| cursor + idx);
+    start..end
+}
+
+/// Returns the zero based index of the row containing `cursor`
+pub(crate) fn row_index(line: &str, cursor: usize) -> usize {
+    line[..cursor].matches('\n').count()
+}
+
+/// Returns the position in the row `delta` rows above (when negative)
+/// or below the row containing `cursor`, keeping the cursor in the
+/// same column where possible.  Returns `None` if there is no such row.
+pub(crate) fn vertical_move(line: &str, cursor: usize, delta: isize) -> Option<usize> {
+    let row = row_index(line, cursor) as isize + delta;
+    if row < 0 {
+        return None;
+    }
+    let current = row_range(line, cursor);
+    let column = unicode_column_width(&line[current.start..cursor], None);
+
+    let start = if row == 0 {
+        0
+    } else {
+        line.match_indices('\n').nth(row as usize - 1)?.0 + 1
+    };
+    let target = row_range(line, start);
+
+    let mut used = 0;
+    for (idx, grapheme) in line[target.clone()].grapheme_indices(true) {
+        used += unicode_column_width(grapheme, None);
+        if used > column {
+            return Some(target.start + idx);
+        }
+    }
+    Some(target.end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn navigate_rows() {
+        let line = "select *\nfrom t\n\nwhere x";
+        assert_eq!(row_range(line, 3), 0..8);
+        assert_eq!(row_range(line, 8), 0..8);
+        assert_eq!(row_range(line, 9), 9..15);
+        assert_eq!(row_range(line, 16), 16..16);
+        assert_eq!(row_index(line, 20), 3);
+
+        // Down from column 7 clamps to the end of the shorter row
+        assert_eq!(vertical_move(line, 7, 1), Some(15));
+        assert_eq!(vertical_move(line, 7, 2), Some(16));
+        assert_eq!(vertical_move(line, 11, -1), Some(2));
+        assert_eq!(vertical_move(line, 11, 2), Some(19));
+        assert_eq!(vertical_move(line, 3, -1), None);
+        assert_eq!(vertical_move(line, 20, 1), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! Undo and redo of edits to the line
+use std::collections::VecDeque;
+
+/// The most undo steps retained; the oldest are discarded first
+const MAX_UNDO: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Snapshot {
+    pub line: String,
+    pub cursor: usize,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct UndoStack {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// True when the most recent step was an insertion that
+    /// further insertions should be merged into
+    coalescing: bool,
+}
+
+impl UndoStack {
+    /// Records the state of the line prior to an edit.
+    /// When `coalesce` is true and the previous edit was also recorded
+    /// with `coalesce`, the two share a single undo step; this is used
+    /// so that a run of typed characters is undone at once.
+    pub fn record(&mut self, line: &str, cursor: usize, coalesce: bool) {
+        self.redo.clear();
+        if !(coalesce && self.coalescing) {
+            if self.undo.len() == MAX_UNDO {
+                self.undo.pop_front();
+            }
+            self.undo.push_back(Snapshot {
+                line: line.to_string(),
+                cursor,
+            });
+        }
+        self.coalescing = coalesce;
+    }
+
+    /// Ensures that the next edit starts a new undo step
+    pub fn break_coalescing(&mut self) {
+        self.coalescing = false;
+    }
+
+    /// Returns the state to restore in order to undo the most recent
+    /// step, saving the current state so that it can be redone
+    pub fn undo(&mut self, line: &str, cursor: usize) -> Option<Snapshot> {
+        self.coalescing = false;
+        let snapshot = self.undo.pop_back()?;
+        self.redo.push(Snapshot {
+            line: line.to_string(),
+            cursor,
+        });
+        Some(snapshot)
+    }
+
+    /// Returns the state to restore in order to redo the most recently
+    /// undone step, saving the current state so that it can be undone
+    pub fn redo(&mut self, line: &str, cursor: usize) -> Option<Snapshot> {
+        self.coalescing = false;
+        let snapshot = self.redo.pop()?;
+        self.undo.push_back(Snapshot {
+            line: line.to_string(),
+            cursor,
+        });
+        Some(snapshot)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.coalescing = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn coalesce_and_redo() {
+        let mut stack = UndoStack::default();
+        stack.record("", 0, true);
+        stack.record("a", 1, true);
+        stack.record("ab", 2, false);
+        stack.record("", 0, true);
+        stack.break_coalescing();
+        stack.record("c", 1, true);
+
+        let snapshot = |line: &str, cursor| {
+            Some(Snapshot {
+                line: line.to_string(),
+                cursor,
+            })
+        };
+
+        assert_eq!(stack.undo("cd", 2), snapshot("c", 1));
+        assert_eq!(stack.undo("c", 1), snapshot("", 0));
+        assert_eq!(stack.undo("", 0), snapshot("ab", 2));
+        assert_eq!(stack.redo("ab", 2), snapshot("", 0));
+        assert_eq!(stack.undo("", 0), snapshot("ab", 2));
+        assert_eq!(stack.undo("ab", 2), snapshot("", 0));
+        assert_eq!(stack.undo("", 0), None);
+
+        // A new edit discards the steps that could have been redone
+        stack.record("", 0, false);
+        assert_eq!(stack.redo("x", 1), None);
+    }
+}
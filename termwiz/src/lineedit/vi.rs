@@ -0,0 +1,417 @@
+//! Key handling and text motions for the vi editing mode
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
+use crate::lineedit::actions::{Action, Movement, Operator, RepeatCount, ViMode};
+use std::ops::Range;
+
+/// Which of the f/F/t/T motions is waiting for its target char
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Find {
+    /// `f`: onto the next occurrence
+    Forward,
+    /// `t`: just before the next occurrence
+    ForwardTill,
+    /// `F`: onto the previous occurrence
+    Backward,
+    /// `T`: just after the previous occurrence
+    BackwardTill,
+}
+
+/// Tracks the vi mode along with any partially entered command,
+/// such as a count or an operator that is awaiting its motion.
+#[derive(Debug, Default)]
+pub(crate) struct ViState {
+    pub mode: ViMode,
+    /// The start of the selection in visual mode
+    pub anchor: usize,
+    /// The text most recently deleted or yanked
+    pub register: String,
+    count: Option<RepeatCount>,
+    operator: Option<(Operator, RepeatCount)>,
+    find: Option<Find>,
+    /// The `i` or `a` that begins a text object after an operator
+    object: Option<char>,
+}
+
+impl ViState {
+    /// Discard any partially entered command
+    pub fn reset_pending(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.find = None;
+        self.object = None;
+    }
+
+    /// Consumes the pending counts; a count given to the operator
+    /// multiplies the count given to its motion, so `2d3w` deletes
+    /// six words.
+    fn take_count(&mut self) -> RepeatCount {
+        let count = self.count.take().unwrap_or(1);
+        match self.operator {
+            Some((_, op_count)) => op_count * count,
+            None => count,
+        }
+    }
+
+    /// Produces the action for a motion, applying the pending operator
+    /// if there is one
+    fn motion(&mut self, movement: Movement) -> Action {
+        self.count = None;
+        self.find = None;
+        match self.operator.take() {
+            Some((op, _)) => Action::Operate(op, movement),
+            None => Action::Move(movement),
+        }
+    }
+
+    /// Resolves a key press in normal or visual mode.
+    /// Returns `None` for keys that have no vi meaning, such as
+    /// the arrow keys and control keys, so that the default keymap
+    /// can handle them.
+    pub fn resolve(&mut self, event: &InputEvent) -> Option<Action> {
+        let c = match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char(c),
+                modifiers: Modifiers::NONE,
+                ..
+            })
+            | InputEvent::Key(KeyEvent {
+                key: KeyCode::Char(c),
+                modifiers: Modifiers::SHIFT,
+                ..
+            }) => *c,
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Escape,
+                ..
+            }) => {
+                self.reset_pending();
+                return Some(match self.mode {
+                    ViMode::Visual => Action::ViNormal,
+                    _ => Action::NoAction,
+                });
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('R'),
+                modifiers: Modifiers::CTRL,
+                ..
+            }) => {
+                self.reset_pending();
+                return Some(Action::Redo);
+            }
+            _ => {
+                self.reset_pending();
+                return None;
+            }
+        };
+
+        if let Some(find) = self.find {
+            let count = self.take_count();
+            let movement = match find {
+                Find::Forward => Movement::ForwardFindChar(count, c),
+                Find::ForwardTill => Movement::ForwardTillChar(count, c),
+                Find::Backward => Movement::BackwardFindChar(count, c),
+                Find::BackwardTill => Movement::BackwardTillChar(count, c),
+            };
+            return Some(self.motion(movement));
+        }
+
+        if let Some(object) = self.object.take() {
+            return Some(match (object, c) {
+                ('i', 'w') | ('i', 'W') => self.motion(Movement::InnerWord),
+                ('a', 'w') | ('a', 'W') => self.motion(Movement::AroundWord),
+                _ => {
+                    self.reset_pending();
+                    Action::NoAction
+                }
+            });
+        }
+
+        if let Some(digit) = c.to_digit(10) {
+            // A leading zero is the start of line motion rather than a count
+            if digit != 0 || self.count.is_some() {
+                let count = self.count.unwrap_or(0);
+                self.count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+                return Some(Action::NoAction);
+            }
+        }
+
+        let movement = match c {
+            'h' => Some(Movement::BackwardChar(self.take_count())),
+            'l' | ' ' => Some(Movement::ForwardChar(self.take_count())),
+            // As in vi, `cw` changes up to the end of the word rather
+            // than also consuming the whitespace that follows it
+            'w' | 'W' if matches!(self.operator, Some((Operator::Change, _))) => {
+                Some(Movement::ForwardWordEnd(self.take_count()))
+            }
+            'w' | 'W' => Some(Movement::ForwardWord(self.take_count())),
+            'b' | 'B' => Some(Movement::BackwardWord(self.take_count())),
+            'e' | 'E' => Some(Movement::ForwardWordEnd(self.take_count())),
+            '0' => Some(Movement::StartOfLine),
+            '^' => Some(Movement::FirstNonBlank),
+            '$' => Some(Movement::EndOfLine),
+            _ => None,
+        };
+        if let Some(movement) = movement {
+            return Some(self.motion(movement));
+        }
+
+        let find = match c {
+            'f' => Some(Find::Forward),
+            't' => Some(Find::ForwardTill),
+            'F' => Some(Find::Backward),
+            'T' => Some(Find::BackwardTill),
+            _ => None,
+        };
+        if find.is_some() {
+            self.find = find;
+            return Some(Action::NoAction);
+        }
+
+        let operator = match c {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            _ => None,
+        };
+
+        if self.mode == ViMode::Visual {
+            self.reset_pending();
+            return Some(match (c, operator) {
+                (_, Some(op)) => Action::OperateOnSelection(op),
+                ('x', _) => Action::OperateOnSelection(Operator::Delete),
+                ('s', _) => Action::OperateOnSelection(Operator::Change),
+                ('v', _) => Action::ViNormal,
+                _ => Action::NoAction,
+            });
+        }
+
+        if let Some(op) = operator {
+            return Some(match self.operator.take() {
+                // Doubling the operator, as in `dd`, applies it to the whole line
+                Some((pending, _)) if pending == op => {
+                    self.reset_pending();
+                    Action::Operate(op, Movement::WholeLine)
+                }
+                Some(_) => {
+                    self.reset_pending();
+                    Action::NoAction
+                }
+                None => {
+                    self.operator = Some((op, self.count.take().unwrap_or(1)));
+                    Action::NoAction
+                }
+            });
+        }
+
+        if self.operator.is_some() && (c == 'i' || c == 'a') {
+            self.object = Some(c);
+            return Some(Action::NoAction);
+        }
+
+        if self.operator.is_some() {
+            // Not a motion, so the pending operator is abandoned
+            self.reset_pending();
+            return Some(Action::NoAction);
+        }
+
+        let count = self.take_count();
+        Some(match c {
+            'i' => Action::ViInsert(Movement::None),
+            'a' => Action::ViInsert(Movement::ForwardChar(1)),
+            'I' => Action::ViInsert(Movement::FirstNonBlank),
+            'A' => Action::ViInsert(Movement::EndOfLine),
+            'x' => Action::Operate(Operator::Delete, Movement::ForwardChar(count)),
+            'X' => Action::Operate(Operator::Delete, Movement::BackwardChar(count)),
+            's' => Action::Operate(Operator::Change, Movement::ForwardChar(count)),
+            'S' => Action::Operate(Operator::Change, Movement::WholeLine),
+            'C' => Action::Operate(Operator::Change, Movement::EndOfLine),
+            'D' => Action::Operate(Operator::Delete, Movement::EndOfLine),
+            'Y' => Action::Operate(Operator::Yank, Movement::WholeLine),
+            'p' => Action::PasteAfter(count),
+            'P' => Action::PasteBefore(count),
+            'k' => Action::HistoryPrevious,
+            'j' => Action::HistoryNext,
+            'v' => Action::ViVisual,
+            'u' => Action::Undo,
+            '/' => Action::HistoryIncSearchBackwards,
+            _ => Action::NoAction,
+        })
+    }
+}
+
+/// Returns the byte index of the last char of the word at or after
+/// the char following `cursor`.  Words are delimited by whitespace.
+pub(crate) fn word_end(line: &str, cursor: usize, rep: RepeatCount) -> usize {
+    let char_indices: Vec<(usize, char)> = line.char_indices().collect();
+    if char_indices.is_empty() {
+        return cursor;
+    }
+    let mut char_position = char_indices
+        .iter()
+        .position(|(idx, _)| *idx == cursor)
+        .unwrap_or(char_indices.len() - 1);
+
+    for _ in 0..rep {
+        char_position += 1;
+        while char_position < char_indices.len() && char_indices[char_position].1.is_whitespace() {
+            char_position += 1;
+        }
+        while char_position + 1 < char_indices.len()
+            && !char_indices[char_position + 1].1.is_whitespace()
+        {
+            char_position += 1;
+        }
+    }
+
+    char_indices[char_position.min(char_indices.len() - 1)].0
+}
+
+/// Returns the range of the word object at `cursor`, as selected by
+/// `iw`, or when `around` is true by `aw`, which adds the whitespace
+/// after the word, or before it if there is none after.  When the
+/// cursor is on whitespace the objects are swapped around: `iw` is the
+/// run of whitespace and `aw` adds the word that follows it.
+pub(crate) fn word_object(line: &str, cursor: usize, around: bool) -> Range<usize> {
+    let cursor = match line[cursor.min(line.len())..].chars().next() {
+        Some(_) => cursor,
+        None => match line.char_indices().next_back() {
+            Some((idx, _)) => idx,
+            None => return 0..0,
+        },
+    };
+    let space = line[cursor..].starts_with(char::is_whitespace);
+    let same = |c: &char| c.is_whitespace() == space;
+
+    let start = line[..cursor]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| same(c))
+        .last()
+        .map_or(cursor, |(idx, _)| idx);
+    let end = line[cursor..]
+        .char_indices()
+        .find(|(_, c)| !same(c))
+        .map_or(line.len(), |(idx, _)| cursor + idx);
+    if !around {
+        return start..end;
+    }
+
+    let after = line[end..]
+        .char_indices()
+        .find(|(_, c)| same(c))
+        .map_or(line.len(), |(idx, _)| end + idx);
+    if after > end || space {
+        return start..after;
+    }
+    let before = line[..start]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_whitespace())
+        .last()
+        .map_or(start, |(idx, _)| idx);
+    before..end
+}
+
+/// Returns the byte index of the `rep`th occurrence of `target` after
+/// (or, when `forward` is false, before) `cursor`
+pub(crate) fn find_char(
+    line: &str,
+    cursor: usize,
+    rep: RepeatCount,
+    target: char,
+    forward: bool,
+) -> Option<usize> {
+    let rep = rep.max(1);
+    if forward {
+        line.char_indices()
+            .filter(|(idx, c)| *idx > cursor && *c == target)
+            .nth(rep - 1)
+            .map(|(idx, _)| idx)
+    } else {
+        line.char_indices()
+            .rev()
+            .filter(|(idx, c)| *idx < cursor && *c == target)
+            .nth(rep - 1)
+            .map(|(idx, _)| idx)
+    }
+}
+
+/// Returns the byte index of the first non-whitespace char
+pub(crate) fn first_non_blank(line: &str) -> usize {
+    line.char_indices()
+        .find(|(_, c)| !c.is_whitespace())
+        .map(|(idx, _)| idx)
+        .unwrap_or(line.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys(state: &mut ViState, keys: &str) -> Vec<Action> {
+        keys.chars()
+            .filter_map(|c| {
+                state.resolve(&InputEvent::Key(KeyEvent::new(
+                    KeyCode::Char(c),
+                    Modifiers::NONE,
+                )))
+            })
+            .filter(|action| !matches!(action, Action::NoAction))
+            .collect()
+    }
+
+    #[test]
+    fn counts_and_operators() {
+        let mut state = ViState {
+            mode: ViMode::Normal,
+            ..Default::default()
+        };
+        assert_eq!(
+            format!("{:?}", keys(&mut state, "2d3w")),
+            "[Operate(Delete, ForwardWord(6))]"
+        );
+        assert_eq!(
+            format!("{:?}", keys(&mut state, "dd10l0")),
+            "[Operate(Delete, WholeLine), Move(ForwardChar(10)), Move(StartOfLine)]"
+        );
+        assert_eq!(
+            format!("{:?}", keys(&mut state, "cw2tx")),
+            "[Operate(Change, ForwardWordEnd(1)), Move(ForwardTillChar(2, 'x'))]"
+        );
+        assert_eq!(
+            format!("{:?}", keys(&mut state, "dixxyFa")),
+            "[Operate(Delete, ForwardChar(1)), Operate(Yank, BackwardFindChar(1, 'a'))]"
+        );
+
+        state.mode = ViMode::Visual;
+        assert_eq!(
+            format!("{:?}", keys(&mut state, "wy")),
+            "[Move(ForwardWord(1)), OperateOnSelection(Yank)]"
+        );
+    }
+
+    #[test]
+    fn motions() {
+        let line = "  foo bar.baz  qux";
+        assert_eq!(first_non_blank(line), 2);
+        assert_eq!(word_end(line, 0, 1), 4);
+        assert_eq!(word_end(line, 4, 1), 12);
+        assert_eq!(word_end(line, 2, 3), 17);
+        assert_eq!(find_char(line, 2, 1, 'a', true), Some(7));
+        assert_eq!(find_char(line, 2, 2, 'a', true), Some(11));
+        assert_eq!(find_char(line, 2, 3, 'a', true), None);
+        assert_eq!(find_char(line, 11, 1, 'a', false), Some(7));
+    }
+
+    #[test]
+    fn word_objects() {
+        let line = "one  two three";
+        assert_eq!(word_object(line, 5, false), 5..8);
+        assert_eq!(word_object(line, 6, true), 5..9);
+        assert_eq!(word_object(line, 3, false), 3..5);
+        assert_eq!(word_object(line, 4, true), 3..8);
+        // Without whitespace after the word, that before it is taken
+        assert_eq!(word_object(line, 13, true), 8..14);
+        assert_eq!(word_object(line, 14, false), 9..14);
+        assert_eq!(word_object("", 0, true), 0..0);
+    }
+}
@@ -1,3 +1,4 @@
+pub mod sixel;
 pub mod terminfo;
 #[cfg(windows)]
 pub mod windows;
@@ -5,4 +6,10 @@ pub mod windows;
 pub trait RenderTty: std::io::Write {
     /// Returns the (cols, rows) for the terminal
     fn get_size_in_cells(&mut self) -> crate::Result<(usize, usize)>;
+
+    /// Returns the (width, height) of the terminal in pixels,
+    /// or (0, 0) if that information is not available
+    fn get_size_in_pixels(&mut self) -> crate::Result<(usize, usize)> {
+        Ok((0, 0))
+    }
 }
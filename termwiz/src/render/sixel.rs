@@ -0,0 +1,508 @@
+//! Encoding of RGBA pixel data as sixel graphics.
+//!
+//! Sixel terminals work from a small indexed palette, so the pixels
+//! are first quantized using median cut, optionally with Floyd-Steinberg
+//! error diffusion, and then emitted as bands of six rows per color.
+//! See <https://vt100.net/docs/vt3xx-gp/chapter14.html>
+use crate::color::RgbColor;
+use crate::escape::{Sixel, SixelData};
+use std::collections::HashMap;
+
+/// Pixels with an alpha value below this are left unpainted
+const ALPHA_THRESHOLD: u8 = 0x80;
+
+/// Configures how RGBA data is converted to sixel data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SixelEncoder {
+    /// The maximum number of palette entries to use.
+    /// Most terminals support at least 256 color registers;
+    /// the value is clamped to the range 1-256.
+    pub max_colors: usize,
+    /// When true, apply Floyd-Steinberg dithering to reduce
+    /// banding caused by palette quantization
+    pub dither: bool,
+}
+
+impl Default for SixelEncoder {
+    fn default() -> Self {
+        Self {
+            max_colors: 256,
+            dither: true,
+        }
+    }
+}
+
+impl SixelEncoder {
+    /// Encode `rgba`, which must hold `width * height` RGBA pixels,
+    /// as a sixel image.
+    pub fn encode(&self, width: u32, height: u32, rgba: &[u8]) -> Sixel {
+        let width = width as usize;
+        let height = height as usize;
+        assert_eq!(
+            width * height * 4,
+            rgba.len(),
+            "invalid dimensions {}x{} for pixel data of length {}",
+            width,
+            height,
+            rgba.len()
+        );
+
+        let palette = Palette::median_cut(rgba, self.max_colors.clamp(1, 256));
+        let indices = if self.dither {
+            palette.map_dithered(width, height, rgba)
+        } else {
+            palette.map(rgba)
+        };
+        let background_is_transparent = indices.iter().any(Option::is_none);
+
+        let mut data = vec![];
+        for (color_number, rgb) in palette.colors.iter().enumerate() {
+            data.push(SixelData::DefineColorMapRGB {
+                color_number: color_number as u16,
+                rgb: *rgb,
+            });
+        }
+
+        let num_bands = height.div_ceil(6);
+        for band in 0..num_bands {
+            let top = band * 6;
+            let rows = (height - top).min(6);
+
+            // Accumulate the sixel values for each color used in the band
+            let mut layers: Vec<(u16, Vec<u8>)> = vec![];
+            let mut layer_by_color = HashMap::new();
+            for dy in 0..rows {
+                for x in 0..width {
+                    if let Some(color) = indices[(top + dy) * width + x] {
+                        let layer = *layer_by_color.entry(color).or_insert_with(|| {
+                            layers.push((color, vec![0u8; width]));
+                            layers.len() - 1
+                        });
+                        layers[layer].1[x] |= 1 << dy;
+                    }
+                }
+            }
+
+            for (idx, (color, values)) in layers.iter().enumerate() {
+                if idx > 0 {
+                    data.push(SixelData::CarriageReturn);
+                }
+                data.push(SixelData::SelectColorMapEntry(*color));
+                emit_run_length(values, &mut data);
+            }
+
+            if band + 1 < num_bands {
+                data.push(SixelData::NewLine);
+            }
+        }
+
+        Sixel {
+            pan: 1,
+            pad: 1,
+            pixel_width: Some(width as u32),
+            pixel_height: Some(height as u32),
+            background_is_transparent,
+            // Terminals ignore the grid size, but specifying it keeps
+            // the parameter list unambiguous for parsers
+            horizontal_grid_size: Some(0),
+            data,
+        }
+    }
+}
+
+/// Emits `values` using the repeat introducer for runs that are
+/// long enough to benefit from it.  Trailing empty sixels are
+/// omitted, as they don't paint anything.
+fn emit_run_length(values: &[u8], data: &mut Vec<SixelData>) {
+    let len = values
+        .iter()
+        .rposition(|&v| v != 0)
+        .map(|pos| pos + 1)
+        .unwrap_or(0);
+    let values = &values[..len];
+
+    let mut x = 0;
+    while x < values.len() {
+        let value = values[x];
+        let run = values[x..].iter().take_while(|&&v| v == value).count();
+        if run > 3 {
+            data.push(SixelData::Repeat {
+                repeat_count: run as u32,
+                data: value,
+            });
+        } else {
+            for _ in 0..run {
+                data.push(SixelData::Data(value));
+            }
+        }
+        x += run;
+    }
+}
+
+/// Sixel color registers are specified as percentages, so we round
+/// palette entries to the nearest representable value; this makes
+/// the palette survive a round trip through the parser unchanged.
+fn quantize_to_percent(value: u8) -> u8 {
+    let percent = (value as f32 * 100. / 255.).round();
+    (percent * 255. / 100.) as u8
+}
+
+struct ColorBox {
+    colors: Vec<([u8; 3], usize)>,
+}
+
+impl ColorBox {
+    fn range(&self, channel: usize) -> u8 {
+        let (min, max) = self
+            .colors
+            .iter()
+            .fold((u8::MAX, u8::MIN), |(min, max), (rgb, _)| {
+                (min.min(rgb[channel]), max.max(rgb[channel]))
+            });
+        max.saturating_sub(min)
+    }
+
+    fn widest_channel(&self) -> (usize, u8) {
+        (0..3)
+            .map(|channel| (channel, self.range(channel)))
+            .max_by_key(|&(_, range)| range)
+            .unwrap()
+    }
+
+    fn population(&self) -> usize {
+        self.colors.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Split the box at the population median of its widest channel
+    fn split(mut self) -> (Self, Self) {
+        let (channel, _) = self.widest_channel();
+        self.colors.sort_by_key(|(rgb, _)| rgb[channel]);
+        let half = self.population() / 2;
+        let mut accumulated = 0;
+        let mut split_at = 1;
+        for (idx, (_, count)) in self.colors.iter().enumerate() {
+            accumulated += count;
+            if accumulated >= half {
+                split_at = idx + 1;
+                break;
+            }
+        }
+        let split_at = split_at.max(1).min(self.colors.len() - 1);
+        let upper = self.colors.split_off(split_at);
+        (self, Self { colors: upper })
+    }
+
+    fn average(&self) -> RgbColor {
+        let mut sum = [0usize; 3];
+        let mut total = 0;
+        for (rgb, count) in &self.colors {
+            for channel in 0..3 {
+                sum[channel] += rgb[channel] as usize * count;
+            }
+            total += count;
+        }
+        let total = total.max(1);
+        RgbColor::new_8bpc(
+            quantize_to_percent(((sum[0] + total / 2) / total) as u8),
+            quantize_to_percent(((sum[1] + total / 2) / total) as u8),
+            quantize_to_percent(((sum[2] + total / 2) / total) as u8),
+        )
+    }
+}
+
+struct Palette {
+    colors: Vec<RgbColor>,
+    cache: std::cell::RefCell<HashMap<[u8; 3], u16>>,
+}
+
+impl Palette {
+    fn median_cut(rgba: &[u8], max_colors: usize) -> Self {
+        let mut histogram: HashMap<[u8; 3], usize> = HashMap::new();
+        for pixel in rgba.chunks_exact(4) {
+            if pixel[3] >= ALPHA_THRESHOLD {
+                *histogram.entry([pixel[0], pixel[1], pixel[2]]).or_insert(0) += 1;
+            }
+        }
+
+        let mut boxes = vec![];
+        if !histogram.is_empty() {
+            boxes.push(ColorBox {
+                colors: histogram.into_iter().collect(),
+            });
+        }
+
+        while boxes.len() < max_colors {
+            // Pick the box with the widest spread, weighted by how
+            // many pixels it covers, so that large areas get more
+            // precise colors than rare outliers.
+            let candidate = boxes
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| b.colors.len() > 1)
+                .max_by_key(|(_, b)| b.widest_channel().1 as usize * b.population());
+            let idx = match candidate {
+                Some((idx, _)) => idx,
+                None => break,
+            };
+            let (a, b) = boxes.swap_remove(idx).split();
+            boxes.push(a);
+            boxes.push(b);
+        }
+
+        let mut colors: Vec<RgbColor> = boxes.iter().map(ColorBox::average).collect();
+        colors.dedup();
+
+        Self {
+            colors,
+            cache: Default::default(),
+        }
+    }
+
+    fn nearest(&self, rgb: [u8; 3]) -> u16 {
+        if let Some(idx) = self.cache.borrow().get(&rgb) {
+            return *idx;
+        }
+
+        let distance = |color: &RgbColor| {
+            let (r, g, b) = color.to_tuple_rgb8();
+            let dr = r as i32 - rgb[0] as i32;
+            let dg = g as i32 - rgb[1] as i32;
+            let db = b as i32 - rgb[2] as i32;
+            dr * dr + dg * dg + db * db
+        };
+        let idx = self
+            .colors
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, color)| distance(color))
+            .map(|(idx, _)| idx as u16)
+            .unwrap_or(0);
+
+        self.cache.borrow_mut().insert(rgb, idx);
+        idx
+    }
+
+    fn map(&self, rgba: &[u8]) -> Vec<Option<u16>> {
+        rgba.chunks_exact(4)
+            .map(|pixel| {
+                if pixel[3] >= ALPHA_THRESHOLD {
+                    Some(self.nearest([pixel[0], pixel[1], pixel[2]]))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn map_dithered(&self, width: usize, height: usize, rgba: &[u8]) -> Vec<Option<u16>> {
+        let mut errors = vec![[0f32; 3]; width * height];
+        let mut result = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let offset = y * width + x;
+                let pixel = &rgba[offset * 4..offset * 4 + 4];
+                if pixel[3] < ALPHA_THRESHOLD {
+                    result.push(None);
+                    continue;
+                }
+
+                let mut wanted = [0u8; 3];
+                for channel in 0..3 {
+                    wanted[channel] = (pixel[channel] as f32 + errors[offset][channel])
+                        .round()
+                        .clamp(0., 255.) as u8;
+                }
+                let idx = self.nearest(wanted);
+                result.push(Some(idx));
+
+                let (r, g, b) = self.colors[idx as usize].to_tuple_rgb8();
+                let actual = [r, g, b];
+                let mut diffuse = |dx: isize, dy: usize, weight: f32| {
+                    let nx = x as isize + dx;
+                    let ny = y + dy;
+                    if nx < 0 || nx as usize >= width || ny >= height {
+                        return;
+                    }
+                    let target = &mut errors[ny * width + nx as usize];
+                    for channel in 0..3 {
+                        target[channel] +=
+                            (wanted[channel] as f32 - actual[channel] as f32) * weight;
+                    }
+                };
+                diffuse(1, 0, 7. / 16.);
+                diffuse(-1, 1, 3. / 16.);
+                diffuse(0, 1, 5. / 16.);
+                diffuse(1, 1, 1. / 16.);
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::escape::parser::Parser;
+    use crate::escape::{Action, Esc, EscCode};
+    use k9::assert_equal as assert_eq;
+
+    fn round_trip(sixel: &Sixel) -> Sixel {
+        let encoded = format!("{}\x1b\\", sixel);
+        let mut p = Parser::new();
+        let actions = p.parse_as_vec(encoded.as_bytes());
+        assert_eq!(actions.len(), 2);
+        assert_eq!(
+            actions[1],
+            Action::Esc(Esc::Code(EscCode::StringTerminator))
+        );
+        match &actions[0] {
+            Action::Sixel(parsed) => (**parsed).clone(),
+            other => panic!("expected a sixel, got {:?}", other),
+        }
+    }
+
+    /// Rasterize a sixel into rgba, using a transparent background
+    fn rasterize(sixel: &Sixel) -> Vec<u8> {
+        let (width, height) = sixel.dimensions();
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let mut palette = HashMap::new();
+        let mut color = RgbColor::default();
+        let (mut x, mut y) = (0u32, 0u32);
+
+        let mut paint = |x: u32, y: u32, value: u8, color: RgbColor| {
+            let (r, g, b) = color.to_tuple_rgb8();
+            for bit in 0..6 {
+                if value & (1 << bit) != 0 && y + bit < height {
+                    let offset = (((y + bit) * width + x) * 4) as usize;
+                    pixels[offset..offset + 4].copy_from_slice(&[r, g, b, 0xff]);
+                }
+            }
+        };
+
+        for d in &sixel.data {
+            match d {
+                SixelData::DefineColorMapRGB { color_number, rgb } => {
+                    palette.insert(*color_number, *rgb);
+                }
+                SixelData::DefineColorMapHSL { .. } => unreachable!(),
+                SixelData::SelectColorMapEntry(n) => color = palette[n],
+                SixelData::Data(value) => {
+                    paint(x, y, *value, color);
+                    x += 1;
+                }
+                SixelData::Repeat { repeat_count, data } => {
+                    for _ in 0..*repeat_count {
+                        paint(x, y, *data, color);
+                        x += 1;
+                    }
+                }
+                SixelData::CarriageReturn => x = 0,
+                SixelData::NewLine => {
+                    x = 0;
+                    y += 6;
+                }
+            }
+        }
+        pixels
+    }
+
+    fn checkerboard(width: u32, height: u32, colors: &[[u8; 4]]) -> Vec<u8> {
+        let mut data = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                data.extend_from_slice(&colors[((x + y) as usize) % colors.len()]);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let colors = [
+            [0xff, 0x00, 0x00, 0xff],
+            [0x00, 0xff, 0x00, 0xff],
+            [0x00, 0x00, 0xff, 0xff],
+        ];
+        let rgba = checkerboard(13, 10, &colors);
+        let encoder = SixelEncoder {
+            dither: false,
+            ..Default::default()
+        };
+        let sixel = encoder.encode(13, 10, &rgba);
+        assert_eq!(sixel.dimensions(), (13, 10));
+        assert_eq!(sixel.background_is_transparent, false);
+
+        let parsed = round_trip(&sixel);
+        assert_eq!(parsed, sixel);
+        // Pure primaries are representable exactly, so we should
+        // get the original pixels back out
+        assert_eq!(rasterize(&parsed), rgba);
+    }
+
+    #[test]
+    fn encode_transparency() {
+        let rgba = checkerboard(4, 7, &[[0xff, 0xff, 0xff, 0xff], [0, 0, 0, 0]]);
+        let sixel = SixelEncoder::default().encode(4, 7, &rgba);
+        assert_eq!(sixel.background_is_transparent, true);
+
+        let parsed = round_trip(&sixel);
+        assert_eq!(parsed, sixel);
+        assert_eq!(rasterize(&parsed), rgba);
+    }
+
+    #[test]
+    fn encode_run_length() {
+        let rgba = checkerboard(40, 6, &[[0xff, 0xff, 0xff, 0xff]]);
+        let sixel = SixelEncoder::default().encode(40, 6, &rgba);
+        assert_eq!(
+            sixel.data,
+            vec![
+                SixelData::DefineColorMapRGB {
+                    color_number: 0,
+                    rgb: RgbColor::new_8bpc(0xff, 0xff, 0xff),
+                },
+                SixelData::SelectColorMapEntry(0),
+                SixelData::Repeat {
+                    repeat_count: 40,
+                    data: 0x3f
+                },
+            ]
+        );
+        assert_eq!(
+            format!("{}", sixel),
+            "\x1bP0;0;0q\"1;1;40;6#0;2;100;100;100#0!40~"
+        );
+        assert_eq!(round_trip(&sixel), sixel);
+    }
+
+    #[test]
+    fn quantize_limits_palette() {
+        let mut rgba = vec![];
+        for y in 0..16u8 {
+            for x in 0..16u8 {
+                rgba.extend_from_slice(&[x * 16, y * 16, 0x80, 0xff]);
+            }
+        }
+
+        for dither in [false, true] {
+            let encoder = SixelEncoder {
+                max_colors: 16,
+                dither,
+            };
+            let sixel = encoder.encode(16, 16, &rgba);
+            let defined = sixel
+                .data
+                .iter()
+                .filter(|d| matches!(d, SixelData::DefineColorMapRGB { .. }))
+                .count();
+            assert!(defined <= 16, "defined {} colors", defined);
+            assert_eq!(round_trip(&sixel), sixel);
+
+            // Every pixel is opaque, so every pixel must be painted
+            let pixels = rasterize(&sixel);
+            assert!(pixels.chunks_exact(4).all(|p| p[3] == 0xff));
+        }
+    }
+}
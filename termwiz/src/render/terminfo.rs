@@ -2,21 +2,44 @@
 use crate::caps::{Capabilities, ColorLevel};
 use crate::cell::{AttributeChange, Blink, CellAttributes, Intensity, Underline};
 use crate::color::{ColorAttribute, ColorSpec};
-use crate::escape::csi::{Cursor, Edit, EraseInDisplay, EraseInLine, Sgr, CSI};
+use crate::escape::apc::{
+    KittyImage, KittyImageCompression, KittyImageData, KittyImageDelete, KittyImageFormat,
+    KittyImagePlacement, KittyImageTransmit, KittyImageVerbosity,
+};
+use crate::escape::csi::{
+    Cursor, DecPrivateMode, DecPrivateModeCode, Edit, EraseInDisplay, EraseInLine, Mode, Sgr, CSI,
+};
 use crate::escape::esc::EscCode;
-use crate::escape::osc::{ITermDimension, ITermFileData, ITermProprietary, OperatingSystemCommand};
+use crate::escape::osc::{
+    base64_encode, ITermDimension, ITermFileData, ITermProprietary, OperatingSystemCommand,
+};
 use crate::escape::{Esc, OneBased};
-use crate::image::{ImageDataType, TextureCoordinate};
+use crate::image::{ImageData, ImageDataType, TextureCoordinate};
+use crate::render::sixel::SixelEncoder;
 use crate::render::RenderTty;
-use crate::surface::{Change, CursorShape, CursorVisibility, LineAttribute, Position};
+use crate::surface::{Change, CursorShape, CursorVisibility, Image, LineAttribute, Position};
 use crate::Result;
+use lru::LruCache;
 use std::io::Write;
 use terminfo::{capability as cap, Capability as TermInfoCapability};
 
+/// How many images sent with the kitty graphics protocol are
+/// remembered for reuse.  When an image is evicted from the cache,
+/// the terminal is asked to delete it too.
+const KITTY_IMAGE_CACHE_SIZE: usize = 64;
+
 pub struct TerminfoRenderer {
     caps: Capabilities,
     current_attr: CellAttributes,
     pending_attr: Option<CellAttributes>,
+    sixel_encoder: SixelEncoder,
+    /// Maps the hash of image data that has been transmitted using
+    /// the kitty graphics protocol to its (image_id, width, height)
+    kitty_images: LruCache<[u8; 32], (u32, u32, u32)>,
+    next_kitty_image_id: u32,
+    /// Whether we believe the cursor to be visible.  Terminals
+    /// show the cursor by default, so this starts out as true.
+    cursor_visible: bool,
     /* TODO: we should record cursor position, shape and color here
      * so that we can optimize updating them on screen. */
 }
@@ -27,9 +50,32 @@ impl TerminfoRenderer {
             caps,
             current_attr: CellAttributes::default(),
             pending_attr: None,
+            sixel_encoder: SixelEncoder::default(),
+            kitty_images: LruCache::new(KITTY_IMAGE_CACHE_SIZE),
+            next_kitty_image_id: 1,
+            cursor_visible: true,
         }
     }
 
+    /// Forget what the renderer assumes about the state of the terminal:
+    /// the current attributes, the cursor visibility and the images that
+    /// have been sent using the kitty graphics protocol.
+    /// Call this after the terminal has been fully reset (RIS), which
+    /// discards all of those, so that the renderer doesn't try to place
+    /// images that no longer exist.
+    pub fn reset(&mut self) {
+        self.current_attr = CellAttributes::default();
+        self.pending_attr = None;
+        self.cursor_visible = true;
+        self.kitty_images.clear();
+    }
+
+    /// Configure how images are encoded when rendering to a terminal
+    /// that supports sixel graphics
+    pub fn set_sixel_encoder(&mut self, encoder: SixelEncoder) {
+        self.sixel_encoder = encoder;
+    }
+
     fn get_capability<'a, T: TermInfoCapability<'a>>(&'a self) -> Option<T> {
         self.caps.terminfo_db().and_then(|db| db.get::<T>())
     }
@@ -318,11 +364,303 @@ impl TerminfoRenderer {
         Ok(())
     }
 
+    /// Blank out the cells and move the cursor to the right spot
+    fn blank_image<W: RenderTty + Write>(&mut self, image: &Image, out: &mut W) -> Result<()> {
+        for y in 0..image.height {
+            for _ in 0..image.width {
+                write!(out, " ")?;
+            }
+
+            if y != image.height - 1 {
+                writeln!(out)?;
+                self.cursor_left(image.width as u32, out)?;
+            }
+        }
+        self.cursor_up(image.height as u32, out)?;
+        Ok(())
+    }
+
+    /// Send the image using the iTerm2 image protocol.
+    /// Returns false if the image data could not be encoded,
+    /// in which case nothing has been written.
+    fn render_iterm2<W: RenderTty + Write>(&mut self, image: &Image, out: &mut W) -> Result<bool> {
+        let whole_image = image.top_left == TextureCoordinate::new_f32(0.0, 0.0)
+            && image.bottom_right == TextureCoordinate::new_f32(1.0, 1.0);
+
+        // If the whole image is requested, we can send the
+        // original image bytes over
+        let original = if whole_image {
+            match &*image.image.data() {
+                ImageDataType::EncodedFile(data) => Some(data.to_vec()),
+                ImageDataType::EncodedLease(lease) => Some(lease.get_data()?),
+                ImageDataType::AnimRgba8 { .. } | ImageDataType::Rgba8 { .. } => None,
+            }
+        } else {
+            None
+        };
+
+        // Otherwise, slice out the requested region and encode it as a PNG
+        let data = match original {
+            Some(data) => data,
+            None => match image_region_rgba(image)? {
+                Some((width, height, pixels)) => match encode_png(width, height, &pixels)? {
+                    Some(png) => png,
+                    None => return Ok(false),
+                },
+                None => return Ok(false),
+            },
+        };
+
+        let file = ITermFileData {
+            name: None,
+            size: Some(data.len()),
+            width: ITermDimension::Cells(image.width as i64),
+            height: ITermDimension::Cells(image.height as i64),
+            preserve_aspect_ratio: true,
+            inline: true,
+            do_not_move_cursor: false,
+            data,
+        };
+
+        let osc = OperatingSystemCommand::ITermProprietary(ITermProprietary::File(Box::new(file)));
+
+        write!(out, "{}", osc)?;
+        Ok(true)
+    }
+
+    /// Send the image using the kitty graphics protocol.
+    /// The image data is transmitted once and then referenced by its
+    /// id, so that subsequent placements of the same image (or of
+    /// other regions of it) don't need to send the pixels again.
+    /// Returns false if the pixel data for the image is not available,
+    /// in which case nothing has been written.
+    fn render_kitty<W: RenderTty + Write>(&mut self, image: &Image, out: &mut W) -> Result<bool> {
+        let hash = image.image.hash();
+        let (image_id, width, height) = match self.kitty_images.get(&hash) {
+            Some(&entry) => entry,
+            None => {
+                let (width, height, pixels) = match image_rgba(&image.image)? {
+                    Some(decoded) => decoded,
+                    None => return Ok(false),
+                };
+                let image_id = self.next_kitty_image_id;
+                self.next_kitty_image_id = self.next_kitty_image_id.wrapping_add(1).max(1);
+                self.transmit_kitty(image_id, width, height, &pixels, out)?;
+                if let Some((_, (evicted, _, _))) =
+                    self.kitty_images.push(hash, (image_id, width, height))
+                {
+                    // Release the least recently used image, along with
+                    // its placements, rather than leaving the terminal to
+                    // decide what to evict
+                    let delete = KittyImage::Delete {
+                        what: KittyImageDelete::ByImageId {
+                            image_id: evicted,
+                            placement_id: None,
+                            delete: true,
+                        },
+                        verbosity: KittyImageVerbosity::Quiet,
+                    };
+                    write!(out, "{}{}", delete, Esc::Code(EscCode::StringTerminator))?;
+                }
+                (image_id, width, height)
+            }
+        };
+
+        let (left, top, right, bottom) = region_rect(image, width, height);
+        if right == left || bottom == top {
+            return Ok(false);
+        }
+        let whole_image = left == 0 && top == 0 && right == width && bottom == height;
+        let rect = |v: u32| if whole_image { None } else { Some(v) };
+
+        let place = KittyImage::Display {
+            image_id: Some(image_id),
+            image_number: None,
+            placement: KittyImagePlacement {
+                x: rect(left),
+                y: rect(top),
+                w: rect(right - left),
+                h: rect(bottom - top),
+                x_offset: None,
+                y_offset: None,
+                columns: Some(image.width as u32),
+                rows: Some(image.height as u32),
+                // We move the cursor ourselves, as the Surface expects
+                do_not_move_cursor: true,
+                placement_id: None,
+                z_index: None,
+            },
+            verbosity: KittyImageVerbosity::Quiet,
+        };
+        write!(out, "{}{}", place, Esc::Code(EscCode::StringTerminator))?;
+        self.cursor_right(image.width as u32, out)?;
+        Ok(true)
+    }
+
+    fn transmit_kitty<W: RenderTty + Write>(
+        &mut self,
+        image_id: u32,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        out: &mut W,
+    ) -> Result<()> {
+        let (format, data) = match encode_png(width, height, pixels)? {
+            Some(png) => (KittyImageFormat::Png, png),
+            None => (KittyImageFormat::Rgba, pixels.to_vec()),
+        };
+
+        // The protocol requires that the base64 payload be sent
+        // in chunks of at most 4096 bytes
+        let encoded = base64_encode(&data);
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+        let num_chunks = chunks.len();
+        for (idx, chunk) in chunks.into_iter().enumerate() {
+            let more_data_follows = idx + 1 < num_chunks;
+            let chunk = String::from_utf8_lossy(chunk).to_string();
+            // Only the first chunk carries the image metadata;
+            // continuation chunks are only permitted to specify
+            // the verbosity and whether more data follows.
+            let transmit = if idx == 0 {
+                KittyImage::TransmitData {
+                    transmit: KittyImageTransmit {
+                        format: Some(format.clone()),
+                        data: KittyImageData::Direct(chunk),
+                        width: Some(width),
+                        height: Some(height),
+                        image_id: Some(image_id),
+                        image_number: None,
+                        compression: KittyImageCompression::None,
+                        more_data_follows,
+                    },
+                    verbosity: KittyImageVerbosity::Quiet,
+                }
+            } else {
+                KittyImage::TransmitData {
+                    transmit: KittyImageTransmit {
+                        format: None,
+                        data: KittyImageData::Direct(chunk),
+                        width: None,
+                        height: None,
+                        image_id: None,
+                        image_number: None,
+                        compression: KittyImageCompression::None,
+                        more_data_follows,
+                    },
+                    verbosity: KittyImageVerbosity::Quiet,
+                }
+            };
+            write!(out, "{}{}", transmit, Esc::Code(EscCode::StringTerminator))?;
+        }
+        Ok(())
+    }
+
+    /// Encode the image as sixel data.
+    /// Returns false if the pixel data for the image is not available,
+    /// in which case nothing has been written.
+    fn render_sixel<W: RenderTty + Write>(&mut self, image: &Image, out: &mut W) -> Result<bool> {
+        let (width, height, data) = match image_region_rgba(image)? {
+            Some(region) => region,
+            None => return Ok(false),
+        };
+
+        // Sixel data is drawn at its native pixel size, so scale it
+        // to fill the requested cells if we know how big they are.
+        let (cols, rows) = out.get_size_in_cells()?;
+        let (xpixel, ypixel) = out.get_size_in_pixels()?;
+        let (width, height, data) = if cols > 0 && rows > 0 && xpixel > 0 && ypixel > 0 {
+            let target_width = (image.width * xpixel / cols).max(1) as u32;
+            let target_height = (image.height * ypixel / rows).max(1) as u32;
+            let data = scale_rgba(width, height, &data, target_width, target_height);
+            (target_width, target_height, data)
+        } else {
+            (width, height, data)
+        };
+
+        let sixel = self.sixel_encoder.encode(width, height, &data);
+
+        // Terminals differ in where they leave the cursor after
+        // displaying sixel data, so put it back where we started
+        // and then move it past the image, as the Surface expects.
+        write!(
+            out,
+            "{}{}{}{}",
+            Esc::Code(EscCode::DecSaveCursorPosition),
+            sixel,
+            Esc::Code(EscCode::StringTerminator),
+            Esc::Code(EscCode::DecRestoreCursorPosition)
+        )?;
+        self.cursor_right(image.width as u32, out)?;
+        Ok(true)
+    }
+
+    /// Render `changes` to `out`.
+    /// The output is accumulated and sent to `out` in a single write.
+    /// If the terminal supports synchronized output, the update is
+    /// bracketed by DEC private mode 2026 so that the terminal presents
+    /// it atomically.  Otherwise, if `hide_cursor_while_rendering` was
+    /// hinted and the changes move the cursor around, a visible cursor
+    /// is hidden with DECTCEM for the duration of the update so that it
+    /// isn't seen jumping around the screen.
+    pub fn render_to<W: RenderTty + Write>(
+        &mut self,
+        changes: &[Change],
+        out: &mut W,
+    ) -> Result<()> {
+        let sync = self.caps.synchronized_output() && !changes.is_empty();
+        let hide_cursor = !sync
+            && self.caps.hide_cursor_while_rendering()
+            && self.cursor_visible
+            && changes
+                .iter()
+                .any(|change| matches!(change, Change::CursorPosition { .. }));
+
+        let mut frame = FrameBuffer {
+            out,
+            buf: Vec::new(),
+        };
+        let synchronized_output = DecPrivateMode::Code(DecPrivateModeCode::SynchronizedOutput);
+
+        if sync {
+            write!(
+                frame,
+                "{}",
+                CSI::Mode(Mode::SetDecPrivateMode(synchronized_output.clone()))
+            )?;
+        }
+        let show_cursor = DecPrivateMode::Code(DecPrivateModeCode::ShowCursor);
+        if hide_cursor {
+            write!(
+                frame,
+                "{}",
+                CSI::Mode(Mode::ResetDecPrivateMode(show_cursor.clone()))
+            )?;
+        }
+
+        self.render_changes(changes, &mut frame)?;
+
+        // Restore the cursor, unless the changes hid it
+        if hide_cursor && self.cursor_visible {
+            write!(frame, "{}", CSI::Mode(Mode::SetDecPrivateMode(show_cursor)))?;
+        }
+        if sync {
+            write!(
+                frame,
+                "{}",
+                CSI::Mode(Mode::ResetDecPrivateMode(synchronized_output))
+            )?;
+        }
+
+        frame.out.write_all(&frame.buf)?;
+        frame.out.flush()?;
+        Ok(())
+    }
+
     #[cfg_attr(
         feature = "cargo-clippy",
         allow(clippy::cyclomatic_complexity, clippy::cognitive_complexity)
     )]
-    pub fn render_to<W: RenderTty + Write>(
+    fn render_changes<W: RenderTty + Write>(
         &mut self,
         changes: &[Change],
         out: &mut W,
@@ -559,69 +897,33 @@ impl TerminfoRenderer {
                         }
                     }
                 },
-                Change::CursorVisibility(visibility) => match visibility {
-                    CursorVisibility::Visible => {
-                        if let Some(show) = self.get_capability::<cap::CursorNormal>() {
-                            show.expand().to(out.by_ref())?;
+                Change::CursorVisibility(visibility) => {
+                    self.cursor_visible = *visibility == CursorVisibility::Visible;
+                    match visibility {
+                        CursorVisibility::Visible => {
+                            if let Some(show) = self.get_capability::<cap::CursorNormal>() {
+                                show.expand().to(out.by_ref())?;
+                            }
                         }
-                    }
-                    CursorVisibility::Hidden => {
-                        if let Some(hide) = self.get_capability::<cap::CursorInvisible>() {
-                            hide.expand().to(out.by_ref())?;
+                        CursorVisibility::Hidden => {
+                            if let Some(hide) = self.get_capability::<cap::CursorInvisible>() {
+                                hide.expand().to(out.by_ref())?;
+                            }
                         }
                     }
-                },
+                }
                 Change::Image(image) => {
-                    if self.caps.iterm2_image() {
-                        let data = if image.top_left == TextureCoordinate::new_f32(0.0, 0.0)
-                            && image.bottom_right == TextureCoordinate::new_f32(1.0, 1.0)
-                        {
-                            // The whole image is requested, so we can send the
-                            // original image bytes over
-                            match &*image.image.data() {
-                                ImageDataType::EncodedFile(data) => data.to_vec(),
-                                ImageDataType::EncodedLease(lease) => lease.get_data()?,
-                                ImageDataType::AnimRgba8 { .. } | ImageDataType::Rgba8 { .. } => {
-                                    unimplemented!()
-                                }
-                            }
-                        } else {
-                            // TODO: slice out the requested region of the image,
-                            // and encode as a PNG.
-                            unimplemented!();
-                        };
-
-                        let file = ITermFileData {
-                            name: None,
-                            size: Some(data.len()),
-                            width: ITermDimension::Cells(image.width as i64),
-                            height: ITermDimension::Cells(image.height as i64),
-                            preserve_aspect_ratio: true,
-                            inline: true,
-                            do_not_move_cursor: false,
-                            data,
-                        };
-
-                        let osc = OperatingSystemCommand::ITermProprietary(ITermProprietary::File(
-                            Box::new(file),
-                        ));
-
-                        write!(out, "{}", osc)?;
-
-                    // TODO: } else if self.caps.sixel() {
+                    let rendered = if self.caps.kitty_graphics() {
+                        self.render_kitty(image, out)?
+                    } else if self.caps.iterm2_image() {
+                        self.render_iterm2(image, out)?
+                    } else if self.caps.sixel() {
+                        self.render_sixel(image, out)?
                     } else {
-                        // Blank out the cells and move the cursor to the right spot
-                        for y in 0..image.height {
-                            for _ in 0..image.width {
-                                write!(out, " ")?;
-                            }
-
-                            if y != image.height - 1 {
-                                writeln!(out)?;
-                                self.cursor_left(image.width as u32, out)?;
-                            }
-                        }
-                        self.cursor_up(image.height as u32, out)?;
+                        false
+                    };
+                    if !rendered {
+                        self.blank_image(image, out)?;
                     }
                 }
                 Change::ScrollRegionUp {
@@ -707,25 +1009,173 @@ impl TerminfoRenderer {
         }
 
         self.flush_pending_attr(out)?;
-        out.flush()?;
         Ok(())
     }
 }
 
+/// Accumulates the output produced by a single call to `render_to`,
+/// so that it can be sent to the terminal in one write rather than
+/// trickling out as the tty's buffer fills up
+struct FrameBuffer<'a, W: RenderTty + Write> {
+    out: &'a mut W,
+    buf: Vec<u8>,
+}
+
+impl<'a, W: RenderTty + Write> Write for FrameBuffer<'a, W> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, W: RenderTty + Write> RenderTty for FrameBuffer<'a, W> {
+    fn get_size_in_cells(&mut self) -> Result<(usize, usize)> {
+        self.out.get_size_in_cells()
+    }
+
+    fn get_size_in_pixels(&mut self) -> Result<(usize, usize)> {
+        self.out.get_size_in_pixels()
+    }
+}
+
+/// Returns the (width, height, rgba) pixels of the first frame of
+/// the image, decoding it if necessary.
+/// Returns None if the pixel data cannot be obtained, which is the
+/// case for encoded images when the `use_image` feature is disabled.
+fn image_rgba(image: &ImageData) -> Result<Option<(u32, u32, Vec<u8>)>> {
+    let decoded = match &*image.data() {
+        ImageDataType::EncodedFile(data) => ImageDataType::EncodedFile(data.clone()).decode(),
+        ImageDataType::EncodedLease(lease) => {
+            ImageDataType::EncodedFile(lease.get_data()?).decode()
+        }
+        ImageDataType::Rgba8 {
+            data,
+            width,
+            height,
+            ..
+        } => return Ok(Some((*width, *height, data.clone()))),
+        ImageDataType::AnimRgba8 {
+            frames,
+            width,
+            height,
+            ..
+        } => return Ok(frames.first().map(|frame| (*width, *height, frame.clone()))),
+    };
+
+    match decoded {
+        ImageDataType::Rgba8 {
+            data,
+            width,
+            height,
+            ..
+        } => Ok(Some((width, height, data))),
+        ImageDataType::AnimRgba8 {
+            mut frames,
+            width,
+            height,
+            ..
+        } if !frames.is_empty() => Ok(Some((width, height, frames.swap_remove(0)))),
+        _ => Ok(None),
+    }
+}
+
+/// Computes the (left, top, right, bottom) pixel bounds of the region
+/// referenced by the texture coordinates of `image`, given the pixel
+/// dimensions of the underlying image data.
+fn region_rect(image: &Image, width: u32, height: u32) -> (u32, u32, u32, u32) {
+    let to_pixels = |coord: f32, size: u32| ((coord * size as f32).round() as u32).min(size);
+    let left = to_pixels(*image.top_left.x, width);
+    let top = to_pixels(*image.top_left.y, height);
+    let right = to_pixels(*image.bottom_right.x, width).max(left);
+    let bottom = to_pixels(*image.bottom_right.y, height).max(top);
+    (left, top, right, bottom)
+}
+
+/// Returns the (width, height, rgba) pixels for the region of the
+/// source image referenced by `image`, decoding it if necessary.
+fn image_region_rgba(image: &Image) -> Result<Option<(u32, u32, Vec<u8>)>> {
+    let (width, height, pixels) = match image_rgba(&image.image)? {
+        Some(decoded) => decoded,
+        None => return Ok(None),
+    };
+
+    let (left, top, right, bottom) = region_rect(image, width, height);
+    let region_width = right - left;
+    let region_height = bottom - top;
+    if region_width == 0 || region_height == 0 {
+        return Ok(None);
+    }
+    if region_width == width && region_height == height {
+        return Ok(Some((width, height, pixels)));
+    }
+
+    let mut region = Vec::with_capacity((region_width * region_height * 4) as usize);
+    for y in top..bottom {
+        let start = ((y * width + left) * 4) as usize;
+        let end = ((y * width + right) * 4) as usize;
+        region.extend_from_slice(&pixels[start..end]);
+    }
+
+    Ok(Some((region_width, region_height, region)))
+}
+
+/// Encode rgba pixel data as a PNG file.
+/// Returns None if the `use_image` feature is disabled.
+#[cfg(feature = "use_image")]
+fn encode_png(width: u32, height: u32, pixels: &[u8]) -> Result<Option<Vec<u8>>> {
+    use image::ImageEncoder;
+    let mut png = vec![];
+    image::codecs::png::PngEncoder::new(&mut png).write_image(
+        pixels,
+        width,
+        height,
+        image::ColorType::Rgba8,
+    )?;
+    Ok(Some(png))
+}
+
+#[cfg(not(feature = "use_image"))]
+fn encode_png(_width: u32, _height: u32, _pixels: &[u8]) -> Result<Option<Vec<u8>>> {
+    Ok(None)
+}
+
+/// Nearest neighbor scaling of rgba pixel data
+fn scale_rgba(width: u32, height: u32, data: &[u8], new_width: u32, new_height: u32) -> Vec<u8> {
+    if width == new_width && height == new_height {
+        return data.to_vec();
+    }
+    let mut scaled = Vec::with_capacity((new_width * new_height * 4) as usize);
+    for y in 0..new_height {
+        let src_y = (y as u64 * height as u64 / new_height as u64) as u32;
+        for x in 0..new_width {
+            let src_x = (x as u64 * width as u64 / new_width as u64) as u32;
+            let offset = ((src_y * width + src_x) * 4) as usize;
+            scaled.extend_from_slice(&data[offset..offset + 4]);
+        }
+    }
+    scaled
+}
+
 #[cfg(all(test, unix))]
 mod test {
     use super::*;
     use crate::bail;
     use crate::caps::ProbeHints;
-    use crate::color::{AnsiColor, ColorAttribute};
+    use crate::color::{AnsiColor, ColorAttribute, RgbColor};
     use crate::escape::parser::Parser;
-    use crate::escape::{Action, Esc, EscCode};
+    use crate::escape::{Action, Esc, EscCode, Sixel, SixelData};
+    use crate::image::ImageData;
     use crate::input::InputEvent;
     use crate::terminal::unix::{Purge, SetAttributeWhen, UnixTty};
     use crate::terminal::{cast, ScreenSize, Terminal, TerminalWaker};
     use libc::winsize;
     use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Write};
     use std::mem;
+    use std::sync::Arc;
     use std::time::Duration;
     use terminfo;
     use termios::Termios;
@@ -776,6 +1226,10 @@ mod test {
         fn get_size_in_cells(&mut self) -> Result<(usize, usize)> {
             Ok((self.size.ws_col as usize, self.size.ws_row as usize))
         }
+
+        fn get_size_in_pixels(&mut self) -> Result<(usize, usize)> {
+            Ok((self.size.ws_xpixel as usize, self.size.ws_ypixel as usize))
+        }
     }
 
     impl UnixTty for FakeTty {
@@ -910,6 +1364,93 @@ mod test {
         assert_eq!(out.renderer.current_attr, CellAttributes::default());
     }
 
+    #[test]
+    fn synchronized_output() {
+        let mut out = FakeTerm::new(xterm_terminfo_with_hints(
+            ProbeHints::default().synchronized_output(Some(true)),
+        ));
+        out.render(&[
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(1),
+            },
+            Change::Text("foo".into()),
+        ])
+        .unwrap();
+        assert_eq!(
+            "\x1b[?2026h\x1b[2;1Hfoo\x1b[?2026l",
+            String::from_utf8(out.write.buf).unwrap()
+        );
+    }
+
+    #[test]
+    fn empty_render_is_not_synchronized() {
+        let mut out = FakeTerm::new(xterm_terminfo_with_hints(
+            ProbeHints::default().synchronized_output(Some(true)),
+        ));
+        out.render(&[]).unwrap();
+        assert_eq!("", String::from_utf8(out.write.buf).unwrap());
+    }
+
+    #[test]
+    fn cursor_not_hidden_by_default() {
+        let mut out = FakeTerm::new(xterm_terminfo());
+        out.render(&[
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(1),
+            },
+            Change::Text("foo".into()),
+        ])
+        .unwrap();
+        assert_eq!("\x1b[2;1Hfoo", String::from_utf8(out.write.buf).unwrap());
+    }
+
+    #[test]
+    fn cursor_hidden_while_moving() {
+        let mut out = FakeTerm::new(xterm_terminfo_with_hints(
+            ProbeHints::default().hide_cursor_while_rendering(Some(true)),
+        ));
+        out.render(&[
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(1),
+            },
+            Change::Text("foo".into()),
+        ])
+        .unwrap();
+        assert_eq!(
+            "\x1b[?25l\x1b[2;1Hfoo\x1b[?25h",
+            String::from_utf8(out.write.buf.split_off(0)).unwrap()
+        );
+
+        // If the changes hide the cursor, it should stay hidden
+        out.render(&[
+            Change::CursorVisibility(CursorVisibility::Hidden),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(0),
+            },
+        ])
+        .unwrap();
+        assert_eq!(
+            "\x1b[?25l\x1b[?25l\x1b[H",
+            String::from_utf8(out.write.buf.split_off(0)).unwrap()
+        );
+
+        // and now that it is hidden, there's no need to hide it
+        // while moving it
+        out.render(&[Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Absolute(1),
+        }])
+        .unwrap();
+        assert_eq!(
+            "\x1b[2;1H",
+            String::from_utf8(out.write.buf.split_off(0)).unwrap()
+        );
+    }
+
     #[test]
     fn bold_text() {
         let mut out = FakeTerm::new(xterm_terminfo());
@@ -1281,4 +1822,344 @@ mod test {
             ]
         );
     }
+
+    fn rgba_image(width: u32, height: u32, rgba: [u8; 4]) -> Arc<ImageData> {
+        let mut data = vec![];
+        for _ in 0..width * height {
+            data.extend_from_slice(&rgba);
+        }
+        Arc::new(ImageData::with_data(ImageDataType::new_single_frame(
+            width, height, data,
+        )))
+    }
+
+    fn sixel_terminfo() -> Capabilities {
+        xterm_terminfo_with_hints(ProbeHints::default().sixel(Some(true)))
+    }
+
+    #[test]
+    fn sixel_image() {
+        let mut out = FakeTerm::new(sixel_terminfo());
+        out.render(&[Change::Image(Image {
+            width: 2,
+            height: 1,
+            top_left: TextureCoordinate::new_f32(0.0, 0.0),
+            bottom_right: TextureCoordinate::new_f32(1.0, 1.0),
+            image: rgba_image(3, 2, [0xff, 0, 0, 0xff]),
+        })])
+        .unwrap();
+
+        let result = out.parse();
+        assert_eq!(
+            result,
+            vec![
+                Action::Esc(Esc::Code(EscCode::DecSaveCursorPosition)),
+                Action::Sixel(Box::new(Sixel {
+                    pan: 1,
+                    pad: 1,
+                    pixel_width: Some(3),
+                    pixel_height: Some(2),
+                    background_is_transparent: false,
+                    horizontal_grid_size: Some(0),
+                    data: vec![
+                        SixelData::DefineColorMapRGB {
+                            color_number: 0,
+                            rgb: RgbColor::new_8bpc(0xff, 0, 0),
+                        },
+                        SixelData::SelectColorMapEntry(0),
+                        SixelData::Data(3),
+                        SixelData::Data(3),
+                        SixelData::Data(3),
+                    ]
+                })),
+                Action::Esc(Esc::Code(EscCode::StringTerminator)),
+                Action::Esc(Esc::Code(EscCode::DecRestoreCursorPosition)),
+                Action::CSI(CSI::Cursor(Cursor::Right(2))),
+            ]
+        );
+    }
+
+    #[test]
+    fn sixel_image_scaled_region() {
+        let mut out = FakeTerm::new(sixel_terminfo());
+        // 10x20 pixel cells
+        out.write.size.ws_xpixel = 800;
+        out.write.size.ws_ypixel = 480;
+
+        out.render(&[Change::Image(Image {
+            width: 1,
+            height: 1,
+            top_left: TextureCoordinate::new_f32(0.5, 0.0),
+            bottom_right: TextureCoordinate::new_f32(1.0, 0.5),
+            image: rgba_image(4, 4, [0, 0xff, 0, 0xff]),
+        })])
+        .unwrap();
+
+        let result = out.parse();
+        match &result[1] {
+            Action::Sixel(sixel) => assert_eq!(sixel.dimensions(), (10, 20)),
+            other => panic!("expected sixel, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn image_without_sixel_is_blanked() {
+        let mut out = FakeTerm::new(xterm_terminfo());
+        out.render(&[Change::Image(Image {
+            width: 2,
+            height: 1,
+            top_left: TextureCoordinate::new_f32(0.0, 0.0),
+            bottom_right: TextureCoordinate::new_f32(1.0, 1.0),
+            image: rgba_image(3, 2, [0xff, 0, 0, 0xff]),
+        })])
+        .unwrap();
+
+        assert_eq!(
+            out.parse(),
+            vec![
+                Action::Print(' '),
+                Action::Print(' '),
+                Action::CSI(CSI::Cursor(Cursor::Up(1))),
+            ]
+        );
+    }
+
+    fn kitty_terminfo() -> Capabilities {
+        xterm_terminfo_with_hints(ProbeHints::default().kitty_graphics(Some(true)))
+    }
+
+    /// Returns the kitty image commands from the parsed actions
+    fn kitty_actions(actions: &[Action]) -> Vec<KittyImage> {
+        actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::KittyImage(img) => Some((**img).clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kitty_image_is_transmitted_once() {
+        let mut out = FakeTerm::new(kitty_terminfo());
+        let data = rgba_image(3, 2, [0, 0, 0xff, 0xff]);
+        let image = Change::Image(Image {
+            width: 2,
+            height: 1,
+            top_left: TextureCoordinate::new_f32(0.0, 0.0),
+            bottom_right: TextureCoordinate::new_f32(1.0, 1.0),
+            image: data.clone(),
+        });
+        out.render(std::slice::from_ref(&image)).unwrap();
+
+        let actions = out.parse();
+        let kitty = kitty_actions(&actions);
+        assert_eq!(kitty.len(), 2);
+        match &kitty[0] {
+            KittyImage::TransmitData {
+                transmit,
+                verbosity,
+            } => {
+                assert_eq!(*verbosity, KittyImageVerbosity::Quiet);
+                assert_eq!(transmit.image_id, Some(1));
+                assert_eq!(transmit.width, Some(3));
+                assert_eq!(transmit.height, Some(2));
+                assert_eq!(transmit.more_data_follows, false);
+            }
+            other => panic!("expected transmit, got {:?}", other),
+        }
+        let display = KittyImage::Display {
+            image_id: Some(1),
+            image_number: None,
+            placement: KittyImagePlacement {
+                x: None,
+                y: None,
+                w: None,
+                h: None,
+                x_offset: None,
+                y_offset: None,
+                columns: Some(2),
+                rows: Some(1),
+                do_not_move_cursor: true,
+                placement_id: None,
+                z_index: None,
+            },
+            verbosity: KittyImageVerbosity::Quiet,
+        };
+        assert_eq!(kitty[1], display);
+        assert_eq!(
+            actions.last().unwrap(),
+            &Action::CSI(CSI::Cursor(Cursor::Right(2)))
+        );
+
+        // Rendering the same image again just places it
+        out.write.buf.clear();
+        out.render(&[image]).unwrap();
+        assert_eq!(kitty_actions(&out.parse()), vec![display]);
+
+        // A region of the image references the source rectangle
+        out.write.buf.clear();
+        out.render(&[Change::Image(Image {
+            width: 1,
+            height: 1,
+            top_left: TextureCoordinate::new_f32(1.0 / 3.0, 0.5),
+            bottom_right: TextureCoordinate::new_f32(1.0, 1.0),
+            image: data,
+        })])
+        .unwrap();
+        match &kitty_actions(&out.parse())[..] {
+            [KittyImage::Display {
+                image_id: Some(1),
+                placement,
+                ..
+            }] => {
+                assert_eq!(
+                    (placement.x, placement.y, placement.w, placement.h),
+                    (Some(1), Some(1), Some(2), Some(1))
+                );
+                assert_eq!((placement.columns, placement.rows), (Some(1), Some(1)));
+            }
+            other => panic!("expected a single placement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn kitty_image_cache_is_bounded() {
+        let mut out = FakeTerm::new(kitty_terminfo());
+        let image = |n: usize| {
+            Change::Image(Image {
+                width: 1,
+                height: 1,
+                top_left: TextureCoordinate::new_f32(0.0, 0.0),
+                bottom_right: TextureCoordinate::new_f32(1.0, 1.0),
+                image: rgba_image(1, 1, [n as u8, (n >> 8) as u8, 0, 0xff]),
+            })
+        };
+        for n in 0..KITTY_IMAGE_CACHE_SIZE {
+            out.render(&[image(n)]).unwrap();
+        }
+        assert!(!kitty_actions(&out.parse())
+            .iter()
+            .any(|action| matches!(action, KittyImage::Delete { .. })));
+
+        // One more image evicts the least recently used one, which is
+        // the first, and deletes it from the terminal
+        out.write.buf.clear();
+        out.render(&[image(KITTY_IMAGE_CACHE_SIZE)]).unwrap();
+        let delete = KittyImage::Delete {
+            what: KittyImageDelete::ByImageId {
+                image_id: 1,
+                placement_id: None,
+                delete: true,
+            },
+            verbosity: KittyImageVerbosity::Quiet,
+        };
+        assert!(kitty_actions(&out.parse()).contains(&delete));
+
+        // so it has to be transmitted again
+        out.write.buf.clear();
+        out.render(&[image(0)]).unwrap();
+        assert!(matches!(
+            kitty_actions(&out.parse())[0],
+            KittyImage::TransmitData { .. }
+        ));
+    }
+
+    #[test]
+    fn kitty_images_forgotten_on_reset() {
+        let mut out = FakeTerm::new(kitty_terminfo());
+        let image = Change::Image(Image {
+            width: 1,
+            height: 1,
+            top_left: TextureCoordinate::new_f32(0.0, 0.0),
+            bottom_right: TextureCoordinate::new_f32(1.0, 1.0),
+            image: rgba_image(1, 1, [0, 0, 0xff, 0xff]),
+        });
+        out.render(std::slice::from_ref(&image)).unwrap();
+        out.renderer.reset();
+        out.write.buf.clear();
+        out.render(&[image]).unwrap();
+        assert!(matches!(
+            kitty_actions(&out.parse())[0],
+            KittyImage::TransmitData { .. }
+        ));
+    }
+
+    #[test]
+    fn kitty_image_is_chunked() {
+        let mut out = FakeTerm::new(kitty_terminfo());
+
+        // Generate some noise so that the data doesn't compress well
+        let (width, height) = (64, 64);
+        let mut seed = 0x1234_5678u32;
+        let mut pixels = vec![];
+        for _ in 0..width * height * 4 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            pixels.push(seed as u8);
+        }
+        let data = Arc::new(ImageData::with_data(ImageDataType::new_single_frame(
+            width, height, pixels,
+        )));
+        out.render(&[Change::Image(Image {
+            width: 4,
+            height: 2,
+            top_left: TextureCoordinate::new_f32(0.0, 0.0),
+            bottom_right: TextureCoordinate::new_f32(1.0, 1.0),
+            image: data,
+        })])
+        .unwrap();
+
+        let kitty = kitty_actions(&out.parse());
+        assert!(kitty.len() > 2, "expected multiple chunks");
+        let (display, chunks) = kitty.split_last().unwrap();
+        assert!(matches!(display, KittyImage::Display { .. }));
+        for (idx, chunk) in chunks.iter().enumerate() {
+            match chunk {
+                KittyImage::TransmitData { transmit, .. } => {
+                    assert_eq!(transmit.more_data_follows, idx + 1 < chunks.len());
+                    assert_eq!(transmit.image_id.is_some(), idx == 0);
+                    match &transmit.data {
+                        KittyImageData::Direct(payload) => assert!(payload.len() <= 4096),
+                        other => panic!("unexpected data {:?}", other),
+                    }
+                }
+                other => panic!("expected transmit, got {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "use_image")]
+    #[test]
+    fn iterm2_rgba_region() {
+        let mut out = FakeTerm::new(xterm_terminfo_with_hints(
+            ProbeHints::default().iterm2_image(Some(true)),
+        ));
+        out.render(&[Change::Image(Image {
+            width: 1,
+            height: 1,
+            top_left: TextureCoordinate::new_f32(0.5, 0.0),
+            bottom_right: TextureCoordinate::new_f32(1.0, 1.0),
+            image: rgba_image(4, 2, [0xff, 0xff, 0, 0xff]),
+        })])
+        .unwrap();
+
+        let actions = out.parse();
+        let file = match &actions[0] {
+            Action::OperatingSystemCommand(osc) => match &**osc {
+                OperatingSystemCommand::ITermProprietary(ITermProprietary::File(file)) => {
+                    file.clone()
+                }
+                other => panic!("expected iTerm2 file, got {:?}", other),
+            },
+            other => panic!("expected OSC, got {:?}", other),
+        };
+        assert_eq!(file.width, ITermDimension::Cells(1));
+        assert_eq!(file.height, ITermDimension::Cells(1));
+
+        let decoded = image::load_from_memory(&file.data).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), (2, 2));
+        assert!(decoded.pixels().all(|p| p.0 == [0xff, 0xff, 0, 0xff]));
+    }
 }
@@ -0,0 +1,434 @@
+//! Building runs of text with attributes
+//!
+//! A `StyledText` is a sequence of `Span`s, each of which holds some
+//! text together with the `CellAttributes` used to display it.
+//! It can be built up span by span:
+//!
+//! ```
+//! use termwiz::color::AnsiColor;
+//! use termwiz::styled::{Span, StyledText};
+//!
+//! let text = StyledText::new()
+//!     .push(Span::new("error").bold().foreground(AnsiColor::Red))
+//!     .push(": file not found");
+//! assert_eq!(text.width(), 21);
+//! ```
+//!
+//! or parsed from markup, in which `[...]` starts a style that lasts
+//! until the matching `[/]`:
+//!
+//! ```
+//! use termwiz::styled::StyledText;
+//!
+//! let text = StyledText::parse_markup("[bold red]error[/]: file not found").unwrap();
+//! assert_eq!(text.spans().len(), 2);
+//! ```
+//!
+//! A style is a list of words separated by spaces, each of which is one of:
+//!
+//! * `bold`, `dim`, `italic`, `underline`, `double-underline`,
+//!   `curly-underline`, `blink`, `reverse`, `strikethrough` or `invisible`
+//! * a color, which sets the foreground color.  Colors are either one of the
+//!   ANSI color names `black`, `red`, `green`, `yellow`, `blue`, `magenta`,
+//!   `cyan` and `white`, optionally prefixed with `bright-`; a palette index
+//!   from 0 to 255; or any color that `SrgbaTuple` can parse, such as
+//!   `#ff8000` or `darkslateblue`
+//! * `on` followed by a color, which sets the background color
+//! * `link=URL`, which makes the text a hyperlink to `URL`
+//!
+//! A literal `[` is written as `[[`.
+use crate::cell::{unicode_column_width, Blink, CellAttributes, Intensity, Underline};
+use crate::color::{AnsiColor, ColorAttribute, SrgbaTuple};
+use crate::hyperlink::Hyperlink;
+use crate::lineedit::OutputElement;
+use crate::surface::Change;
+use crate::{bail, Result};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Some text, together with the attributes used to display it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    text: String,
+    attributes: CellAttributes,
+}
+
+impl Span {
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Self {
+            text: text.into(),
+            attributes: CellAttributes::default(),
+        }
+    }
+
+    pub fn with_attributes<S: Into<String>>(text: S, attributes: CellAttributes) -> Self {
+        Self {
+            text: text.into(),
+            attributes,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn attributes(&self) -> &CellAttributes {
+        &self.attributes
+    }
+
+    /// Returns the number of cells that the text occupies
+    pub fn width(&self) -> usize {
+        unicode_column_width(&self.text, None)
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.attributes.set_intensity(Intensity::Bold);
+        self
+    }
+
+    pub fn dim(mut self) -> Self {
+        self.attributes.set_intensity(Intensity::Half);
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.attributes.set_italic(true);
+        self
+    }
+
+    pub fn underline(mut self, underline: Underline) -> Self {
+        self.attributes.set_underline(underline);
+        self
+    }
+
+    pub fn reverse(mut self) -> Self {
+        self.attributes.set_reverse(true);
+        self
+    }
+
+    pub fn strikethrough(mut self) -> Self {
+        self.attributes.set_strikethrough(true);
+        self
+    }
+
+    pub fn foreground<C: Into<ColorAttribute>>(mut self, color: C) -> Self {
+        self.attributes.set_foreground(color);
+        self
+    }
+
+    pub fn background<C: Into<ColorAttribute>>(mut self, color: C) -> Self {
+        self.attributes.set_background(color);
+        self
+    }
+
+    pub fn hyperlink(mut self, link: Hyperlink) -> Self {
+        self.attributes.set_hyperlink(Some(Arc::new(link)));
+        self
+    }
+}
+
+impl From<&str> for Span {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<String> for Span {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+/// A sequence of `Span`s
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StyledText {
+    spans: Vec<Span>,
+}
+
+impl StyledText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a span, returning the updated text
+    pub fn push<S: Into<Span>>(mut self, span: S) -> Self {
+        self.push_span(span);
+        self
+    }
+
+    /// Appends a span
+    pub fn push_span<S: Into<Span>>(&mut self, span: S) {
+        let span = span.into();
+        match self.spans.last_mut() {
+            Some(last) if last.attributes == span.attributes => last.text.push_str(&span.text),
+            _ if span.text.is_empty() => {}
+            _ => self.spans.push(span),
+        }
+    }
+
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Returns the number of cells that the text occupies
+    pub fn width(&self) -> usize {
+        self.spans.iter().map(Span::width).sum()
+    }
+
+    /// Returns the text without any attributes
+    pub fn plain_text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// Parses text containing style markup; see the module documentation
+    /// for the syntax.  Styles that are still open at the end of the
+    /// markup are closed implicitly.
+    pub fn parse_markup(markup: &str) -> Result<Self> {
+        let mut result = Self::new();
+        let mut stack = vec![CellAttributes::default()];
+        let mut text = String::new();
+        let mut rest = markup;
+
+        while let Some(idx) = rest.find('[') {
+            text.push_str(&rest[..idx]);
+            rest = &rest[idx + 1..];
+            if let Some(stripped) = rest.strip_prefix('[') {
+                text.push('[');
+                rest = stripped;
+                continue;
+            }
+
+            let end = match rest.find(']') {
+                Some(end) => end,
+                None => bail!("unterminated `[` in markup {:?}", markup),
+            };
+            let tag = &rest[..end];
+            rest = &rest[end + 1..];
+
+            let attributes = stack.last().cloned().unwrap_or_default();
+            result.push_span(Span::with_attributes(std::mem::take(&mut text), attributes));
+
+            if tag == "/" {
+                if stack.len() == 1 {
+                    bail!("`[/]` without a matching style in markup {:?}", markup);
+                }
+                stack.pop();
+            } else {
+                let mut attributes = stack.last().cloned().unwrap_or_default();
+                apply_style(&mut attributes, tag)?;
+                stack.push(attributes);
+            }
+        }
+
+        text.push_str(rest);
+        let attributes = stack.pop().unwrap_or_default();
+        result.push_span(Span::with_attributes(text, attributes));
+        Ok(result)
+    }
+
+    /// Returns the changes that display the text.  The attributes are
+    /// reset to the defaults at the end.
+    pub fn to_changes(&self) -> Vec<Change> {
+        self.elements(Change::AllAttributes, Change::Text)
+    }
+
+    /// Returns the text as a sequence of `OutputElement`s, as used by
+    /// `LineEditorHost`.  The attributes are reset to the defaults at
+    /// the end.
+    pub fn to_output_elements(&self) -> Vec<OutputElement> {
+        self.elements(OutputElement::AllAttributes, OutputElement::Text)
+    }
+
+    fn elements<T>(
+        &self,
+        attributes: impl Fn(CellAttributes) -> T,
+        text: impl Fn(String) -> T,
+    ) -> Vec<T> {
+        let mut result = vec![];
+        for span in &self.spans {
+            result.push(attributes(span.attributes.clone()));
+            result.push(text(span.text.clone()));
+        }
+        if !result.is_empty() {
+            result.push(attributes(CellAttributes::default()));
+        }
+        result
+    }
+}
+
+impl From<Span> for StyledText {
+    fn from(span: Span) -> Self {
+        Self::new().push(span)
+    }
+}
+
+impl From<StyledText> for Vec<Change> {
+    fn from(text: StyledText) -> Self {
+        text.to_changes()
+    }
+}
+
+impl From<StyledText> for Vec<OutputElement> {
+    fn from(text: StyledText) -> Self {
+        text.to_output_elements()
+    }
+}
+
+fn apply_style(attributes: &mut CellAttributes, style: &str) -> Result<()> {
+    let mut words = style.split_whitespace();
+    if style.trim().is_empty() {
+        bail!("empty style `[{}]` in markup", style);
+    }
+
+    while let Some(word) = words.next() {
+        match word {
+            "bold" => {
+                attributes.set_intensity(Intensity::Bold);
+            }
+            "dim" => {
+                attributes.set_intensity(Intensity::Half);
+            }
+            "italic" => {
+                attributes.set_italic(true);
+            }
+            "underline" => {
+                attributes.set_underline(Underline::Single);
+            }
+            "double-underline" => {
+                attributes.set_underline(Underline::Double);
+            }
+            "curly-underline" => {
+                attributes.set_underline(Underline::Curly);
+            }
+            "blink" => {
+                attributes.set_blink(Blink::Slow);
+            }
+            "reverse" => {
+                attributes.set_reverse(true);
+            }
+            "strikethrough" => {
+                attributes.set_strikethrough(true);
+            }
+            "invisible" => {
+                attributes.set_invisible(true);
+            }
+            "on" => match words.next() {
+                Some(color) => {
+                    attributes.set_background(parse_color(color)?);
+                }
+                None => bail!("expected a color after `on` in style `[{}]`", style),
+            },
+            _ => {
+                if let Some(url) = word.strip_prefix("link=") {
+                    attributes.set_hyperlink(Some(Arc::new(Hyperlink::new(url))));
+                } else {
+                    attributes.set_foreground(parse_color(word)?);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_color(color: &str) -> Result<ColorAttribute> {
+    let ansi = match color {
+        "black" => Some(AnsiColor::Black),
+        "red" => Some(AnsiColor::Maroon),
+        "green" => Some(AnsiColor::Green),
+        "yellow" => Some(AnsiColor::Olive),
+        "blue" => Some(AnsiColor::Navy),
+        "magenta" => Some(AnsiColor::Purple),
+        "cyan" => Some(AnsiColor::Teal),
+        "white" => Some(AnsiColor::Silver),
+        "bright-black" => Some(AnsiColor::Grey),
+        "bright-red" => Some(AnsiColor::Red),
+        "bright-green" => Some(AnsiColor::Lime),
+        "bright-yellow" => Some(AnsiColor::Yellow),
+        "bright-blue" => Some(AnsiColor::Blue),
+        "bright-magenta" => Some(AnsiColor::Fuchsia),
+        "bright-cyan" => Some(AnsiColor::Aqua),
+        "bright-white" => Some(AnsiColor::White),
+        _ => None,
+    };
+    if let Some(ansi) = ansi {
+        return Ok(ansi.into());
+    }
+    if let Ok(idx) = color.parse::<u8>() {
+        return Ok(ColorAttribute::PaletteIndex(idx));
+    }
+    match SrgbaTuple::from_str(color) {
+        Ok(color) => Ok(color.into()),
+        Err(()) => bail!("unknown style or color `{}` in markup", color),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use k9::assert_equal as assert_eq;
+
+    #[test]
+    fn builder() {
+        let text = StyledText::new()
+            .push("a ")
+            .push("b")
+            .push(Span::new("\u{1f915}").bold())
+            .push(Span::new("").italic());
+        assert_eq!(text.spans().len(), 2);
+        assert_eq!(text.plain_text(), "a b\u{1f915}");
+        assert_eq!(text.width(), 5);
+
+        let bold = CellAttributes::default()
+            .set_intensity(Intensity::Bold)
+            .clone();
+        assert_eq!(
+            text.to_changes(),
+            vec![
+                Change::AllAttributes(CellAttributes::default()),
+                Change::Text("a b".to_string()),
+                Change::AllAttributes(bold.clone()),
+                Change::Text("\u{1f915}".to_string()),
+                Change::AllAttributes(CellAttributes::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn markup() {
+        let text =
+            StyledText::parse_markup("a [[b] [bold red]c [on #ff0000]d[/] e[/] [link=http://x]f")
+                .unwrap();
+        let bold_red = CellAttributes::default()
+            .set_intensity(Intensity::Bold)
+            .set_foreground(AnsiColor::Maroon)
+            .clone();
+        let on_red = bold_red
+            .clone()
+            .set_background(SrgbaTuple(1.0, 0.0, 0.0, 1.0))
+            .clone();
+        let link = CellAttributes::default()
+            .set_hyperlink(Some(Arc::new(Hyperlink::new("http://x"))))
+            .clone();
+        assert_eq!(
+            text,
+            StyledText::new()
+                .push("a [b] ")
+                .push(Span::with_attributes("c ", bold_red.clone()))
+                .push(Span::with_attributes("d", on_red))
+                .push(Span::with_attributes(" e", bold_red))
+                .push(" ")
+                .push(Span::with_attributes("f", link))
+        );
+
+        assert_eq!(
+            StyledText::parse_markup("[dim 42]x").unwrap().spans()[0]
+                .attributes()
+                .foreground(),
+            ColorAttribute::PaletteIndex(42)
+        );
+        assert!(StyledText::parse_markup("[bold").is_err());
+        assert!(StyledText::parse_markup("x[/]").is_err());
+        assert!(StyledText::parse_markup("[bold on]x").is_err());
+        assert!(StyledText::parse_markup("[nosuchcolor]x").is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Line drawing glyphs, and the rules for merging them where lines meet
+use self::Weight::{Double as D, Heavy as H, Light as L, None as O};
+
+/// Selects the glyphs used to draw boxes and lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoxStyle {
+    #[default]
+    Single,
+    Double,
+    /// Like `Single`, but with rounded corners
+    Rounded,
+    Heavy,
+}
+
+impl BoxStyle {
+    fn weight(self) -> Weight {
+        match self {
+            BoxStyle::Single | BoxStyle::Rounded => Weight::Light,
+            BoxStyle::Double => Weight::Double,
+            BoxStyle::Heavy => Weight::Heavy,
+        }
+    }
+}
+
+/// The weight of a line leaving a cell in one direction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Weight {
+    None,
+    Light,
+    Heavy,
+    Double,
+}
+
+/// The lines leaving a cell, in the order up, down, left, right
+pub(crate) type Arms = [Weight; 4];
+
+pub(crate) const UP: usize = 0;
+pub(crate) const DOWN: usize = 1;
+pub(crate) const LEFT: usize = 2;
+pub(crate) const RIGHT: usize = 3;
+
+/// Returns the arms of a glyph in `style`, with the arms in `dirs` set
+pub(crate) fn arms(style: BoxStyle, dirs: &[usize]) -> Arms {
+    let mut arms = [O; 4];
+    for &dir in dirs {
+        arms[dir] = style.weight();
+    }
+    arms
+}
+
+/// Returns the arms of a line drawing glyph, or `None` if the text
+/// isn't a line drawing glyph
+pub(crate) fn glyph_arms(text: &str) -> Option<Arms> {
+    let mut chars = text.chars();
+    let c = chars.next()?;
+    if chars.next().is_some() {
+        return None;
+    }
+    ROUNDED
+        .iter()
+        .chain(GLYPHS.iter())
+        .find(|(glyph, _)| *glyph == c)
+        .map(|(_, arms)| *arms)
+}
+
+/// Returns the glyph that joins `existing` with the arms of a line
+/// being drawn over it.
+pub(crate) fn merge(existing: Arms, line: Arms, style: BoxStyle) -> char {
+    let mut merged = existing;
+    for (arm, weight) in merged.iter_mut().zip(line.iter()) {
+        if *weight != O {
+            *arm = *weight;
+        }
+    }
+
+    // Where there's no glyph for the combination, first try making
+    // the new line continue through the existing one, then try
+    // drawing the new arms in the weight of the existing line
+    let mut crossed = merged;
+    for axis in [[UP, DOWN], [LEFT, RIGHT]].iter() {
+        if let Some(weight) = axis.iter().map(|&dir| line[dir]).find(|w| *w != O) {
+            for &dir in axis {
+                if crossed[dir] != O {
+                    crossed[dir] = weight;
+                }
+            }
+        }
+    }
+    let mut joined = merged;
+    if let Some(weight) = existing.iter().find(|w| **w != O) {
+        for (arm, new) in joined.iter_mut().zip(line.iter()) {
+            if *new != O {
+                *arm = *weight;
+            }
+        }
+    }
+    let mut uniform = merged;
+    for arm in uniform.iter_mut() {
+        if *arm != O {
+            *arm = style.weight();
+        }
+    }
+
+    lookup(merged, style)
+        .or_else(|| lookup(crossed, style))
+        .or_else(|| lookup(joined, style))
+        .or_else(|| lookup(uniform, style))
+        .or_else(|| lookup(line, style))
+        .unwrap_or(' ')
+}
+
+fn lookup(arms: Arms, style: BoxStyle) -> Option<char> {
+    let rounded: &[(char, Arms)] = if style == BoxStyle::Rounded {
+        ROUNDED
+    } else {
+        &[]
+    };
+    rounded
+        .iter()
+        .chain(GLYPHS.iter())
+        .find(|(_, a)| *a == arms)
+        .map(|(glyph, _)| *glyph)
+}
+
+const ROUNDED: &[(char, Arms)] = &[
+    ('╭', [O, L, O, L]),
+    ('╮', [O, L, L, O]),
+    ('╯', [L, O, L, O]),
+    ('╰', [L, O, O, L]),
+];
+
+const GLYPHS: &[(char, Arms)] = &[
+    ('─', [O, O, L, L]),
+    ('━', [O, O, H, H]),
+    ('│', [L, L, O, O]),
+    ('┃', [H, H, O, O]),
+    ('┌', [O, L, O, L]),
+    ('┍', [O, L, O, H]),
+    ('┎', [O, H, O, L]),
+    ('┏', [O, H, O, H]),
+    ('┐', [O, L, L, O]),
+    ('┑', [O, L, H, O]),
+    ('┒', [O, H, L, O]),
+    ('┓', [O, H, H, O]),
+    ('└', [L, O, O, L]),
+    ('┕', [L, O, O, H]),
+    ('┖', [H, O, O, L]),
+    ('┗', [H, O, O, H]),
+    ('┘', [L, O, L, O]),
+    ('┙', [L, O, H, O]),
+    ('┚', [H, O, L, O]),
+    ('┛', [H, O, H, O]),
+    ('├', [L, L, O, L]),
+    ('┝', [L, L, O, H]),
+    ('┞', [H, L, O, L]),
+    ('┟', [L, H, O, L]),
+    ('┠', [H, H, O, L]),
+    ('┡', [H, L, O, H]),
+    ('┢', [L, H, O, H]),
+    ('┣', [H, H, O, H]),
+    ('┤', [L, L, L, O]),
+    ('┥', [L, L, H, O]),
+    ('┦', [H, L, L, O]),
+    ('┧', [L, H, L, O]),
+    ('┨', [H, H, L, O]),
+    ('┩', [H, L, H, O]),
+    ('┪', [L, H, H, O]),
+    ('┫', [H, H, H, O]),
+    ('┬', [O, L, L, L]),
+    ('┭', [O, L, H, L]),
+    ('┮', [O, L, L, H]),
+    ('┯', [O, L, H, H]),
+    ('┰', [O, H, L, L]),
+    ('┱', [O, H, H, L]),
+    ('┲', [O, H, L, H]),
+    ('┳', [O, H, H, H]),
+    ('┴', [L, O, L, L]),
+    ('┵', [L, O, H, L]),
+    ('┶', [L, O, L, H]),
+    ('┷', [L, O, H, H]),
+    ('┸', [H, O, L, L]),
+    ('┹', [H, O, H, L]),
+    ('┺', [H, O, L, H]),
+    ('┻', [H, O, H, H]),
+    ('┼', [L, L, L, L]),
+    ('┽', [L, L, H, L]),
+    ('┾', [L, L, L, H]),
+    ('┿', [L, L, H, H]),
+    ('╀', [H, L, L, L]),
+    ('╁', [L, H, L, L]),
+    ('╂', [H, H, L, L]),
+    ('╃', [H, L, H, L]),
+    ('╄', [H, L, L, H]),
+    ('╅', [L, H, H, L]),
+    ('╆', [L, H, L, H]),
+    ('╇', [H, L, H, H]),
+    ('╈', [L, H, H, H]),
+    ('╉', [H, H, H, L]),
+    ('╊', [H, H, L, H]),
+    ('╋', [H, H, H, H]),
+    ('═', [O, O, D, D]),
+    ('║', [D, D, O, O]),
+    ('╒', [O, L, O, D]),
+    ('╓', [O, D, O, L]),
+    ('╔', [O, D, O, D]),
+    ('╕', [O, L, D, O]),
+    ('╖', [O, D, L, O]),
+    ('╗', [O, D, D, O]),
+    ('╘', [L, O, O, D]),
+    ('╙', [D, O, O, L]),
+    ('╚', [D, O, O, D]),
+    ('╛', [L, O, D, O]),
+    ('╜', [D, O, L, O]),
+    ('╝', [D, O, D, O]),
+    ('╞', [L, L, O, D]),
+    ('╟', [D, D, O, L]),
+    ('╠', [D, D, O, D]),
+    ('╡', [L, L, D, O]),
+    ('╢', [D, D, L, O]),
+    ('╣', [D, D, D, O]),
+    ('╤', [O, L, D, D]),
+    ('╥', [O, D, L, L]),
+    ('╦', [O, D, D, D]),
+    ('╧', [L, O, D, D]),
+    ('╨', [D, O, L, L]),
+    ('╩', [D, O, D, D]),
+    ('╪', [L, L, D, D]),
+    ('╫', [D, D, L, L]),
+    ('╬', [D, D, D, D]),
+    ('╴', [O, O, L, O]),
+    ('╵', [L, O, O, O]),
+    ('╶', [O, O, O, L]),
+    ('╷', [O, L, O, O]),
+    ('╸', [O, O, H, O]),
+    ('╹', [H, O, O, O]),
+    ('╺', [O, O, O, H]),
+    ('╻', [O, H, O, O]),
+    ('╼', [O, O, L, H]),
+    ('╽', [L, H, O, O]),
+    ('╾', [O, O, H, L]),
+    ('╿', [H, L, O, O]),
+];
@@ -10,11 +10,15 @@ use std::borrow::Cow;
 use std::cmp::min;
 use wezterm_dynamic::{FromDynamic, ToDynamic};
 
+mod boxdrawing;
 pub mod change;
 pub mod line;
+mod view;
 
+pub use self::boxdrawing::BoxStyle;
 pub use self::change::{Change, Image, LineAttribute, TextureCoordinate};
 pub use self::line::Line;
+pub use self::view::{truncate_with_ellipsis, SurfaceView};
 
 /// Position holds 0-based positioning information, where
 /// Absolute(0) is the start of the line or column,
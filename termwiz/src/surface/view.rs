@@ -0,0 +1,660 @@
+//! Drawing into a rectangular region of a `Surface`
+use crate::cell::{grapheme_column_width, unicode_column_width, CellAttributes};
+use crate::color::ColorAttribute;
+use crate::surface::boxdrawing::{self, BoxStyle, DOWN, LEFT, RIGHT, UP};
+use crate::surface::{compute_position_change, Change, Position, SequenceNo, Surface};
+use finl_unicode::grapheme_clusters::Graphemes;
+use std::borrow::Cow;
+
+/// The region of the underlying `Surface` that a view may modify,
+/// in surface coordinates.  `right` and `bottom` are exclusive.
+#[derive(Clone, Copy, Debug)]
+struct Clip {
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+}
+
+impl Clip {
+    fn intersect(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        let left = x.max(self.left);
+        let top = y.max(self.top);
+        Self {
+            left,
+            top,
+            right: x.saturating_add(width).min(self.right).max(left),
+            bottom: y.saturating_add(height).min(self.bottom).max(top),
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.right - self.left
+    }
+}
+
+/// A rectangular region of a `Surface` that can be drawn into as
+/// though it were a `Surface` of its own.
+///
+/// The view has its own origin: cursor positions are relative to
+/// the top left of the view, and `Position::EndRelative` is relative
+/// to its bottom right.  Anything that falls outside of the view, or
+/// outside of the view that it was created from, is clipped rather
+/// than being drawn over the neighbouring cells.  Text is not wrapped
+/// at the right edge of the view, and the view does not scroll when
+/// text moves past its last row.
+///
+/// The changes are recorded in the underlying `Surface` in the usual
+/// way, so the view doesn't need to be rendered separately.
+pub struct SurfaceView<'a> {
+    surface: &'a mut Surface,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    clip: Clip,
+    xpos: usize,
+    ypos: usize,
+}
+
+impl Surface {
+    /// Returns a view of the region at `x`, `y` of size `width`, `height`.
+    /// The region may extend beyond the edges of the surface, in which
+    /// case the parts that lie outside of the surface are clipped.
+    pub fn view(&mut self, x: usize, y: usize, width: usize, height: usize) -> SurfaceView<'_> {
+        let clip = Clip {
+            left: 0,
+            top: 0,
+            right: self.width,
+            bottom: self.height,
+        }
+        .intersect(x, y, width, height);
+        SurfaceView {
+            surface: self,
+            x,
+            y,
+            width,
+            height,
+            clip,
+            xpos: 0,
+            ypos: 0,
+        }
+    }
+}
+
+impl<'a> SurfaceView<'a> {
+    /// Returns a view of the region at `x`, `y`, relative to this view,
+    /// of size `width`, `height`.  The new view is clipped to this one.
+    pub fn view(&mut self, x: usize, y: usize, width: usize, height: usize) -> SurfaceView<'_> {
+        let x = self.x.saturating_add(x);
+        let y = self.y.saturating_add(y);
+        SurfaceView {
+            clip: self.clip.intersect(x, y, width, height),
+            surface: &mut *self.surface,
+            x,
+            y,
+            width,
+            height,
+            xpos: 0,
+            ypos: 0,
+        }
+    }
+
+    /// Returns the (width, height) of the view
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Returns the cursor position, relative to the view
+    pub fn cursor_position(&self) -> (usize, usize) {
+        (self.xpos, self.ypos)
+    }
+
+    /// Apply a series of changes.
+    /// Returns the sequence number of the underlying surface at the
+    /// end of the changes.
+    pub fn add_changes(&mut self, changes: Vec<Change>) -> SequenceNo {
+        for change in changes {
+            self.apply_change(change);
+        }
+        self.surface.current_seqno()
+    }
+
+    /// Apply a change.
+    /// Returns the sequence number of the underlying surface at the
+    /// end of the change.
+    pub fn add_change<C: Into<Change>>(&mut self, change: C) -> SequenceNo {
+        self.apply_change(change.into());
+        self.surface.current_seqno()
+    }
+
+    /// Draws a box with its top left corner at `x`, `y`, using the
+    /// current attributes.  Where the box meets lines that have already
+    /// been drawn, the glyphs are merged to form junctions.
+    pub fn draw_box(&mut self, x: usize, y: usize, width: usize, height: usize, style: BoxStyle) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        if height == 1 {
+            self.draw_horizontal_line(x, y, width, style);
+            return;
+        }
+        if width == 1 {
+            self.draw_vertical_line(x, y, height, style);
+            return;
+        }
+
+        let right = x + width - 1;
+        let bottom = y + height - 1;
+        self.draw_arms(x, y, &[DOWN, RIGHT], style);
+        self.draw_arms(right, y, &[DOWN, LEFT], style);
+        self.draw_arms(x, bottom, &[UP, RIGHT], style);
+        self.draw_arms(right, bottom, &[UP, LEFT], style);
+        for col in x + 1..right {
+            self.draw_arms(col, y, &[LEFT, RIGHT], style);
+            self.draw_arms(col, bottom, &[LEFT, RIGHT], style);
+        }
+        for row in y + 1..bottom {
+            self.draw_arms(x, row, &[UP, DOWN], style);
+            self.draw_arms(right, row, &[UP, DOWN], style);
+        }
+    }
+
+    /// Draws a horizontal line `len` cells long, starting at `x`, `y`,
+    /// using the current attributes.  Where the line crosses or ends on
+    /// lines that have already been drawn, the glyphs are merged to form
+    /// junctions.
+    pub fn draw_horizontal_line(&mut self, x: usize, y: usize, len: usize, style: BoxStyle) {
+        self.draw_line(x, y, len, style, (1, 0), [LEFT, RIGHT]);
+    }
+
+    /// Draws a vertical line `len` cells long, starting at `x`, `y`,
+    /// using the current attributes.  Where the line crosses or ends on
+    /// lines that have already been drawn, the glyphs are merged to form
+    /// junctions.
+    pub fn draw_vertical_line(&mut self, x: usize, y: usize, len: usize, style: BoxStyle) {
+        self.draw_line(x, y, len, style, (0, 1), [UP, DOWN]);
+    }
+
+    fn draw_line(
+        &mut self,
+        x: usize,
+        y: usize,
+        len: usize,
+        style: BoxStyle,
+        step: (usize, usize),
+        dirs: [usize; 2],
+    ) {
+        for n in 0..len {
+            let (col, row) = (x + n * step.0, y + n * step.1);
+            // A line that starts or ends on another line joins it
+            // rather than continuing through it
+            let at_end = n == 0 || n + 1 == len;
+            let dirs: &[usize] = if len == 1 || !at_end || self.glyph_arms_at(col, row).is_none() {
+                &dirs
+            } else if n == 0 {
+                &dirs[1..]
+            } else {
+                &dirs[..1]
+            };
+            self.draw_arms(col, row, dirs, style);
+        }
+    }
+
+    /// Returns the arms of the line drawing glyph at `x`, `y`, if any
+    fn glyph_arms_at(&self, x: usize, y: usize) -> Option<boxdrawing::Arms> {
+        let (x, y) = self.to_surface(x, y)?;
+        let cell = self.surface.lines[y].get_cell(x)?;
+        boxdrawing::glyph_arms(cell.str())
+    }
+
+    /// Draws the glyph with the arms in `dirs` at `x`, `y`, merging
+    /// it with any line drawing glyph that is already there
+    fn draw_arms(&mut self, x: usize, y: usize, dirs: &[usize], style: BoxStyle) {
+        if self.to_surface(x, y).is_none() {
+            return;
+        }
+        let existing = self
+            .glyph_arms_at(x, y)
+            .unwrap_or([boxdrawing::Weight::None; 4]);
+        let glyph = boxdrawing::merge(existing, boxdrawing::arms(style, dirs), style);
+
+        let cursor = (self.xpos, self.ypos);
+        self.xpos = x;
+        self.ypos = y;
+        self.print_text(glyph.encode_utf8(&mut [0; 4]));
+        self.xpos = cursor.0;
+        self.ypos = cursor.1;
+    }
+
+    /// Translates a position within the view to surface coordinates,
+    /// returning `None` if it is clipped
+    fn to_surface(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let (x, y) = (self.x + x, self.y + y);
+        if x < self.clip.left || x >= self.clip.right || y < self.clip.top || y >= self.clip.bottom
+        {
+            return None;
+        }
+        Some((x, y))
+    }
+
+    fn apply_change(&mut self, change: Change) {
+        match change {
+            Change::Text(text) => self.print_text(&text),
+            Change::CursorPosition { x, y } => {
+                self.xpos = compute_position_change(self.xpos, &x, self.width);
+                self.ypos = compute_position_change(self.ypos, &y, self.height);
+            }
+            Change::ClearScreen(color) => {
+                self.set_background(color);
+                for row in 0..self.height {
+                    self.fill_row(row, 0);
+                }
+                self.xpos = 0;
+                self.ypos = 0;
+            }
+            Change::ClearToEndOfLine(color) => {
+                self.set_background(color);
+                self.fill_row(self.ypos, self.xpos);
+            }
+            Change::ClearToEndOfScreen(color) => {
+                self.set_background(color);
+                self.fill_row(self.ypos, self.xpos);
+                for row in self.ypos + 1..self.height {
+                    self.fill_row(row, 0);
+                }
+            }
+            Change::ScrollRegionUp {
+                first_row,
+                region_size,
+                scroll_count,
+            } => self.scroll_region(first_row, region_size, scroll_count, true),
+            Change::ScrollRegionDown {
+                first_row,
+                region_size,
+                scroll_count,
+            } => self.scroll_region(first_row, region_size, scroll_count, false),
+            Change::Image(image) => {
+                // An image is only drawn if it fits entirely in the view
+                let fits = image.width > 0
+                    && image.height > 0
+                    && self.to_surface(self.xpos, self.ypos).is_some()
+                    && self
+                        .to_surface(self.xpos + image.width - 1, self.ypos + image.height - 1)
+                        .is_some();
+                if fits {
+                    self.move_surface_cursor(self.xpos, self.ypos);
+                    self.surface.add_change(Change::Image(image.clone()));
+                }
+                self.xpos += image.width;
+            }
+            Change::LineAttribute(attr) => {
+                // Line attributes apply to the whole of the line, so only
+                // a view that spans the width of the surface can set them
+                let full_width = self.clip.width() == self.surface.width;
+                if full_width && self.to_surface(0, self.ypos).is_some() {
+                    self.move_surface_cursor(0, self.ypos);
+                    self.surface.add_change(Change::LineAttribute(attr));
+                }
+            }
+            Change::AllAttributes(_)
+            | Change::Attribute(_)
+            | Change::CursorColor(_)
+            | Change::CursorShape(_)
+            | Change::CursorVisibility(_)
+            | Change::Title(_) => {
+                self.surface.add_change(change);
+            }
+        }
+    }
+
+    fn move_surface_cursor(&mut self, x: usize, y: usize) {
+        self.surface.add_change(Change::CursorPosition {
+            x: Position::Absolute(self.x + x),
+            y: Position::Absolute(self.y + y),
+        });
+    }
+
+    fn set_background(&mut self, color: ColorAttribute) {
+        self.surface.add_change(Change::AllAttributes(
+            CellAttributes::default().set_background(color).clone(),
+        ));
+    }
+
+    /// Fills `row` with blanks in the current attributes, from `col`
+    /// to the right edge of the view
+    fn fill_row(&mut self, row: usize, col: usize) {
+        if row >= self.height || col >= self.width {
+            return;
+        }
+        let y = self.y + row;
+        if y < self.clip.top || y >= self.clip.bottom {
+            return;
+        }
+        let left = (self.x + col).max(self.clip.left);
+        let right = (self.x + self.width).min(self.clip.right);
+        if left < right {
+            self.surface.add_changes(vec![
+                Change::CursorPosition {
+                    x: Position::Absolute(left),
+                    y: Position::Absolute(y),
+                },
+                Change::Text(" ".repeat(right - left)),
+            ]);
+        }
+    }
+
+    /// Scrolls the visible part of a region of rows up or down.  The
+    /// rows that are exposed are blanked with the default attributes.
+    fn scroll_region(&mut self, first_row: usize, size: usize, count: usize, up: bool) {
+        let top = (self.y + first_row).max(self.clip.top);
+        let bottom =
+            (self.y + first_row.saturating_add(size).min(self.height)).min(self.clip.bottom);
+        if top >= bottom || self.clip.width() == 0 {
+            return;
+        }
+        let size = bottom - top;
+        let count = count.min(size);
+
+        if count < size {
+            let (src, dest) = if up {
+                (top + count, top)
+            } else {
+                (top, top + count)
+            };
+            self.surface.copy_region(
+                self.clip.left,
+                src,
+                self.clip.width(),
+                size - count,
+                self.clip.left,
+                dest,
+            );
+        }
+
+        let blank = if up {
+            bottom - count..bottom
+        } else {
+            top..top + count
+        };
+        let attributes = self.surface.attributes.clone();
+        self.surface
+            .add_change(Change::AllAttributes(CellAttributes::default()));
+        for y in blank {
+            self.surface.add_changes(vec![
+                Change::CursorPosition {
+                    x: Position::Absolute(self.clip.left),
+                    y: Position::Absolute(y),
+                },
+                Change::Text(" ".repeat(self.clip.width())),
+            ]);
+        }
+        self.surface.add_change(Change::AllAttributes(attributes));
+    }
+
+    fn print_text(&mut self, text: &str) {
+        // Runs of visible text are batched up so that they can be
+        // emitted with a single cursor movement
+        let mut run = String::new();
+        let mut run_start = (0, 0);
+        let mut run_end = None;
+
+        for g in Graphemes::new(text) {
+            match g {
+                "\r\n" | "\n" => {
+                    if g == "\r\n" {
+                        self.xpos = 0;
+                    }
+                    self.ypos = (self.ypos + 1).min(self.height);
+                    continue;
+                }
+                "\r" => {
+                    self.xpos = 0;
+                    continue;
+                }
+                _ => {}
+            }
+
+            // As with `Surface`, zero-width graphemes occupy a cell
+            let width = grapheme_column_width(g, None).max(1);
+            let start = self.to_surface(self.xpos, self.ypos);
+            let end = self.to_surface(self.xpos + width - 1, self.ypos);
+            self.xpos += width;
+
+            let (pos, text) = match (start, end) {
+                (Some(pos), Some(_)) => (pos, Cow::Borrowed(g)),
+                (None, None) => continue,
+                // A wide grapheme that straddles the edge of the clip
+                // can't be drawn, so blank the part that is visible
+                _ => {
+                    let cols = (0..width)
+                        .filter_map(|n| self.to_surface(self.xpos - width + n, self.ypos))
+                        .collect::<Vec<_>>();
+                    (cols[0], Cow::Owned(" ".repeat(cols.len())))
+                }
+            };
+
+            if run_end != Some(pos) {
+                self.flush_run(run_start, &mut run);
+                run_start = pos;
+            }
+            run.push_str(&text);
+            run_end = Some((pos.0 + unicode_column_width(&text, None).max(1), pos.1));
+        }
+
+        self.flush_run(run_start, &mut run);
+    }
+
+    fn flush_run(&mut self, (x, y): (usize, usize), run: &mut String) {
+        if run.is_empty() {
+            return;
+        }
+        self.surface.add_changes(vec![
+            Change::CursorPosition {
+                x: Position::Absolute(x),
+                y: Position::Absolute(y),
+            },
+            Change::Text(std::mem::take(run)),
+        ]);
+    }
+}
+
+/// Returns `text` if it fits in `width` columns, otherwise the longest
+/// prefix of whole graphemes that fits in `width` columns with an
+/// ellipsis appended.
+pub fn truncate_with_ellipsis(text: &str, width: usize) -> Cow<'_, str> {
+    if unicode_column_width(text, None) <= width {
+        return Cow::Borrowed(text);
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+
+    let mut result = String::new();
+    let mut used = 0;
+    for g in Graphemes::new(text) {
+        let g_width = grapheme_column_width(g, None);
+        if used + g_width > width - 1 {
+            break;
+        }
+        result.push_str(g);
+        used += g_width;
+    }
+    result.push('\u{2026}');
+    Cow::Owned(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use k9::assert_equal as assert_eq;
+
+    #[test]
+    fn clipped_text() {
+        let mut s = Surface::new(8, 3);
+        s.add_change("........\r\n........\r\n........");
+
+        let mut view = s.view(2, 1, 4, 5);
+        view.add_change("hello world\r\nab");
+        assert_eq!(view.cursor_position(), (2, 1));
+        assert_eq!(
+            s.screen_chars_to_string(),
+            "........\n\
+             ..hell..\n\
+             ..ab....\n"
+        );
+
+        let mut view = s.view(0, 0, 8, 3);
+        view.add_change(Change::CursorPosition {
+            x: Position::Absolute(6),
+            y: Position::Absolute(0),
+        });
+        // The wide grapheme doesn't fit, so its first column is blanked
+        view.add_change("x\u{1f915}");
+        assert_eq!(s.screen_chars_to_string().lines().next(), Some("......x "));
+    }
+
+    #[test]
+    fn nested_views_and_clearing() {
+        let mut s = Surface::new(6, 4);
+        s.add_change("......\r\n......\r\n......\r\n......");
+
+        let mut outer = s.view(1, 1, 4, 2);
+        // The inner view extends beyond the outer one and is clipped to it
+        let mut inner = outer.view(2, 1, 10, 10);
+        assert_eq!(inner.dimensions(), (10, 10));
+        inner.add_change(Change::ClearScreen(Default::default()));
+        inner.add_change("xyz");
+        outer.add_change(Change::CursorPosition {
+            x: Position::Absolute(1),
+            y: Position::Absolute(0),
+        });
+        outer.add_change(Change::ClearToEndOfLine(Default::default()));
+
+        assert_eq!(
+            s.screen_chars_to_string(),
+            "......\n\
+             ..   .\n\
+             ...xy.\n\
+             ......\n"
+        );
+    }
+
+    #[test]
+    fn scroll_region() {
+        let mut s = Surface::new(5, 4);
+        s.add_change("aaaaa\r\nbbbbb\r\nccccc\r\nddddd");
+        let mut view = s.view(1, 0, 3, 4);
+        view.add_change(Change::ScrollRegionUp {
+            first_row: 1,
+            region_size: 3,
+            scroll_count: 1,
+        });
+        assert_eq!(
+            s.screen_chars_to_string(),
+            "aaaaa\n\
+             bcccb\n\
+             cdddc\n\
+             d   d\n"
+        );
+    }
+
+    #[test]
+    fn boxes_and_junctions() {
+        let mut s = Surface::new(7, 5);
+        let mut view = s.view(0, 0, 7, 5);
+        view.draw_box(0, 0, 7, 5, BoxStyle::Single);
+        view.draw_horizontal_line(0, 2, 7, BoxStyle::Single);
+        view.draw_vertical_line(3, 0, 5, BoxStyle::Single);
+        assert_eq!(
+            s.screen_chars_to_string(),
+            "┌──┬──┐\n\
+             │  │  │\n\
+             ├──┼──┤\n\
+             │  │  │\n\
+             └──┴──┘\n"
+        );
+
+        let mut s = Surface::new(8, 4);
+        let mut view = s.view(0, 0, 8, 4);
+        view.draw_box(0, 0, 4, 4, BoxStyle::Rounded);
+        view.draw_box(3, 0, 5, 4, BoxStyle::Double);
+        view.draw_horizontal_line(0, 2, 4, BoxStyle::Heavy);
+        assert_eq!(
+            s.screen_chars_to_string(),
+            "╭──╦═══╗\n\
+             │  ║   ║\n\
+             ┝━━╣   ║\n\
+             ╰──╩═══╝\n"
+        );
+
+        // Lines that don't start or end on another line are drawn in full
+        let mut s = Surface::new(4, 1);
+        s.view(0, 0, 4, 1)
+            .draw_horizontal_line(1, 0, 2, BoxStyle::Heavy);
+        assert_eq!(s.screen_chars_to_string(), " ━━ \n");
+    }
+
+    #[test]
+    fn ellipsis() {
+        assert_eq!(truncate_with_ellipsis("hello", 5), "hello");
+        assert_eq!(truncate_with_ellipsis("hello", 4), "hel\u{2026}");
+        assert_eq!(truncate_with_ellipsis("hello", 1), "\u{2026}");
+        assert_eq!(truncate_with_ellipsis("hello", 0), "");
+        // Wide and combining graphemes are not split
+        assert_eq!(truncate_with_ellipsis("a\u{1f915}b", 3), "a\u{2026}");
+        assert_eq!(
+            truncate_with_ellipsis("e\u{301}e\u{301}e\u{301}", 2),
+            "e\u{301}\u{2026}"
+        );
+    }
+
+    #[test]
+    fn images() {
+        use crate::image::{ImageData, TextureCoordinate};
+        use crate::surface::change::Image;
+        use std::sync::Arc;
+
+        let image = |width, height| {
+            Change::Image(Image {
+                width,
+                height,
+                top_left: TextureCoordinate::new_f32(0.0, 0.0),
+                bottom_right: TextureCoordinate::new_f32(1.0, 1.0),
+                image: Arc::new(ImageData::with_raw_data(vec![])),
+            })
+        };
+        let mut s = Surface::new(6, 4);
+        let mut view = s.view(1, 1, 3, 2);
+
+        // Images without any area occupy no space
+        view.add_change(image(0, 0));
+        view.add_change(image(0, 2));
+        view.add_change(image(2, 0));
+        assert_eq!(view.cursor_position(), (2, 0));
+
+        // Only images that fit entirely within the view are drawn
+        view.add_change(image(2, 1));
+        view.add_change(Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Absolute(1),
+        });
+        view.add_change(image(2, 1));
+
+        let drawn: Vec<(usize, usize)> = s
+            .screen_cells()
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, cell)| cell.attrs().images().is_some())
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect();
+        assert_eq!(drawn, vec![(1, 2), (2, 2)]);
+    }
+}
@@ -1,6 +1,7 @@
 //! A Terminal buffered with a Surface
 
-use crate::surface::{SequenceNo, Surface};
+use crate::color::ColorAttribute;
+use crate::surface::{Change, Position, SequenceNo, Surface};
 use crate::terminal::Terminal;
 use crate::Result;
 use std::ops::{Deref, DerefMut};
@@ -14,10 +15,61 @@ use std::ops::{Deref, DerefMut};
 /// The `flush` method is used to compute the optimized set
 /// of changes and actually render them to the underlying
 /// `Terminal`.  No output will be visible until it is flushed!
+///
+/// A `BufferedTerminal` created with `new_inline` doesn't take over
+/// the whole screen; see `new_inline` for more details.
 pub struct BufferedTerminal<T: Terminal> {
     terminal: T,
     surface: Surface,
     seqno: SequenceNo,
+    inline: Option<Inline>,
+}
+
+/// Tracks the region of the screen used by an inline `BufferedTerminal`
+struct Inline {
+    /// The number of rows requested for the region; the region is
+    /// limited to the height of the screen
+    rows: usize,
+    /// The contents of the region as they were last rendered
+    rendered: Surface,
+    /// The number of rows of the region that have been claimed from
+    /// the terminal, starting with the row that the region started on
+    reserved: usize,
+    /// The row of the region that the terminal cursor is on
+    cursor_row: usize,
+}
+
+impl Inline {
+    /// Returns a change that moves the terminal cursor to `x` on `row`
+    /// of the region
+    fn move_to(&mut self, x: usize, row: usize) -> Change {
+        let change = Change::CursorPosition {
+            x: Position::Absolute(x),
+            y: Position::Relative(row as isize - self.cursor_row as isize),
+        };
+        self.cursor_row = row;
+        change
+    }
+
+    /// Forgets what was rendered, so that the next flush paints the
+    /// region from scratch.  The appearance of the cursor is kept,
+    /// as the terminal retains it.
+    fn forget_rendered(&mut self, width: usize, height: usize) {
+        let mut rendered = Surface::new(width, height);
+        rendered.add_change(Change::CursorVisibility(self.rendered.cursor_visibility()));
+        if let Some(shape) = self.rendered.cursor_shape() {
+            rendered.add_change(Change::CursorShape(shape));
+        }
+        self.rendered = rendered;
+    }
+
+    /// Returns the changes that erase the region
+    fn erase(&mut self) -> Vec<Change> {
+        vec![
+            self.move_to(0, 0),
+            Change::ClearToEndOfScreen(ColorAttribute::Default),
+        ]
+    }
 }
 
 impl<T: Terminal> BufferedTerminal<T> {
@@ -30,9 +82,49 @@ impl<T: Terminal> BufferedTerminal<T> {
             terminal,
             surface,
             seqno: 0,
+            inline: None,
         })
     }
 
+    /// Create a new `BufferedTerminal` that renders its `Surface` into
+    /// a region of `rows` rows starting at the line that the cursor is
+    /// on, rather than using the whole screen.  This is intended for
+    /// live updating output such as progress bars, without needing to
+    /// switch to the alternate screen.
+    ///
+    /// The region is claimed from the terminal as it is flushed,
+    /// scrolling the screen up if there isn't room below the cursor.
+    /// `print_above` adds lines above the region, which move into the
+    /// scrollback as usual, and `finish_inline` leaves the final
+    /// contents of the region on the screen.
+    ///
+    /// The region is limited to the height of the screen.  Changes that
+    /// move the cursor outside of the `Surface` are clipped to it, and
+    /// the `Surface` doesn't scroll the terminal, so text that scrolls
+    /// off the top of the `Surface` is lost; use `print_above` for
+    /// output that should be kept.
+    pub fn new_inline(mut terminal: T, rows: usize) -> Result<Self> {
+        let size = terminal.get_screen_size()?;
+        let height = rows.min(size.rows);
+        Ok(Self {
+            terminal,
+            surface: Surface::new(size.cols, height),
+            seqno: 0,
+            inline: Some(Inline {
+                rows,
+                rendered: Surface::new(size.cols, height),
+                reserved: 0,
+                cursor_row: 0,
+            }),
+        })
+    }
+
+    /// Returns true if this `BufferedTerminal` was created with
+    /// `new_inline`
+    pub fn is_inline(&self) -> bool {
+        self.inline.is_some()
+    }
+
     /// Get a mutable reference to the underlying terminal instance
     pub fn terminal(&mut self) -> &mut T {
         &mut self.terminal
@@ -47,7 +139,14 @@ impl<T: Terminal> BufferedTerminal<T> {
     /// Applications typically build in a refresh function (CTRL-L
     /// is common for unix applications) to request a repaint.
     /// You can use the `repaint` function for that situation.
+    /// When the terminal supports synchronized output (see
+    /// `Capabilities::synchronized_output`), the renderer presents
+    /// each flush to the user atomically, which avoids tearing during
+    /// large updates.
     pub fn flush(&mut self) -> Result<()> {
+        if self.inline.is_some() {
+            return self.flush_inline();
+        }
         {
             let (seq, changes) = self.surface.get_changes(self.seqno);
             // If we encounter an error during rendering, we want to
@@ -65,10 +164,150 @@ impl<T: Terminal> BufferedTerminal<T> {
     /// Clears the screen and re-draws the surface contents onto
     /// the Terminal.
     pub fn repaint(&mut self) -> Result<()> {
+        if let Some(inline) = self.inline.as_mut() {
+            let (width, height) = self.surface.dimensions();
+            inline.forget_rendered(width, height);
+            let changes = inline.erase();
+            self.terminal.render(&changes)?;
+        }
         self.seqno = 0;
         self.flush()
     }
 
+    /// Renders the changes to an inline region.  Rather than the change
+    /// log, this uses the difference between the `Surface` and what was
+    /// last rendered, so that all of the cursor movement can be made
+    /// relative to the start of the region.
+    fn flush_inline(&mut self) -> Result<()> {
+        let inline = match self.inline.as_mut() {
+            Some(inline) => inline,
+            None => return Ok(()),
+        };
+        let height = self.surface.dimensions().1;
+        let mut changes = vec![];
+
+        // Claim any rows of the region that we don't yet have.  The new
+        // lines scroll the screen if the region reaches the bottom.
+        if inline.reserved < height {
+            let last = inline.reserved.max(1) - 1;
+            changes.push(inline.move_to(0, last));
+            if inline.reserved == 0 {
+                changes.push(Change::ClearToEndOfLine(ColorAttribute::Default));
+            }
+            for row in last + 1..height {
+                changes.push(Change::CursorPosition {
+                    x: Position::Absolute(0),
+                    y: Position::Relative(1),
+                });
+                changes.push(Change::ClearToEndOfLine(ColorAttribute::Default));
+                inline.cursor_row = row;
+            }
+            inline.reserved = height;
+        }
+
+        let diff = inline.rendered.diff_screens(&self.surface);
+        for change in &diff {
+            match change {
+                Change::CursorPosition {
+                    x: Position::Absolute(x),
+                    y: Position::Absolute(y),
+                } => changes.push(inline.move_to(*x, *y)),
+                change => changes.push(change.clone()),
+            }
+        }
+        inline.rendered.add_changes(diff);
+
+        let (x, y) = self.surface.cursor_position();
+        changes.push(inline.move_to(x, y));
+
+        // The diff doesn't cover the appearance of the cursor, which
+        // the full screen path picks up from the change log
+        let mut cursor = vec![];
+        if self.surface.cursor_shape() != inline.rendered.cursor_shape() {
+            if let Some(shape) = self.surface.cursor_shape() {
+                cursor.push(Change::CursorShape(shape));
+            }
+        }
+        let visibility = self.surface.cursor_visibility();
+        if visibility != inline.rendered.cursor_visibility() {
+            cursor.push(Change::CursorVisibility(visibility));
+        }
+        changes.extend_from_slice(&cursor);
+        inline.rendered.add_changes(cursor);
+
+        if let Err(err) = self.terminal.render(&changes) {
+            // As above, if we encounter an error during rendering we
+            // want a subsequent flush to paint the region from scratch
+            let (width, height) = self.surface.dimensions();
+            inline.forget_rendered(width, height);
+            return Err(err);
+        }
+
+        let seqno = self.surface.current_seqno();
+        self.surface.flush_changes_older_than(seqno);
+        Ok(())
+    }
+
+    /// In inline mode, prints `changes` on a new line above the region,
+    /// moving the region down, and then repaints the region.  The changes
+    /// are expected to be text and attributes; the attributes are reset
+    /// to the defaults afterwards.
+    /// Outside of inline mode, this just renders `changes` to the terminal.
+    pub fn print_above(&mut self, changes: &[Change]) -> Result<()> {
+        let inline = match self.inline.as_mut() {
+            Some(inline) => inline,
+            None => return self.terminal.render(changes),
+        };
+
+        let mut output = inline.erase();
+        output.extend_from_slice(changes);
+        output.push(Change::AllAttributes(Default::default()));
+        output.push(Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Relative(1),
+        });
+
+        // The cursor is now at the start of the region, and the rows
+        // of the region need to be claimed again
+        let (width, height) = self.surface.dimensions();
+        inline.forget_rendered(width, height);
+        inline.reserved = 0;
+        inline.cursor_row = 0;
+        self.terminal.render(&output)?;
+        self.flush()
+    }
+
+    /// In inline mode, flushes the region and then leaves it as it is,
+    /// moving the cursor to the start of the line below it.  The
+    /// contents of the `Surface` are cleared, and the next flush starts
+    /// a new region.
+    /// Outside of inline mode, this is the same as `flush`.
+    pub fn finish_inline(&mut self) -> Result<()> {
+        self.flush()?;
+        let inline = match self.inline.as_mut() {
+            Some(inline) => inline,
+            None => return Ok(()),
+        };
+
+        let height = self.surface.dimensions().1;
+        let changes = vec![
+            inline.move_to(0, height.saturating_sub(1)),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Relative(1),
+            },
+        ];
+        let (width, height) = self.surface.dimensions();
+        inline.forget_rendered(width, height);
+        inline.reserved = 0;
+        inline.cursor_row = 0;
+        self.terminal.render(&changes)?;
+
+        self.surface = Surface::new(width, height);
+        self.seqno = 0;
+        Ok(())
+    }
+
     /// Check to see if the Terminal has been resized by its user.
     /// If it has, resize the surface to match the new dimensions
     /// and return true.  If the terminal was resized, the application
@@ -93,10 +332,28 @@ impl<T: Terminal> BufferedTerminal<T> {
     /// consume the input records.  Such a thing is possible, but is
     /// better suited for a higher level abstraction than this basic
     /// `BufferedTerminal` interface.
+    ///
+    /// In inline mode, the region is limited to the new height of the
+    /// screen.  The terminal may have rewrapped the region, so it is
+    /// erased, on a best effort basis, and will be painted from scratch
+    /// on the next flush.
     pub fn check_for_resize(&mut self) -> Result<bool> {
         let size = self.terminal.get_screen_size()?;
         let (width, height) = self.surface.dimensions();
 
+        if let Some(inline) = self.inline.as_mut() {
+            let rows = inline.rows.min(size.rows);
+            if width == size.cols && height == rows {
+                return Ok(false);
+            }
+            let changes = inline.erase();
+            inline.forget_rendered(size.cols, rows);
+            inline.reserved = inline.reserved.min(rows);
+            self.surface.resize(size.cols, rows);
+            self.terminal.render(&changes)?;
+            return Ok(true);
+        }
+
         if (width != size.cols) || (height != size.rows) {
             self.surface.resize(size.cols, size.rows);
             Ok(true)
@@ -119,3 +376,92 @@ impl<T: Terminal> DerefMut for BufferedTerminal<T> {
         &mut self.surface
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::surface::{CursorShape, CursorVisibility};
+    use crate::terminal::surface_terminal::SurfaceTerminal;
+    use k9::assert_equal as assert_eq;
+
+    #[test]
+    fn inline() {
+        let mut screen = Surface::new(8, 6);
+        screen.add_change("$ cmd\r\n");
+        let mut buf = BufferedTerminal::new_inline(SurfaceTerminal::new(screen), 2).unwrap();
+        assert_eq!(buf.dimensions(), (8, 2));
+
+        buf.add_change("50%\r\ntask");
+        buf.flush().unwrap();
+        assert_eq!(
+            buf.terminal().screen.screen_chars_to_string(),
+            "$ cmd   \n\
+             50%     \n\
+             task    \n\
+             \x20       \n\
+             \x20       \n\
+             \x20       \n"
+        );
+
+        buf.print_above(&[Change::Text("done".to_string())])
+            .unwrap();
+        buf.add_change(Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Absolute(0),
+        });
+        buf.add_change("75%");
+        buf.flush().unwrap();
+        assert_eq!(
+            buf.terminal().screen.screen_chars_to_string(),
+            "$ cmd   \n\
+             done    \n\
+             75%     \n\
+             task    \n\
+             \x20       \n\
+             \x20       \n"
+        );
+        assert_eq!(buf.terminal().screen.cursor_position(), (3, 2));
+
+        buf.finish_inline().unwrap();
+        assert_eq!(buf.terminal().screen.cursor_position(), (0, 4));
+        buf.add_change("next");
+        buf.flush().unwrap();
+        assert_eq!(
+            buf.terminal().screen.screen_chars_to_string(),
+            "$ cmd   \n\
+             done    \n\
+             75%     \n\
+             task    \n\
+             next    \n\
+             \x20       \n"
+        );
+    }
+
+    #[test]
+    fn inline_cursor_appearance() {
+        let mut buf =
+            BufferedTerminal::new_inline(SurfaceTerminal::new(Surface::new(8, 4)), 2).unwrap();
+        buf.add_change(Change::CursorShape(CursorShape::BlinkingBar));
+        buf.add_change(Change::CursorVisibility(CursorVisibility::Hidden));
+        buf.flush().unwrap();
+        assert_eq!(
+            buf.terminal().screen.cursor_shape(),
+            Some(CursorShape::BlinkingBar)
+        );
+        assert_eq!(
+            buf.terminal().screen.cursor_visibility(),
+            CursorVisibility::Hidden
+        );
+
+        // Printing above the region doesn't lose track of the cursor,
+        // so showing it again takes effect
+        buf.print_above(&[Change::Text("done".to_string())])
+            .unwrap();
+        buf.add_change(Change::CursorVisibility(CursorVisibility::Visible));
+        buf.flush().unwrap();
+        assert_eq!(
+            buf.terminal().screen.cursor_visibility(),
+            CursorVisibility::Visible
+        );
+    }
+}
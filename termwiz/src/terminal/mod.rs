@@ -1,9 +1,11 @@
 //! An abstraction over a terminal device
 
+use crate::caps::probed::ProbeCapabilities;
 use crate::caps::Capabilities;
+use crate::escape::csi::KittyKeyboardFlags;
 use crate::input::InputEvent;
 use crate::surface::Change;
-use crate::{format_err, Result};
+use crate::{bail, format_err, Result};
 use num_traits::NumCast;
 use std::fmt::Display;
 use std::time::Duration;
@@ -14,6 +16,8 @@ pub mod unix;
 pub mod windows;
 
 pub mod buffered;
+#[cfg(test)]
+pub(crate) mod surface_terminal;
 
 #[cfg(unix)]
 pub use self::unix::{UnixTerminal, UnixTerminalWaker as TerminalWaker};
@@ -65,6 +69,39 @@ pub trait Terminal {
     /// Exit the alternate screen.
     fn exit_alternate_screen(&mut self) -> Result<()>;
 
+    /// Push `flags` onto the terminal's stack of kitty keyboard
+    /// protocol enhancements, changing how key presses are reported.
+    /// Terminals that don't implement the protocol ignore this; use
+    /// `Capabilities::kitty_keyboard` to find out whether it is supported.
+    /// Any flags still pushed are popped when the `Terminal` is dropped.
+    /// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/#progressive-enhancement>
+    /// The default implementation returns an error.
+    fn push_kitty_keyboard_flags(&mut self, _flags: KittyKeyboardFlags) -> Result<()> {
+        bail!("kitty keyboard flags are not supported by this Terminal");
+    }
+
+    /// Pop the flags most recently pushed by `push_kitty_keyboard_flags`,
+    /// restoring the prior keyboard reporting behavior.
+    /// The default implementation returns an error.
+    fn pop_kitty_keyboard_flags(&mut self) -> Result<()> {
+        bail!("kitty keyboard flags are not supported by this Terminal");
+    }
+
+    /// Ask the terminal to report when it gains or loses focus, which
+    /// will yield `InputEvent::FocusGained` and `InputEvent::FocusLost`.
+    /// Focus reporting is disabled automatically when the `Terminal`
+    /// is dropped.
+    /// The default implementation returns an error.
+    fn enable_focus_reporting(&mut self) -> Result<()> {
+        bail!("focus reporting is not supported by this Terminal");
+    }
+
+    /// Stop reporting focus changes.
+    /// The default implementation returns an error.
+    fn disable_focus_reporting(&mut self) -> Result<()> {
+        bail!("focus reporting is not supported by this Terminal");
+    }
+
     /// Queries the current screen size, returning width, height.
     fn get_screen_size(&mut self) -> Result<ScreenSize>;
 
@@ -92,6 +129,16 @@ pub trait Terminal {
     fn poll_input(&mut self, wait: Option<Duration>) -> Result<Option<InputEvent>>;
 
     fn waker(&self) -> TerminalWaker;
+
+    /// Returns a helper that can be used to actively query the
+    /// terminal for capabilities that cannot be discovered from
+    /// the environment.  Returns None if the implementation
+    /// doesn't support probing.
+    /// The probes read from the terminal input directly, so they
+    /// should be run in raw mode and before consuming input events.
+    fn probe_capabilities(&mut self) -> Option<ProbeCapabilities<'_>> {
+        None
+    }
 }
 
 /// `SystemTerminal` is a concrete implementation of `Terminal`.
@@ -0,0 +1,81 @@
+//! A `Terminal` for tests, which applies the rendered changes to a
+//! `Surface` rather than a tty
+
+use crate::bail;
+use crate::input::InputEvent;
+use crate::surface::{Change, Surface};
+use crate::terminal::{ScreenSize, Terminal, TerminalWaker};
+use crate::Result;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Applies the rendered changes to `screen`, and returns the events
+/// queued in `input` from `poll_input`
+pub(crate) struct SurfaceTerminal {
+    pub screen: Surface,
+    pub input: VecDeque<InputEvent>,
+}
+
+impl SurfaceTerminal {
+    pub fn new(screen: Surface) -> Self {
+        Self {
+            screen,
+            input: VecDeque::new(),
+        }
+    }
+}
+
+impl Terminal for SurfaceTerminal {
+    fn set_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_cooked_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn enter_alternate_screen(&mut self) -> Result<()> {
+        bail!("not implemented");
+    }
+
+    fn exit_alternate_screen(&mut self) -> Result<()> {
+        bail!("not implemented");
+    }
+
+    fn get_screen_size(&mut self) -> Result<ScreenSize> {
+        let (cols, rows) = self.screen.dimensions();
+        Ok(ScreenSize {
+            rows,
+            cols,
+            xpixel: 0,
+            ypixel: 0,
+        })
+    }
+
+    fn set_screen_size(&mut self, size: ScreenSize) -> Result<()> {
+        self.screen.resize(size.cols, size.rows);
+        Ok(())
+    }
+
+    fn render(&mut self, changes: &[Change]) -> Result<()> {
+        self.screen.add_changes(changes.to_vec());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn poll_input(&mut self, wait: Option<Duration>) -> Result<Option<InputEvent>> {
+        match self.input.pop_front() {
+            Some(event) => Ok(Some(event)),
+            // Waiting forever would never end
+            None if wait.is_none() => bail!("no more input"),
+            None => Ok(None),
+        }
+    }
+
+    fn waker(&self) -> TerminalWaker {
+        unimplemented!();
+    }
+}
@@ -17,8 +17,12 @@ use termios::{
     TCSAFLUSH, TCSANOW,
 };
 
+use crate::caps::probed::ProbeCapabilities;
 use crate::caps::Capabilities;
-use crate::escape::csi::{DecPrivateMode, DecPrivateModeCode, Mode, XtermKeyModifierResource, CSI};
+use crate::escape::csi::{
+    DecPrivateMode, DecPrivateModeCode, Keyboard, KittyKeyboardFlags, KittyKeyboardMode, Mode,
+    XtermKeyModifierResource, CSI,
+};
 use crate::input::{InputEvent, InputParser};
 use crate::render::terminfo::TerminfoRenderer;
 use crate::surface::Change;
@@ -137,6 +141,11 @@ impl RenderTty for TtyWriteHandle {
         let size = self.get_size()?;
         Ok((size.ws_col as usize, size.ws_row as usize))
     }
+
+    fn get_size_in_pixels(&mut self) -> Result<(usize, usize)> {
+        let size = self.get_size()?;
+        Ok((size.ws_xpixel as usize, size.ws_ypixel as usize))
+    }
 }
 
 impl UnixTty for TtyWriteHandle {
@@ -207,6 +216,12 @@ pub struct UnixTerminal {
     wake_pipe_write: Arc<Mutex<UnixStream>>,
     caps: Capabilities,
     in_alternate_screen: bool,
+    /// How many sets of kitty keyboard flags we have pushed
+    kitty_keyboard_pushes: u32,
+    focus_reporting: bool,
+    /// Input read during capability probing that wasn't part of a
+    /// probe response, and which has yet to be parsed
+    typeahead: Vec<u8>,
 }
 
 impl UnixTerminal {
@@ -254,6 +269,9 @@ impl UnixTerminal {
             wake_pipe,
             wake_pipe_write: Arc::new(Mutex::new(wake_pipe_write)),
             in_alternate_screen: false,
+            kitty_keyboard_pushes: 0,
+            focus_reporting: false,
+            typeahead: vec![],
         })
     }
 
@@ -375,6 +393,53 @@ impl Terminal for UnixTerminal {
         Ok(())
     }
 
+    fn push_kitty_keyboard_flags(&mut self, flags: KittyKeyboardFlags) -> Result<()> {
+        write!(
+            self.write,
+            "{}",
+            CSI::Keyboard(Keyboard::PushKittyState {
+                flags,
+                mode: KittyKeyboardMode::AssignAll,
+            })
+        )?;
+        self.kitty_keyboard_pushes += 1;
+        Ok(())
+    }
+
+    fn pop_kitty_keyboard_flags(&mut self) -> Result<()> {
+        if self.kitty_keyboard_pushes > 0 {
+            write!(self.write, "{}", CSI::Keyboard(Keyboard::PopKittyState(1)))?;
+            self.kitty_keyboard_pushes -= 1;
+        }
+        Ok(())
+    }
+
+    fn enable_focus_reporting(&mut self) -> Result<()> {
+        write!(
+            self.write,
+            "{}",
+            CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::FocusTracking
+            )))
+        )?;
+        self.focus_reporting = true;
+        Ok(())
+    }
+
+    fn disable_focus_reporting(&mut self) -> Result<()> {
+        if self.focus_reporting {
+            write!(
+                self.write,
+                "{}",
+                CSI::Mode(Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+                    DecPrivateModeCode::FocusTracking
+                )))
+            )?;
+            self.focus_reporting = false;
+        }
+        Ok(())
+    }
+
     fn get_screen_size(&mut self) -> Result<ScreenSize> {
         let size = self.write.get_size()?;
         Ok(ScreenSize {
@@ -403,6 +468,13 @@ impl Terminal for UnixTerminal {
     }
 
     fn poll_input(&mut self, wait: Option<Duration>) -> Result<Option<InputEvent>> {
+        if !self.typeahead.is_empty() {
+            let typeahead = std::mem::take(&mut self.typeahead);
+            let input_queue = &mut self.input_queue;
+            self.input_parser
+                .parse(&typeahead, |evt| input_queue.push_back(evt), false);
+        }
+
         if let Some(event) = self.input_queue.pop_front() {
             return Ok(Some(event));
         }
@@ -502,6 +574,21 @@ impl Terminal for UnixTerminal {
             pipe: self.wake_pipe_write.clone(),
         }
     }
+
+    fn probe_capabilities(&mut self) -> Option<ProbeCapabilities<'_>> {
+        let fd = self.read.fd.as_raw_fd();
+        let mut probe = ProbeCapabilities::new(&mut self.read, &mut self.write);
+        probe.set_wait_for_input(move |timeout| {
+            let mut pfd = [pollfd {
+                fd,
+                events: POLLIN,
+                revents: 0,
+            }];
+            Ok(poll(&mut pfd, Some(timeout))? > 0)
+        });
+        probe.set_typeahead_sink(&mut self.typeahead);
+        Some(probe)
+    }
 }
 
 impl Drop for UnixTerminal {
@@ -529,7 +616,18 @@ impl Drop for UnixTerminal {
             decreset!(SGRMouse);
             decreset!(AnyEventMouse);
         }
+        if self.focus_reporting {
+            decreset!(FocusTracking);
+        }
         self.write.modify_other_keys(0).unwrap();
+        if self.kitty_keyboard_pushes > 0 {
+            write!(
+                self.write,
+                "{}",
+                CSI::Keyboard(Keyboard::PopKittyState(self.kitty_keyboard_pushes))
+            )
+            .unwrap();
+        }
         self.exit_alternate_screen().unwrap();
         self.write.flush().unwrap();
 
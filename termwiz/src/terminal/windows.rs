@@ -1,4 +1,6 @@
-use crate::escape::csi::{DecPrivateMode, DecPrivateModeCode, Mode, CSI};
+use crate::escape::csi::{
+    DecPrivateMode, DecPrivateModeCode, Keyboard, KittyKeyboardFlags, KittyKeyboardMode, Mode, CSI,
+};
 use crate::istty::IsTty;
 use crate::{bail, ensure, format_err, Result};
 use filedescriptor::{FileDescriptor, OwnedHandle};
@@ -25,6 +27,7 @@ use winapi::um::wincon::{
 };
 use winapi::um::winnls::CP_UTF8;
 
+use crate::caps::probed::ProbeCapabilities;
 use crate::caps::Capabilities;
 use crate::input::{InputEvent, InputParser};
 use crate::render::terminfo::TerminfoRenderer;
@@ -490,7 +493,13 @@ pub struct WindowsTerminal {
     saved_input_cp: u32,
     saved_output_cp: u32,
     in_alternate_screen: bool,
+    /// How many sets of kitty keyboard flags we have pushed
+    kitty_keyboard_pushes: u32,
+    focus_reporting: bool,
     caps: Capabilities,
+    /// Input read during capability probing that wasn't part of a
+    /// probe response, and which has yet to be parsed
+    typeahead: Vec<u8>,
 }
 
 impl Drop for WindowsTerminal {
@@ -515,6 +524,17 @@ impl Drop for WindowsTerminal {
             decreset!(BracketedPaste);
             decreset!(SGRMouse);
             decreset!(AnyEventMouse);
+            if self.focus_reporting {
+                decreset!(FocusTracking);
+            }
+            if self.kitty_keyboard_pushes > 0 {
+                write!(
+                    self.output_handle,
+                    "{}",
+                    CSI::Keyboard(Keyboard::PopKittyState(self.kitty_keyboard_pushes))
+                )
+                .unwrap();
+            }
         }
 
         self.exit_alternate_screen().unwrap();
@@ -607,6 +627,9 @@ impl WindowsTerminal {
             input_parser,
             input_queue: VecDeque::new(),
             in_alternate_screen: false,
+            kitty_keyboard_pushes: 0,
+            focus_reporting: false,
+            typeahead: vec![],
             caps,
         };
 
@@ -757,6 +780,64 @@ impl Terminal for WindowsTerminal {
         Ok(())
     }
 
+    fn push_kitty_keyboard_flags(&mut self, flags: KittyKeyboardFlags) -> Result<()> {
+        // The legacy console has no equivalent to the kitty protocol
+        if matches!(&self.renderer, Renderer::Terminfo(_)) {
+            write!(
+                self.output_handle,
+                "{}",
+                CSI::Keyboard(Keyboard::PushKittyState {
+                    flags,
+                    mode: KittyKeyboardMode::AssignAll,
+                })
+            )?;
+            self.kitty_keyboard_pushes += 1;
+        }
+        Ok(())
+    }
+
+    fn pop_kitty_keyboard_flags(&mut self) -> Result<()> {
+        if self.kitty_keyboard_pushes > 0 {
+            write!(
+                self.output_handle,
+                "{}",
+                CSI::Keyboard(Keyboard::PopKittyState(1))
+            )?;
+            self.kitty_keyboard_pushes -= 1;
+        }
+        Ok(())
+    }
+
+    fn enable_focus_reporting(&mut self) -> Result<()> {
+        // The legacy console reports focus changes via FOCUS_EVENT
+        // input records without needing to be asked
+        if matches!(&self.renderer, Renderer::Terminfo(_)) {
+            write!(
+                self.output_handle,
+                "{}",
+                CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(
+                    DecPrivateModeCode::FocusTracking
+                )))
+            )?;
+            self.focus_reporting = true;
+        }
+        Ok(())
+    }
+
+    fn disable_focus_reporting(&mut self) -> Result<()> {
+        if self.focus_reporting {
+            write!(
+                self.output_handle,
+                "{}",
+                CSI::Mode(Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+                    DecPrivateModeCode::FocusTracking
+                )))
+            )?;
+            self.focus_reporting = false;
+        }
+        Ok(())
+    }
+
     fn get_screen_size(&mut self) -> Result<ScreenSize> {
         let info = self.output_handle.get_buffer_info()?;
         let (cols, rows) = dimensions_from_buffer_info(info);
@@ -800,6 +881,13 @@ impl Terminal for WindowsTerminal {
     }
 
     fn poll_input(&mut self, wait: Option<Duration>) -> Result<Option<InputEvent>> {
+        if !self.typeahead.is_empty() {
+            let typeahead = std::mem::take(&mut self.typeahead);
+            let input_queue = &mut self.input_queue;
+            self.input_parser
+                .parse(&typeahead, |evt| input_queue.push_back(evt), false);
+        }
+
         loop {
             if let Some(event) = self.input_queue.pop_front() {
                 return Ok(Some(event));
@@ -849,4 +937,27 @@ impl Terminal for WindowsTerminal {
             handle: self.waker_handle.clone(),
         }
     }
+
+    fn probe_capabilities(&mut self) -> Option<ProbeCapabilities<'_>> {
+        let handle = self.input_handle.handle.as_raw_handle();
+        let mut probe = ProbeCapabilities::new(&mut self.input_handle, &mut self.output_handle);
+        probe.set_wait_for_input(move |timeout| {
+            let mut handles = [handle as *mut _];
+            let result = unsafe {
+                WaitForMultipleObjects(1, handles.as_mut_ptr(), 0, timeout.as_millis() as u32)
+            };
+            if result == WAIT_OBJECT_0 {
+                Ok(true)
+            } else if result == WAIT_FAILED {
+                bail!(
+                    "failed to WaitForMultipleObjects: {}",
+                    IoError::last_os_error()
+                );
+            } else {
+                Ok(false)
+            }
+        });
+        probe.set_typeahead_sink(&mut self.typeahead);
+        Some(probe)
+    }
 }
@@ -0,0 +1,214 @@
+use crate::cell::CellAttributes;
+use crate::surface::{BoxStyle, Change};
+use crate::widgets::layout::Constraints;
+use crate::widgets::pane::Pane;
+use crate::widgets::text::{print_at, truncate_to_width};
+use crate::widgets::{FocusMovement, Rect, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+
+/// The characters used for the border of a `Frame`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BorderStyle {
+    /// Drawn with the line drawing glyphs of the `BoxStyle`
+    Box(BoxStyle),
+    /// Uses `+`, `-` and `|`, for terminals that lack line drawing glyphs
+    Ascii,
+}
+
+impl Default for BorderStyle {
+    fn default() -> Self {
+        BorderStyle::Box(BoxStyle::default())
+    }
+}
+
+impl From<BoxStyle> for BorderStyle {
+    fn from(style: BoxStyle) -> Self {
+        BorderStyle::Box(style)
+    }
+}
+
+/// Draws a border, with an optional title, around another widget
+pub struct Frame<W> {
+    pane: Pane<W>,
+    title: String,
+    style: BorderStyle,
+    attributes: CellAttributes,
+    constraints: Constraints,
+}
+
+impl<W: Widget> Frame<W> {
+    pub fn new(title: &str, widget: W) -> Self {
+        Self {
+            pane: Pane::new(widget),
+            title: title.to_string(),
+            style: BorderStyle::default(),
+            attributes: CellAttributes::default(),
+            constraints: Constraints::default(),
+        }
+    }
+
+    /// Returns the widget inside the frame
+    pub fn inner(&self) -> &W {
+        &self.pane.widget
+    }
+
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.pane.widget
+    }
+
+    /// Sets the title shown in the top edge of the border
+    pub fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+    }
+
+    pub fn set_border_style<S: Into<BorderStyle>>(&mut self, style: S) {
+        self.style = style.into();
+    }
+
+    /// Sets the attributes used for the border and title
+    pub fn set_attributes(&mut self, attributes: CellAttributes) {
+        self.attributes = attributes;
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+}
+
+impl<W: Widget + 'static> Widget for Frame<W> {
+    fn process_event(&mut self, event: &WidgetEvent, args: &mut UpdateArgs) -> bool {
+        if let WidgetEvent::FocusGained | WidgetEvent::FocusLost = event {
+            let focus = matches!(event, WidgetEvent::FocusGained);
+            self.pane.set_focus(focus, args);
+            return true;
+        }
+        if let Some(coords) = event.mouse_coords() {
+            if !self.pane.contains(coords.x, coords.y) {
+                return false;
+            }
+        }
+        self.pane.process_event(event, args)
+    }
+
+    fn accepts_focus(&self) -> bool {
+        self.pane.widget.accepts_focus()
+    }
+
+    fn move_focus(&mut self, movement: FocusMovement, args: &mut UpdateArgs) -> bool {
+        self.pane.move_focus(movement, args)
+    }
+
+    fn on_mount(&mut self, args: &mut UpdateArgs) {
+        self.pane.on_mount(args)
+    }
+
+    fn on_unmount(&mut self, args: &mut UpdateArgs) {
+        self.pane.on_unmount(args)
+    }
+
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, height) = args.surface.dimensions();
+        args.surface
+            .add_change(Change::ClearScreen(self.attributes.background()));
+        if width < 2 || height < 2 {
+            return;
+        }
+
+        match self.style {
+            BorderStyle::Box(style) => {
+                let mut view = args.surface.view(0, 0, width, height);
+                view.add_change(Change::AllAttributes(self.attributes.clone()));
+                view.draw_box(0, 0, width, height, style);
+            }
+            BorderStyle::Ascii => {
+                let line = format!("+{}+", "-".repeat(width - 2));
+                print_at(args.surface, 0, 0, &line, &self.attributes);
+                for y in 1..height - 1 {
+                    print_at(args.surface, 0, y, "|", &self.attributes);
+                    print_at(args.surface, width - 1, y, "|", &self.attributes);
+                }
+                print_at(args.surface, 0, height - 1, &line, &self.attributes);
+            }
+        }
+
+        if !self.title.is_empty() && width > 4 {
+            let title = format!(" {} ", self.title);
+            print_at(
+                args.surface,
+                1,
+                0,
+                truncate_to_width(&title, width - 2),
+                &self.attributes,
+            );
+        }
+
+        let rect = Rect {
+            x: 1,
+            y: 1,
+            width: width - 2,
+            height: height - 2,
+        };
+        self.pane.render(args, rect, true);
+    }
+
+    fn get_size_constraints(&self) -> Constraints {
+        self.constraints
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::surface::Surface;
+    use crate::widgets::{Label, Ui};
+
+    #[test]
+    fn render_frame() {
+        let mut ui = Ui::new();
+        let id = ui.set_root(Frame::new("Title", Label::new("hello")));
+
+        let mut surface = Surface::new(12, 4);
+        ui.render_to_screen(&mut surface).unwrap();
+        ui.render_to_screen(&mut surface).unwrap();
+        assert_eq!(
+            surface.screen_chars_to_string(),
+            "┌ Title ───┐\n\
+             │hello     │\n\
+             │          │\n\
+             └──────────┘\n"
+        );
+
+        ui.widget_mut::<Frame<Label>>(id)
+            .unwrap()
+            .inner_mut()
+            .set_text("bye");
+        ui.render_to_screen(&mut surface).unwrap();
+        assert!(surface.screen_chars_to_string().contains("│bye       │"));
+    }
+
+    #[test]
+    fn border_styles() {
+        let mut ui = Ui::new();
+        let id = ui.set_root(Frame::new("", Label::new("hi")));
+        let mut surface = Surface::new(5, 3);
+
+        let mut render = |style: BorderStyle| {
+            ui.widget_mut::<Frame<Label>>(id)
+                .unwrap()
+                .set_border_style(style);
+            ui.render_to_screen(&mut surface).unwrap();
+            surface.screen_chars_to_string()
+        };
+        assert_eq!(render(BoxStyle::Rounded.into()), "╭───╮\n│hi │\n╰───╯\n");
+        assert_eq!(render(BoxStyle::Double.into()), "╔═══╗\n║hi ║\n╚═══╝\n");
+        assert_eq!(render(BorderStyle::Ascii), "+---+\n|hi |\n+---+\n");
+    }
+}
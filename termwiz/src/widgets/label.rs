@@ -0,0 +1,136 @@
+use crate::cell::{unicode_column_width, CellAttributes};
+use crate::surface::Change;
+use crate::widgets::layout::{Constraints, HorizontalAlignment};
+use crate::widgets::text::{print_at, truncate_to_width, wrap_text};
+use crate::widgets::{RenderArgs, Widget};
+use std::any::Any;
+
+/// Displays a block of text, optionally wrapping it to
+/// fit the width of the widget
+pub struct Label {
+    text: String,
+    attributes: CellAttributes,
+    wrap: bool,
+    alignment: HorizontalAlignment,
+    constraints: Constraints,
+}
+
+impl Label {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            attributes: CellAttributes::default(),
+            wrap: true,
+            alignment: HorizontalAlignment::Left,
+            constraints: Constraints::default(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+    }
+
+    /// Sets the attributes used for the text and the background
+    pub fn set_attributes(&mut self, attributes: CellAttributes) {
+        self.attributes = attributes;
+    }
+
+    /// When true (the default), lines of text that are wider than
+    /// the widget are wrapped; otherwise they are truncated
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// Sets how each line of text is aligned within the widget
+    pub fn set_alignment(&mut self, alignment: HorizontalAlignment) {
+        self.alignment = alignment;
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+}
+
+impl Widget for Label {
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, height) = args.surface.dimensions();
+        args.surface
+            .add_change(Change::ClearScreen(self.attributes.background()));
+
+        let lines = if self.wrap {
+            wrap_text(&self.text, width)
+        } else {
+            self.text
+                .lines()
+                .map(|line| truncate_to_width(line, width).to_string())
+                .collect()
+        };
+
+        for (y, line) in lines.iter().take(height).enumerate() {
+            let spare = width.saturating_sub(unicode_column_width(line, None));
+            let x = match self.alignment {
+                HorizontalAlignment::Left => 0,
+                HorizontalAlignment::Center => spare / 2,
+                HorizontalAlignment::Right => spare,
+            };
+            print_at(args.surface, x, y, line, &self.attributes);
+        }
+    }
+
+    fn get_size_constraints(&self) -> Constraints {
+        self.constraints
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::surface::Surface;
+    use crate::widgets::Ui;
+
+    fn render(label: Label, width: usize, height: usize) -> String {
+        let mut ui = Ui::new();
+        ui.set_root(label);
+        let mut surface = Surface::new(width, height);
+        ui.render_to_screen(&mut surface).unwrap();
+        surface.screen_chars_to_string()
+    }
+
+    #[test]
+    fn alignment() {
+        let mut label = Label::new("ab\nabcd");
+        label.set_alignment(HorizontalAlignment::Center);
+        assert_eq!(render(label, 8, 2), "   ab   \n  abcd  \n");
+
+        let mut label = Label::new("ab\nabcd");
+        label.set_alignment(HorizontalAlignment::Right);
+        assert_eq!(render(label, 8, 2), "      ab\n    abcd\n");
+    }
+
+    #[test]
+    fn wrap_and_truncate() {
+        let text = "hello world\nxy";
+        assert_eq!(
+            render(Label::new(text), 8, 3),
+            "hello   \nworld   \nxy      \n"
+        );
+
+        // Without wrapping, long lines are cut off at the right edge
+        let mut label = Label::new(text);
+        label.set_wrap(false);
+        label.set_alignment(HorizontalAlignment::Right);
+        assert_eq!(render(label, 8, 3), "hello wo\n      xy\n        \n");
+    }
+}
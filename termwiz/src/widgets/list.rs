@@ -0,0 +1,261 @@
+use crate::cell::CellAttributes;
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons, MouseEvent};
+use crate::surface::{Change, CursorVisibility};
+use crate::widgets::layout::Constraints;
+use crate::widgets::text::{fill_at, print_at};
+use crate::widgets::{RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+
+/// Tracks the selected row of a scrollable set of rows,
+/// and which of them are visible
+#[derive(Debug, Default)]
+pub(crate) struct Selection {
+    pub selected: Option<usize>,
+    /// The first visible row
+    pub top: usize,
+    /// The number of visible rows, as of the most recent render
+    pub page: usize,
+}
+
+impl Selection {
+    /// Clamps the selection to the number of rows
+    pub fn set_len(&mut self, len: usize) {
+        self.selected = match self.selected {
+            _ if len == 0 => None,
+            Some(idx) => Some(idx.min(len - 1)),
+            None => Some(0),
+        };
+    }
+
+    /// Moves the selection in response to the navigation keys.
+    /// Returns true if the key was handled.
+    pub fn process_key(&mut self, key: &KeyEvent, len: usize) -> bool {
+        if len == 0 || key.modifiers != Modifiers::NONE {
+            return false;
+        }
+        let current = self.selected.unwrap_or(0);
+        let page = self.page.max(1);
+        let selected = match key.key {
+            KeyCode::UpArrow | KeyCode::ApplicationUpArrow => current.saturating_sub(1),
+            KeyCode::DownArrow | KeyCode::ApplicationDownArrow => current + 1,
+            KeyCode::PageUp => current.saturating_sub(page),
+            KeyCode::PageDown => current + page,
+            KeyCode::Home => 0,
+            KeyCode::End => len - 1,
+            _ => return false,
+        };
+        self.selected = Some(selected.min(len - 1));
+        true
+    }
+
+    /// Scrolls in response to the mouse wheel.
+    /// Returns true if the event was handled.
+    pub fn process_wheel(&mut self, mouse: &MouseEvent, len: usize) -> bool {
+        if !mouse.mouse_buttons.contains(MouseButtons::VERT_WHEEL) {
+            return false;
+        }
+        if mouse.mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+            self.top = self.top.saturating_sub(1);
+        } else {
+            self.top = (self.top + 1).min(len.saturating_sub(self.page));
+        }
+        true
+    }
+
+    /// Selects the row displayed at widget row `y`.  `first_row` is
+    /// the widget row at which the rows begin.  Returns true if there
+    /// is a row at `y`.
+    pub fn process_click(&mut self, y: usize, first_row: usize, len: usize) -> bool {
+        if y >= first_row && self.top + y - first_row < len {
+            self.selected = Some(self.top + y - first_row);
+            return true;
+        }
+        false
+    }
+
+    /// Scrolls the rows, if needed, so that the selection is visible
+    /// within `page` rows.  The wheel may scroll the selection out
+    /// of view, so this is only called when the selection changes.
+    pub fn scroll_to_selection(&mut self) {
+        if let Some(selected) = self.selected {
+            if selected < self.top {
+                self.top = selected;
+            } else if self.page > 0 && selected >= self.top + self.page {
+                self.top = selected + 1 - self.page;
+            }
+        }
+    }
+}
+
+/// A scrollable list of items, of which one is selected.
+/// The selection is moved with the arrow, Page Up/Down, Home and End
+/// keys or by clicking on an item, and the mouse wheel scrolls the list.
+pub struct List {
+    items: Vec<String>,
+    selection: Selection,
+    attributes: CellAttributes,
+    selected_attributes: CellAttributes,
+    constraints: Constraints,
+}
+
+impl List {
+    pub fn new(items: Vec<String>) -> Self {
+        let mut selection = Selection::default();
+        selection.set_len(items.len());
+        Self {
+            items,
+            selection,
+            attributes: CellAttributes::default(),
+            selected_attributes: CellAttributes::default().set_reverse(true).clone(),
+            constraints: Constraints::default(),
+        }
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Replaces the items, keeping the selected index if it is still valid
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.items = items;
+        self.selection.set_len(self.items.len());
+        self.selection.scroll_to_selection();
+    }
+
+    /// Returns the index of the selected item
+    pub fn selected(&self) -> Option<usize> {
+        self.selection.selected
+    }
+
+    /// Returns the selected item
+    pub fn selected_item(&self) -> Option<&str> {
+        self.items.get(self.selection.selected?).map(String::as_str)
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.items.len() {
+            self.selection.selected = Some(index);
+            self.selection.scroll_to_selection();
+        }
+    }
+
+    pub fn set_attributes(&mut self, attributes: CellAttributes) {
+        self.attributes = attributes;
+    }
+
+    /// Sets the attributes used for the selected item.
+    /// The default is reverse video.
+    pub fn set_selected_attributes(&mut self, attributes: CellAttributes) {
+        self.selected_attributes = attributes;
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+}
+
+impl Widget for List {
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        let len = self.items.len();
+        match event {
+            WidgetEvent::Input(InputEvent::Key(key)) if !key.is_release() => {
+                let handled = self.selection.process_key(key, len);
+                self.selection.scroll_to_selection();
+                handled
+            }
+            WidgetEvent::Input(InputEvent::Mouse(mouse)) => {
+                self.selection.process_wheel(mouse, len)
+            }
+            WidgetEvent::Click {
+                coords,
+                button: MouseButtons::LEFT,
+                ..
+            } => self.selection.process_click(coords.y, 0, len),
+            _ => false,
+        }
+    }
+
+    fn accepts_focus(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, height) = args.surface.dimensions();
+        self.selection.page = height;
+        args.surface
+            .add_change(Change::ClearScreen(self.attributes.background()));
+        args.cursor.visibility = CursorVisibility::Hidden;
+
+        let rows = self.items.iter().enumerate().skip(self.selection.top);
+        for (y, (idx, item)) in rows.take(height).enumerate() {
+            let attributes = if Some(idx) == self.selection.selected {
+                fill_at(args.surface, 0, y, width, &self.selected_attributes);
+                &self.selected_attributes
+            } else {
+                &self.attributes
+            };
+            print_at(args.surface, 0, y, item, attributes);
+        }
+    }
+
+    fn get_size_constraints(&self) -> Constraints {
+        self.constraints
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn navigate() {
+        let mut selection = Selection {
+            page: 3,
+            ..Default::default()
+        };
+        selection.set_len(10);
+        assert_eq!(selection.selected, Some(0));
+
+        let press = |selection: &mut Selection, key| {
+            let handled = selection.process_key(&KeyEvent::new(key, Modifiers::NONE), 10);
+            selection.scroll_to_selection();
+            handled
+        };
+
+        assert!(press(&mut selection, KeyCode::UpArrow));
+        assert_eq!((selection.selected, selection.top), (Some(0), 0));
+        assert!(press(&mut selection, KeyCode::PageDown));
+        assert_eq!((selection.selected, selection.top), (Some(3), 1));
+        assert!(press(&mut selection, KeyCode::End));
+        assert_eq!((selection.selected, selection.top), (Some(9), 7));
+        assert!(press(&mut selection, KeyCode::DownArrow));
+        assert_eq!(selection.selected, Some(9));
+        assert!(!press(&mut selection, KeyCode::Char('x')));
+
+        assert!(selection.process_click(1, 0, 10));
+        assert_eq!(selection.selected, Some(8));
+        assert!(!selection.process_click(3, 0, 10));
+
+        let wheel = MouseEvent {
+            x: 0,
+            y: 0,
+            mouse_buttons: MouseButtons::VERT_WHEEL | MouseButtons::WHEEL_POSITIVE,
+            modifiers: Modifiers::NONE,
+        };
+        assert!(selection.process_wheel(&wheel, 10));
+        assert_eq!(selection.top, 6);
+
+        selection.set_len(5);
+        assert_eq!(selection.selected, Some(4));
+        selection.set_len(0);
+        assert_eq!(selection.selected, None);
+    }
+}
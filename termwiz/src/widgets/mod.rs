@@ -2,21 +2,165 @@
 // right place for it to take effect
 #![allow(clippy::new_without_default)]
 use crate::color::ColorAttribute;
-use crate::input::InputEvent;
+use crate::input::{InputEvent, KeyCode, Modifiers, MouseButtons};
 use crate::surface::{Change, CursorShape, CursorVisibility, Position, SequenceNo, Surface};
-use crate::Result;
+use crate::terminal::{Terminal, TerminalWaker};
+use crate::{bail, Result};
 use fnv::FnvHasher;
-use std::collections::{HashMap, VecDeque};
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::BuildHasherDefault;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// fnv is a more appropriate hasher for the WidgetIds we use in this module.
 type FnvHashMap<K, V> = HashMap<K, V, BuildHasherDefault<FnvHasher>>;
 
+mod frame;
+mod label;
 pub mod layout;
-
-/// Describes an event that may need to be processed by the widget
+mod list;
+mod pane;
+mod progress;
+mod split;
+mod table;
+mod tabs;
+mod text;
+mod textinput;
+pub use frame::{BorderStyle, Frame};
+pub use label::Label;
+pub use list::List;
+pub use progress::ProgressBar;
+pub use split::{Split, SplitPane, SplitSize};
+pub use table::{Column, ColumnWidth, Table};
+pub use tabs::Tabs;
+pub use textinput::TextInput;
+
+/// Describes an event that may need to be processed by the widget.
+/// In addition to the input from the terminal, the `Ui` synthesizes
+/// higher level events from the mouse reports and changes of focus.
+/// The coordinates held by the mouse related events are relative to
+/// the widget that receives them.
+#[derive(Clone, Debug)]
 pub enum WidgetEvent {
     Input(InputEvent),
+    /// The delay requested via `UpdateArgs::request_tick` has elapsed.
+    /// Ticks are not repeated; request another to keep ticking.
+    Tick,
+    /// An event sent by the application via `UiEventSender::send`.
+    /// Use `downcast_ref` to recover the value that was sent.
+    User(Arc<dyn Any + Send + Sync>),
+    /// A mouse button was pressed and released without the mouse
+    /// moving.  `streak` is 1 for a single click, 2 for a double
+    /// click and so on.  The event is delivered to the widget over
+    /// which the button was pressed.
+    Click {
+        coords: ParentRelativeCoords,
+        button: MouseButtons,
+        streak: usize,
+    },
+    /// The mouse moved while a button was held.  `coords` is the
+    /// position at which the button was pressed.  This and the
+    /// subsequent `Drag` and `DragEnd` events are delivered to the
+    /// widget over which the button was pressed.
+    DragStart {
+        coords: ParentRelativeCoords,
+        button: MouseButtons,
+    },
+    Drag {
+        coords: ParentRelativeCoords,
+        button: MouseButtons,
+    },
+    /// The button was released, ending the drag
+    DragEnd {
+        coords: ParentRelativeCoords,
+        button: MouseButtons,
+    },
+    /// The mouse moved over the widget
+    MouseEnter,
+    /// The mouse moved away from the widget
+    MouseLeave,
+    /// The widget became the focused widget
+    FocusGained,
+    /// The widget is no longer the focused widget
+    FocusLost,
+}
+
+/// Moves the focus among the widgets owned by a container widget;
+/// see `Widget::move_focus`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FocusMovement {
+    /// The focus is entering the widget via `Ui::focus_next`
+    First,
+    /// The focus is entering the widget via `Ui::focus_previous`
+    Last,
+    Next,
+    Previous,
+}
+
+impl WidgetEvent {
+    /// Returns the coordinates of the mouse related events
+    pub fn mouse_coords(&self) -> Option<ParentRelativeCoords> {
+        match self {
+            WidgetEvent::Input(InputEvent::Mouse(m)) => {
+                Some(ParentRelativeCoords::new(m.x as usize, m.y as usize))
+            }
+            WidgetEvent::Click { coords, .. }
+            | WidgetEvent::DragStart { coords, .. }
+            | WidgetEvent::Drag { coords, .. }
+            | WidgetEvent::DragEnd { coords, .. } => Some(*coords),
+            _ => None,
+        }
+    }
+
+    /// Returns a copy of a mouse related event with its
+    /// coordinates replaced by `coords`
+    pub fn with_mouse_coords(&self, coords: ParentRelativeCoords) -> Self {
+        let mut event = self.clone();
+        match &mut event {
+            WidgetEvent::Input(InputEvent::Mouse(m)) => {
+                m.x = coords.x as u16;
+                m.y = coords.y as u16;
+            }
+            WidgetEvent::Click { coords: c, .. }
+            | WidgetEvent::DragStart { coords: c, .. }
+            | WidgetEvent::Drag { coords: c, .. }
+            | WidgetEvent::DragEnd { coords: c, .. } => *c = coords,
+            _ => {}
+        }
+        event
+    }
+}
+
+/// Clicks that are closer together than this form a streak
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A mouse button that is being held down
+struct MousePress {
+    button: MouseButtons,
+    /// Where the button was pressed
+    coords: ScreenRelativeCoords,
+    /// The most recent position of the mouse
+    last: ScreenRelativeCoords,
+    /// The widget over which the button was pressed
+    widget: WidgetId,
+    dragging: bool,
+}
+
+struct MouseClick {
+    button: MouseButtons,
+    coords: ScreenRelativeCoords,
+    time: Instant,
+    streak: usize,
+}
+
+/// Tracks the state of the mouse in order to synthesize events
+#[derive(Default)]
+struct MouseState {
+    hovered: Option<WidgetId>,
+    press: Option<MousePress>,
+    last_click: Option<MouseClick>,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -49,6 +193,23 @@ pub struct UpdateArgs<'a> {
     /// The id of the current widget
     pub id: WidgetId,
     pub cursor: &'a mut CursorShapeAndPosition,
+    tick: Option<Instant>,
+}
+
+impl<'a> UpdateArgs<'a> {
+    /// Requests that the widget receive `WidgetEvent::Tick` once `delay`
+    /// has elapsed.  If a tick is already pending for the widget, the
+    /// earlier of the two is kept.
+    pub fn request_tick(&mut self, delay: Duration) {
+        self.request_tick_at(Instant::now() + delay);
+    }
+
+    fn request_tick_at(&mut self, deadline: Instant) {
+        self.tick = Some(match self.tick {
+            Some(tick) => tick.min(deadline),
+            None => deadline,
+        });
+    }
 }
 
 /// Implementing the `Widget` trait allows for defining a potentially
@@ -73,6 +234,85 @@ pub trait Widget {
     fn process_event(&mut self, _event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
         false
     }
+
+    /// Override this to return true if the widget can receive keyboard
+    /// focus.  This determines the widgets that `Ui::focus_next` and
+    /// `Ui::focus_previous` move between, and whether clicking on the
+    /// widget focuses it.
+    fn accepts_focus(&self) -> bool {
+        false
+    }
+
+    /// Container widgets, such as `Split`, that own other widgets
+    /// override this to move the focus among them.
+    /// `Ui::focus_next` and `Ui::focus_previous` first pass
+    /// `FocusMovement::Next` or `Previous` to the focused widget, which
+    /// keeps the focus if this returns true.  Otherwise the focus moves
+    /// to another widget, which is first passed `FocusMovement::First`
+    /// or `Last` so that it can pick the widget that it focuses.
+    /// The default implementation returns false.
+    fn move_focus(&mut self, _movement: FocusMovement, _args: &mut UpdateArgs) -> bool {
+        false
+    }
+
+    /// Called when the widget has been added to the `Ui`
+    fn on_mount(&mut self, _args: &mut UpdateArgs) {}
+
+    /// Called when the widget is about to be removed from the `Ui`,
+    /// either explicitly via `Ui::remove` or `Ui::replace`, or because
+    /// it is no longer reachable from the root widget.
+    /// The children of a widget are unmounted before the widget itself.
+    fn on_unmount(&mut self, _args: &mut UpdateArgs) {}
+
+    /// Override this to return `Some(self)` so that the widget can be
+    /// retrieved by its concrete type via `Ui::widget`.  This is only
+    /// possible for widgets that don't borrow from their environment.
+    fn as_any(&self) -> Option<&dyn Any> {
+        None
+    }
+
+    /// The mutable counterpart of `as_any`, used by `Ui::widget_mut`
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
+}
+
+impl<W: Widget + ?Sized> Widget for Box<W> {
+    fn render(&mut self, args: &mut RenderArgs) {
+        (**self).render(args)
+    }
+
+    fn get_size_constraints(&self) -> layout::Constraints {
+        (**self).get_size_constraints()
+    }
+
+    fn process_event(&mut self, event: &WidgetEvent, args: &mut UpdateArgs) -> bool {
+        (**self).process_event(event, args)
+    }
+
+    fn accepts_focus(&self) -> bool {
+        (**self).accepts_focus()
+    }
+
+    fn move_focus(&mut self, movement: FocusMovement, args: &mut UpdateArgs) -> bool {
+        (**self).move_focus(movement, args)
+    }
+
+    fn on_mount(&mut self, args: &mut UpdateArgs) {
+        (**self).on_mount(args)
+    }
+
+    fn on_unmount(&mut self, args: &mut UpdateArgs) {
+        (**self).on_unmount(args)
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        (**self).as_any()
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        (**self).as_any_mut()
+    }
 }
 
 /// Relative to the top left of the parent container
@@ -175,6 +415,36 @@ impl Graph {
             .map(|v| v.as_slice())
             .unwrap_or_else(|| &[])
     }
+
+    /// Returns the ids of the widget and its descendants, with
+    /// each widget following its descendants
+    fn subtree(&self, id: WidgetId) -> Vec<WidgetId> {
+        let mut ids = vec![];
+        for child in self.children(id) {
+            ids.extend(self.subtree(*child));
+        }
+        ids.push(id);
+        ids
+    }
+
+    /// Removes a widget and its descendants from the graph,
+    /// returning their ids in the order produced by `subtree`
+    fn remove(&mut self, id: WidgetId) -> Vec<WidgetId> {
+        let ids = self.subtree(id);
+        if let Some(parent) = self.parent.get(&id) {
+            if let Some(siblings) = self.children.get_mut(parent) {
+                siblings.retain(|sibling| *sibling != id);
+            }
+        }
+        for id in &ids {
+            self.children.remove(id);
+            self.parent.remove(id);
+        }
+        if self.root == Some(id) {
+            self.root = None;
+        }
+        ids
+    }
 }
 
 /// Manages the widgets on the display
@@ -184,6 +454,67 @@ pub struct Ui<'widget> {
     render: FnvHashMap<WidgetId, RenderData<'widget>>,
     input_queue: VecDeque<WidgetEvent>,
     focused: Option<WidgetId>,
+    mouse: MouseState,
+    /// The time at which each widget that requested a tick is due one
+    ticks: FnvHashMap<WidgetId, Instant>,
+    user_events: UserEvents,
+    /// The screen dimensions for which the layout was last computed
+    layout_size: Option<(usize, usize)>,
+}
+
+/// The events sent to a `Ui` via its `UiEventSender`s
+struct UserEvents {
+    sender: Sender<(WidgetId, Arc<dyn Any + Send + Sync>)>,
+    receiver: Receiver<(WidgetId, Arc<dyn Any + Send + Sync>)>,
+    /// Events that have been received but not yet delivered
+    pending: VecDeque<(WidgetId, Arc<dyn Any + Send + Sync>)>,
+    /// Wakes the terminal that the `Ui` is waiting on in `Ui::poll_input`
+    waker: Arc<Mutex<Option<TerminalWaker>>>,
+}
+
+impl Default for UserEvents {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver,
+            pending: VecDeque::new(),
+            waker: Default::default(),
+        }
+    }
+}
+
+impl UserEvents {
+    /// Moves the events that have been sent into `pending`
+    fn receive(&mut self) {
+        self.pending.extend(self.receiver.try_iter());
+    }
+}
+
+/// Sends events to the widgets of a `Ui` from other threads, waking
+/// `Ui::poll_input` so that they are processed promptly.
+/// Obtain one from `Ui::event_sender`.
+#[derive(Clone)]
+pub struct UiEventSender {
+    sender: Sender<(WidgetId, Arc<dyn Any + Send + Sync>)>,
+    waker: Arc<Mutex<Option<TerminalWaker>>>,
+}
+
+impl UiEventSender {
+    /// Queues `value` for delivery to the specified widget as
+    /// `WidgetEvent::User`.  If the widget doesn't handle it, the
+    /// event propagates to its parent.  Events for widgets that have
+    /// since been removed are discarded.
+    /// Returns an error if the `Ui` has been dropped.
+    pub fn send<T: Any + Send + Sync>(&self, id: WidgetId, value: T) -> Result<()> {
+        if self.sender.send((id, Arc::new(value))).is_err() {
+            bail!("the Ui has been dropped");
+        }
+        if let Some(waker) = self.waker.lock().unwrap().as_ref() {
+            waker.wake()?;
+        }
+        Ok(())
+    }
 }
 
 impl<'widget> Ui<'widget> {
@@ -191,6 +522,9 @@ impl<'widget> Ui<'widget> {
         Default::default()
     }
 
+    /// Adds a widget as a child of `parent`, or when `parent` is `None`
+    /// without a parent.  The first widget added without a parent
+    /// becomes the root, and has the focus until it is moved.
     pub fn add<W: Widget + 'widget>(&mut self, parent: Option<WidgetId>, w: W) -> WidgetId {
         let id = self.graph.add(parent);
 
@@ -204,10 +538,11 @@ impl<'widget> Ui<'widget> {
             },
         );
 
+        self.mount(id);
+
         if parent.is_none() && self.focused.is_none() {
-            self.focused = Some(id);
+            self.set_focus(id);
         }
-
         id
     }
 
@@ -219,44 +554,214 @@ impl<'widget> Ui<'widget> {
         self.add(Some(parent), w)
     }
 
-    fn do_deliver(&mut self, id: WidgetId, event: &WidgetEvent) -> bool {
+    /// Removes the widget and all of its descendants from the Ui,
+    /// calling `Widget::on_unmount` for each of them.
+    /// If the focused widget is removed, focus moves to the parent
+    /// of the removed widget.
+    /// Returns false if there is no widget with the specified id.
+    pub fn remove(&mut self, id: WidgetId) -> bool {
+        if !self.render.contains_key(&id) {
+            return false;
+        }
+
+        let parent = self.graph.parent.get(&id).copied();
+        let removed = self.graph.remove(id);
+        let lost_focus = matches!(self.focused, Some(focus) if removed.contains(&focus));
+        self.unmount(&removed);
+        if lost_focus {
+            self.focused = None;
+            if let Some(parent) = parent {
+                self.set_focus(parent);
+            }
+        }
+        true
+    }
+
+    /// Replaces the widget with the specified id, keeping its position
+    /// among its siblings.  The descendants of the prior widget are
+    /// removed, and the id is retained by the new widget, which is
+    /// mounted after the prior widget is unmounted.
+    /// Returns false, without using `w`, if there is no widget with
+    /// the specified id.
+    pub fn replace<W: Widget + 'widget>(&mut self, id: WidgetId, w: W) -> bool {
+        if !self.render.contains_key(&id) {
+            return false;
+        }
+
+        let children = self.graph.children(id).to_vec();
+        for child in children {
+            self.remove(child);
+        }
+
+        self.unmount(&[id]);
+        self.render.insert(
+            id,
+            RenderData {
+                surface: Surface::new(1, 1),
+                cursor: Default::default(),
+                coordinates: Default::default(),
+                widget: Box::new(w),
+            },
+        );
+        self.mount(id);
+        true
+    }
+
+    /// Returns true if there is a widget with the specified id
+    pub fn contains(&self, id: WidgetId) -> bool {
+        self.render.contains_key(&id)
+    }
+
+    fn mount(&mut self, id: WidgetId) {
         let render_data = self.render.get_mut(&id).unwrap();
         let mut args = UpdateArgs {
             id,
             cursor: &mut render_data.cursor,
+            tick: None,
+        };
+        render_data.widget.on_mount(&mut args);
+        if let Some(tick) = args.tick {
+            self.schedule_tick(id, tick);
+        }
+    }
+
+    /// Records a tick requested by a widget while handling an event
+    fn schedule_tick(&mut self, id: WidgetId, deadline: Instant) {
+        let tick = self.ticks.entry(id).or_insert(deadline);
+        *tick = (*tick).min(deadline);
+    }
+
+    /// Unmounts and drops the widgets in the order specified
+    fn unmount(&mut self, ids: &[WidgetId]) {
+        for id in ids {
+            if self.mouse.hovered == Some(*id) {
+                self.mouse.hovered = None;
+            }
+            if matches!(&self.mouse.press, Some(press) if press.widget == *id) {
+                self.mouse.press = None;
+            }
+            self.ticks.remove(id);
+            if let Some(mut render_data) = self.render.remove(id) {
+                let mut args = UpdateArgs {
+                    id: *id,
+                    cursor: &mut render_data.cursor,
+                    tick: None,
+                };
+                render_data.widget.on_unmount(&mut args);
+            }
+        }
+    }
+
+    /// Removes the widgets that can no longer be reached from the root;
+    /// for example, those added with no parent after the root was set
+    fn collect_garbage(&mut self) {
+        let reachable: HashSet<WidgetId> = match self.graph.root {
+            Some(root) => self.graph.subtree(root).into_iter().collect(),
+            None => HashSet::new(),
+        };
+        if reachable.len() == self.render.len() {
+            return;
+        }
+
+        let mut unreachable: Vec<WidgetId> = self
+            .render
+            .keys()
+            .filter(|id| !reachable.contains(id))
+            .copied()
+            .collect();
+        // Unmount the children of each unreachable widget first
+        unreachable.sort_by_key(|id| std::cmp::Reverse(self.depth(*id)));
+        for id in &unreachable {
+            self.graph.children.remove(id);
+            self.graph.parent.remove(id);
+        }
+        if matches!(self.focused, Some(focus) if unreachable.contains(&focus)) {
+            self.focused = self.graph.root;
+        }
+        self.unmount(&unreachable);
+    }
+
+    /// Returns the number of ancestors of a widget
+    fn depth(&self, mut id: WidgetId) -> usize {
+        let mut depth = 0;
+        while let Some(parent) = self.graph.parent.get(&id) {
+            depth += 1;
+            id = *parent;
+        }
+        depth
+    }
+
+    /// Returns the widget with the specified id, provided that it is
+    /// of type `W` and that it implements `Widget::as_any`.
+    /// This allows the application to read the state of a widget,
+    /// such as the text entered into a `TextInput`.
+    pub fn widget<W: Widget + 'static>(&self, id: WidgetId) -> Option<&W> {
+        self.render.get(&id)?.widget.as_any()?.downcast_ref()
+    }
+
+    /// Returns the widget with the specified id, provided that it is
+    /// of type `W` and that it implements `Widget::as_any_mut`.
+    pub fn widget_mut<W: Widget + 'static>(&mut self, id: WidgetId) -> Option<&mut W> {
+        self.render
+            .get_mut(&id)?
+            .widget
+            .as_any_mut()?
+            .downcast_mut()
+    }
+
+    /// Calls `f` with the widget and a set of `UpdateArgs` for it,
+    /// scheduling any tick that it requests.
+    /// Returns `None` if there is no widget with the specified id.
+    fn update_widget<R>(
+        &mut self,
+        id: WidgetId,
+        f: impl FnOnce(&mut dyn Widget, &mut UpdateArgs) -> R,
+    ) -> Option<R> {
+        let render_data = self.render.get_mut(&id)?;
+        let mut args = UpdateArgs {
+            id,
+            cursor: &mut render_data.cursor,
+            tick: None,
         };
 
-        render_data.widget.process_event(event, &mut args)
+        let result = f(&mut *render_data.widget, &mut args);
+        if let Some(tick) = args.tick {
+            self.schedule_tick(id, tick);
+        }
+        Some(result)
+    }
+
+    fn do_deliver(&mut self, id: WidgetId, event: &WidgetEvent) -> bool {
+        self.update_widget(id, |widget, args| widget.process_event(event, args))
+            .unwrap_or(false)
     }
 
-    fn deliver_event(&mut self, mut id: WidgetId, event: &WidgetEvent) {
+    /// Delivers an event to a widget, and then to each of its ancestors
+    /// in turn until one of them handles it.  The coordinates of mouse
+    /// related events are screen relative, and are converted to be
+    /// relative to each widget.  Returns true if the event was handled.
+    fn deliver_event(&mut self, mut id: WidgetId, event: &WidgetEvent) -> bool {
         loop {
             let handled = match event {
                 WidgetEvent::Input(InputEvent::Resized { .. }) => true,
-                WidgetEvent::Input(InputEvent::Mouse(m)) => {
-                    let mut m = m.clone();
-                    // convert from screen to widget coords
-                    let coords = self.to_widget_coords(
-                        id,
-                        &ScreenRelativeCoords::new(m.x as usize, m.y as usize),
-                    );
-                    m.x = coords.x as u16;
-                    m.y = coords.y as u16;
-                    self.do_deliver(id, &WidgetEvent::Input(InputEvent::Mouse(m)))
-                }
-                WidgetEvent::Input(InputEvent::Paste(_))
-                | WidgetEvent::Input(InputEvent::PixelMouse(_))
-                | WidgetEvent::Input(InputEvent::Key(_))
-                | WidgetEvent::Input(InputEvent::Wake) => self.do_deliver(id, event),
+                _ => match event.mouse_coords() {
+                    Some(coords) => {
+                        // convert from screen to widget coords
+                        let coords = self
+                            .to_widget_coords(id, &ScreenRelativeCoords::new(coords.x, coords.y));
+                        self.do_deliver(id, &event.with_mouse_coords(coords))
+                    }
+                    None => self.do_deliver(id, event),
+                },
             };
 
             if handled {
-                return;
+                return true;
             }
 
             id = match self.graph.parent.get(&id) {
                 Some(parent) => *parent,
-                None => return,
+                None => return false,
             };
         }
     }
@@ -279,7 +784,7 @@ impl<'widget> Ui<'widget> {
 
     /// Recursive helper for hovered_widget().  The `best` tuple holds the
     /// best (depth, widget) pair.  Depth is incremented each time the function
-    /// recurses.
+    /// recurses.  The coordinates are relative to the parent of `widget`.
     fn hovered_recursive(
         &self,
         widget: WidgetId,
@@ -290,27 +795,29 @@ impl<'widget> Ui<'widget> {
     ) {
         let render = &self.render[&widget];
 
-        // only consider the dimensions if this node is at the same or a deeper
-        // depth.  If so, then we check to see if the coords are within the bounds.
-        if depth >= best.0 && x >= render.coordinates.x && y >= render.coordinates.y {
-            let (width, height) = render.surface.dimensions();
+        // Children are positioned within their parent, so if the
+        // coords are outside of this widget they can't be in a child
+        if x < render.coordinates.x || y < render.coordinates.y {
+            return;
+        }
+        let (x, y) = (x - render.coordinates.x, y - render.coordinates.y);
+        let (width, height) = render.surface.dimensions();
+        if x >= width || y >= height {
+            return;
+        }
 
-            if (x - render.coordinates.x < width) && (y - render.coordinates.y < height) {
-                *best = (depth, widget);
-            }
+        // only consider this node if it is at the same or a deeper depth
+        if depth >= best.0 {
+            *best = (depth, widget);
         }
 
         for child in self.graph.children(widget) {
-            self.hovered_recursive(
-                *child,
-                depth + 1,
-                x + render.coordinates.x,
-                y + render.coordinates.y,
-                best,
-            );
+            self.hovered_recursive(*child, depth + 1, x, y, best);
         }
     }
 
+    /// Processes the queued events, followed by the events sent via
+    /// `UiEventSender`s and then the ticks that are due
     pub fn process_event_queue(&mut self) -> Result<()> {
         while let Some(event) = self.input_queue.pop_front() {
             match event {
@@ -318,36 +825,382 @@ impl<'widget> Ui<'widget> {
                     self.compute_layout(cols, rows)?;
                 }
                 WidgetEvent::Input(InputEvent::Mouse(ref m)) => {
-                    if let Some(hover) =
-                        self.hovered_widget(&ScreenRelativeCoords::new(m.x as usize, m.y as usize))
-                    {
+                    let coords = ScreenRelativeCoords::new(m.x as usize, m.y as usize);
+                    let hover = self.hovered_widget(&coords);
+                    self.set_hovered(hover);
+                    if let Some(hover) = hover {
+                        self.deliver_event(hover, &event);
+                    }
+                    self.synthesize_mouse_events(m.mouse_buttons.clone(), coords, hover);
+                }
+                WidgetEvent::Input(InputEvent::Key(ref key)) => {
+                    let handled = match self.focused {
+                        Some(focus) => self.deliver_event(focus, &event),
+                        None => false,
+                    };
+                    if !handled && !key.is_release() {
+                        match (&key.key, key.modifiers) {
+                            (KeyCode::Tab, Modifiers::NONE) => self.focus_next(),
+                            (KeyCode::Tab, Modifiers::SHIFT) => self.focus_previous(),
+                            _ => {}
+                        }
+                    }
+                }
+                WidgetEvent::Click { coords, .. }
+                | WidgetEvent::DragStart { coords, .. }
+                | WidgetEvent::Drag { coords, .. }
+                | WidgetEvent::DragEnd { coords, .. } => {
+                    let screen = ScreenRelativeCoords::new(coords.x, coords.y);
+                    if let Some(hover) = self.hovered_widget(&screen) {
                         self.deliver_event(hover, &event);
                     }
                 }
-                WidgetEvent::Input(InputEvent::Key(_))
-                | WidgetEvent::Input(InputEvent::Paste(_))
+                WidgetEvent::Input(InputEvent::Paste(_))
                 | WidgetEvent::Input(InputEvent::PixelMouse(_))
-                | WidgetEvent::Input(InputEvent::Wake) => {
+                | WidgetEvent::Input(InputEvent::Wake)
+                | WidgetEvent::Input(InputEvent::FocusGained)
+                | WidgetEvent::Input(InputEvent::FocusLost)
+                | WidgetEvent::Input(InputEvent::TerminalResponse(_))
+                | WidgetEvent::MouseEnter
+                | WidgetEvent::MouseLeave
+                | WidgetEvent::FocusGained
+                | WidgetEvent::FocusLost
+                | WidgetEvent::Tick
+                | WidgetEvent::User(_) => {
                     if let Some(focus) = self.focused {
                         self.deliver_event(focus, &event);
                     }
                 }
             }
         }
+
+        self.user_events.receive();
+        while let Some((id, value)) = self.user_events.pending.pop_front() {
+            if self.render.contains_key(&id) {
+                self.deliver_event(id, &WidgetEvent::User(value));
+            }
+        }
+
+        // Ticks requested while delivering these are left for the next
+        // call, so that a widget can't starve the loop with zero delays
+        let now = Instant::now();
+        let mut due: Vec<(Instant, WidgetId)> = self
+            .ticks
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(id, deadline)| (*deadline, *id))
+            .collect();
+        due.sort();
+        for (_, id) in &due {
+            self.ticks.remove(id);
+        }
+        for (_, id) in due {
+            self.do_deliver(id, &WidgetEvent::Tick);
+        }
+
         Ok(())
     }
 
+    /// Returns the time at which the next tick is due, if any widget
+    /// has requested one
+    pub fn next_tick(&self) -> Option<Instant> {
+        self.ticks.values().min().copied()
+    }
+
+    /// Returns a sender that can be used to deliver events to the
+    /// widgets from other threads
+    pub fn event_sender(&self) -> UiEventSender {
+        UiEventSender {
+            sender: self.user_events.sender.clone(),
+            waker: Arc::clone(&self.user_events.waker),
+        }
+    }
+
+    /// Waits for input from the terminal for up to `wait`, or
+    /// indefinitely if `wait` is `None`.  The wait ends early when a
+    /// widget is due a tick or an event is sent via a `UiEventSender`,
+    /// in which case `Ok(None)` is returned; call `process_event_queue`
+    /// to deliver them.  Input is returned so that the application can
+    /// act on it before passing it to `queue_event`.
+    ///
+    /// This allows the run loop of an application to sleep until there
+    /// is something to do:
+    ///
+    /// ```no_run
+    /// # use termwiz::widgets::{Ui, WidgetEvent};
+    /// # use termwiz::terminal::buffered::BufferedTerminal;
+    /// # use termwiz::terminal::Terminal;
+    /// # fn run<T: Terminal>(ui: &mut Ui, buf: &mut BufferedTerminal<T>) -> termwiz::Result<()> {
+    /// loop {
+    ///     ui.process_event_queue()?;
+    ///     if ui.render_to_screen(buf)? {
+    ///         continue;
+    ///     }
+    ///     buf.flush()?;
+    ///     if let Some(input) = ui.poll_input(buf.terminal(), None)? {
+    ///         ui.queue_event(WidgetEvent::Input(input));
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn poll_input<T: Terminal + ?Sized>(
+        &mut self,
+        terminal: &mut T,
+        wait: Option<Duration>,
+    ) -> Result<Option<InputEvent>> {
+        self.user_events
+            .waker
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| terminal.waker());
+
+        self.user_events.receive();
+        if !self.user_events.pending.is_empty() {
+            return Ok(None);
+        }
+
+        let wait = match self.next_tick() {
+            Some(tick) => {
+                let until_tick = tick.saturating_duration_since(Instant::now());
+                Some(wait.map_or(until_tick, |wait| wait.min(until_tick)))
+            }
+            None => wait,
+        };
+
+        match terminal.poll_input(wait)? {
+            Some(InputEvent::Wake) | None => Ok(None),
+            Some(input) => Ok(Some(input)),
+        }
+    }
+
+    /// Sends `MouseLeave` and `MouseEnter` events when the widget
+    /// under the mouse changes.  These events don't propagate to the
+    /// parent of the widget.
+    fn set_hovered(&mut self, hover: Option<WidgetId>) {
+        if hover == self.mouse.hovered {
+            return;
+        }
+        if let Some(prior) = self.mouse.hovered.take() {
+            self.do_deliver(prior, &WidgetEvent::MouseLeave);
+        }
+        if let Some(hover) = hover {
+            self.do_deliver(hover, &WidgetEvent::MouseEnter);
+        }
+        self.mouse.hovered = hover;
+    }
+
+    /// Tracks the mouse buttons, synthesizing click and drag events
+    /// from the sequence of mouse reports
+    fn synthesize_mouse_events(
+        &mut self,
+        buttons: MouseButtons,
+        coords: ScreenRelativeCoords,
+        hover: Option<WidgetId>,
+    ) {
+        let button = [
+            MouseButtons::LEFT,
+            MouseButtons::MIDDLE,
+            MouseButtons::RIGHT,
+        ]
+        .iter()
+        .find(|button| buttons.contains((*button).clone()))
+        .cloned();
+        let widget_coords = ParentRelativeCoords::new(coords.x, coords.y);
+
+        match (self.mouse.press.take(), button) {
+            (None, Some(button)) => {
+                if let Some(widget) = hover {
+                    self.focus_from(widget);
+                    self.mouse.press = Some(MousePress {
+                        button,
+                        coords,
+                        last: coords,
+                        widget,
+                        dragging: false,
+                    });
+                }
+            }
+            (Some(mut press), Some(_)) => {
+                if coords != press.last {
+                    if !press.dragging {
+                        press.dragging = true;
+                        let start = ParentRelativeCoords::new(press.coords.x, press.coords.y);
+                        self.deliver_event(
+                            press.widget,
+                            &WidgetEvent::DragStart {
+                                coords: start,
+                                button: press.button.clone(),
+                            },
+                        );
+                    }
+                    press.last = coords;
+                    self.deliver_event(
+                        press.widget,
+                        &WidgetEvent::Drag {
+                            coords: widget_coords,
+                            button: press.button.clone(),
+                        },
+                    );
+                }
+                // The press may have been cancelled by the widget
+                // having been removed while handling the event
+                if self.render.contains_key(&press.widget) {
+                    self.mouse.press = Some(press);
+                }
+            }
+            (Some(press), None) => {
+                if press.dragging {
+                    self.deliver_event(
+                        press.widget,
+                        &WidgetEvent::DragEnd {
+                            coords: widget_coords,
+                            button: press.button.clone(),
+                        },
+                    );
+                    return;
+                }
+
+                let now = Instant::now();
+                let streak = match self.mouse.last_click.take() {
+                    Some(last)
+                        if last.button == press.button
+                            && last.coords == coords
+                            && now.duration_since(last.time) < MULTI_CLICK_INTERVAL =>
+                    {
+                        last.streak + 1
+                    }
+                    _ => 1,
+                };
+                self.mouse.last_click = Some(MouseClick {
+                    button: press.button.clone(),
+                    coords,
+                    time: now,
+                    streak,
+                });
+                self.deliver_event(
+                    press.widget,
+                    &WidgetEvent::Click {
+                        coords: widget_coords,
+                        button: press.button.clone(),
+                        streak,
+                    },
+                );
+            }
+            (None, None) => {}
+        }
+    }
+
     /// Queue up an event.  Events are processed by the appropriate
-    /// `Widget::update_state` method.  Events may be re-processed to
-    /// simplify handling for widgets. eg: a TODO: is to synthesize double
-    /// and triple click events.
+    /// `Widget::process_event` method.  Mouse reports are also used to
+    /// synthesize click, drag and hover events, and the Tab key moves
+    /// the focus when the focused widget doesn't handle it.
     pub fn queue_event(&mut self, event: WidgetEvent) {
         self.input_queue.push_back(event);
     }
 
     /// Assign keyboard focus to the specified widget.
+    /// The previously focused widget receives `WidgetEvent::FocusLost`
+    /// and the newly focused widget `WidgetEvent::FocusGained`.
     pub fn set_focus(&mut self, id: WidgetId) {
-        self.focused = Some(id);
+        if self.focused == Some(id) {
+            return;
+        }
+        if let Some(prior) = self.focused.replace(id) {
+            self.do_deliver(prior, &WidgetEvent::FocusLost);
+        }
+        self.do_deliver(id, &WidgetEvent::FocusGained);
+    }
+
+    /// Returns the focused widget
+    pub fn focused(&self) -> Option<WidgetId> {
+        self.focused
+    }
+
+    /// Focuses the nearest widget, starting with `id` and then its
+    /// ancestors, that accepts focus
+    fn focus_from(&mut self, mut id: WidgetId) {
+        loop {
+            if self.render[&id].widget.accepts_focus() {
+                self.set_focus(id);
+                return;
+            }
+            id = match self.graph.parent.get(&id) {
+                Some(parent) => *parent,
+                None => return,
+            };
+        }
+    }
+
+    /// Returns the widgets that accept focus, in the order in which
+    /// they are visited by `focus_next`
+    fn focus_order(&self) -> Vec<WidgetId> {
+        let mut order = vec![];
+        let mut stack: Vec<WidgetId> = self.graph.root.into_iter().collect();
+        while let Some(id) = stack.pop() {
+            if self.render[&id].widget.accepts_focus() {
+                order.push(id);
+            }
+            stack.extend(self.graph.children(id).iter().rev());
+        }
+        order
+    }
+
+    /// Moves focus to the next widget that accepts focus, in the order
+    /// in which the widgets were added to their parents, with each
+    /// widget preceding its children.  After the last such widget,
+    /// focus returns to the first.  The widgets owned by container
+    /// widgets are visited in turn via `Widget::move_focus`.
+    pub fn focus_next(&mut self) {
+        if self.move_focus_within(FocusMovement::Next) {
+            return;
+        }
+        let order = self.focus_order();
+        let next = match self
+            .focused
+            .and_then(|focus| order.iter().position(|id| *id == focus))
+        {
+            Some(idx) => order.get(idx + 1).or_else(|| order.first()),
+            None => order.first(),
+        };
+        if let Some(next) = next.copied() {
+            self.update_widget(next, |widget, args| {
+                widget.move_focus(FocusMovement::First, args)
+            });
+            self.set_focus(next);
+        }
+    }
+
+    /// Moves focus to the previous widget in the order used by
+    /// `focus_next`
+    pub fn focus_previous(&mut self) {
+        if self.move_focus_within(FocusMovement::Previous) {
+            return;
+        }
+        let order = self.focus_order();
+        let prev = match self
+            .focused
+            .and_then(|focus| order.iter().position(|id| *id == focus))
+        {
+            Some(0) | None => order.last(),
+            Some(idx) => order.get(idx - 1),
+        };
+        if let Some(prev) = prev.copied() {
+            self.update_widget(prev, |widget, args| {
+                widget.move_focus(FocusMovement::Last, args)
+            });
+            self.set_focus(prev);
+        }
+    }
+
+    /// Gives the focused widget the chance to move the focus among
+    /// the widgets that it owns.  Returns true if it did so.
+    fn move_focus_within(&mut self, movement: FocusMovement) -> bool {
+        match self.focused {
+            Some(focus) => self
+                .update_widget(focus, |widget, args| widget.move_focus(movement, args))
+                .unwrap_or(false),
+            None => false,
+        }
     }
 
     /// Helper for applying the surfaces from the widgets to the target
@@ -393,9 +1246,13 @@ impl<'widget> Ui<'widget> {
     /// Reconsider the layout constraints and apply them.
     /// Returns true if the layout was changed, false if no changes were made.
     fn compute_layout(&mut self, width: usize, height: usize) -> Result<bool> {
+        self.layout_size = Some((width, height));
         let mut layout = layout::LayoutState::new();
 
-        let root = self.graph.root.unwrap();
+        let root = match self.graph.root {
+            Some(root) => root,
+            None => return Ok(false),
+        };
         self.add_widget_to_layout(&mut layout, root)?;
         let mut changed = false;
 
@@ -437,12 +1294,20 @@ impl<'widget> Ui<'widget> {
     }
 
     /// Apply the current state of the widgets to the screen.
+    /// The widgets are laid out again first if the size of `screen`
+    /// differs from the size they were last laid out for.
     /// This has the side effect of clearing out any unconsumed input queue.
     /// Returns true if the Ui may need to be updated again; for example,
     /// if the most recent update operation changed layout.
     pub fn render_to_screen(&mut self, screen: &mut Surface) -> Result<bool> {
         if let Some(root) = self.graph.root {
             let (width, height) = screen.dimensions();
+            // Lay the widgets out before rendering them for the first
+            // time, or at a new size, so that they aren't rendered at
+            // a size that is about to change
+            if self.layout_size != Some((width, height)) {
+                self.compute_layout(width, height)?;
+            }
             // Render from scratch into a fresh screen buffer
             let mut alt_screen = Surface::new(width, height);
             self.render_recursive(root, &mut alt_screen, &ScreenRelativeCoords::new(0, 0))?;
@@ -450,7 +1315,7 @@ impl<'widget> Ui<'widget> {
             let diff = screen.diff_screens(&alt_screen);
             screen.add_changes(diff);
         }
-        // TODO: garbage collect unreachable WidgetId's from self.state
+        self.collect_garbage();
 
         if let Some(id) = self.focused {
             let cursor = &self.render[&id].cursor;
@@ -510,7 +1375,9 @@ impl<'widget> Ui<'widget> {
         widget: WidgetId,
         coords: &ScreenRelativeCoords,
     ) -> ParentRelativeCoords {
-        let (x, y) = self.coord_walk(widget, coords.x, coords.y, |a, b| a - b);
+        // The coordinates may be beyond the top left of the widget,
+        // for example while dragging; those are clamped to its edge
+        let (x, y) = self.coord_walk(widget, coords.x, coords.y, usize::saturating_sub);
         ParentRelativeCoords { x, y }
     }
 }
@@ -537,4 +1404,377 @@ mod test {
         ui.render_to_screen(&mut surface).unwrap();
         assert_eq!(CursorVisibility::Hidden, surface.cursor_visibility());
     }
+
+    /// Records the dimensions of the surface that it is rendered into
+    struct SizeLogger(std::rc::Rc<std::cell::RefCell<Vec<(usize, usize)>>>);
+
+    impl Widget for SizeLogger {
+        fn render(&mut self, args: &mut RenderArgs) {
+            self.0.borrow_mut().push(args.surface.dimensions());
+        }
+    }
+
+    #[test]
+    fn layout_follows_screen_size() {
+        let log = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut ui = Ui::new();
+        ui.set_root(SizeLogger(log.clone()));
+
+        // No resize event is needed for the widgets to be laid out
+        // at the size of the screen, before they are rendered
+        ui.render_to_screen(&mut Surface::new(10, 4)).unwrap();
+        ui.render_to_screen(&mut Surface::new(10, 4)).unwrap();
+        ui.render_to_screen(&mut Surface::new(20, 6)).unwrap();
+        assert_eq!(*log.borrow(), vec![(10, 4), (10, 4), (20, 6)]);
+    }
+    /// Records when it is mounted and unmounted
+    struct Lifecycle<'a> {
+        name: &'static str,
+        log: &'a std::cell::RefCell<Vec<String>>,
+    }
+
+    impl<'a> Widget for Lifecycle<'a> {
+        fn render(&mut self, _args: &mut RenderArgs) {}
+
+        fn on_mount(&mut self, _args: &mut UpdateArgs) {
+            self.log.borrow_mut().push(format!("mount {}", self.name));
+        }
+
+        fn on_unmount(&mut self, _args: &mut UpdateArgs) {
+            self.log.borrow_mut().push(format!("unmount {}", self.name));
+        }
+    }
+
+    #[test]
+    fn remove_and_replace() {
+        let log = std::cell::RefCell::new(vec![]);
+        let widget = |name| Lifecycle { name, log: &log };
+        let mut ui = Ui::new();
+
+        let root = ui.set_root(widget("root"));
+        let a = ui.add_child(root, widget("a"));
+        let a1 = ui.add_child(a, widget("a1"));
+        let b = ui.add_child(root, widget("b"));
+        ui.set_focus(a1);
+        log.borrow_mut().clear();
+
+        assert!(ui.remove(a));
+        assert!(!ui.remove(a1));
+        assert_eq!(*log.borrow(), vec!["unmount a1", "unmount a"]);
+        assert_eq!(ui.graph.children(root), &[b]);
+        assert_eq!(ui.focused, Some(root));
+        assert!(!ui.contains(a1));
+        log.borrow_mut().clear();
+
+        let b1 = ui.add_child(b, widget("b1"));
+        let c = ui.add_child(root, widget("c"));
+        assert!(ui.replace(b, widget("d")));
+        assert_eq!(
+            *log.borrow(),
+            vec!["mount b1", "mount c", "unmount b1", "unmount b", "mount d"]
+        );
+        assert_eq!(ui.graph.children(root), &[b, c]);
+        assert!(!ui.contains(b1));
+        log.borrow_mut().clear();
+
+        // A second parentless widget can never be rendered,
+        // so it is collected when the screen is next rendered
+        ui.add(None, widget("orphan"));
+        let mut surface = Surface::new(10, 10);
+        ui.render_to_screen(&mut surface).unwrap();
+        assert_eq!(*log.borrow(), vec!["mount orphan", "unmount orphan"]);
+        assert_eq!(ui.render.len(), 3);
+        log.borrow_mut().clear();
+
+        assert!(ui.remove(root));
+        assert_eq!(ui.focused, None);
+        assert!(ui.render.is_empty());
+        ui.render_to_screen(&mut surface).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            vec!["unmount d", "unmount c", "unmount root"]
+        );
+    }
+
+    /// Records the events that it receives
+    struct Recorder<'a> {
+        name: &'static str,
+        log: &'a std::cell::RefCell<Vec<String>>,
+        focusable: bool,
+        handles_keys: bool,
+        width: u16,
+    }
+
+    impl<'a> Widget for Recorder<'a> {
+        fn render(&mut self, _args: &mut RenderArgs) {}
+
+        fn get_size_constraints(&self) -> layout::Constraints {
+            let mut c = layout::Constraints::default();
+            c.set_fixed_width(self.width);
+            c
+        }
+
+        fn accepts_focus(&self) -> bool {
+            self.focusable
+        }
+
+        fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+            let entry = match event {
+                WidgetEvent::Input(InputEvent::Key(crate::input::KeyEvent {
+                    key: KeyCode::Char(c),
+                    ..
+                })) if self.handles_keys => format!("key {}", c),
+                WidgetEvent::Input(_) => return false,
+                WidgetEvent::Click { coords, streak, .. } => {
+                    format!("click {},{} x{}", coords.x, coords.y, streak)
+                }
+                WidgetEvent::DragStart { coords, .. } => {
+                    format!("drag start {},{}", coords.x, coords.y)
+                }
+                WidgetEvent::Drag { coords, .. } => format!("drag {},{}", coords.x, coords.y),
+                WidgetEvent::DragEnd { coords, .. } => {
+                    format!("drag end {},{}", coords.x, coords.y)
+                }
+                WidgetEvent::MouseEnter => "enter".to_string(),
+                WidgetEvent::MouseLeave => "leave".to_string(),
+                WidgetEvent::FocusGained => "focus".to_string(),
+                WidgetEvent::FocusLost => "blur".to_string(),
+                WidgetEvent::Tick => "tick".to_string(),
+                WidgetEvent::User(value) => match value.downcast_ref::<u32>() {
+                    Some(value) => format!("user {}", value),
+                    None => return false,
+                },
+            };
+            self.log
+                .borrow_mut()
+                .push(format!("{} {}", self.name, entry));
+            true
+        }
+    }
+
+    fn mouse(x: u16, y: u16, mouse_buttons: MouseButtons) -> WidgetEvent {
+        WidgetEvent::Input(InputEvent::Mouse(crate::input::MouseEvent {
+            x,
+            y,
+            mouse_buttons,
+            modifiers: Modifiers::NONE,
+        }))
+    }
+
+    fn key(key: KeyCode, modifiers: Modifiers) -> WidgetEvent {
+        WidgetEvent::Input(InputEvent::Key(crate::input::KeyEvent::new(key, modifiers)))
+    }
+
+    #[test]
+    fn synthesized_mouse_events() {
+        let log = std::cell::RefCell::new(vec![]);
+        let widget = |name, width| Recorder {
+            name,
+            log: &log,
+            focusable: true,
+            handles_keys: false,
+            width,
+        };
+        let mut ui = Ui::new();
+        let root = ui.set_root(widget("root", 10));
+        let a = ui.add_child(root, widget("a", 4));
+        let b = ui.add_child(root, widget("b", 6));
+        // The root is focused when it is added
+        assert_eq!(std::mem::take(&mut *log.borrow_mut()), vec!["root focus"]);
+        let mut surface = Surface::new(10, 2);
+        ui.render_to_screen(&mut surface).unwrap();
+        assert_eq!(
+            ui.to_screen_coords(b, &ParentRelativeCoords::new(0, 0)).x,
+            4
+        );
+
+        let events = |ui: &mut Ui, events: Vec<WidgetEvent>| {
+            for event in events {
+                ui.queue_event(event);
+            }
+            ui.process_event_queue().unwrap();
+            std::mem::take(&mut *log.borrow_mut())
+        };
+
+        assert_eq!(
+            events(
+                &mut ui,
+                vec![
+                    mouse(1, 0, MouseButtons::NONE),
+                    mouse(1, 0, MouseButtons::LEFT),
+                    mouse(1, 0, MouseButtons::NONE),
+                    mouse(1, 0, MouseButtons::LEFT),
+                    mouse(1, 0, MouseButtons::NONE),
+                ]
+            ),
+            vec![
+                "a enter",
+                "root blur",
+                "a focus",
+                "a click 1,0 x1",
+                "a click 1,0 x2"
+            ]
+        );
+        assert_eq!(ui.focused, Some(a));
+
+        // Dragging from a into b keeps delivering to a
+        assert_eq!(
+            events(
+                &mut ui,
+                vec![
+                    mouse(2, 1, MouseButtons::LEFT),
+                    mouse(3, 1, MouseButtons::LEFT),
+                    mouse(5, 1, MouseButtons::LEFT),
+                    mouse(5, 1, MouseButtons::NONE),
+                ]
+            ),
+            vec![
+                "a drag start 2,1",
+                "a drag 3,1",
+                "a leave",
+                "b enter",
+                "a drag 5,1",
+                "a drag end 5,1",
+            ]
+        );
+
+        // Clicking b moves the focus to it, and the streak
+        // is broken by clicking elsewhere
+        assert_eq!(
+            events(
+                &mut ui,
+                vec![
+                    mouse(5, 0, MouseButtons::RIGHT),
+                    mouse(5, 0, MouseButtons::NONE),
+                ]
+            ),
+            vec!["a blur", "b focus", "b click 1,0 x1"]
+        );
+    }
+
+    #[test]
+    fn focus_traversal_and_bubbling() {
+        let log = std::cell::RefCell::new(vec![]);
+        let widget = |name, focusable, handles_keys| Recorder {
+            name,
+            log: &log,
+            focusable,
+            handles_keys,
+            width: 2,
+        };
+        let mut ui = Ui::new();
+        let root = ui.set_root(widget("root", false, true));
+        let a = ui.add_child(root, widget("a", true, false));
+        let label = ui.add_child(root, widget("label", false, false));
+        let b = ui.add_child(label, widget("b", true, false));
+        let c = ui.add_child(root, widget("c", true, false));
+
+        let mut focus_after = |event: WidgetEvent| {
+            ui.queue_event(event);
+            ui.process_event_queue().unwrap();
+            ui.focused()
+        };
+
+        assert_eq!(focus_after(key(KeyCode::Tab, Modifiers::NONE)), Some(a));
+        assert_eq!(focus_after(key(KeyCode::Tab, Modifiers::NONE)), Some(b));
+        assert_eq!(focus_after(key(KeyCode::Tab, Modifiers::NONE)), Some(c));
+        assert_eq!(focus_after(key(KeyCode::Tab, Modifiers::NONE)), Some(a));
+        assert_eq!(focus_after(key(KeyCode::Tab, Modifiers::SHIFT)), Some(c));
+        assert_eq!(focus_after(key(KeyCode::Tab, Modifiers::SHIFT)), Some(b));
+
+        // The focused widget doesn't handle keys,
+        // so they bubble up to the root
+        log.borrow_mut().clear();
+        assert_eq!(
+            focus_after(key(KeyCode::Char('x'), Modifiers::NONE)),
+            Some(b)
+        );
+        assert_eq!(*log.borrow(), vec!["root key x"]);
+    }
+
+    /// Counts its ticks, requesting another until it has had three
+    struct Ticker(std::rc::Rc<std::cell::Cell<usize>>);
+
+    impl Widget for Ticker {
+        fn render(&mut self, _args: &mut RenderArgs) {}
+
+        fn on_mount(&mut self, args: &mut UpdateArgs) {
+            args.request_tick(Duration::from_secs(0));
+        }
+
+        fn process_event(&mut self, event: &WidgetEvent, args: &mut UpdateArgs) -> bool {
+            if let WidgetEvent::Tick = event {
+                self.0.set(self.0.get() + 1);
+                if self.0.get() < 3 {
+                    args.request_tick(Duration::from_secs(0));
+                }
+                return true;
+            }
+            false
+        }
+    }
+
+    #[test]
+    fn ticks() {
+        let count = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut ui = Ui::new();
+        // The frame passes the ticks through to the widget within it
+        ui.set_root(Frame::new("", Ticker(count.clone())));
+        assert!(ui.next_tick().is_some());
+
+        // A tick requested while delivering ticks waits for the next call
+        for expect in 1..=3 {
+            ui.process_event_queue().unwrap();
+            assert_eq!(count.get(), expect);
+        }
+        assert!(ui.next_tick().is_none());
+        ui.process_event_queue().unwrap();
+        assert_eq!(count.get(), 3);
+    }
+
+    #[test]
+    fn user_events() {
+        let log = std::cell::RefCell::new(vec![]);
+        let mut ui = Ui::new();
+        let root = ui.set_root(Recorder {
+            name: "root",
+            log: &log,
+            focusable: false,
+            handles_keys: false,
+            width: 1,
+        });
+        let child = ui.add_child(
+            root,
+            Recorder {
+                name: "child",
+                log: &log,
+                focusable: false,
+                handles_keys: false,
+                width: 1,
+            },
+        );
+        log.borrow_mut().clear();
+
+        let sender = ui.event_sender();
+        std::thread::spawn(move || {
+            sender.send(child, 1u32).unwrap();
+            // Neither widget handles this, so it is discarded
+            sender.send(child, "text").unwrap();
+            sender.send(root, 2u32).unwrap();
+        })
+        .join()
+        .unwrap();
+        ui.process_event_queue().unwrap();
+        assert_eq!(*log.borrow(), vec!["child user 1", "root user 2"]);
+
+        log.borrow_mut().clear();
+        ui.event_sender().send(child, 3u32).unwrap();
+        ui.remove(child);
+        ui.process_event_queue().unwrap();
+        assert!(log.borrow().is_empty());
+
+        let sender = ui.event_sender();
+        drop(ui);
+        assert!(sender.send(root, 4u32).is_err());
+    }
 }
@@ -0,0 +1,150 @@
+//! Support for container widgets that own and arrange other widgets
+use crate::surface::{SequenceNo, Surface};
+use crate::widgets::{
+    CursorShapeAndPosition, FocusMovement, ParentRelativeCoords, Rect, RenderArgs, UpdateArgs,
+    Widget, WidgetEvent,
+};
+use std::time::Instant;
+
+/// A widget owned by a container widget, along with the surface
+/// that it renders into and the area of the container that it occupies
+pub(crate) struct Pane<W> {
+    pub widget: W,
+    surface: Surface,
+    cursor: CursorShapeAndPosition,
+    rect: Rect,
+    /// Whether the widget was most recently told that it has focus
+    has_focus: bool,
+    /// When the tick requested by the widget, if any, is due
+    tick: Option<Instant>,
+}
+
+impl<W: Widget> Pane<W> {
+    pub fn new(widget: W) -> Self {
+        Self {
+            widget,
+            surface: Surface::new(1, 1),
+            cursor: Default::default(),
+            rect: Default::default(),
+            has_focus: false,
+            tick: None,
+        }
+    }
+
+    /// Returns true if the container relative coordinates
+    /// are within the area most recently occupied by the pane
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.rect.x
+            && y >= self.rect.y
+            && x < self.rect.x + self.rect.width
+            && y < self.rect.y + self.rect.height
+    }
+
+    /// Renders the widget into `rect` of the container's surface.
+    /// When `focused` is true the widget is told that it has focus if
+    /// the container does, and its cursor becomes the container's cursor.
+    pub fn render(&mut self, args: &mut RenderArgs, rect: Rect, focused: bool) {
+        self.rect = rect;
+        if self.rect.width == 0 || self.rect.height == 0 {
+            return;
+        }
+        if self.surface.dimensions() != (self.rect.width, self.rect.height) {
+            self.surface.resize(self.rect.width, self.rect.height);
+        }
+
+        self.widget.render(&mut RenderArgs {
+            id: args.id,
+            is_focused: args.is_focused && focused,
+            cursor: &mut self.cursor,
+            surface: &mut self.surface,
+        });
+        args.surface
+            .draw_from_screen(&self.surface, self.rect.x, self.rect.y);
+        self.surface.flush_changes_older_than(SequenceNo::MAX);
+
+        if focused {
+            *args.cursor = CursorShapeAndPosition {
+                coords: (
+                    self.cursor.coords.x + self.rect.x,
+                    self.cursor.coords.y + self.rect.y,
+                )
+                    .into(),
+                ..self.cursor.clone()
+            };
+        }
+    }
+
+    /// Calls `f` with the widget and a set of `UpdateArgs` for it,
+    /// recording any tick that it requests and passing it on to the
+    /// container
+    fn update<R>(
+        &mut self,
+        args: &mut UpdateArgs,
+        f: impl FnOnce(&mut W, &mut UpdateArgs) -> R,
+    ) -> R {
+        let mut pane_args = UpdateArgs {
+            id: args.id,
+            cursor: &mut self.cursor,
+            tick: None,
+        };
+        let result = f(&mut self.widget, &mut pane_args);
+        if let Some(tick) = pane_args.tick {
+            self.tick = Some(self.tick.map_or(tick, |t| t.min(tick)));
+            args.request_tick_at(tick);
+        }
+        result
+    }
+
+    /// Passes an event to the widget, converting the coordinates of
+    /// mouse events to be relative to the pane.  The container's ticks
+    /// are only passed on once the tick requested by the widget is due;
+    /// until then the container is asked for another at that time.
+    pub fn process_event(&mut self, event: &WidgetEvent, args: &mut UpdateArgs) -> bool {
+        if let WidgetEvent::Tick = event {
+            match self.tick {
+                Some(tick) if tick <= Instant::now() => self.tick = None,
+                Some(tick) => {
+                    args.request_tick_at(tick);
+                    return false;
+                }
+                None => return false,
+            }
+        }
+        let event = match event.mouse_coords() {
+            Some(coords) => event.with_mouse_coords(ParentRelativeCoords::new(
+                coords.x.saturating_sub(self.rect.x),
+                coords.y.saturating_sub(self.rect.y),
+            )),
+            None => event.clone(),
+        };
+        self.update(args, |widget, args| widget.process_event(&event, args))
+    }
+
+    /// Tells the widget whether it has the focus, via
+    /// `WidgetEvent::FocusGained` or `WidgetEvent::FocusLost`,
+    /// if that has changed
+    pub fn set_focus(&mut self, focus: bool, args: &mut UpdateArgs) {
+        if focus == self.has_focus {
+            return;
+        }
+        self.has_focus = focus;
+        let event = if focus {
+            WidgetEvent::FocusGained
+        } else {
+            WidgetEvent::FocusLost
+        };
+        self.update(args, |widget, args| widget.process_event(&event, args));
+    }
+
+    pub fn move_focus(&mut self, movement: FocusMovement, args: &mut UpdateArgs) -> bool {
+        self.update(args, |widget, args| widget.move_focus(movement, args))
+    }
+
+    pub fn on_mount(&mut self, args: &mut UpdateArgs) {
+        self.update(args, |widget, args| widget.on_mount(args))
+    }
+
+    pub fn on_unmount(&mut self, args: &mut UpdateArgs) {
+        self.update(args, |widget, args| widget.on_unmount(args))
+    }
+}
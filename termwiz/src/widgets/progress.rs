@@ -0,0 +1,167 @@
+use crate::cell::{unicode_column_width, CellAttributes};
+use crate::surface::{Change, CursorVisibility};
+use crate::widgets::layout::Constraints;
+use crate::widgets::text::print_at;
+use crate::widgets::{RenderArgs, Widget};
+use std::any::Any;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The block elements that fill 1/8th through 7/8ths of a cell
+const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// A horizontal bar showing the progress of an operation.
+/// The bar is drawn in the foreground color of its attributes,
+/// using block elements to show the progress in eighths of a cell.
+pub struct ProgressBar {
+    progress: f64,
+    label: Option<String>,
+    attributes: CellAttributes,
+    constraints: Constraints,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        Self {
+            progress: 0.0,
+            label: None,
+            attributes: CellAttributes::default(),
+            constraints: *Constraints::default().set_fixed_height(1),
+        }
+    }
+
+    /// Returns the progress, from 0.0 to 1.0
+    pub fn progress(&self) -> f64 {
+        self.progress
+    }
+
+    /// Sets the progress, from 0.0 to 1.0
+    pub fn set_progress(&mut self, progress: f64) {
+        self.progress = if progress.is_nan() {
+            0.0
+        } else {
+            progress.clamp(0.0, 1.0)
+        };
+    }
+
+    /// Sets the text shown centered over the bar.
+    /// When no label is set, the percentage is shown.
+    pub fn set_label(&mut self, label: Option<&str>) {
+        self.label = label.map(str::to_string);
+    }
+
+    pub fn set_attributes(&mut self, attributes: CellAttributes) {
+        self.attributes = attributes;
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+}
+
+impl Widget for ProgressBar {
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, height) = args.surface.dimensions();
+        args.surface
+            .add_change(Change::ClearScreen(self.attributes.background()));
+        args.cursor.visibility = CursorVisibility::Hidden;
+
+        let eighths = (self.progress * (width * 8) as f64).round() as usize;
+        let (full, partial) = (eighths / 8, eighths % 8);
+        let mut bar = "█".repeat(full);
+        if partial > 0 {
+            bar.push(PARTIAL_BLOCKS[partial - 1]);
+        }
+        let y = height / 2;
+        print_at(args.surface, 0, y, &bar, &self.attributes);
+
+        // Draw the label in reverse video where it overlaps the bar,
+        // so that it remains legible
+        let label = match &self.label {
+            Some(label) => label.clone(),
+            None => format!("{:.0}%", self.progress * 100.0),
+        };
+        let mut reversed = self.attributes.clone();
+        reversed.set_reverse(!self.attributes.reverse());
+        let mut x = width.saturating_sub(unicode_column_width(&label, None)) / 2;
+        for grapheme in label.graphemes(true) {
+            let attributes = if x < full {
+                &reversed
+            } else {
+                &self.attributes
+            };
+            print_at(args.surface, x, y, grapheme, attributes);
+            x += unicode_column_width(grapheme, None);
+        }
+    }
+
+    fn get_size_constraints(&self) -> Constraints {
+        self.constraints
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::surface::Surface;
+    use crate::widgets::Ui;
+
+    fn render(bar: ProgressBar) -> Surface {
+        let mut ui = Ui::new();
+        ui.set_root(bar);
+        let mut surface = Surface::new(8, 1);
+        ui.render_to_screen(&mut surface).unwrap();
+        surface
+    }
+
+    #[test]
+    fn render_progress() {
+        // Progress is shown to the nearest eighth of a cell
+        let mut bar = ProgressBar::new();
+        bar.set_label(Some(""));
+        bar.set_progress(0.52);
+        assert_eq!(render(bar).screen_chars_to_string(), "████▏   \n");
+
+        let mut bar = ProgressBar::new();
+        bar.set_progress(f64::NAN);
+        assert_eq!(bar.progress(), 0.0);
+        assert_eq!(render(bar).screen_chars_to_string(), "   0%   \n");
+
+        let mut bar = ProgressBar::new();
+        bar.set_progress(2.0);
+        assert_eq!(bar.progress(), 1.0);
+        assert_eq!(render(bar).screen_chars_to_string(), "██100%██\n");
+    }
+
+    #[test]
+    fn label_over_bar() {
+        let mut bar = ProgressBar::new();
+        bar.set_label(Some("ab"));
+        bar.set_progress(0.5);
+        let mut surface = render(bar);
+        assert_eq!(surface.screen_chars_to_string(), "███ab   \n");
+
+        // The part of the label that overlaps the bar is reversed
+        let reversed: Vec<bool> = surface.screen_cells()[0]
+            .iter()
+            .map(|cell| cell.attrs().reverse())
+            .collect();
+        assert_eq!(
+            reversed,
+            vec![false, false, false, true, false, false, false, false]
+        );
+    }
+}
@@ -0,0 +1,472 @@
+use crate::cell::CellAttributes;
+use crate::input::{InputEvent, MouseButtons};
+use crate::surface::Change;
+use crate::widgets::layout::{ChildOrientation, Constraints};
+use crate::widgets::pane::Pane;
+use crate::widgets::text::print_at;
+use crate::widgets::{FocusMovement, Rect, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+
+/// Specifies the size of the first pane of a `Split`;
+/// the second pane occupies the remaining space
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitSize {
+    /// Occupy a fixed number of cells
+    Fixed(usize),
+    /// Occupy a percentage of the space in the split
+    Percentage(u8),
+}
+
+impl Default for SplitSize {
+    fn default() -> Self {
+        SplitSize::Percentage(50)
+    }
+}
+
+/// Identifies one of the panes of a `Split`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitPane {
+    First,
+    Second,
+}
+
+/// Divides its area between two widgets, either side by side
+/// (`ChildOrientation::Horizontal`) or one above the other
+/// (`ChildOrientation::Vertical`), with a divider line between them.
+/// Keyboard input goes to the focused pane; clicking in a pane
+/// focuses it, as do `Ui::focus_next` and `Ui::focus_previous`.
+pub struct Split<A, B> {
+    first: Pane<A>,
+    second: Pane<B>,
+    orientation: ChildOrientation,
+    size: SplitSize,
+    focus: SplitPane,
+    /// Whether the split is the focused widget of the `Ui`
+    has_focus: bool,
+    divider_attributes: CellAttributes,
+    constraints: Constraints,
+}
+
+impl<A: Widget, B: Widget> Split<A, B> {
+    pub fn new(orientation: ChildOrientation, first: A, second: B) -> Self {
+        Self {
+            first: Pane::new(first),
+            second: Pane::new(second),
+            orientation,
+            size: SplitSize::default(),
+            focus: SplitPane::First,
+            has_focus: false,
+            divider_attributes: CellAttributes::default(),
+            constraints: Constraints::default(),
+        }
+    }
+
+    pub fn first(&self) -> &A {
+        &self.first.widget
+    }
+
+    pub fn first_mut(&mut self) -> &mut A {
+        &mut self.first.widget
+    }
+
+    pub fn second(&self) -> &B {
+        &self.second.widget
+    }
+
+    pub fn second_mut(&mut self) -> &mut B {
+        &mut self.second.widget
+    }
+
+    /// Sets the size of the first pane
+    pub fn set_size(&mut self, size: SplitSize) {
+        self.size = size;
+    }
+
+    /// Returns the pane that receives keyboard input
+    pub fn focus(&self) -> SplitPane {
+        self.focus
+    }
+
+    /// Sets the pane that receives keyboard input.  The panes are
+    /// sent `WidgetEvent::FocusLost` and `WidgetEvent::FocusGained`
+    /// when the split next handles an event.
+    pub fn set_focus(&mut self, focus: SplitPane) {
+        self.focus = focus;
+    }
+
+    pub fn set_divider_attributes(&mut self, attributes: CellAttributes) {
+        self.divider_attributes = attributes;
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+
+    fn pane_accepts_focus(&self, pane: SplitPane) -> bool {
+        match pane {
+            SplitPane::First => self.first.widget.accepts_focus(),
+            SplitPane::Second => self.second.widget.accepts_focus(),
+        }
+    }
+
+    fn pane_move_focus(
+        &mut self,
+        pane: SplitPane,
+        movement: FocusMovement,
+        args: &mut UpdateArgs,
+    ) -> bool {
+        match pane {
+            SplitPane::First => self.first.move_focus(movement, args),
+            SplitPane::Second => self.second.move_focus(movement, args),
+        }
+    }
+
+    /// Tells the panes whether they have the focus, which the focused
+    /// pane does while the split does.  The pane that loses the focus
+    /// is told first.
+    fn sync_focus(&mut self, args: &mut UpdateArgs) {
+        let focus = |pane| self.has_focus && self.focus == pane;
+        let (first, second) = (focus(SplitPane::First), focus(SplitPane::Second));
+        if first {
+            self.second.set_focus(false, args);
+            self.first.set_focus(true, args);
+        } else {
+            self.first.set_focus(false, args);
+            self.second.set_focus(second, args);
+        }
+    }
+
+    /// Computes the size of the first pane when the split spans
+    /// `extent` cells; one cell is reserved for the divider
+    fn first_extent(&self, extent: usize) -> usize {
+        let available = extent.saturating_sub(1);
+        let size = match self.size {
+            SplitSize::Fixed(n) => n,
+            SplitSize::Percentage(pct) => available * pct as usize / 100,
+        };
+        size.min(available)
+    }
+}
+
+impl<A: Widget + 'static, B: Widget + 'static> Widget for Split<A, B> {
+    fn process_event(&mut self, event: &WidgetEvent, args: &mut UpdateArgs) -> bool {
+        match event {
+            WidgetEvent::FocusGained => self.has_focus = true,
+            WidgetEvent::FocusLost => self.has_focus = false,
+            _ => {}
+        }
+        self.sync_focus(args);
+        if let WidgetEvent::FocusGained | WidgetEvent::FocusLost = event {
+            return true;
+        }
+
+        if let WidgetEvent::Tick = event {
+            // Each pane passes the tick on if its widget requested it
+            let first = self.first.process_event(event, args);
+            let second = self.second.process_event(event, args);
+            return first || second;
+        }
+
+        let coords = match event {
+            // A drag belongs to the pane in which it started,
+            // which was focused when the button was pressed
+            WidgetEvent::Drag { .. } | WidgetEvent::DragEnd { .. } => None,
+            _ => event.mouse_coords(),
+        };
+        if let Some(coords) = coords {
+            let pane = if self.first.contains(coords.x, coords.y) {
+                SplitPane::First
+            } else if self.second.contains(coords.x, coords.y) {
+                SplitPane::Second
+            } else {
+                return false;
+            };
+            if let WidgetEvent::Input(InputEvent::Mouse(mouse)) = event {
+                if mouse.mouse_buttons.contains(MouseButtons::LEFT) {
+                    self.focus = pane;
+                    self.sync_focus(args);
+                }
+            }
+            return match pane {
+                SplitPane::First => self.first.process_event(event, args),
+                SplitPane::Second => self.second.process_event(event, args),
+            };
+        }
+
+        match self.focus {
+            SplitPane::First => self.first.process_event(event, args),
+            SplitPane::Second => self.second.process_event(event, args),
+        }
+    }
+
+    fn accepts_focus(&self) -> bool {
+        self.first.widget.accepts_focus() || self.second.widget.accepts_focus()
+    }
+
+    fn move_focus(&mut self, movement: FocusMovement, args: &mut UpdateArgs) -> bool {
+        let panes: Vec<SplitPane> = [SplitPane::First, SplitPane::Second]
+            .iter()
+            .copied()
+            .filter(|pane| self.pane_accepts_focus(*pane))
+            .collect();
+        let target = match movement {
+            FocusMovement::First => panes.first(),
+            FocusMovement::Last => panes.last(),
+            // A nested container may move the focus within itself
+            _ if self.pane_move_focus(self.focus, movement, args) => return true,
+            FocusMovement::Next if self.focus == SplitPane::First => {
+                panes.iter().find(|pane| **pane == SplitPane::Second)
+            }
+            FocusMovement::Previous if self.focus == SplitPane::Second => {
+                panes.iter().find(|pane| **pane == SplitPane::First)
+            }
+            _ => None,
+        };
+        let target = match target {
+            Some(target) => *target,
+            None => return false,
+        };
+        let entry = match movement {
+            FocusMovement::Last | FocusMovement::Previous => FocusMovement::Last,
+            FocusMovement::First | FocusMovement::Next => FocusMovement::First,
+        };
+
+        self.focus = target;
+        self.pane_move_focus(target, entry, args);
+        self.sync_focus(args);
+        true
+    }
+
+    fn on_mount(&mut self, args: &mut UpdateArgs) {
+        self.first.on_mount(args);
+        self.second.on_mount(args);
+    }
+
+    fn on_unmount(&mut self, args: &mut UpdateArgs) {
+        self.first.on_unmount(args);
+        self.second.on_unmount(args);
+    }
+
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, height) = args.surface.dimensions();
+        args.surface
+            .add_change(Change::ClearScreen(self.divider_attributes.background()));
+
+        let (first, second) = match self.orientation {
+            ChildOrientation::Horizontal => {
+                let size = self.first_extent(width);
+                for y in 0..height {
+                    print_at(args.surface, size, y, "│", &self.divider_attributes);
+                }
+                (
+                    Rect {
+                        x: 0,
+                        y: 0,
+                        width: size,
+                        height,
+                    },
+                    Rect {
+                        x: size + 1,
+                        y: 0,
+                        width: width.saturating_sub(size + 1),
+                        height,
+                    },
+                )
+            }
+            ChildOrientation::Vertical => {
+                let size = self.first_extent(height);
+                let line = "─".repeat(width);
+                print_at(args.surface, 0, size, &line, &self.divider_attributes);
+                (
+                    Rect {
+                        x: 0,
+                        y: 0,
+                        width,
+                        height: size,
+                    },
+                    Rect {
+                        x: 0,
+                        y: size + 1,
+                        width,
+                        height: height.saturating_sub(size + 1),
+                    },
+                )
+            }
+        };
+
+        self.first
+            .render(args, first, self.focus == SplitPane::First);
+        self.second
+            .render(args, second, self.focus == SplitPane::Second);
+    }
+
+    fn get_size_constraints(&self) -> Constraints {
+        self.constraints
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::{KeyCode, KeyEvent, Modifiers};
+    use crate::surface::Surface;
+    use crate::widgets::{Frame, Label, Ui};
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    /// Counts its ticks, requesting one every `delay` until it has
+    /// had `limit` of them
+    struct Ticker {
+        delay: Duration,
+        limit: usize,
+        count: Rc<Cell<usize>>,
+    }
+
+    impl Widget for Ticker {
+        fn render(&mut self, _args: &mut RenderArgs) {}
+
+        fn on_mount(&mut self, args: &mut UpdateArgs) {
+            args.request_tick(self.delay);
+        }
+
+        fn process_event(&mut self, event: &WidgetEvent, args: &mut UpdateArgs) -> bool {
+            if let WidgetEvent::Tick = event {
+                self.count.set(self.count.get() + 1);
+                if self.count.get() < self.limit {
+                    args.request_tick(self.delay);
+                }
+                return true;
+            }
+            false
+        }
+    }
+
+    /// Records the changes to its focus and the keys that it receives
+    struct Field {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Widget for Field {
+        fn render(&mut self, args: &mut RenderArgs) {
+            if args.is_focused {
+                self.log.borrow_mut().push(format!("{} render", self.name));
+            }
+        }
+
+        fn accepts_focus(&self) -> bool {
+            true
+        }
+
+        fn get_size_constraints(&self) -> Constraints {
+            Constraints::with_fixed_width_height(4, 1)
+        }
+
+        fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+            let entry = match event {
+                WidgetEvent::FocusGained => "focus".to_string(),
+                WidgetEvent::FocusLost => "blur".to_string(),
+                WidgetEvent::Input(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char(c),
+                    ..
+                })) => format!("key {}", c),
+                _ => return false,
+            };
+            self.log
+                .borrow_mut()
+                .push(format!("{} {}", self.name, entry));
+            true
+        }
+    }
+
+    fn key(key: KeyCode, modifiers: Modifiers) -> WidgetEvent {
+        WidgetEvent::Input(InputEvent::Key(KeyEvent::new(key, modifiers)))
+    }
+
+    #[test]
+    fn focus_traversal() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let field = |name| Field {
+            name,
+            log: Rc::clone(&log),
+        };
+        let inner = Split::new(ChildOrientation::Vertical, field("b"), field("c"));
+        let mut split = Split::new(
+            ChildOrientation::Horizontal,
+            field("a"),
+            Frame::new("", inner),
+        );
+        split.set_constraints(Constraints::with_fixed_width_height(16, 6));
+        let mut ui = Ui::new();
+        let root = ui.set_root(Label::new(""));
+        ui.add_child(root, split);
+        ui.add_child(root, field("d"));
+
+        let mut events = |events: Vec<WidgetEvent>| {
+            for event in events {
+                ui.queue_event(event);
+            }
+            ui.process_event_queue().unwrap();
+            std::mem::take(&mut *log.borrow_mut())
+        };
+        let tab = || key(KeyCode::Tab, Modifiers::NONE);
+        let back_tab = || key(KeyCode::Tab, Modifiers::SHIFT);
+
+        assert_eq!(events(vec![tab()]), vec!["a focus"]);
+        assert_eq!(
+            events(vec![key(KeyCode::Char('x'), Modifiers::NONE)]),
+            vec!["a key x"]
+        );
+        assert_eq!(events(vec![tab()]), vec!["a blur", "b focus"]);
+        assert_eq!(events(vec![tab()]), vec!["b blur", "c focus"]);
+        assert_eq!(
+            events(vec![key(KeyCode::Char('y'), Modifiers::NONE)]),
+            vec!["c key y"]
+        );
+        // Then on to the widget after the split, and around again
+        assert_eq!(events(vec![tab()]), vec!["c blur", "d focus"]);
+        assert_eq!(events(vec![tab()]), vec!["d blur", "a focus"]);
+        assert_eq!(events(vec![back_tab()]), vec!["a blur", "d focus"]);
+        assert_eq!(events(vec![back_tab()]), vec!["d blur", "c focus"]);
+
+        // Only the focused pane renders as focused
+        let mut surface = Surface::new(20, 6);
+        ui.render_to_screen(&mut surface).unwrap();
+        assert_eq!(std::mem::take(&mut *log.borrow_mut()), vec!["c render"]);
+    }
+
+    #[test]
+    fn ticks_reach_only_the_panes_that_are_due() {
+        let spinner = Rc::new(Cell::new(0));
+        let clock = Rc::new(Cell::new(0));
+        let ticker = |delay, limit, count: &Rc<Cell<usize>>| Ticker {
+            delay,
+            limit,
+            count: Rc::clone(count),
+        };
+        let mut ui = Ui::new();
+        ui.set_root(Split::new(
+            ChildOrientation::Horizontal,
+            ticker(Duration::from_secs(0), 2, &spinner),
+            ticker(Duration::from_secs(3600), 2, &clock),
+        ));
+        for _ in 0..3 {
+            ui.process_event_queue().unwrap();
+        }
+        assert_eq!(spinner.get(), 2);
+        assert_eq!(clock.get(), 0);
+
+        // The clock's tick is still pending once the spinner stops
+        let next = ui.next_tick().unwrap();
+        assert!(next > Instant::now() + Duration::from_secs(60));
+    }
+}
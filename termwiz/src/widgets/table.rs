@@ -0,0 +1,270 @@
+use crate::cell::{unicode_column_width, CellAttributes, Intensity};
+use crate::input::{InputEvent, MouseButtons};
+use crate::surface::{Change, CursorVisibility};
+use crate::widgets::layout::Constraints;
+use crate::widgets::list::Selection;
+use crate::widgets::text::{fill_at, print_at, truncate_to_width};
+use crate::widgets::{RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+
+/// Specifies how wide a table column should be
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColumnWidth {
+    /// Occupy a fixed number of cells
+    Fixed(usize),
+    /// Occupy a percentage of the width of the table
+    Percentage(u8),
+    /// Fit the content of the column, sharing the space left over by
+    /// the other columns with the other `Auto` columns
+    #[default]
+    Auto,
+}
+
+/// Describes a column of a `Table`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Column {
+    pub title: String,
+    pub width: ColumnWidth,
+}
+
+impl Column {
+    pub fn new(title: &str, width: ColumnWidth) -> Self {
+        Self {
+            title: title.to_string(),
+            width,
+        }
+    }
+}
+
+/// A scrollable table of text with a header row, of which one row is
+/// selected.  The selection is navigated in the same way as a `List`.
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+    selection: Selection,
+    header_attributes: CellAttributes,
+    attributes: CellAttributes,
+    selected_attributes: CellAttributes,
+    constraints: Constraints,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: vec![],
+            selection: Selection::default(),
+            header_attributes: CellAttributes::default()
+                .set_intensity(Intensity::Bold)
+                .clone(),
+            attributes: CellAttributes::default(),
+            selected_attributes: CellAttributes::default().set_reverse(true).clone(),
+            constraints: Constraints::default(),
+        }
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    /// Replaces the rows, keeping the selected index if it is still valid.
+    /// Each row holds the text for each column in turn.
+    pub fn set_rows(&mut self, rows: Vec<Vec<String>>) {
+        self.rows = rows;
+        self.selection.set_len(self.rows.len());
+        self.selection.scroll_to_selection();
+    }
+
+    /// Returns the index of the selected row
+    pub fn selected(&self) -> Option<usize> {
+        self.selection.selected
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.rows.len() {
+            self.selection.selected = Some(index);
+            self.selection.scroll_to_selection();
+        }
+    }
+
+    /// Sets the attributes used for the column titles.
+    /// The default is bold.
+    pub fn set_header_attributes(&mut self, attributes: CellAttributes) {
+        self.header_attributes = attributes;
+    }
+
+    pub fn set_attributes(&mut self, attributes: CellAttributes) {
+        self.attributes = attributes;
+    }
+
+    /// Sets the attributes used for the selected row.
+    /// The default is reverse video.
+    pub fn set_selected_attributes(&mut self, attributes: CellAttributes) {
+        self.selected_attributes = attributes;
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+
+    /// Computes the width of each column when the table is `width`
+    /// cells wide.  Columns are separated by a single space.
+    fn column_widths(&self, width: usize) -> Vec<usize> {
+        let separators = self.columns.len().saturating_sub(1);
+        let available = width.saturating_sub(separators);
+
+        let mut widths: Vec<usize> = self
+            .columns
+            .iter()
+            .map(|column| match column.width {
+                ColumnWidth::Fixed(n) => n,
+                ColumnWidth::Percentage(pct) => available * pct as usize / 100,
+                ColumnWidth::Auto => 0,
+            })
+            .collect();
+        let mut remaining = available.saturating_sub(widths.iter().sum());
+
+        // Share the remaining space among the automatic columns,
+        // starting with the narrowest content so that any space that
+        // a narrow column doesn't need goes to the wider ones
+        let mut auto: Vec<(usize, usize)> = self
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| column.width == ColumnWidth::Auto)
+            .map(|(idx, column)| {
+                let content = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(idx))
+                    .map(|cell| unicode_column_width(cell, None))
+                    .chain(std::iter::once(unicode_column_width(&column.title, None)))
+                    .max()
+                    .unwrap_or(0);
+                (idx, content)
+            })
+            .collect();
+        auto.sort_by_key(|(_, content)| *content);
+
+        let count = auto.len();
+        for (n, (idx, content)) in auto.into_iter().enumerate() {
+            let share = remaining / (count - n);
+            widths[idx] = content.min(share);
+            remaining -= widths[idx];
+        }
+
+        widths
+    }
+
+    fn render_row(
+        args: &mut RenderArgs,
+        y: usize,
+        cells: &[String],
+        widths: &[usize],
+        attributes: &CellAttributes,
+    ) {
+        let mut x = 0;
+        for (cell, width) in cells.iter().zip(widths) {
+            print_at(
+                args.surface,
+                x,
+                y,
+                truncate_to_width(cell, *width),
+                attributes,
+            );
+            x += width + 1;
+        }
+    }
+}
+
+impl Widget for Table {
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        let len = self.rows.len();
+        match event {
+            WidgetEvent::Input(InputEvent::Key(key)) if !key.is_release() => {
+                let handled = self.selection.process_key(key, len);
+                self.selection.scroll_to_selection();
+                handled
+            }
+            WidgetEvent::Input(InputEvent::Mouse(mouse)) => {
+                self.selection.process_wheel(mouse, len)
+            }
+            WidgetEvent::Click {
+                coords,
+                button: MouseButtons::LEFT,
+                ..
+            } => self.selection.process_click(coords.y, 1, len),
+            _ => false,
+        }
+    }
+
+    fn accepts_focus(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, height) = args.surface.dimensions();
+        self.selection.page = height.saturating_sub(1);
+        args.surface
+            .add_change(Change::ClearScreen(self.attributes.background()));
+        args.cursor.visibility = CursorVisibility::Hidden;
+
+        let widths = self.column_widths(width);
+        let titles: Vec<String> = self.columns.iter().map(|c| c.title.clone()).collect();
+        Self::render_row(args, 0, &titles, &widths, &self.header_attributes);
+
+        let rows = self.rows.iter().enumerate().skip(self.selection.top);
+        for (y, (idx, row)) in rows.take(self.selection.page).enumerate() {
+            let attributes = if Some(idx) == self.selection.selected {
+                fill_at(args.surface, 0, y + 1, width, &self.selected_attributes);
+                &self.selected_attributes
+            } else {
+                &self.attributes
+            };
+            Self::render_row(args, y + 1, row, &widths, attributes);
+        }
+    }
+
+    fn get_size_constraints(&self) -> Constraints {
+        self.constraints
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn column_sizing() {
+        let mut table = Table::new(vec![
+            Column::new("id", ColumnWidth::Fixed(4)),
+            Column::new("name", ColumnWidth::Auto),
+            Column::new("description", ColumnWidth::Auto),
+            Column::new("size", ColumnWidth::Percentage(10)),
+        ]);
+        table.set_rows(vec![vec![
+            "1".to_string(),
+            "termwiz".to_string(),
+            "a terminal wizardry crate".to_string(),
+            "1MB".to_string(),
+        ]]);
+
+        // 3 separators, leaving 80; the auto columns share 80 - 4 - 8
+        assert_eq!(table.column_widths(83), vec![4, 7, 25, 8]);
+        assert_eq!(table.column_widths(33), vec![4, 7, 16, 3]);
+        assert_eq!(table.column_widths(23), vec![4, 7, 7, 2]);
+        assert_eq!(table.column_widths(2), vec![4, 0, 0, 0]);
+    }
+}
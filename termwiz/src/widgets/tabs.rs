@@ -0,0 +1,378 @@
+use crate::cell::{unicode_column_width, CellAttributes};
+use crate::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons};
+use crate::surface::Change;
+use crate::widgets::layout::Constraints;
+use crate::widgets::pane::Pane;
+use crate::widgets::text::print_at;
+use crate::widgets::{FocusMovement, Rect, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+
+struct Tab {
+    title: String,
+    pane: Pane<Box<dyn Widget>>,
+}
+
+/// Shows one of a number of widgets, with a row of tabs above them
+/// to choose between them.  A tab is chosen by clicking on its title,
+/// or with CTRL-PageUp and CTRL-PageDown when the selected widget
+/// doesn't handle those keys itself.
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    selected: usize,
+    /// Whether the tabs are the focused widget of the `Ui`
+    has_focus: bool,
+    attributes: CellAttributes,
+    selected_attributes: CellAttributes,
+    constraints: Constraints,
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tabs {
+    pub fn new() -> Self {
+        Self {
+            tabs: vec![],
+            selected: 0,
+            has_focus: false,
+            attributes: CellAttributes::default(),
+            selected_attributes: CellAttributes::default().set_reverse(true).clone(),
+            constraints: Constraints::default(),
+        }
+    }
+
+    /// Adds a tab, returning its index.  The widget is only notified
+    /// via `Widget::on_mount` if the tab is added before the `Tabs`
+    /// is added to the `Ui`.
+    pub fn add_tab<W: Widget + 'static>(&mut self, title: &str, widget: W) -> usize {
+        self.tabs.push(Tab {
+            title: title.to_string(),
+            pane: Pane::new(Box::new(widget)),
+        });
+        self.tabs.len() - 1
+    }
+
+    /// Returns the widget in the specified tab, provided that it is
+    /// of type `W`; see `Ui::widget`
+    pub fn widget<W: Widget + 'static>(&self, index: usize) -> Option<&W> {
+        self.tabs.get(index)?.pane.widget.as_any()?.downcast_ref()
+    }
+
+    pub fn widget_mut<W: Widget + 'static>(&mut self, index: usize) -> Option<&mut W> {
+        self.tabs
+            .get_mut(index)?
+            .pane
+            .widget
+            .as_any_mut()?
+            .downcast_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    /// Returns the index of the selected tab
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects the tab to show.  The widgets in the tabs are sent
+    /// `WidgetEvent::FocusLost` and `WidgetEvent::FocusGained` when
+    /// the `Tabs` next handles an event.
+    pub fn select(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.selected = index;
+        }
+    }
+
+    pub fn set_attributes(&mut self, attributes: CellAttributes) {
+        self.attributes = attributes;
+    }
+
+    /// Sets the attributes used for the title of the selected tab.
+    /// The default is reverse video.
+    pub fn set_selected_attributes(&mut self, attributes: CellAttributes) {
+        self.selected_attributes = attributes;
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+
+    /// Tells the widgets in the tabs whether they have the focus, which
+    /// that in the selected tab does while the `Tabs` does.  The widget
+    /// that loses the focus is told first.
+    fn sync_focus(&mut self, args: &mut UpdateArgs) {
+        for (idx, tab) in self.tabs.iter_mut().enumerate() {
+            if idx != self.selected {
+                tab.pane.set_focus(false, args);
+            }
+        }
+        if let Some(tab) = self.tabs.get_mut(self.selected) {
+            tab.pane.set_focus(self.has_focus, args);
+        }
+    }
+
+    /// Returns the index of the tab whose title is at the specified column
+    fn tab_at_column(&self, column: usize) -> Option<usize> {
+        let mut x = 0;
+        for (idx, tab) in self.tabs.iter().enumerate() {
+            x += unicode_column_width(&tab.title, None) + 2;
+            if column < x {
+                return Some(idx);
+            }
+        }
+        None
+    }
+}
+
+impl Widget for Tabs {
+    fn process_event(&mut self, event: &WidgetEvent, args: &mut UpdateArgs) -> bool {
+        match event {
+            WidgetEvent::FocusGained => self.has_focus = true,
+            WidgetEvent::FocusLost => self.has_focus = false,
+            _ => {}
+        }
+        self.sync_focus(args);
+        if let WidgetEvent::FocusGained | WidgetEvent::FocusLost = event {
+            return true;
+        }
+
+        if let WidgetEvent::Tick = event {
+            // Each pane passes the tick on if its widget requested it
+            let mut handled = false;
+            for tab in &mut self.tabs {
+                handled |= tab.pane.process_event(event, args);
+            }
+            return handled;
+        }
+
+        if let Some(coords) = event.mouse_coords() {
+            if coords.y == 0 {
+                if let WidgetEvent::Click {
+                    button: MouseButtons::LEFT,
+                    ..
+                } = event
+                {
+                    if let Some(idx) = self.tab_at_column(coords.x) {
+                        self.selected = idx;
+                        self.sync_focus(args);
+                        return true;
+                    }
+                }
+                return false;
+            }
+        }
+
+        let tab = match self.tabs.get_mut(self.selected) {
+            Some(tab) => tab,
+            None => return false,
+        };
+        if tab.pane.process_event(event, args) {
+            return true;
+        }
+
+        match event {
+            WidgetEvent::Input(InputEvent::Key(KeyEvent {
+                key: KeyCode::PageUp,
+                modifiers: Modifiers::CTRL,
+                ..
+            })) => {
+                self.selected = self.selected.saturating_sub(1);
+                self.sync_focus(args);
+                true
+            }
+            WidgetEvent::Input(InputEvent::Key(KeyEvent {
+                key: KeyCode::PageDown,
+                modifiers: Modifiers::CTRL,
+                ..
+            })) => {
+                self.selected = (self.selected + 1).min(self.tabs.len() - 1);
+                self.sync_focus(args);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn accepts_focus(&self) -> bool {
+        true
+    }
+
+    fn move_focus(&mut self, movement: FocusMovement, args: &mut UpdateArgs) -> bool {
+        match self.tabs.get_mut(self.selected) {
+            Some(tab) => tab.pane.move_focus(movement, args),
+            None => false,
+        }
+    }
+
+    fn on_mount(&mut self, args: &mut UpdateArgs) {
+        for tab in &mut self.tabs {
+            tab.pane.on_mount(args);
+        }
+    }
+
+    fn on_unmount(&mut self, args: &mut UpdateArgs) {
+        for tab in &mut self.tabs {
+            tab.pane.on_unmount(args);
+        }
+    }
+
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, height) = args.surface.dimensions();
+        args.surface
+            .add_change(Change::ClearScreen(self.attributes.background()));
+
+        let mut x = 0;
+        for (idx, tab) in self.tabs.iter().enumerate() {
+            let attributes = if idx == self.selected {
+                &self.selected_attributes
+            } else {
+                &self.attributes
+            };
+            let title = format!(" {} ", tab.title);
+            print_at(args.surface, x, 0, &title, attributes);
+            x += unicode_column_width(&title, None);
+        }
+
+        if let Some(tab) = self.tabs.get_mut(self.selected) {
+            let rect = Rect {
+                x: 0,
+                y: 1,
+                width,
+                height: height.saturating_sub(1),
+            };
+            tab.pane.render(args, rect, true);
+        }
+    }
+
+    fn get_size_constraints(&self) -> Constraints {
+        self.constraints
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::widgets::Ui;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    /// Counts its ticks, requesting one every `delay` until it has
+    /// had `limit` of them
+    struct Ticker {
+        delay: Duration,
+        limit: usize,
+        count: Rc<Cell<usize>>,
+    }
+
+    impl Widget for Ticker {
+        fn render(&mut self, _args: &mut RenderArgs) {}
+
+        fn on_mount(&mut self, args: &mut UpdateArgs) {
+            args.request_tick(self.delay);
+        }
+
+        fn process_event(&mut self, event: &WidgetEvent, args: &mut UpdateArgs) -> bool {
+            if let WidgetEvent::Tick = event {
+                self.count.set(self.count.get() + 1);
+                if self.count.get() < self.limit {
+                    args.request_tick(self.delay);
+                }
+                return true;
+            }
+            false
+        }
+    }
+
+    /// Records the changes to its focus
+    struct Field {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Widget for Field {
+        fn render(&mut self, _args: &mut RenderArgs) {}
+
+        fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+            let entry = match event {
+                WidgetEvent::FocusGained => "focus",
+                WidgetEvent::FocusLost => "blur",
+                _ => return false,
+            };
+            self.log
+                .borrow_mut()
+                .push(format!("{} {}", self.name, entry));
+            true
+        }
+    }
+
+    #[test]
+    fn focus_follows_selection() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut tabs = Tabs::new();
+        for name in ["a", "b"] {
+            tabs.add_tab(
+                name,
+                Field {
+                    name,
+                    log: Rc::clone(&log),
+                },
+            );
+        }
+        let mut ui = Ui::new();
+        ui.set_root(tabs);
+        assert_eq!(std::mem::take(&mut *log.borrow_mut()), vec!["a focus"]);
+
+        ui.queue_event(WidgetEvent::Input(InputEvent::Key(KeyEvent::new(
+            KeyCode::PageDown,
+            Modifiers::CTRL,
+        ))));
+        ui.process_event_queue().unwrap();
+        assert_eq!(
+            std::mem::take(&mut *log.borrow_mut()),
+            vec!["a blur", "b focus"]
+        );
+    }
+
+    #[test]
+    fn ticks_reach_only_the_panes_that_are_due() {
+        let spinner = Rc::new(Cell::new(0));
+        let clock = Rc::new(Cell::new(0));
+        let ticker = |delay, limit, count: &Rc<Cell<usize>>| Ticker {
+            delay,
+            limit,
+            count: Rc::clone(count),
+        };
+        let mut ui = Ui::new();
+        let mut tabs = Tabs::new();
+        tabs.add_tab("spinner", ticker(Duration::from_secs(0), 2, &spinner));
+        tabs.add_tab("clock", ticker(Duration::from_secs(3600), 2, &clock));
+        ui.set_root(tabs);
+        for _ in 0..3 {
+            ui.process_event_queue().unwrap();
+        }
+        assert_eq!(spinner.get(), 2);
+        assert_eq!(clock.get(), 0);
+
+        // The clock's tick is still pending once the spinner stops
+        let next = ui.next_tick().unwrap();
+        assert!(next > Instant::now() + Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,116 @@
+//! Helpers for laying out and drawing text within widgets
+use crate::cell::{unicode_column_width, CellAttributes};
+use crate::surface::{Change, Position, Surface};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Returns the longest prefix of `text` that fits in `width` columns
+pub(crate) fn truncate_to_width(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (idx, grapheme) in text.grapheme_indices(true) {
+        used += unicode_column_width(grapheme, None);
+        if used > width {
+            return &text[..idx];
+        }
+    }
+    text
+}
+
+/// Breaks `text` into lines of at most `width` columns.
+/// Lines are broken at spaces where possible; words that are
+/// wider than `width` are split across lines.  Newlines in the
+/// text always start a new line.
+pub(crate) fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    if width == 0 {
+        return lines;
+    }
+
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_width = 0;
+
+        for word in paragraph.trim_end_matches('\r').split(' ') {
+            let word_width = unicode_column_width(word, None);
+            if !line.is_empty() {
+                if line_width + 1 + word_width <= width {
+                    line.push(' ');
+                    line.push_str(word);
+                    line_width += 1 + word_width;
+                    continue;
+                }
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+
+            for grapheme in word.graphemes(true) {
+                let grapheme_width = unicode_column_width(grapheme, None);
+                if line_width + grapheme_width > width && !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                    line_width = 0;
+                }
+                line.push_str(grapheme);
+                line_width += grapheme_width;
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Draws `text` at the specified position, truncating it at the
+/// right edge of the surface so that it doesn't wrap
+pub(crate) fn print_at(
+    surface: &mut Surface,
+    x: usize,
+    y: usize,
+    text: &str,
+    attributes: &CellAttributes,
+) {
+    let (width, height) = surface.dimensions();
+    if x >= width || y >= height {
+        return;
+    }
+    surface.add_changes(vec![
+        Change::CursorPosition {
+            x: Position::Absolute(x),
+            y: Position::Absolute(y),
+        },
+        Change::AllAttributes(attributes.clone()),
+        Change::Text(truncate_to_width(text, width - x).to_string()),
+    ]);
+}
+
+/// Fills `columns` cells starting at the specified position with
+/// spaces in the specified attributes
+pub(crate) fn fill_at(
+    surface: &mut Surface,
+    x: usize,
+    y: usize,
+    columns: usize,
+    attributes: &CellAttributes,
+) {
+    print_at(surface, x, y, &" ".repeat(columns), attributes);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wrap() {
+        assert_eq!(
+            wrap_text("the quick brown fox", 10),
+            vec!["the quick", "brown fox"]
+        );
+        assert_eq!(
+            wrap_text("a verylongword\n\nend", 5),
+            vec!["a", "veryl", "ongwo", "rd", "", "end"]
+        );
+        assert_eq!(wrap_text("日本語", 4), vec!["日本", "語"]);
+        assert!(wrap_text("anything", 0).is_empty());
+
+        assert_eq!(truncate_to_width("日本語", 5), "日本");
+        assert_eq!(truncate_to_width("abc", 5), "abc");
+    }
+}
@@ -0,0 +1,347 @@
+use crate::cell::{unicode_column_width, CellAttributes, Intensity};
+use crate::input::{InputEvent, MouseButtons};
+use crate::lineedit::bindings::emacs_action;
+use crate::lineedit::killring::KillRing;
+use crate::lineedit::movement::eval_movement;
+use crate::lineedit::undo::UndoStack;
+use crate::lineedit::{Action, LastAction, Movement};
+use crate::surface::{Change, CursorShape};
+use crate::widgets::layout::Constraints;
+use crate::widgets::text::print_at;
+use crate::widgets::{CursorShapeAndPosition, RenderArgs, UpdateArgs, Widget, WidgetEvent};
+use std::any::Any;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A single line text entry field.
+/// The editing keys are the same as the default key bindings of the
+/// `LineEditor`, including the kill ring and undo.
+/// The keys that the `LineEditor` uses to accept or cancel the line,
+/// navigate history or complete text, such as Enter, Tab and the up
+/// and down arrows, are not handled by the widget and so are passed
+/// on to its parent.
+pub struct TextInput {
+    line: String,
+    /// The byte index of the cursor within the line
+    cursor: usize,
+    /// The column of the line shown at the left edge of the widget
+    scroll: usize,
+    placeholder: String,
+    attributes: CellAttributes,
+    placeholder_attributes: CellAttributes,
+    undo: UndoStack,
+    kill_ring: KillRing,
+    last_action: LastAction,
+    constraints: Constraints,
+}
+
+impl Default for TextInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self {
+            line: String::new(),
+            cursor: 0,
+            scroll: 0,
+            placeholder: String::new(),
+            attributes: CellAttributes::default(),
+            placeholder_attributes: CellAttributes::default()
+                .set_intensity(Intensity::Half)
+                .clone(),
+            undo: UndoStack::default(),
+            kill_ring: KillRing::default(),
+            last_action: LastAction::Other,
+            constraints: *Constraints::default().set_fixed_height(1),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.line
+    }
+
+    /// Replaces the text, placing the cursor at the end of it.
+    /// This also discards the undo history.
+    pub fn set_text(&mut self, text: &str) {
+        self.line = text.replace(['\r', '\n'], " ");
+        self.cursor = self.line.len();
+        self.undo.clear();
+        self.last_action = LastAction::Other;
+    }
+
+    /// Returns the byte index of the cursor within the text
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Sets text to display, dimmed, while the input is empty
+    pub fn set_placeholder(&mut self, placeholder: &str) {
+        self.placeholder = placeholder.to_string();
+    }
+
+    pub fn set_attributes(&mut self, attributes: CellAttributes) {
+        self.attributes = attributes;
+    }
+
+    pub fn set_placeholder_attributes(&mut self, attributes: CellAttributes) {
+        self.placeholder_attributes = attributes;
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+
+    /// Deletes the text between the cursor and the position after
+    /// `kill_movement`, then applies `move_movement`
+    fn kill_text(&mut self, kill_movement: Movement, move_movement: Movement, accumulate: bool) {
+        let kill_pos = eval_movement(&self.line, self.cursor, kill_movement);
+        let new_cursor = eval_movement(&self.line, self.cursor, move_movement);
+
+        let backward = kill_pos < self.cursor;
+        let range = if backward {
+            kill_pos..self.cursor
+        } else {
+            self.cursor..kill_pos
+        };
+        if range.is_empty() {
+            return;
+        }
+
+        if !matches!(
+            kill_movement,
+            Movement::BackwardChar(1) | Movement::ForwardChar(1)
+        ) {
+            self.kill_ring
+                .kill(&self.line[range.clone()], accumulate, backward);
+            self.last_action = LastAction::Kill;
+        }
+        self.line.replace_range(range, "");
+        self.cursor = new_cursor.min(self.line.len());
+    }
+
+    fn insert(&mut self, text: &str) {
+        self.line.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Applies an editing action, returning false for the actions
+    /// that don't apply to a single line text field
+    pub fn apply_action(&mut self, action: Action) -> bool {
+        let last_action = std::mem::take(&mut self.last_action);
+        let prior_line = self.line.clone();
+        let prior_cursor = self.cursor;
+        let coalesce = matches!(action, Action::InsertChar(..));
+        let accumulate = matches!(last_action, LastAction::Kill);
+
+        match action {
+            Action::InsertChar(rep, c) if c != '\n' => {
+                self.insert(&c.to_string().repeat(rep));
+            }
+            Action::InsertText(rep, text) => {
+                self.insert(&text.replace(['\r', '\n'], " ").repeat(rep));
+            }
+            Action::Move(movement) => {
+                self.cursor = eval_movement(&self.line, self.cursor, movement);
+            }
+            Action::Kill(movement) => self.kill_text(movement, movement, accumulate),
+            Action::KillAndMove(kill_movement, move_movement) => {
+                self.kill_text(kill_movement, move_movement, accumulate)
+            }
+            // As in readline, this deletes forwards unless the line is empty
+            Action::EndOfFile if !self.line.is_empty() => {
+                self.kill_text(Movement::ForwardChar(1), Movement::None, false)
+            }
+            Action::Yank => {
+                if let Some(text) = self.kill_ring.yank() {
+                    let text = text.to_string();
+                    let start = self.cursor;
+                    self.insert(&text);
+                    self.last_action = LastAction::Yank(start..self.cursor);
+                }
+            }
+            Action::YankPop => {
+                if let LastAction::Yank(range) = last_action {
+                    if let Some(text) = self.kill_ring.yank_pop() {
+                        self.line.replace_range(range.clone(), text);
+                        self.cursor = range.start + text.len();
+                        self.last_action = LastAction::Yank(range.start..self.cursor);
+                    }
+                }
+            }
+            Action::Undo | Action::Redo => {
+                let snapshot = if matches!(action, Action::Undo) {
+                    self.undo.undo(&self.line, self.cursor)
+                } else {
+                    self.undo.redo(&self.line, self.cursor)
+                };
+                if let Some(snapshot) = snapshot {
+                    self.line = snapshot.line;
+                    self.cursor = snapshot.cursor;
+                }
+                return true;
+            }
+            _ => {
+                self.last_action = last_action;
+                return false;
+            }
+        }
+
+        if self.line != prior_line {
+            self.undo.record(&prior_line, prior_cursor, coalesce);
+        } else if !coalesce {
+            self.undo.break_coalescing();
+        }
+        true
+    }
+
+    /// Returns the byte index of the grapheme displayed at `column`
+    fn index_at_column(&self, column: usize) -> usize {
+        let mut used = 0;
+        for (idx, grapheme) in self.line.grapheme_indices(true) {
+            used += unicode_column_width(grapheme, None);
+            if used > column {
+                return idx;
+            }
+        }
+        self.line.len()
+    }
+}
+
+impl Widget for TextInput {
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        match event {
+            WidgetEvent::Input(InputEvent::Key(key)) if key.is_release() => false,
+            WidgetEvent::Click {
+                coords,
+                button: MouseButtons::LEFT,
+                ..
+            }
+            | WidgetEvent::Drag {
+                coords,
+                button: MouseButtons::LEFT,
+            } => {
+                self.cursor = self.index_at_column(self.scroll + coords.x);
+                self.undo.break_coalescing();
+                true
+            }
+            WidgetEvent::Input(InputEvent::Mouse(_)) => false,
+            WidgetEvent::Input(input) => match emacs_action(input) {
+                Some(action) => self.apply_action(action),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn accepts_focus(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, args: &mut RenderArgs) {
+        let (width, _height) = args.surface.dimensions();
+        args.surface
+            .add_change(Change::ClearScreen(self.attributes.background()));
+        if width == 0 {
+            // There is nowhere to show the text or the cursor
+            return;
+        }
+
+        // Scroll horizontally to keep the cursor in view
+        let cursor_column = unicode_column_width(&self.line[..self.cursor], None);
+        if cursor_column < self.scroll {
+            self.scroll = cursor_column;
+        } else if cursor_column >= self.scroll + width {
+            self.scroll = cursor_column + 1 - width;
+        }
+
+        if self.line.is_empty() {
+            print_at(
+                args.surface,
+                0,
+                0,
+                &self.placeholder,
+                &self.placeholder_attributes,
+            );
+        } else {
+            let start = self.index_at_column(self.scroll);
+            print_at(args.surface, 0, 0, &self.line[start..], &self.attributes);
+        }
+
+        *args.cursor = CursorShapeAndPosition {
+            coords: (cursor_column - self.scroll, 0).into(),
+            shape: CursorShape::SteadyBar,
+            ..Default::default()
+        };
+    }
+
+    fn get_size_constraints(&self) -> Constraints {
+        self.constraints
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::{KeyCode, KeyEvent, Modifiers};
+    use crate::widgets::WidgetId;
+
+    fn press(input: &mut TextInput, key: KeyCode, modifiers: Modifiers) -> bool {
+        let mut cursor = CursorShapeAndPosition::default();
+        let mut args = UpdateArgs {
+            id: WidgetId::new(),
+            cursor: &mut cursor,
+            tick: None,
+        };
+        let event = WidgetEvent::Input(InputEvent::Key(KeyEvent::new(key, modifiers)));
+        input.process_event(&event, &mut args)
+    }
+
+    #[test]
+    fn edit() {
+        let mut input = TextInput::new();
+        for c in "hello world".chars() {
+            assert!(press(&mut input, KeyCode::Char(c), Modifiers::NONE));
+        }
+        assert!(press(&mut input, KeyCode::Char('W'), Modifiers::CTRL));
+        assert_eq!(input.text(), "hello ");
+        assert!(press(&mut input, KeyCode::Home, Modifiers::NONE));
+        assert!(press(&mut input, KeyCode::Char('Y'), Modifiers::CTRL));
+        assert_eq!(input.text(), "worldhello ");
+        assert_eq!(input.cursor(), 5);
+
+        assert!(press(&mut input, KeyCode::Char('_'), Modifiers::CTRL));
+        assert_eq!(input.text(), "hello ");
+        assert!(press(&mut input, KeyCode::Char('_'), Modifiers::CTRL));
+        assert_eq!(input.text(), "hello world");
+
+        // Keys that the field doesn't use are left for the parent
+        assert!(!press(&mut input, KeyCode::Enter, Modifiers::NONE));
+        assert!(!press(&mut input, KeyCode::UpArrow, Modifiers::NONE));
+        assert!(!press(&mut input, KeyCode::Char('\n'), Modifiers::NONE));
+        assert_eq!(input.text(), "hello world");
+    }
+
+    #[test]
+    fn render_without_room() {
+        let mut input = TextInput::new();
+        assert!(press(&mut input, KeyCode::Char('a'), Modifiers::NONE));
+        let mut surface = crate::surface::Surface::new(0, 1);
+        let mut cursor = CursorShapeAndPosition::default();
+        input.render(&mut RenderArgs {
+            id: WidgetId::new(),
+            is_focused: true,
+            cursor: &mut cursor,
+            surface: &mut surface,
+        });
+    }
+}
@@ -0,0 +1,63 @@
+//! Runs a command, or the default shell, in a framed pane.
+//! Press CTRL-Q to quit.
+use portable_pty::CommandBuilder;
+use std::sync::Arc;
+use termwiz::caps::Capabilities;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
+use termwiz::surface::Change;
+use termwiz::terminal::buffered::BufferedTerminal;
+use termwiz::terminal::{new_terminal, Terminal};
+use termwiz::widgets::{Frame, Ui, WidgetEvent};
+use wezterm_portable::config::DefaultConfiguration;
+use wezterm_portable::widget::TerminalWidget;
+
+fn main() -> anyhow::Result<()> {
+    let argv: Vec<_> = std::env::args_os().skip(1).collect();
+    let cmd = if argv.is_empty() {
+        CommandBuilder::new_default_prog()
+    } else {
+        CommandBuilder::from_argv(argv)
+    };
+
+    let caps = Capabilities::new_from_env()?;
+    let mut buf = BufferedTerminal::new(new_terminal(caps)?)?;
+    buf.terminal().set_raw_mode()?;
+    buf.terminal().enter_alternate_screen()?;
+
+    let mut ui = Ui::new();
+    let mut terminal = TerminalWidget::spawn(cmd, Arc::new(DefaultConfiguration), None)?;
+    terminal.set_event_sender(ui.event_sender());
+    let frame_id = ui.set_root(Frame::new("", terminal));
+
+    loop {
+        ui.process_event_queue()?;
+
+        let frame = ui.widget_mut::<Frame<TerminalWidget>>(frame_id).unwrap();
+        if frame.inner().is_dead() {
+            break;
+        }
+        let title = format!("{} (CTRL-Q to quit)", frame.inner().title());
+        frame.set_title(&title);
+
+        if ui.render_to_screen(&mut buf)? {
+            continue;
+        }
+        buf.flush()?;
+
+        match ui.poll_input(buf.terminal(), None)? {
+            Some(InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('q') | KeyCode::Char('Q'),
+                modifiers: Modifiers::CTRL,
+                ..
+            })) => break,
+            Some(InputEvent::Resized { rows, cols }) => {
+                buf.add_change(Change::ClearScreen(Default::default()));
+                buf.resize(cols, rows);
+            }
+            Some(input) => ui.queue_event(WidgetEvent::Input(input)),
+            None => {}
+        }
+    }
+
+    Ok(())
+}
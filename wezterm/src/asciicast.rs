@@ -0,0 +1,508 @@
+//! Recording and replaying pty sessions in the asciicast v2 format.
+//!
+//! A recording starts with a header line holding the size of the
+//! terminal, followed by one line per event of the form
+//! `[time, code, data]`, where `time` is the number of seconds since the
+//! recording started and `code` is `"o"` for output from the program,
+//! `"i"` for input sent to it, `"r"` for a resize (with `data` of the form
+//! `COLSxROWS`) or `"m"` for a marker.  The format is described at
+//! <https://docs.asciinema.org/manual/asciicast/v2/> and recordings can
+//! be played back by asciinema as well as by `Recording::play`.
+//!
+//! A `Recorder` is passed to `mux::read_from_pty_with_recorder` to capture
+//! the output of a program, and wrapped around the pty writer with
+//! `RecordingWriter` to capture its input:
+//!
+//! ```no_run
+//! use wezterm_portable::asciicast::Recording;
+//! use wezterm_portable::terminalstate::TerminalState;
+//! # fn replay(terminal: &mut TerminalState) -> anyhow::Result<()> {
+//! let recording = Recording::load("bug-report.cast")?;
+//! recording.play(terminal, wezterm_portable::asciicast::Pace::AsFastAsPossible);
+//! # Ok(())
+//! # }
+//! ```
+use super::terminalstate::{TerminalSize, TerminalState};
+use anyhow::{anyhow, bail, Context};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use termwiz::escape::parser::Parser;
+
+/// The kind of an event in a recording
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    /// Data output by the program
+    Output,
+    /// Data sent to the program
+    Input,
+    /// The terminal was resized; the data is `COLSxROWS`
+    Resize,
+    /// A marker, labelled by the data
+    Marker,
+}
+
+impl EventKind {
+    fn code(self) -> &'static str {
+        match self {
+            Self::Output => "o",
+            Self::Input => "i",
+            Self::Resize => "r",
+            Self::Marker => "m",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "o" => Some(Self::Output),
+            "i" => Some(Self::Input),
+            "r" => Some(Self::Resize),
+            "m" => Some(Self::Marker),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Seconds since the start of the recording
+    pub time: f64,
+    pub kind: EventKind,
+    pub data: String,
+}
+
+impl Event {
+    /// Returns the size for a `Resize` event, as `(cols, rows)`
+    pub fn resize_dimensions(&self) -> Option<(usize, usize)> {
+        if self.kind != EventKind::Resize {
+            return None;
+        }
+        let (cols, rows) = self.data.split_once('x')?;
+        Some((cols.parse().ok()?, rows.parse().ok()?))
+    }
+}
+
+/// Decodes a stream of bytes as UTF-8, holding back a sequence that is
+/// split across two chunks until the rest of it arrives.  The format
+/// stores data as JSON strings, so bytes that are not valid UTF-8 are
+/// recorded as U+FFFD.
+#[derive(Default)]
+struct Utf8Stream {
+    pending: Vec<u8>,
+}
+
+impl Utf8Stream {
+    fn decode(&mut self, data: &[u8]) -> String {
+        self.pending.extend_from_slice(data);
+        let mut text = String::new();
+        let mut bytes = &self.pending[..];
+        loop {
+            match std::str::from_utf8(bytes) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    bytes = &[];
+                    break;
+                }
+                Err(err) => {
+                    let (valid, rest) = bytes.split_at(err.valid_up_to());
+                    text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                    match err.error_len() {
+                        Some(len) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            bytes = &rest[len..];
+                        }
+                        // An incomplete sequence at the end
+                        None => {
+                            bytes = rest;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.pending = bytes.to_vec();
+        text
+    }
+}
+
+struct RecorderInner {
+    out: Box<dyn Write + Send>,
+    start: Instant,
+    output: Utf8Stream,
+    input: Utf8Stream,
+    failed: bool,
+}
+
+impl RecorderInner {
+    fn event(&mut self, kind: EventKind, data: &str) {
+        if self.failed || data.is_empty() {
+            return;
+        }
+        let time = (self.start.elapsed().as_secs_f64() * 1e6).round() / 1e6;
+        let line = json!([time, kind.code(), data]).to_string();
+        // Flush each event so that the recording is usable even if
+        // the process doesn't exit cleanly
+        if let Err(err) = writeln!(self.out, "{}", line).and_then(|_| self.out.flush()) {
+            log::error!("failed to write to recording, stopping it: {:#}", err);
+            self.failed = true;
+        }
+    }
+}
+
+/// Writes an asciicast v2 recording.  Clones share the same recording,
+/// so that output can be recorded on the thread reading from the pty
+/// while input is recorded by the writer.
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Arc<Mutex<RecorderInner>>,
+}
+
+impl Recorder {
+    /// Starts a recording of a terminal of the specified size, writing
+    /// the header to `out`
+    pub fn new<W: Write + Send + 'static>(
+        mut out: W,
+        cols: usize,
+        rows: usize,
+    ) -> anyhow::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let header = json!({
+            "version": 2,
+            "width": cols,
+            "height": rows,
+            "timestamp": timestamp,
+        });
+        writeln!(out, "{}", header).context("writing recording header")?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(RecorderInner {
+                out: Box::new(out),
+                start: Instant::now(),
+                output: Utf8Stream::default(),
+                input: Utf8Stream::default(),
+                failed: false,
+            })),
+        })
+    }
+
+    /// Starts a recording in the file at `path`, replacing it if it exists
+    pub fn create<P: AsRef<Path>>(path: P, cols: usize, rows: usize) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("creating recording {}", path.display()))?;
+        Self::new(file, cols, rows)
+    }
+
+    /// Records data output by the program
+    pub fn output(&self, data: &[u8]) {
+        let mut inner = self.inner.lock().unwrap();
+        let text = inner.output.decode(data);
+        inner.event(EventKind::Output, &text);
+    }
+
+    /// Records data sent to the program
+    pub fn input(&self, data: &[u8]) {
+        let mut inner = self.inner.lock().unwrap();
+        let text = inner.input.decode(data);
+        inner.event(EventKind::Input, &text);
+    }
+
+    /// Records a change in the size of the terminal
+    pub fn resize(&self, cols: usize, rows: usize) {
+        self.inner
+            .lock()
+            .unwrap()
+            .event(EventKind::Resize, &format!("{}x{}", cols, rows));
+    }
+
+    /// Records a marker, which players can use as a point to jump to
+    pub fn marker(&self, label: &str) {
+        self.inner.lock().unwrap().event(EventKind::Marker, label);
+    }
+}
+
+/// Wraps the writer connected to the pty, recording what is written
+/// to it as input
+pub struct RecordingWriter {
+    inner: Box<dyn Write + Send>,
+    recorder: Recorder,
+}
+
+impl RecordingWriter {
+    pub fn new(inner: Box<dyn Write + Send>, recorder: Recorder) -> Self {
+        Self { inner, recorder }
+    }
+}
+
+impl Write for RecordingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // Record the input before the program can see it, so that it
+        // comes before the output that it provokes
+        self.recorder.input(buf);
+        self.inner.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// How fast `Recording::play` feeds the events to the terminal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pace {
+    /// Sleep between events to reproduce the recorded timing
+    RealTime,
+    /// Apply the events without waiting, for example for tests or
+    /// benchmarks
+    AsFastAsPossible,
+}
+
+/// A recording loaded from an asciicast v2 file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    pub width: usize,
+    pub height: usize,
+    pub events: Vec<Event>,
+}
+
+impl Recording {
+    /// Loads the recording from the file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("opening recording {}", path.display()))?;
+        Self::parse(BufReader::new(file))
+            .with_context(|| format!("reading recording {}", path.display()))
+    }
+
+    /// Parses a recording.  Events with codes other than the ones
+    /// described by `EventKind` are skipped.
+    pub fn parse<R: BufRead>(reader: R) -> anyhow::Result<Self> {
+        let mut lines = reader.lines().enumerate();
+        let header: Value = loop {
+            match lines.next() {
+                Some((_, line)) => {
+                    let line = line?;
+                    if !line.trim().is_empty() {
+                        break serde_json::from_str(&line).context("parsing header")?;
+                    }
+                }
+                None => bail!("the recording is empty"),
+            }
+        };
+        if header["version"].as_u64() != Some(2) {
+            bail!("unsupported asciicast version {}", header["version"]);
+        }
+        let dimension = |name: &str| {
+            header[name]
+                .as_u64()
+                .map(|n| n as usize)
+                .ok_or_else(|| anyhow!("the header has no {}", name))
+        };
+        let width = dimension("width")?;
+        let height = dimension("height")?;
+
+        let mut events = vec![];
+        for (idx, line) in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value: Value =
+                serde_json::from_str(&line).with_context(|| format!("parsing line {}", idx + 1))?;
+            let (time, code, data) = match value.as_array().map(Vec::as_slice) {
+                Some([time, code, data]) => (time.as_f64(), code.as_str(), data.as_str()),
+                _ => (None, None, None),
+            };
+            let (time, code, data) = match (time, code, data) {
+                (Some(time), Some(code), Some(data)) => (time, code, data),
+                _ => bail!("line {} is not a valid event: {}", idx + 1, line),
+            };
+            if let Some(kind) = EventKind::from_code(code) {
+                events.push(Event {
+                    time,
+                    kind,
+                    data: data.to_string(),
+                });
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            events,
+        })
+    }
+
+    /// Feeds the output and resize events to `terminal`, after resizing
+    /// it to the size in the header.  Input events are not replayed,
+    /// since their effect is part of the recorded output.
+    pub fn play(&self, terminal: &mut TerminalState, pace: Pace) {
+        resize(terminal, self.width, self.height);
+        let mut parser = Parser::new();
+        let start = Instant::now();
+        for event in &self.events {
+            if pace == Pace::RealTime {
+                // Times that are negative, not a number or too large to
+                // represent are applied without waiting
+                let due = Duration::try_from_secs_f64(event.time)
+                    .ok()
+                    .and_then(|offset| start.checked_add(offset));
+                match due {
+                    Some(due) => {
+                        if let Some(delay) = due.checked_duration_since(Instant::now()) {
+                            std::thread::sleep(delay);
+                        }
+                    }
+                    None => log::warn!("not waiting for event with invalid time {}", event.time),
+                }
+            }
+            match event.kind {
+                EventKind::Output => {
+                    terminal.increment_seqno();
+                    parser.parse(event.data.as_bytes(), |action| terminal.perform(action));
+                    terminal.flush_print();
+                }
+                EventKind::Resize => match event.resize_dimensions() {
+                    Some((cols, rows)) => resize(terminal, cols, rows),
+                    None => log::warn!("ignoring invalid resize event {:?}", event.data),
+                },
+                EventKind::Input | EventKind::Marker => {}
+            }
+        }
+    }
+}
+
+fn resize(terminal: &mut TerminalState, cols: usize, rows: usize) {
+    let size = terminal.get_size();
+    if (size.cols, size.rows) != (cols, rows) {
+        terminal.resize(TerminalSize { cols, rows, ..size });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DefaultConfiguration;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_and_play() {
+        let buffer = SharedBuffer::default();
+        let recorder = Recorder::new(buffer.clone(), 10, 3).unwrap();
+        recorder.output(b"hello \xc3");
+        recorder.output(b"\xa9t\xe9\r\n");
+        let mut writer = RecordingWriter::new(Box::new(std::io::sink()), recorder.clone());
+        writer.write_all(b"q").unwrap();
+        recorder.resize(10, 4);
+        recorder.output(b"\x1b[1mbye");
+
+        let recording = Recording::parse(&buffer.0.lock().unwrap()[..]).unwrap();
+        assert_eq!((recording.width, recording.height), (10, 3));
+        let events: Vec<_> = recording
+            .events
+            .iter()
+            .map(|e| (e.kind, e.data.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (EventKind::Output, "hello "),
+                (EventKind::Output, "\u{e9}t\u{fffd}\r\n"),
+                (EventKind::Input, "q"),
+                (EventKind::Resize, "10x4"),
+                (EventKind::Output, "\x1b[1mbye"),
+            ]
+        );
+        assert!(recording.events.windows(2).all(|w| w[0].time <= w[1].time));
+
+        let mut terminal = TerminalState::new(
+            TerminalSize::default(),
+            Arc::new(DefaultConfiguration),
+            "wezterm_portable",
+            env!("CARGO_PKG_VERSION"),
+            Box::new(std::io::sink()),
+        );
+        recording.play(&mut terminal, Pace::AsFastAsPossible);
+        let size = terminal.get_size();
+        assert_eq!((size.cols, size.rows), (10, 4));
+        let lines: Vec<_> = terminal
+            .screen()
+            .visible_lines()
+            .iter()
+            .map(|line| line.as_str().trim_end().to_string())
+            .collect();
+        assert_eq!(lines, vec!["hello \u{e9}t\u{fffd}", "bye", "", ""]);
+    }
+
+    #[test]
+    fn play_invalid_times() {
+        let events = [-1.0, f64::NAN, 1e300, f64::INFINITY]
+            .iter()
+            .map(|&time| Event {
+                time,
+                kind: EventKind::Output,
+                data: "x".to_string(),
+            })
+            .collect();
+        let recording = Recording {
+            width: 10,
+            height: 2,
+            events,
+        };
+        let mut terminal = TerminalState::new(
+            TerminalSize::default(),
+            Arc::new(DefaultConfiguration),
+            "wezterm_portable",
+            env!("CARGO_PKG_VERSION"),
+            Box::new(std::io::sink()),
+        );
+        recording.play(&mut terminal, Pace::RealTime);
+        assert_eq!(
+            terminal.screen().visible_lines()[0].as_str().trim_end(),
+            "xxxx"
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Recording::parse(&b""[..]).is_err());
+        assert!(
+            Recording::parse(&b"{\"version\": 1, \"width\": 80, \"height\": 24}\n"[..]).is_err()
+        );
+        assert!(Recording::parse(
+            &b"{\"version\": 2, \"width\": 80, \"height\": 24}\n[1.0, \"o\"]\n"[..]
+        )
+        .is_err());
+        let recording = Recording::parse(
+            &b"{\"version\": 2, \"width\": 80, \"height\": 24}\n\n[0.5, \"x\", \"?\"]\n[1.0, \"o\", \"hi\"]\n"[..],
+        )
+        .unwrap();
+        assert_eq!(
+            recording.events,
+            vec![Event {
+                time: 1.0,
+                kind: EventKind::Output,
+                data: "hi".to_string(),
+            }]
+        );
+    }
+}
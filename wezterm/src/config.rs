@@ -237,3 +237,14 @@ impl BidiMode {
         line.set_bidi_info(self.enabled, self.hint, seqno);
     }
 }
+
+/// A `TerminalConfiguration` that uses the default color palette, and
+/// the defaults for everything else
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultConfiguration;
+
+impl TerminalConfiguration for DefaultConfiguration {
+    fn color_palette(&self) -> ColorPalette {
+        ColorPalette::default()
+    }
+}
@@ -0,0 +1,383 @@
+//! Drives a program running in a pty, for testing what it displays.
+//!
+//! `TerminalHarness` spawns a command in a pty and feeds its output
+//! into a `TerminalState` via a `session::TerminalSession`.  Tests can then
+//! send it keys, wait for text to appear or for the output to settle,
+//! and compare the screen against a golden snapshot:
+//!
+//! ```no_run
+//! use portable_pty::CommandBuilder;
+//! use std::time::Duration;
+//! use wezterm_portable::harness::TerminalHarness;
+//! use wezterm_portable::terminalstate::TerminalSize;
+//!
+//! let size = TerminalSize { rows: 5, cols: 20, ..Default::default() };
+//! let mut harness = TerminalHarness::spawn(CommandBuilder::new("my-cli"), size).unwrap();
+//! harness.wait_for_text("ready>", Duration::from_secs(5)).unwrap();
+//! harness.send_text("help\r").unwrap();
+//! harness.wait_for_quiescence(Duration::from_millis(100), Duration::from_secs(5)).unwrap();
+//! harness.assert_snapshot("tests/snapshots/help.snap");
+//! ```
+use super::asciicast::Recorder;
+use super::config::{DefaultConfiguration, TerminalConfiguration};
+use super::session::TerminalSession;
+use super::terminalstate::{TerminalSize, TerminalState};
+use anyhow::{bail, Context};
+use portable_pty::{CommandBuilder, ExitStatus};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termwiz::cell::{Blink, CellAttributes, Intensity, Underline};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{KeyCode, Modifiers};
+
+/// Setting this environment variable makes `assert_snapshot` write
+/// the golden files rather than comparing against them
+pub const UPDATE_SNAPSHOTS_ENV: &str = "WEZTERM_UPDATE_SNAPSHOTS";
+
+/// How often `wait_for_exit` checks whether the process has exited
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A program running in a pty, whose output is applied to a
+/// `TerminalState`.  The process is killed when the harness is dropped.
+pub struct TerminalHarness {
+    session: TerminalSession,
+}
+
+impl TerminalHarness {
+    /// Spawns `cmd` in a pty of the specified size
+    pub fn spawn(cmd: CommandBuilder, size: TerminalSize) -> anyhow::Result<Self> {
+        Self::spawn_with_config(cmd, size, Arc::new(DefaultConfiguration))
+    }
+
+    pub fn spawn_with_config(
+        cmd: CommandBuilder,
+        size: TerminalSize,
+        config: Arc<dyn TerminalConfiguration>,
+    ) -> anyhow::Result<Self> {
+        Self::spawn_recorded(cmd, size, config, None)
+    }
+
+    /// Spawns `cmd` like `spawn_with_config`, recording its output, the
+    /// input sent to it and any resizes with `recorder`, if any.  The
+    /// recording can be replayed with `asciicast::Recording::play` to
+    /// reproduce what the terminal showed.
+    pub fn spawn_recorded(
+        cmd: CommandBuilder,
+        size: TerminalSize,
+        config: Arc<dyn TerminalConfiguration>,
+        recorder: Option<Recorder>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            session: TerminalSession::spawn(cmd, size, config, recorder)?,
+        })
+    }
+
+    pub fn terminal(&self) -> &TerminalState {
+        self.session.terminal()
+    }
+
+    pub fn terminal_mut(&mut self) -> &mut TerminalState {
+        self.session.terminal_mut()
+    }
+
+    /// Returns true once the output has ended, which is when the
+    /// process has exited or closed the pty
+    pub fn is_dead(&self) -> bool {
+        self.session.is_dead()
+    }
+
+    /// Sends a key press, encoded according to the modes that the
+    /// program has enabled
+    pub fn send_key(&mut self, key: KeyCode, mods: Modifiers) -> anyhow::Result<()> {
+        self.terminal_mut().key_down(key, mods)
+    }
+
+    /// Types `text`, one key press per character.  Use `\r` for Enter.
+    pub fn send_text(&mut self, text: &str) -> anyhow::Result<()> {
+        for c in text.chars() {
+            self.send_key(KeyCode::Char(c), Modifiers::NONE)?;
+        }
+        Ok(())
+    }
+
+    /// Pastes `text`, bracketing it if the program has enabled
+    /// bracketed paste
+    pub fn send_paste(&mut self, text: &str) -> anyhow::Result<()> {
+        self.terminal_mut().send_paste(text)?;
+        Ok(())
+    }
+
+    /// Resizes the terminal and the pty
+    pub fn resize(&mut self, cols: usize, rows: usize) -> anyhow::Result<()> {
+        self.session.resize(cols, rows)
+    }
+
+    /// Applies any output that has already arrived, returning true
+    /// if there was some
+    pub fn pump(&mut self) -> bool {
+        self.session.pump()
+    }
+
+    /// Waits up to `timeout` for `text` to appear on the screen.
+    /// The lines are searched including their trailing blanks, so text
+    /// that spans several lines only matches when its lines are full width.
+    pub fn wait_for_text(&mut self, text: &str, timeout: Duration) -> anyhow::Result<()> {
+        let deadline = Instant::now() + timeout;
+        self.pump();
+        while !self.screen_contains(text) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::ZERO || self.session.is_dead() {
+                bail!(
+                    "{} waiting for {:?}; the screen shows:\n{}",
+                    if self.session.is_dead() {
+                        "output ended"
+                    } else {
+                        "timed out"
+                    },
+                    text,
+                    self.screen_text()
+                );
+            }
+            self.session.pump_for(remaining);
+        }
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for the output to pause for at least
+    /// `quiet`, or to end
+    pub fn wait_for_quiescence(
+        &mut self,
+        quiet: Duration,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        let deadline = Instant::now() + timeout;
+        let mut last_output = Instant::now();
+        loop {
+            if self.pump() {
+                last_output = Instant::now();
+            }
+            if self.session.is_dead() {
+                return Ok(());
+            }
+            let now = Instant::now();
+            let settled = last_output + quiet;
+            if now >= settled {
+                return Ok(());
+            }
+            if now >= deadline {
+                bail!(
+                    "timed out waiting for the output to pause for {:?}; the screen shows:\n{}",
+                    quiet,
+                    self.screen_text()
+                );
+            }
+            if self.session.pump_for(settled.min(deadline) - now) {
+                last_output = Instant::now();
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for the process to exit, applying its
+    /// output in the meantime
+    pub fn wait_for_exit(&mut self, timeout: Duration) -> anyhow::Result<ExitStatus> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = self.session.try_wait().context("checking for exit")? {
+                // Pick up whatever was output before it exited
+                while !self.session.is_dead() && self.session.pump_for(EXIT_POLL_INTERVAL) {}
+                return Ok(status);
+            }
+            if Instant::now() >= deadline {
+                bail!(
+                    "timed out waiting for the process to exit; the screen shows:\n{}",
+                    self.screen_text()
+                );
+            }
+            self.session.pump_for(EXIT_POLL_INTERVAL);
+        }
+    }
+
+    /// Returns true if `text` appears on the screen.  Unlike `screen_text`,
+    /// the trailing whitespace of the lines is kept, so that `text` can end
+    /// with a space.
+    fn screen_contains(&self, text: &str) -> bool {
+        self.terminal()
+            .screen()
+            .visible_lines()
+            .iter()
+            .map(|line| line.as_str().into_owned())
+            .collect::<Vec<_>>()
+            .join("\n")
+            .contains(text)
+    }
+
+    /// Returns the text of the visible lines, without trailing
+    /// whitespace, separated by newlines
+    pub fn screen_text(&self) -> String {
+        self.terminal()
+            .screen()
+            .visible_lines()
+            .iter()
+            .map(|line| line.as_str().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Captures the text, attributes and cursor position of the
+    /// visible screen
+    pub fn snapshot(&self) -> Snapshot {
+        let lines = self.terminal().screen().visible_lines();
+        let mut text = String::new();
+        for line in &lines {
+            text.push_str(line.as_str().trim_end());
+            text.push('\n');
+        }
+
+        text.push_str("--\n");
+        for (row, line) in lines.iter().enumerate() {
+            let mut run: Option<(usize, usize, String)> = None;
+            for cell in line.visible_cells() {
+                let attrs = describe_attributes(cell.attrs());
+                match &mut run {
+                    Some((_, end, current)) if *current == attrs && *end == cell.cell_index() => {
+                        *end += cell.width();
+                        continue;
+                    }
+                    _ => {}
+                }
+                if let Some((start, end, attrs)) = run.take() {
+                    text.push_str(&format!("{}:{}-{} {}\n", row, start, end, attrs));
+                }
+                if !attrs.is_empty() {
+                    run = Some((cell.cell_index(), cell.cell_index() + cell.width(), attrs));
+                }
+            }
+            if let Some((start, end, attrs)) = run {
+                text.push_str(&format!("{}:{}-{} {}\n", row, start, end, attrs));
+            }
+        }
+
+        let cursor = self.terminal().cursor_pos();
+        text.push_str(&format!("--\ncursor {},{}\n", cursor.x, cursor.y));
+        Snapshot(text)
+    }
+
+    /// Compares the screen against the snapshot in the golden file at
+    /// `path`, panicking if they differ.  When the environment variable
+    /// named by `UPDATE_SNAPSHOTS_ENV` is set, the golden file is written
+    /// instead.
+    pub fn assert_snapshot<P: AsRef<Path>>(&self, path: P) {
+        let path = path.as_ref();
+        let actual = self.snapshot();
+        if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).ok();
+            }
+            if let Err(err) = std::fs::write(path, actual.as_str()) {
+                panic!("failed to write snapshot {}: {:#}", path.display(), err);
+            }
+            return;
+        }
+
+        let expected = match std::fs::read_to_string(path) {
+            Ok(expected) => expected,
+            Err(err) => panic!(
+                "failed to read snapshot {}: {:#}\n\
+                 set {} to create it; the screen is:\n{}",
+                path.display(),
+                err,
+                UPDATE_SNAPSHOTS_ENV,
+                actual
+            ),
+        };
+        if expected != actual.as_str() {
+            panic!(
+                "the screen doesn't match snapshot {}\n\
+                 set {} to update it\n\
+                 expected:\n{}\nactual:\n{}",
+                path.display(),
+                UPDATE_SNAPSHOTS_ENV,
+                expected,
+                actual
+            );
+        }
+    }
+}
+
+/// The contents of the screen in the form used for golden files.
+/// It consists of three sections separated by `--` lines: the text of
+/// each visible line, without trailing whitespace; the runs of cells
+/// with attributes other than the defaults, as `row:start-end` followed
+/// by the attributes; and the position of the cursor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot(String);
+
+impl Snapshot {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Describes the attributes that differ from the defaults, as a
+/// space separated list of words
+fn describe_attributes(attrs: &CellAttributes) -> String {
+    let mut words = vec![];
+    match attrs.intensity() {
+        Intensity::Normal => {}
+        Intensity::Bold => words.push("bold".to_string()),
+        Intensity::Half => words.push("dim".to_string()),
+    }
+    match attrs.underline() {
+        Underline::None => {}
+        Underline::Single => words.push("underline".to_string()),
+        Underline::Double => words.push("double-underline".to_string()),
+        Underline::Curly => words.push("curly-underline".to_string()),
+        Underline::Dotted => words.push("dotted-underline".to_string()),
+        Underline::Dashed => words.push("dashed-underline".to_string()),
+    }
+    match attrs.blink() {
+        Blink::None => {}
+        Blink::Slow => words.push("blink".to_string()),
+        Blink::Rapid => words.push("rapid-blink".to_string()),
+    }
+    for (set, word) in [
+        (attrs.italic(), "italic"),
+        (attrs.reverse(), "reverse"),
+        (attrs.strikethrough(), "strikethrough"),
+        (attrs.invisible(), "invisible"),
+        (attrs.overline(), "overline"),
+    ]
+    .iter()
+    {
+        if *set {
+            words.push(word.to_string());
+        }
+    }
+    if let Some(color) = describe_color(attrs.foreground()) {
+        words.push(format!("fg={}", color));
+    }
+    if let Some(color) = describe_color(attrs.background()) {
+        words.push(format!("bg={}", color));
+    }
+    if let Some(link) = attrs.hyperlink() {
+        words.push(format!("link={}", link.uri()));
+    }
+    words.join(" ")
+}
+
+fn describe_color(color: ColorAttribute) -> Option<String> {
+    match color {
+        ColorAttribute::Default => None,
+        ColorAttribute::PaletteIndex(idx) => Some(idx.to_string()),
+        ColorAttribute::TrueColorWithDefaultFallback(color)
+        | ColorAttribute::TrueColorWithPaletteFallback(color, _) => Some(color.to_rgb_string()),
+    }
+}
@@ -3,4 +3,8 @@ pub mod threaded_writer;
 pub mod terminalstate;
 pub mod config;
 pub mod color;
-pub mod screen;
\ No newline at end of file
+pub mod screen;
+pub mod session;
+pub mod widget;
+pub mod harness;
+pub mod asciicast;
@@ -1,6 +1,7 @@
 // forked from wezterm/mux/src/lib.rs git commit f4abf8fde
 // MIT License
 
+use crate::asciicast::Recorder;
 use anyhow::Context;
 use filedescriptor::{poll, pollfd, socketpair, AsRawSocketDescriptor, FileDescriptor, POLLIN};
 #[cfg(unix)]
@@ -157,8 +158,18 @@ fn allocate_socketpair() -> anyhow::Result<(FileDescriptor, FileDescriptor)> {
 /// all platforms and pty/tty types), parse the escape sequences and
 /// relay the actions to the mux thread to apply them to the pane.
 pub fn read_from_pty(
+    actions_sender: ActionsSender,
+    pty_reader: Box<dyn std::io::Read>,
+) {
+    read_from_pty_with_recorder(actions_sender, pty_reader, None)
+}
+
+/// Like `read_from_pty`, but also passes the data read from the pty
+/// to `recorder`, if any, before it is parsed
+pub fn read_from_pty_with_recorder(
     actions_sender: ActionsSender,
     mut pty_reader: Box<dyn std::io::Read>,
+    recorder: Option<Recorder>,
 ) {
     let mut buf = vec![0; BUFSIZE];
 
@@ -199,6 +210,10 @@ pub fn read_from_pty(
             Ok(size) => {
                 log::trace!("read_pty read {size} bytes");
 
+                if let Some(recorder) = &recorder {
+                    recorder.output(&buf[..size]);
+                }
+
                 if let Err(err) = tx.write_all(&buf[..size]) {
                     log::error!(
                         "read_pty failed to write to parser: {:?}",
@@ -388,7 +388,6 @@ impl Screen {
     }
 
     /// Returns a copy of the visible lines in the screen (no scrollback)
-    #[cfg(test)]
     pub fn visible_lines(&self) -> Vec<Line> {
         let line_idx = self.lines.len() - self.physical_rows;
         let mut lines = Vec::new();
@@ -0,0 +1,209 @@
+//! A `TerminalState` that is fed the output of a program, usually one
+//! running in a pty, as used by both `harness::TerminalHarness` and
+//! `widget::TerminalWidget`.
+use super::asciicast::{Recorder, RecordingWriter};
+use super::config::TerminalConfiguration;
+use super::mux::{read_from_pty_with_recorder, ActionsReceiver};
+use super::terminalstate::{TerminalSize, TerminalState};
+use crossbeam_channel::{Select, TryRecvError};
+use portable_pty::{native_pty_system, Child, CommandBuilder, ExitStatus, MasterPty, PtySize};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(crate) fn pty_size(size: &TerminalSize) -> PtySize {
+    PtySize {
+        rows: size.rows as u16,
+        cols: size.cols as u16,
+        pixel_width: size.pixel_width as u16,
+        pixel_height: size.pixel_height as u16,
+    }
+}
+
+/// The pty, the process running in it and the recorder, if any,
+/// of the session
+struct Pty {
+    master: Box<dyn MasterPty + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    recorder: Option<Recorder>,
+}
+
+/// A terminal along with the channel on which the actions parsed from
+/// the output of its program arrive, as produced by
+/// `mux::read_from_pty`.  When the session spawned the program, it
+/// also owns the pty, and the process is killed when it is dropped.
+pub struct TerminalSession {
+    terminal: TerminalState,
+    actions: ActionsReceiver,
+    pty: Option<Pty>,
+    /// Set once all of the output has been applied
+    dead: bool,
+}
+
+impl TerminalSession {
+    /// Applies the actions received from `actions` to `terminal`.
+    /// Input is written to the writer with which the terminal was created.
+    pub fn new(terminal: TerminalState, actions: ActionsReceiver) -> Self {
+        Self {
+            terminal,
+            actions,
+            pty: None,
+            dead: false,
+        }
+    }
+
+    /// Runs `cmd` in a new pty of the specified size.  The output, the
+    /// input sent to it and any resizes are recorded with `recorder`,
+    /// if any.
+    pub fn spawn(
+        cmd: CommandBuilder,
+        size: TerminalSize,
+        config: Arc<dyn TerminalConfiguration>,
+        recorder: Option<Recorder>,
+    ) -> anyhow::Result<Self> {
+        let pair = native_pty_system().openpty(pty_size(&size))?;
+        let child = pair.slave.spawn_command(cmd)?;
+        // Release our handle on the slave so that we see EOF
+        // once the child exits
+        drop(pair.slave);
+
+        let reader = pair.master.try_clone_reader()?;
+        let mut writer = pair.master.take_writer()?;
+        if let Some(recorder) = &recorder {
+            writer = Box::new(RecordingWriter::new(writer, recorder.clone()));
+        }
+        let terminal = TerminalState::new(
+            size,
+            config,
+            "wezterm_portable",
+            env!("CARGO_PKG_VERSION"),
+            writer,
+        );
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        std::thread::spawn({
+            let recorder = recorder.clone();
+            move || read_from_pty_with_recorder(sender, reader, recorder)
+        });
+
+        let mut session = Self::new(terminal, receiver);
+        session.pty = Some(Pty {
+            master: pair.master,
+            child,
+            recorder,
+        });
+        Ok(session)
+    }
+
+    /// Calls `notify` each time that output arrives, and once more
+    /// when it ends, from a thread that relays the output to the session
+    pub fn notify_on_output(&mut self, notify: impl Fn() + Send + 'static) {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let actions = std::mem::replace(&mut self.actions, receiver);
+        std::thread::spawn(move || {
+            for batch in actions {
+                if sender.send(batch).is_err() {
+                    return;
+                }
+                notify();
+            }
+            // Let the session find out that the output has ended
+            drop(sender);
+            notify();
+        });
+    }
+
+    pub fn terminal(&self) -> &TerminalState {
+        &self.terminal
+    }
+
+    pub fn terminal_mut(&mut self) -> &mut TerminalState {
+        &mut self.terminal
+    }
+
+    /// Returns true once the output has ended; for a spawned process,
+    /// this is when it has exited or closed the pty
+    pub fn is_dead(&self) -> bool {
+        self.dead
+    }
+
+    /// Returns the exit status of the spawned process if it has
+    /// terminated.  Always returns None if no process was spawned.
+    pub fn try_wait(&mut self) -> anyhow::Result<Option<ExitStatus>> {
+        match &mut self.pty {
+            Some(pty) => Ok(pty.child.try_wait()?),
+            None => Ok(None),
+        }
+    }
+
+    /// Resizes the terminal, and the pty, recording the new size when
+    /// the session is being recorded
+    pub fn resize(&mut self, cols: usize, rows: usize) -> anyhow::Result<()> {
+        let size = TerminalSize {
+            cols,
+            rows,
+            ..self.terminal.get_size()
+        };
+        self.terminal.resize(size);
+        match &self.pty {
+            Some(pty) => {
+                if let Some(recorder) = &pty.recorder {
+                    recorder.resize(cols, rows);
+                }
+                pty.master.resize(pty_size(&size))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Applies any output that has already arrived, returning true
+    /// if there was some
+    pub fn pump(&mut self) -> bool {
+        let mut applied = false;
+        loop {
+            match self.actions.try_recv() {
+                Ok(actions) => {
+                    self.terminal.increment_seqno();
+                    for action in actions {
+                        self.terminal.perform(action);
+                    }
+                    applied = true;
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.dead = true;
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+        if applied {
+            self.terminal.flush_print();
+        }
+        applied
+    }
+
+    /// Waits up to `timeout` for some output to arrive, or for it to
+    /// end, without applying it.  Returns false if the wait timed out.
+    pub fn wait_for_output(&self, timeout: Duration) -> bool {
+        let mut select = Select::new();
+        select.recv(&self.actions);
+        select.ready_timeout(timeout).is_ok()
+    }
+
+    /// Waits up to `timeout` for some output to arrive and applies it,
+    /// returning true if there was some
+    pub fn pump_for(&mut self, timeout: Duration) -> bool {
+        if self.dead {
+            std::thread::sleep(timeout);
+            return false;
+        }
+        self.wait_for_output(timeout) && self.pump()
+    }
+}
+
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        if let Some(pty) = &mut self.pty {
+            pty.child.kill().ok();
+        }
+    }
+}
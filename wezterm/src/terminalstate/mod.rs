@@ -2009,6 +2009,14 @@ impl TerminalState {
             Mode::QueryDecPrivateMode(_) | Mode::QueryMode(_) => {
                 self.decqrm_response(mode, false, false);
             }
+
+            Mode::ReportDecPrivateMode { .. } | Mode::ReportMode { .. } => {
+                // These are responses sent by a terminal; they have
+                // no meaning when sent to us
+                if self.config.log_unknown_escape_sequences() {
+                    log::warn!("unhandled mode report {:?}", mode);
+                }
+            }
         }
     }
 
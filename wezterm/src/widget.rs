@@ -0,0 +1,355 @@
+//! A termwiz widget that embeds a terminal emulator, along with the
+//! process running in it, so that terminal programs can be hosted in
+//! a pane of a termwiz `Ui`.
+
+use super::asciicast::Recorder;
+use super::config::TerminalConfiguration;
+use super::mux::ActionsReceiver;
+use super::session::TerminalSession;
+use super::terminalstate::mouse::{MouseButton, MouseEvent, MouseEventKind};
+use super::terminalstate::{TerminalSize, TerminalState};
+use portable_pty::{CommandBuilder, ExitStatus};
+use std::sync::{Arc, Mutex};
+use termwiz::cell::CellAttributes;
+use termwiz::input::{InputEvent, MouseButtons, MouseEvent as InputMouseEvent};
+use termwiz::surface::{Change, Line, Position, Surface};
+use termwiz::widgets::layout::Constraints;
+use termwiz::widgets::{
+    CursorShapeAndPosition, RenderArgs, UiEventSender, UpdateArgs, Widget, WidgetEvent, WidgetId,
+};
+
+/// Sent to the widget via the `UiEventSender` when there is output
+/// waiting to be applied to the terminal
+struct OutputReady;
+
+/// Where to send `OutputReady`; both are needed before it can be sent
+#[derive(Default)]
+struct Notify {
+    sender: Option<UiEventSender>,
+    id: Option<WidgetId>,
+}
+
+impl Notify {
+    fn output_ready(&self) {
+        if let (Some(sender), Some(id)) = (&self.sender, self.id) {
+            sender.send(id, OutputReady).ok();
+        }
+    }
+}
+
+/// Hosts a `TerminalState`, rendering its visible lines and cursor,
+/// and feeding it the keys, mouse reports, pastes and focus changes
+/// that the widget receives.  The terminal, and the pty when there is
+/// one, are resized to match the area allotted to the widget.
+///
+/// Output is applied to the terminal each time that the widget is
+/// rendered.  Provide a `UiEventSender` via `set_event_sender` to have
+/// the `Ui` woken up as soon as output arrives.
+pub struct TerminalWidget {
+    session: TerminalSession,
+    notify: Arc<Mutex<Notify>>,
+    /// The buttons held as of the previous mouse report, used to
+    /// tell presses and releases apart from motion
+    mouse_buttons: MouseButtons,
+    constraints: Constraints,
+}
+
+impl TerminalWidget {
+    /// Hosts a terminal whose output has been parsed into the actions
+    /// received from `actions`, as produced by `mux::read_from_pty`.
+    /// Input is written to the writer with which the terminal was created.
+    pub fn new(terminal: TerminalState, actions: ActionsReceiver) -> Self {
+        Self::with_session(TerminalSession::new(terminal, actions))
+    }
+
+    /// Runs `cmd` in a new pty, hosting its terminal.  The output, the
+    /// input sent to it and any resizes are recorded with `recorder`,
+    /// if any.
+    pub fn spawn(
+        cmd: CommandBuilder,
+        config: Arc<dyn TerminalConfiguration>,
+        recorder: Option<Recorder>,
+    ) -> anyhow::Result<Self> {
+        let session = TerminalSession::spawn(cmd, TerminalSize::default(), config, recorder)?;
+        Ok(Self::with_session(session))
+    }
+
+    fn with_session(mut session: TerminalSession) -> Self {
+        // Let the Ui know when output arrives
+        let notify = Arc::new(Mutex::new(Notify::default()));
+        session.notify_on_output({
+            let notify = Arc::clone(&notify);
+            move || notify.lock().unwrap().output_ready()
+        });
+        Self {
+            session,
+            notify,
+            mouse_buttons: MouseButtons::NONE,
+            constraints: Constraints::default(),
+        }
+    }
+
+    /// Sets the sender used to wake the `Ui` when output arrives
+    pub fn set_event_sender(&mut self, sender: UiEventSender) {
+        self.notify.lock().unwrap().sender = Some(sender);
+    }
+
+    pub fn terminal(&self) -> &TerminalState {
+        self.session.terminal()
+    }
+
+    pub fn terminal_mut(&mut self) -> &mut TerminalState {
+        self.session.terminal_mut()
+    }
+
+    /// Returns the title set by the program running in the terminal
+    pub fn title(&self) -> &str {
+        self.terminal().get_title()
+    }
+
+    /// Returns true once the output has ended; for a spawned process,
+    /// this is when it has exited or closed the pty
+    pub fn is_dead(&self) -> bool {
+        self.session.is_dead()
+    }
+
+    /// Returns the exit status of the spawned process once it has
+    /// terminated.  Always returns None if no process was spawned.
+    pub fn exit_status(&mut self) -> Option<ExitStatus> {
+        self.session.try_wait().ok()?
+    }
+
+    pub fn set_constraints(&mut self, constraints: Constraints) {
+        self.constraints = constraints;
+    }
+
+    /// Resizes the terminal, and the pty, to `cols` x `rows`
+    fn resize(&mut self, cols: usize, rows: usize) {
+        let size = self.terminal().get_size();
+        if (size.cols, size.rows) == (cols, rows) || cols == 0 || rows == 0 {
+            return;
+        }
+        if let Err(err) = self.session.resize(cols, rows) {
+            log::error!("failed to resize pty: {:#}", err);
+        }
+    }
+
+    /// Converts a termwiz mouse report, in which the buttons are those
+    /// held at the time of the report, into the press, release and
+    /// motion events expected by the terminal
+    fn mouse_event(&mut self, mouse: &InputMouseEvent) -> anyhow::Result<()> {
+        let buttons = &mouse.mouse_buttons;
+        let positive = buttons.contains(MouseButtons::WHEEL_POSITIVE);
+        let (kind, button) = if buttons.contains(MouseButtons::VERT_WHEEL) {
+            let button = if positive {
+                MouseButton::WheelUp(1)
+            } else {
+                MouseButton::WheelDown(1)
+            };
+            (MouseEventKind::Press, button)
+        } else if buttons.contains(MouseButtons::HORZ_WHEEL) {
+            let button = if positive {
+                MouseButton::WheelLeft(1)
+            } else {
+                MouseButton::WheelRight(1)
+            };
+            (MouseEventKind::Press, button)
+        } else {
+            let mapping = [
+                (MouseButtons::LEFT, MouseButton::Left),
+                (MouseButtons::MIDDLE, MouseButton::Middle),
+                (MouseButtons::RIGHT, MouseButton::Right),
+            ];
+            let pressed = mapping.iter().find(|(b, _)| {
+                buttons.contains(b.clone()) && !self.mouse_buttons.contains(b.clone())
+            });
+            let released = mapping.iter().find(|(b, _)| {
+                !buttons.contains(b.clone()) && self.mouse_buttons.contains(b.clone())
+            });
+            let held = mapping.iter().find(|(b, _)| buttons.contains(b.clone()));
+            self.mouse_buttons = buttons.clone();
+            match (pressed, released, held) {
+                (Some((_, button)), _, _) => (MouseEventKind::Press, *button),
+                (None, Some((_, button)), _) => (MouseEventKind::Release, *button),
+                (None, None, Some((_, button))) => (MouseEventKind::Move, *button),
+                (None, None, None) => (MouseEventKind::Move, MouseButton::None),
+            }
+        };
+
+        self.session.terminal_mut().mouse_event(MouseEvent {
+            kind,
+            x: mouse.x as usize,
+            y: mouse.y as i64,
+            x_pixel_offset: 0,
+            y_pixel_offset: 0,
+            button,
+            modifiers: mouse.modifiers,
+        })
+    }
+}
+
+/// Renders a line of the terminal into row `y` of the surface
+fn render_line(surface: &mut Surface, y: usize, line: &Line, cols: usize) {
+    surface.add_change(Change::CursorPosition {
+        x: Position::Absolute(0),
+        y: Position::Absolute(y),
+    });
+    // Group runs of cells with the same attributes into a single change
+    let mut run = String::new();
+    let mut attrs = CellAttributes::default();
+    for cell in line.visible_cells() {
+        if cell.cell_index() + cell.width() > cols {
+            break;
+        }
+        if *cell.attrs() != attrs {
+            if !run.is_empty() {
+                surface.add_change(std::mem::take(&mut run));
+            }
+            attrs = cell.attrs().clone();
+            surface.add_change(Change::AllAttributes(attrs.clone()));
+        }
+        run.push_str(cell.str());
+    }
+    if !run.is_empty() {
+        surface.add_change(run);
+    }
+    surface.add_change(Change::AllAttributes(CellAttributes::default()));
+}
+
+impl Widget for TerminalWidget {
+    fn process_event(&mut self, event: &WidgetEvent, _args: &mut UpdateArgs) -> bool {
+        let result = match event {
+            WidgetEvent::User(value) if value.is::<OutputReady>() => {
+                self.session.pump();
+                Ok(())
+            }
+            WidgetEvent::Input(InputEvent::Key(key)) if key.is_release() => Ok(()),
+            WidgetEvent::Input(InputEvent::Key(key)) => {
+                self.session.terminal_mut().key_down(key.key, key.modifiers)
+            }
+            WidgetEvent::Input(InputEvent::Mouse(mouse)) => self.mouse_event(mouse),
+            WidgetEvent::Input(InputEvent::Paste(text)) => {
+                self.session.terminal_mut().send_paste(text)
+            }
+            WidgetEvent::FocusGained => {
+                self.session.terminal_mut().focus_changed(true);
+                Ok(())
+            }
+            WidgetEvent::FocusLost => {
+                self.session.terminal_mut().focus_changed(false);
+                Ok(())
+            }
+            _ => return false,
+        };
+        if let Err(err) = result {
+            log::error!("terminal widget: {:#}", err);
+        }
+        true
+    }
+
+    fn render(&mut self, args: &mut RenderArgs) {
+        self.session.pump();
+        let (cols, rows) = args.surface.dimensions();
+        self.resize(cols, rows);
+
+        args.surface
+            .add_change(Change::ClearScreen(Default::default()));
+        let screen = self.terminal().screen();
+        let range = screen.phys_range(&(0..screen.physical_rows as i64));
+        screen.with_phys_lines(range, |lines| {
+            for (y, line) in lines.iter().enumerate() {
+                render_line(args.surface, y, line, cols);
+            }
+        });
+
+        let cursor = self.terminal().cursor_pos();
+        *args.cursor = CursorShapeAndPosition {
+            coords: (cursor.x, cursor.y.max(0) as usize).into(),
+            shape: cursor.shape,
+            visibility: cursor.visibility,
+            ..Default::default()
+        };
+    }
+
+    fn get_size_constraints(&self) -> Constraints {
+        self.constraints
+    }
+
+    fn accepts_focus(&self) -> bool {
+        true
+    }
+
+    fn on_mount(&mut self, args: &mut UpdateArgs) {
+        self.notify.lock().unwrap().id = Some(args.id);
+    }
+
+    fn on_unmount(&mut self, _args: &mut UpdateArgs) {
+        self.notify.lock().unwrap().id = None;
+    }
+
+    fn as_any(&self) -> Option<&dyn std::any::Any> {
+        Some(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn std::any::Any> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::DefaultConfiguration;
+    use std::time::Duration;
+    use termwiz::widgets::Ui;
+
+    #[test]
+    fn render_terminal() {
+        let size = TerminalSize {
+            rows: 3,
+            cols: 8,
+            ..Default::default()
+        };
+        let terminal = TerminalState::new(
+            size,
+            Arc::new(DefaultConfiguration),
+            "test",
+            "0",
+            Box::new(std::io::sink()),
+        );
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut ui = Ui::new();
+        let id = ui.set_root(TerminalWidget::new(terminal, receiver));
+
+        let mut parser = termwiz::escape::parser::Parser::new();
+        sender
+            .send(parser.parse_as_vec(b"hello\r\n\x1b[1mworld"))
+            .unwrap();
+        drop(sender);
+
+        // The relay thread delivers the output, and then its end,
+        // asynchronously; wait for each before rendering it
+        let mut surface = Surface::new(8, 3);
+        while !ui.widget::<TerminalWidget>(id).unwrap().is_dead() {
+            let widget = ui.widget::<TerminalWidget>(id).unwrap();
+            assert!(
+                widget.session.wait_for_output(Duration::from_secs(5)),
+                "timed out waiting for the output"
+            );
+            ui.render_to_screen(&mut surface).unwrap();
+        }
+        assert_eq!(
+            surface.screen_chars_to_string(),
+            "hello   \nworld   \n        \n"
+        );
+        assert_eq!(surface.cursor_position(), (5, 1));
+        assert!(ui.widget::<TerminalWidget>(id).unwrap().is_dead());
+
+        // The terminal follows the size of the widget
+        let mut surface = Surface::new(10, 4);
+        ui.render_to_screen(&mut surface).unwrap();
+        let widget = ui.widget::<TerminalWidget>(id).unwrap();
+        let size = widget.terminal().get_size();
+        assert_eq!((size.cols, size.rows), (10, 4));
+    }
+}
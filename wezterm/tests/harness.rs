@@ -0,0 +1,101 @@
+#![cfg(unix)]
+use portable_pty::CommandBuilder;
+use std::sync::Arc;
+use std::time::Duration;
+use wezterm_portable::asciicast::{EventKind, Pace, Recorder, Recording};
+use wezterm_portable::config::DefaultConfiguration;
+use wezterm_portable::harness::TerminalHarness;
+use wezterm_portable::terminalstate::{TerminalSize, TerminalState};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn snapshot(name: &str) -> String {
+    format!(
+        "{}/tests/snapshots/{}.snap",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    )
+}
+
+#[test]
+fn prompt_and_reply() {
+    let mut cmd = CommandBuilder::new("sh");
+    cmd.args([
+        "-c",
+        r#"printf 'hello\n\033[1;31mred\033[0m \033[4;38;2;0;128;255mblue\033[0m\n> '; read line; echo "got $line""#,
+    ]);
+    let size = TerminalSize {
+        rows: 5,
+        cols: 20,
+        ..Default::default()
+    };
+    let mut harness = TerminalHarness::spawn(cmd, size).unwrap();
+
+    harness.wait_for_text("> ", TIMEOUT).unwrap();
+    harness
+        .wait_for_quiescence(Duration::from_millis(50), TIMEOUT)
+        .unwrap();
+    harness.assert_snapshot(snapshot("prompt"));
+
+    harness.send_text("abc\r").unwrap();
+    harness.wait_for_text("got abc", TIMEOUT).unwrap();
+    assert!(harness.wait_for_exit(TIMEOUT).unwrap().success());
+    assert!(harness.is_dead());
+    harness.assert_snapshot(snapshot("reply"));
+}
+
+#[test]
+fn record_and_replay() {
+    let path = std::env::temp_dir().join(format!("wezterm-harness-{}.cast", std::process::id()));
+    let mut cmd = CommandBuilder::new("sh");
+    cmd.args([
+        "-c",
+        r#"printf '\033[7mname?\033[0m '; read line; echo "hi $line""#,
+    ]);
+    let size = TerminalSize {
+        rows: 4,
+        cols: 20,
+        ..Default::default()
+    };
+    let recorder = Recorder::create(&path, size.cols, size.rows).unwrap();
+    let mut harness =
+        TerminalHarness::spawn_recorded(cmd, size, Arc::new(DefaultConfiguration), Some(recorder))
+            .unwrap();
+
+    harness.wait_for_text("name? ", TIMEOUT).unwrap();
+    harness.resize(20, 5).unwrap();
+    harness.send_text("bob\r").unwrap();
+    harness.wait_for_text("hi bob", TIMEOUT).unwrap();
+    assert!(harness.wait_for_exit(TIMEOUT).unwrap().success());
+
+    let recording = Recording::load(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!((recording.width, recording.height), (20, 4));
+    let input: String = recording
+        .events
+        .iter()
+        .filter(|e| e.kind == EventKind::Input)
+        .map(|e| e.data.as_str())
+        .collect();
+    assert_eq!(input, "bob\r");
+    assert!(recording
+        .events
+        .iter()
+        .any(|e| e.resize_dimensions() == Some((20, 5))));
+
+    let mut terminal = TerminalState::new(
+        TerminalSize::default(),
+        Arc::new(DefaultConfiguration),
+        "wezterm_portable",
+        env!("CARGO_PKG_VERSION"),
+        Box::new(std::io::sink()),
+    );
+    recording.play(&mut terminal, Pace::AsFastAsPossible);
+    let replayed: Vec<_> = terminal
+        .screen()
+        .visible_lines()
+        .iter()
+        .map(|line| line.as_str().trim_end().to_string())
+        .collect();
+    assert_eq!(replayed.join("\n"), harness.screen_text());
+}